@@ -0,0 +1,170 @@
+use crate::audit::{AuditLog, AuditOperation, AuditOutcome, AuditRecord, now_ms};
+use crate::types::{ProgramHash, ProverError, program_hash};
+use frostgate_zkip::{ZkBackend, ZkError};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// Allow and deny lists for one scope
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AccessRules {
+    /// When set, only these programs may be proven
+    pub allow: Option<HashSet<ProgramHash>>,
+    /// Programs that may never be proven; takes precedence over `allow`
+    pub deny: HashSet<ProgramHash>,
+}
+
+impl AccessRules {
+    fn check(&self, hash: &str) -> Result<(), String> {
+        if self.deny.contains(hash) {
+            return Err("denylisted".to_string());
+        }
+        match &self.allow {
+            Some(allow) if !allow.contains(hash) => Err("not allowlisted".to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Default)]
+struct AccessState {
+    global: AccessRules,
+    tenants: HashMap<String, AccessRules>,
+}
+
+/// Restricts which programs may be set up and proven, globally and per tenant
+#[derive(Default)]
+pub struct ProgramAccessControl {
+    state: RwLock<AccessState>,
+    audit: Option<Arc<AuditLog>>,
+}
+
+impl ProgramAccessControl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record rejections in `log`
+    pub fn with_audit_log(mut self, log: Arc<AuditLog>) -> Self {
+        self.audit = Some(log);
+        self
+    }
+
+    /// Replace the global rules
+    pub fn set_global(&self, rules: AccessRules) {
+        self.state.write().unwrap_or_else(PoisonError::into_inner).global = rules;
+    }
+
+    /// Replace the rules for one tenant
+    pub fn set_tenant(&self, tenant: &str, rules: AccessRules) {
+        self.state
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .tenants
+            .insert(tenant.to_string(), rules);
+    }
+
+    /// Add a program to the global denylist
+    pub fn deny(&self, hash: ProgramHash) {
+        self.state.write().unwrap_or_else(PoisonError::into_inner).global.deny.insert(hash);
+    }
+
+    /// Check whether `tenant` may prove `hash`. Both the global and the
+    /// tenant's rules must allow it.
+    pub fn check(&self, tenant: &str, hash: &str) -> Result<(), ProverError> {
+        let result = {
+            let state = self.state.read().unwrap_or_else(PoisonError::into_inner);
+            state.global.check(hash).map_err(|r| format!("{} globally", r)).and_then(|_| {
+                match state.tenants.get(tenant) {
+                    Some(rules) => rules.check(hash).map_err(|r| format!("{} for tenant '{}'", r, tenant)),
+                    None => Ok(()),
+                }
+            })
+        };
+
+        result.map_err(|reason| {
+            tracing::warn!("rejected program {} for tenant '{}': {}", hash, tenant, reason);
+            self.audit_rejection(tenant, hash, &reason);
+            ProverError::PolicyViolation(format!("program {} is {}", hash, reason))
+        })
+    }
+
+    fn audit_rejection(&self, tenant: &str, hash: &str, reason: &str) {
+        let Some(log) = &self.audit else {
+            return;
+        };
+        let now_ms = now_ms();
+        let record = AuditRecord {
+            operation: AuditOperation::Prove,
+            caller: tenant.to_string(),
+            program_hash: hash.to_string(),
+            input_digest: String::new(),
+            outcome: AuditOutcome::Failure(format!("access denied: {}", reason)),
+            started_at_ms: now_ms,
+            finished_at_ms: now_ms,
+        };
+        if let Err(e) = log.append(record) {
+            tracing::error!("failed to audit access rejection: {:?}", e);
+        }
+    }
+}
+
+/// Backend wrapper enforcing program access control for one tenant
+pub struct AccessControlledBackend {
+    inner: Arc<dyn ZkBackend>,
+    control: Arc<ProgramAccessControl>,
+    tenant: String,
+}
+
+impl AccessControlledBackend {
+    pub fn new(inner: Arc<dyn ZkBackend>, control: Arc<ProgramAccessControl>, tenant: String) -> Self {
+        Self { inner, control, tenant }
+    }
+}
+
+impl ZkBackend for AccessControlledBackend {
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        self.control
+            .check(&self.tenant, &program_hash(program))
+            .map_err(ProverError::into_zk_error)?;
+        self.inner.prove(program, input)
+    }
+
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        self.inner.verify(program, proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_global_and_tenant_rules() {
+        let log = Arc::new(AuditLog::in_memory());
+        let control = ProgramAccessControl::new().with_audit_log(log.clone());
+        control.set_global(AccessRules {
+            allow: Some(["bridge".to_string(), "analytics".to_string()].into()),
+            deny: HashSet::new(),
+        });
+        control.set_tenant(
+            "relayer",
+            AccessRules {
+                allow: Some(["bridge".to_string()].into()),
+                deny: HashSet::new(),
+            },
+        );
+
+        control.check("relayer", "bridge").unwrap();
+        control.check("research", "analytics").unwrap();
+        assert!(control.check("relayer", "analytics").is_err());
+        assert!(control.check("research", "unknown").is_err());
+
+        control.deny("bridge".to_string());
+        assert!(control.check("relayer", "bridge").is_err());
+
+        assert_eq!(log.entries().unwrap().len(), 3);
+        log.verify().unwrap();
+    }
+}
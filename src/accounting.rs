@@ -0,0 +1,116 @@
+use crate::payments::Settlement;
+use std::collections::BTreeMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::SystemTime;
+
+/// Free-form labels attached to a request (team, environment, message id, ...)
+pub type Labels = BTreeMap<String, String>;
+
+/// Label value used when a request did not carry the grouped label
+pub const UNLABELED: &str = "unlabeled";
+
+/// A single settled request
+#[derive(Debug, Clone)]
+pub struct LedgerEntry {
+    pub settlement: Settlement,
+    pub recorded_at: SystemTime,
+}
+
+/// Local record of network spend, attributable by request labels
+#[derive(Default)]
+pub struct SpendLedger {
+    entries: Mutex<Vec<LedgerEntry>>,
+}
+
+impl SpendLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a settled request
+    pub fn record(&self, settlement: &Settlement) {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner).push(LedgerEntry {
+            settlement: settlement.clone(),
+            recorded_at: SystemTime::now(),
+        });
+    }
+
+    /// All recorded entries, oldest first
+    pub fn entries(&self) -> Vec<LedgerEntry> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /// Total fulfilled spend
+    pub fn total(&self) -> u64 {
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .filter(|e| e.settlement.fulfilled)
+            .map(|e| e.settlement.cost)
+            .sum()
+    }
+
+    /// Fulfilled spend grouped by the value of label `key`
+    pub fn totals_by(&self, key: &str) -> BTreeMap<String, u64> {
+        let mut totals = BTreeMap::new();
+        for entry in self.entries.lock().unwrap_or_else(PoisonError::into_inner).iter() {
+            if !entry.settlement.fulfilled {
+                continue;
+            }
+            let value = entry
+                .settlement
+                .labels
+                .get(key)
+                .cloned()
+                .unwrap_or_else(|| UNLABELED.to_string());
+            *totals.entry(value).or_insert(0) += entry.settlement.cost;
+        }
+        totals
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn settlement(cost: u64, fulfilled: bool, labels: &[(&str, &str)]) -> Settlement {
+        Settlement {
+            request_id: Uuid::new_v4(),
+            program_hash: "hash".to_string(),
+            cost,
+            fulfilled,
+            labels: labels.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        }
+    }
+
+    #[test]
+    fn test_totals_by_label() {
+        let ledger = SpendLedger::new();
+        ledger.record(&settlement(10, true, &[("team", "bridge"), ("env", "prod")]));
+        ledger.record(&settlement(20, true, &[("team", "oracle"), ("env", "prod")]));
+        ledger.record(&settlement(40, false, &[("team", "oracle")]));
+        ledger.record(&settlement(5, true, &[]));
+
+        let entries = ledger.entries();
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[0].settlement.cost, 10);
+        assert!(entries[0].recorded_at <= entries[3].recorded_at);
+
+        // Unfulfilled requests cost nothing
+        assert_eq!(ledger.total(), 35);
+        assert_eq!(
+            ledger.totals_by("team"),
+            BTreeMap::from([
+                ("bridge".to_string(), 10),
+                ("oracle".to_string(), 20),
+                (UNLABELED.to_string(), 5),
+            ])
+        );
+        assert_eq!(
+            ledger.totals_by("env"),
+            BTreeMap::from([("prod".to_string(), 30), (UNLABELED.to_string(), 5)])
+        );
+    }
+}
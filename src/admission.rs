@@ -0,0 +1,301 @@
+use crate::registry::BackendMiddleware;
+use crate::types::ProverError;
+use frostgate_zkip::{ZkBackend, ZkError};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Retry hint given when the queue is full and no proof has finished yet
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// Limits applied to prove requests on one backend
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdmissionConfig {
+    /// Sustained prove requests admitted per second
+    pub rate_per_sec: f64,
+    /// Requests that may be admitted back to back above the sustained rate
+    pub burst: u32,
+    /// Proofs running at once
+    pub max_concurrent: usize,
+    /// Admitted requests that may wait for a free slot; further requests are
+    /// rejected with [`ProverError::Busy`] rather than queued
+    pub max_queue_depth: usize,
+}
+
+impl Default for AdmissionConfig {
+    fn default() -> Self {
+        Self {
+            rate_per_sec: 10.0,
+            burst: 20,
+            max_concurrent: 1,
+            max_queue_depth: 16,
+        }
+    }
+}
+
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+#[derive(Default)]
+struct Slots {
+    running: usize,
+    queued: usize,
+}
+
+/// Token-bucket rate limiting plus a bounded wait queue in front of a
+/// backend's proving slots
+pub struct AdmissionController {
+    config: AdmissionConfig,
+    bucket: Mutex<TokenBucket>,
+    slots: Mutex<Slots>,
+    freed: Condvar,
+    /// Moving average of proof durations, for retry hints
+    avg_prove_ms: AtomicU64,
+}
+
+impl AdmissionController {
+    pub fn new(config: AdmissionConfig) -> Self {
+        Self {
+            bucket: Mutex::new(TokenBucket {
+                tokens: config.burst.max(1) as f64,
+                last_refill: Instant::now(),
+            }),
+            slots: Mutex::new(Slots::default()),
+            freed: Condvar::new(),
+            avg_prove_ms: AtomicU64::new(0),
+            config,
+        }
+    }
+
+    /// Admit one prove request, waiting for a slot if the queue has room.
+    /// Requests over the rate or beyond the queue depth fail immediately
+    /// with [`ProverError::Busy`].
+    pub fn admit(&self) -> Result<AdmissionPermit<'_>, ProverError> {
+        self.take_token()?;
+        let mut slots = self.slots.lock().unwrap_or_else(PoisonError::into_inner);
+        let max_concurrent = self.config.max_concurrent.max(1);
+        if slots.running >= max_concurrent {
+            if slots.queued >= self.config.max_queue_depth {
+                return Err(ProverError::Busy {
+                    reason: format!("{} requests already queued", slots.queued),
+                    retry_after: self.queue_wait(slots.queued),
+                });
+            }
+            slots.queued += 1;
+            while slots.running >= max_concurrent {
+                slots = self.freed.wait(slots).unwrap_or_else(PoisonError::into_inner);
+            }
+            slots.queued -= 1;
+        }
+        slots.running += 1;
+        Ok(AdmissionPermit {
+            controller: self,
+            start: Instant::now(),
+        })
+    }
+
+    pub fn running(&self) -> usize {
+        self.slots.lock().unwrap_or_else(PoisonError::into_inner).running
+    }
+
+    pub fn queued(&self) -> usize {
+        self.slots.lock().unwrap_or_else(PoisonError::into_inner).queued
+    }
+
+    fn take_token(&self) -> Result<(), ProverError> {
+        let mut bucket = self.bucket.lock().unwrap_or_else(PoisonError::into_inner);
+        let now = Instant::now();
+        let refilled = now.duration_since(bucket.last_refill).as_secs_f64() * self.config.rate_per_sec;
+        bucket.tokens = (bucket.tokens + refilled).min(self.config.burst.max(1) as f64);
+        bucket.last_refill = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        let missing = 1.0 - bucket.tokens;
+        let retry_after = if self.config.rate_per_sec > 0.0 {
+            Duration::from_secs_f64(missing / self.config.rate_per_sec)
+        } else {
+            Duration::MAX
+        };
+        Err(ProverError::Busy {
+            reason: format!("rate limit of {} proofs/s exceeded", self.config.rate_per_sec),
+            retry_after,
+        })
+    }
+
+    /// Rough time until a request queued behind `queued` others would start
+    fn queue_wait(&self, queued: usize) -> Duration {
+        match self.avg_prove_ms.load(Ordering::Relaxed) {
+            0 => DEFAULT_RETRY_AFTER,
+            avg => {
+                let rounds = (queued + 1).div_ceil(self.config.max_concurrent.max(1)) as u64;
+                Duration::from_millis(avg.saturating_mul(rounds))
+            }
+        }
+    }
+
+    fn release(&self, elapsed: Duration) {
+        let sample = elapsed.as_millis().max(1) as u64;
+        let _ = self
+            .avg_prove_ms
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |avg| match avg {
+                0 => Some(sample),
+                avg => Some((avg * 7 + sample) / 8),
+            });
+        self.slots.lock().unwrap_or_else(PoisonError::into_inner).running -= 1;
+        self.freed.notify_one();
+    }
+}
+
+/// A proving slot, released on drop
+pub struct AdmissionPermit<'a> {
+    controller: &'a AdmissionController,
+    start: Instant,
+}
+
+impl Drop for AdmissionPermit<'_> {
+    fn drop(&mut self) {
+        self.controller.release(self.start.elapsed());
+    }
+}
+
+/// Backend whose prove calls pass through an [`AdmissionController`].
+/// Verification is cheap and isn't limited.
+pub struct AdmittedBackend {
+    inner: Arc<dyn ZkBackend>,
+    controller: Arc<AdmissionController>,
+}
+
+impl AdmittedBackend {
+    pub fn new(inner: Arc<dyn ZkBackend>, config: AdmissionConfig) -> Self {
+        Self::with_controller(inner, Arc::new(AdmissionController::new(config)))
+    }
+
+    /// Share a controller with other wrappers of the same backend
+    pub fn with_controller(inner: Arc<dyn ZkBackend>, controller: Arc<AdmissionController>) -> Self {
+        Self { inner, controller }
+    }
+
+    /// Prove, keeping [`ProverError::Busy`] and its retry hint intact
+    pub fn prove_admitted(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ProverError> {
+        let _permit = self.controller.admit()?;
+        Ok(self.inner.prove(program, input)?)
+    }
+}
+
+impl ZkBackend for AdmittedBackend {
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        self.prove_admitted(program, input).map_err(ProverError::into_zk_error)
+    }
+
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        self.inner.verify(program, proof)
+    }
+}
+
+/// Registry middleware applying per-backend admission limits. Each backend
+/// id gets one controller shared by every handle the registry gives out.
+#[derive(Default)]
+pub struct AdmissionMiddleware {
+    default: Option<AdmissionConfig>,
+    configs: HashMap<String, AdmissionConfig>,
+    controllers: Mutex<HashMap<String, Arc<AdmissionController>>>,
+}
+
+impl AdmissionMiddleware {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits for backends without their own; without a default those
+    /// backends are left unlimited
+    pub fn with_default(mut self, config: AdmissionConfig) -> Self {
+        self.default = Some(config);
+        self
+    }
+
+    pub fn with_backend(mut self, id: &str, config: AdmissionConfig) -> Self {
+        self.configs.insert(id.to_string(), config);
+        self
+    }
+
+    /// Controller of backend `id`, if it is limited
+    pub fn controller(&self, id: &str) -> Option<Arc<AdmissionController>> {
+        let config = self.configs.get(id).or(self.default.as_ref())?;
+        let mut controllers = self.controllers.lock().unwrap_or_else(PoisonError::into_inner);
+        Some(
+            controllers
+                .entry(id.to_string())
+                .or_insert_with(|| Arc::new(AdmissionController::new(config.clone())))
+                .clone(),
+        )
+    }
+}
+
+impl BackendMiddleware for AdmissionMiddleware {
+    fn wrap(&self, id: &str, backend: Arc<dyn ZkBackend>) -> Arc<dyn ZkBackend> {
+        match self.controller(id) {
+            Some(controller) => Arc::new(AdmittedBackend::with_controller(backend, controller)),
+            None => backend,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockBackend, MockConfig};
+
+    #[test]
+    fn test_rate_limit() {
+        let backend = AdmittedBackend::new(
+            Arc::new(MockBackend::default()),
+            AdmissionConfig {
+                rate_per_sec: 1.0,
+                burst: 2,
+                ..AdmissionConfig::default()
+            },
+        );
+        assert!(backend.prove_admitted(b"elf", b"1").is_ok());
+        assert!(backend.prove_admitted(b"elf", b"2").is_ok());
+        let err = backend.prove_admitted(b"elf", b"3").unwrap_err();
+        assert!(matches!(err, ProverError::Busy { .. }));
+        assert!(err.retry_after().unwrap() <= Duration::from_secs(1));
+        assert!(err.is_retryable());
+    }
+
+    #[test]
+    fn test_queue_depth() {
+        let backend = Arc::new(AdmittedBackend::new(
+            Arc::new(MockBackend::new(MockConfig {
+                prove_latency: Duration::from_millis(200),
+                ..MockConfig::default()
+            })),
+            AdmissionConfig {
+                max_concurrent: 1,
+                max_queue_depth: 1,
+                ..AdmissionConfig::default()
+            },
+        ));
+        let workers: Vec<_> = (0..2)
+            .map(|i| {
+                let backend = backend.clone();
+                std::thread::spawn(move || backend.prove_admitted(b"elf", &[i]))
+            })
+            .collect();
+        while backend.controller.running() + backend.controller.queued() < 2 {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+
+        assert!(matches!(backend.prove_admitted(b"elf", b"3"), Err(ProverError::Busy { .. })));
+        for worker in workers {
+            assert!(worker.join().unwrap().is_ok());
+        }
+    }
+}
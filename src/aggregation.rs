@@ -0,0 +1,196 @@
+use crate::binding::PublicValuesExtractor;
+use crate::envelope::{ProofEnvelope, hex_bytes};
+use crate::inspect::PROOF_MODE_KEY;
+use crate::pipeline::ProofMode;
+use crate::types::{ProgramHash, ProverError, ct_eq, program_hash};
+use frostgate_zkip::ZkBackend;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+use std::sync::Arc;
+
+/// Metadata key holding the number of proofs folded into an aggregate
+pub const AGGREGATED_COUNT_KEY: &str = "aggregated_count";
+
+/// One proof handed to the aggregation guest for recursive verification
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AggregatedProof {
+    pub program_hash: ProgramHash,
+    #[serde(with = "hex_bytes")]
+    pub public_values: Vec<u8>,
+    #[serde(with = "hex_bytes")]
+    pub proof: Vec<u8>,
+}
+
+/// Stdin of the aggregation guest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AggregationInput {
+    pub proofs: Vec<AggregatedProof>,
+    /// Digest the guest must commit, see [`aggregation_commitment`]
+    #[serde(with = "hex_bytes")]
+    pub commitment: Vec<u8>,
+}
+
+/// Digest over the program and public values of every aggregated proof, in
+/// order. The aggregation guest commits it so a verifier can check which
+/// statements the aggregate covers.
+pub fn aggregation_commitment(proofs: &[ProofEnvelope]) -> Vec<u8> {
+    let mut hasher = Sha3_256::new();
+    hasher.update((proofs.len() as u64).to_le_bytes());
+    for proof in proofs {
+        hasher.update(proof.program_hash.as_bytes());
+        hasher.update(Sha3_256::digest(&proof.public_values));
+    }
+    hasher.finalize().to_vec()
+}
+
+/// Folds many proofs into one by proving a guest program that verifies each
+/// of them recursively. `extract` reads the commitment out of the backend's
+/// proofs, so an aggregate is only returned if it covers the proofs given.
+pub struct ProofAggregator {
+    backend: Arc<dyn ZkBackend>,
+    aggregation_program: Vec<u8>,
+    extract: PublicValuesExtractor,
+    max_proofs: usize,
+}
+
+impl ProofAggregator {
+    pub fn new(backend: Arc<dyn ZkBackend>, aggregation_program: Vec<u8>, extract: PublicValuesExtractor) -> Self {
+        Self {
+            backend,
+            aggregation_program,
+            extract,
+            max_proofs: 512,
+        }
+    }
+
+    /// Largest number of proofs folded in one call
+    pub fn with_max_proofs(mut self, max_proofs: usize) -> Self {
+        self.max_proofs = max_proofs;
+        self
+    }
+
+    /// Aggregate `proofs`, which may come from the same or different programs
+    /// but must share a backend and be compressed
+    pub fn aggregate(&self, proofs: &[ProofEnvelope]) -> Result<ProofEnvelope, ProverError> {
+        let first = proofs
+            .first()
+            .ok_or_else(|| ProverError::Other("nothing to aggregate".to_string()))?;
+        if proofs.len() > self.max_proofs {
+            return Err(ProverError::Other(format!(
+                "{} proofs exceed the aggregation limit of {}",
+                proofs.len(),
+                self.max_proofs
+            )));
+        }
+        for proof in proofs {
+            check_aggregatable(proof, &first.backend)?;
+        }
+
+        let commitment = aggregation_commitment(proofs);
+        let input = AggregationInput {
+            proofs: proofs
+                .iter()
+                .map(|proof| AggregatedProof {
+                    program_hash: proof.program_hash.clone(),
+                    public_values: proof.public_values.clone(),
+                    proof: proof.payload.clone(),
+                })
+                .collect(),
+            commitment: commitment.clone(),
+        };
+        let payload = self
+            .backend
+            .prove(&self.aggregation_program, &serde_json::to_vec(&input)?)?;
+        if !ct_eq(&(self.extract)(&payload)?, &commitment) {
+            tracing::warn!("aggregation guest committed something other than the aggregation commitment");
+            return Err(ProverError::PublicInputsMismatch);
+        }
+        tracing::info!("aggregated {} proofs", proofs.len());
+
+        let mut envelope = ProofEnvelope::new(
+            &first.backend,
+            program_hash(&self.aggregation_program),
+            payload,
+            commitment,
+        );
+        envelope.insert_metadata(PROOF_MODE_KEY, ProofMode::Compressed.name());
+        envelope.insert_metadata(AGGREGATED_COUNT_KEY, proofs.len().to_string());
+        Ok(envelope)
+    }
+}
+
+fn check_aggregatable(proof: &ProofEnvelope, backend: &str) -> Result<(), ProverError> {
+    if !proof.checksum_valid() {
+        return Err(ProverError::MalformedEnvelope(format!(
+            "proof for program {} fails its checksum",
+            proof.program_hash
+        )));
+    }
+    if proof.backend != backend {
+        return Err(ProverError::Other(format!(
+            "cannot aggregate {} proofs with {} proofs",
+            proof.backend, backend
+        )));
+    }
+    match proof.metadata.get(PROOF_MODE_KEY).map(String::as_str) {
+        Some("compressed") => Ok(()),
+        mode => Err(ProverError::Other(format!(
+            "only compressed proofs can be aggregated, got {}",
+            mode.unwrap_or("a proof without a recorded mode")
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockBackend, canned_envelope, mock_public_values};
+    use frostgate_zkip::ZkError;
+
+    /// Mock aggregation guest committing the commitment its input asks for
+    fn aggregator() -> ProofAggregator {
+        let guest = MockBackend::default().with_guest(Arc::new(|_, input| {
+            let input: AggregationInput = serde_json::from_slice(input).map_err(|e| ZkError::Config(e.to_string()))?;
+            Ok(input.commitment)
+        }));
+        ProofAggregator::new(Arc::new(guest), b"aggregator".to_vec(), mock_public_values())
+    }
+
+    fn compressed(program: &[u8], input: &[u8]) -> ProofEnvelope {
+        let mut proof = canned_envelope(program, input);
+        proof.insert_metadata(PROOF_MODE_KEY, ProofMode::Compressed.name());
+        proof
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let aggregator = aggregator();
+        let proofs = vec![compressed(b"a", b"1"), compressed(b"b", b"2")];
+
+        let aggregate = aggregator.aggregate(&proofs).unwrap();
+        assert_eq!(aggregate.public_values, aggregation_commitment(&proofs));
+        assert_eq!(aggregate.metadata.get(AGGREGATED_COUNT_KEY).map(String::as_str), Some("2"));
+        assert!(aggregator.aggregate(&[]).is_err());
+    }
+
+    #[test]
+    fn test_rejects_wrapped_proofs() {
+        let aggregator = aggregator();
+        let mut proof = compressed(b"a", b"1");
+        proof.insert_metadata(PROOF_MODE_KEY, ProofMode::Groth16.name());
+        assert!(aggregator.aggregate(&[proof]).is_err());
+        // The mode must be recorded, not assumed
+        assert!(aggregator.aggregate(&[canned_envelope(b"a", b"1")]).is_err());
+    }
+
+    #[test]
+    fn test_aggregate_must_commit_commitment() {
+        // Guest committing its whole input instead of the commitment
+        let guest = MockBackend::default().with_guest(Arc::new(|_, input| Ok(input.to_vec())));
+        let aggregator = ProofAggregator::new(Arc::new(guest), b"aggregator".to_vec(), mock_public_values());
+        assert!(matches!(
+            aggregator.aggregate(&[compressed(b"a", b"1")]),
+            Err(ProverError::PublicInputsMismatch)
+        ));
+    }
+}
@@ -0,0 +1,313 @@
+use crate::provenance::SP1_VERSION;
+use crate::types::{ProverError, ct_eq};
+use crate::workdirs::WorkDirs;
+use sha3::{Digest, Sha3_256};
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Mutex, PoisonError};
+
+/// Marker written once an artifact directory is completely installed
+const INSTALLED_MARKER: &str = ".installed";
+
+/// Bn254 circuits SP1 wraps proofs into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CircuitKind {
+    Groth16,
+    Plonk,
+}
+
+impl CircuitKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CircuitKind::Groth16 => "groth16",
+            CircuitKind::Plonk => "plonk",
+        }
+    }
+}
+
+/// Where to fetch a circuit's artifacts from
+#[derive(Debug, Clone)]
+pub struct ArtifactSource {
+    pub kind: CircuitKind,
+    pub url: String,
+    /// Expected SHA3-256 of the archive; unchecked when `None`
+    pub sha3_256: Option<String>,
+}
+
+impl ArtifactSource {
+    /// Succinct's published artifacts for the SP1 version this crate uses,
+    /// pinned to `sha3_256`, the digest published for that archive
+    pub fn sp1_default(kind: CircuitKind, sha3_256: &str) -> Self {
+        Self {
+            kind,
+            url: format!(
+                "https://sp1-circuits.s3-us-east-2.amazonaws.com/v{}-{}.tar.gz",
+                SP1_VERSION,
+                kind.name()
+            ),
+            sha3_256: Some(sha3_256.to_ascii_lowercase()),
+        }
+    }
+}
+
+/// Downloads, checks and installs circuit artifacts into the configured directory
+pub struct ArtifactManager {
+    dirs: WorkDirs,
+    sources: Vec<ArtifactSource>,
+    install_lock: Mutex<()>,
+}
+
+impl ArtifactManager {
+    /// Manage the default artifacts for both circuits, pinned to their
+    /// published digests
+    pub fn new(dirs: WorkDirs, groth16_sha3_256: &str, plonk_sha3_256: &str) -> Self {
+        Self::with_sources(
+            dirs,
+            vec![
+                ArtifactSource::sp1_default(CircuitKind::Groth16, groth16_sha3_256),
+                ArtifactSource::sp1_default(CircuitKind::Plonk, plonk_sha3_256),
+            ],
+        )
+    }
+
+    pub fn with_sources(dirs: WorkDirs, sources: Vec<ArtifactSource>) -> Self {
+        Self {
+            dirs,
+            sources,
+            install_lock: Mutex::new(()),
+        }
+    }
+
+    /// Directory holding the artifacts for `kind`
+    pub fn dir_for(&self, kind: CircuitKind) -> PathBuf {
+        self.dirs
+            .artifacts()
+            .join(format!("v{}", SP1_VERSION))
+            .join(kind.name())
+    }
+
+    /// Whether the artifacts for `kind` are completely installed and match
+    /// the expected archive checksum
+    pub fn is_installed(&self, kind: CircuitKind) -> bool {
+        let Ok(installed_digest) = fs::read_to_string(self.dir_for(kind).join(INSTALLED_MARKER)) else {
+            return false;
+        };
+        match self.sources.iter().find(|s| s.kind == kind).and_then(|s| s.sha3_256.as_ref()) {
+            Some(expected) => ct_eq(expected.as_bytes(), installed_digest.trim().as_bytes()),
+            None => true,
+        }
+    }
+
+    /// Install and validate every configured circuit, returning their
+    /// directories. Call at deploy time to avoid downloading on the first
+    /// wrapped proof.
+    pub fn ensure_artifacts(&self) -> Result<Vec<(CircuitKind, PathBuf)>, ProverError> {
+        self.sources
+            .iter()
+            .map(|source| Ok((source.kind, self.ensure(source.kind)?)))
+            .collect()
+    }
+
+    /// Return the artifact directory for `kind`, installing it on first use
+    pub fn ensure(&self, kind: CircuitKind) -> Result<PathBuf, ProverError> {
+        let dir = self.dir_for(kind);
+        if self.is_installed(kind) {
+            return Ok(dir);
+        }
+
+        let _guard = self.install_lock.lock().unwrap_or_else(PoisonError::into_inner);
+        if self.is_installed(kind) {
+            return Ok(dir);
+        }
+        let source = self
+            .sources
+            .iter()
+            .find(|s| s.kind == kind)
+            .ok_or_else(|| ProverError::Other(format!("No artifact source for {}", kind.name())))?;
+
+        tracing::info!("installing {} artifacts from {}", kind.name(), source.url);
+        let job = self.dirs.job_dir()?;
+        let archive = job.path().join("artifacts.tar.gz");
+        let digest = download(&source.url, &archive)?;
+        if let Some(expected) = &source.sha3_256
+            && !ct_eq(expected.as_bytes(), digest.as_bytes())
+        {
+            return Err(ProverError::Other(format!(
+                "{} artifacts checksum mismatch: expected {}, got {}",
+                kind.name(),
+                expected,
+                digest
+            )));
+        }
+
+        // Extracted next to its final place, so the rename below never
+        // crosses filesystems as one out of the temp root could
+        let parent = dir
+            .parent()
+            .ok_or_else(|| ProverError::Other(format!("{} has no parent", dir.display())))?;
+        fs::create_dir_all(parent)?;
+        let staging = parent.join(format!(".{}-{}.staging", kind.name(), uuid::Uuid::new_v4()));
+        let installed = extract(&archive, &staging, &digest).and_then(|()| {
+            if dir.exists() {
+                // Left over from an interrupted install
+                fs::remove_dir_all(&dir)?;
+            }
+            Ok(fs::rename(&staging, &dir)?)
+        });
+        if let Err(e) = installed {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+        Ok(dir)
+    }
+}
+
+/// Unpack `archive` into `staging` and mark it installed from `digest`
+fn extract(archive: &Path, staging: &Path, digest: &str) -> Result<(), ProverError> {
+    fs::create_dir_all(staging)?;
+    let status = Command::new("tar")
+        .arg("-xzf")
+        .arg(archive)
+        .arg("-C")
+        .arg(staging)
+        .status()?;
+    if !status.success() {
+        return Err(ProverError::Other(format!(
+            "extracting {} failed with {}",
+            archive.display(),
+            status
+        )));
+    }
+    fs::write(staging.join(INSTALLED_MARKER), digest)?;
+    Ok(())
+}
+
+/// Download `url` to `path`, returning the SHA3-256 of the contents
+fn download(url: &str, path: &Path) -> Result<String, ProverError> {
+    let response = ureq::get(url)
+        .call()
+        .map_err(|e| ProverError::NetworkUnavailable(format!("downloading {}: {}", url, e)))?;
+    let mut reader = response.into_reader();
+    let mut file = File::create(path)?;
+    let mut hasher = Sha3_256::new();
+    let mut buf = vec![0u8; 1 << 16];
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        hasher.update(&buf[..n]);
+        file.write_all(&buf[..n])?;
+    }
+    file.sync_all()?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_installed_artifacts_are_validated() {
+        let root = std::env::temp_dir().join(format!("frostgate-artifacts-{}", uuid::Uuid::new_v4()));
+        let dirs = WorkDirs::new(&root.join("artifacts"), &root.join("tmp")).unwrap();
+        let source = |digest: &str| ArtifactSource {
+            kind: CircuitKind::Groth16,
+            url: "http://127.0.0.1:9/unused.tar.gz".to_string(),
+            sha3_256: Some(digest.to_string()),
+        };
+
+        let manager = ArtifactManager::with_sources(dirs.clone(), vec![source("aa")]);
+        let dir = manager.dir_for(CircuitKind::Groth16);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(INSTALLED_MARKER), "aa").unwrap();
+        assert!(manager.is_installed(CircuitKind::Groth16));
+        assert_eq!(manager.ensure_artifacts().unwrap(), vec![(CircuitKind::Groth16, dir)]);
+
+        let stale = ArtifactManager::with_sources(dirs, vec![source("bb")]);
+        assert!(!stale.is_installed(CircuitKind::Groth16));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    /// Serve `body` once over HTTP, returning its URL
+    fn serve_once(body: Vec<u8>) -> String {
+        use std::io::{BufRead, BufReader};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/artifacts.tar.gz", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", body.len()).unwrap();
+            stream.write_all(&body).unwrap();
+        });
+        url
+    }
+
+    #[test]
+    fn test_install_stages_beside_artifacts() {
+        let root = std::env::temp_dir().join(format!("frostgate-artifacts-{}", uuid::Uuid::new_v4()));
+        let dirs = WorkDirs::new(&root.join("artifacts"), &root.join("tmp")).unwrap();
+        let content = root.join("content");
+        fs::create_dir_all(&content).unwrap();
+        fs::write(content.join("groth16_pk.bin"), b"pk").unwrap();
+        let archive = root.join("groth16.tar.gz");
+        let status = Command::new("tar")
+            .arg("-czf")
+            .arg(&archive)
+            .arg("-C")
+            .arg(&content)
+            .arg(".")
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let body = fs::read(&archive).unwrap();
+        let digest = hex::encode(Sha3_256::digest(&body));
+
+        let manager = ArtifactManager::with_sources(
+            dirs.clone(),
+            vec![ArtifactSource {
+                kind: CircuitKind::Groth16,
+                url: serve_once(body),
+                sha3_256: Some(digest),
+            }],
+        );
+        let dir = manager.ensure(CircuitKind::Groth16).unwrap();
+        assert_eq!(fs::read(dir.join("groth16_pk.bin")).unwrap(), b"pk");
+        assert!(manager.is_installed(CircuitKind::Groth16));
+        let leftovers: Vec<_> = fs::read_dir(dir.parent().unwrap())
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .filter(|name| name.to_string_lossy().ends_with(".staging"))
+            .collect();
+        assert!(leftovers.is_empty());
+
+        // A bad archive leaves neither an install nor its staging behind
+        let plonk = ArtifactManager::with_sources(
+            dirs,
+            vec![ArtifactSource {
+                kind: CircuitKind::Plonk,
+                url: serve_once(b"not a tarball".to_vec()),
+                sha3_256: None,
+            }],
+        );
+        assert!(plonk.ensure(CircuitKind::Plonk).is_err());
+        assert!(!plonk.dir_for(CircuitKind::Plonk).exists());
+        let leftovers: Vec<_> = fs::read_dir(dir.parent().unwrap())
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .filter(|name| name.to_string_lossy().ends_with(".staging"))
+            .collect();
+        assert!(leftovers.is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
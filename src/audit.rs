@@ -0,0 +1,466 @@
+use crate::events::{EventSubscriber, LifecycleEvent};
+use crate::types::{ProgramHash, ProverError, ct_eq, program_hash};
+use frostgate_zkip::{ZkBackend, ZkError};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Hash preceding the first entry of a chain
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Audited proving operations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AuditOperation {
+    Prove,
+    Verify,
+    Convert,
+}
+
+/// Outcome of an audited operation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AuditOutcome {
+    Success,
+    Failure(String),
+}
+
+/// An operation to be recorded in the audit log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub operation: AuditOperation,
+    pub caller: String,
+    pub program_hash: ProgramHash,
+    pub input_digest: String,
+    pub outcome: AuditOutcome,
+    pub started_at_ms: u64,
+    pub finished_at_ms: u64,
+}
+
+/// A record chained to its predecessor
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub seq: u64,
+    pub record: AuditRecord,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+impl AuditEntry {
+    fn compute_hash(seq: u64, record: &AuditRecord, prev_hash: &str) -> Result<String, ProverError> {
+        let mut hasher = Sha3_256::new();
+        hasher.update(seq.to_le_bytes());
+        hasher.update(serde_json::to_vec(record)?);
+        hasher.update(prev_hash.as_bytes());
+        Ok(hex::encode(hasher.finalize()))
+    }
+}
+
+enum AuditStore {
+    Memory(Vec<AuditEntry>),
+    File { path: PathBuf, file: File },
+}
+
+struct AuditState {
+    next_seq: u64,
+    last_hash: String,
+    store: AuditStore,
+}
+
+/// Append-only, hash-chained log of proving operations. A file-backed log
+/// keeps only the chain head in memory and reads entries back from disk.
+pub struct AuditLog {
+    state: Mutex<AuditState>,
+}
+
+impl AuditLog {
+    /// Create a log kept only in memory
+    pub fn in_memory() -> Self {
+        Self {
+            state: Mutex::new(AuditState {
+                next_seq: 0,
+                last_hash: GENESIS_HASH.to_string(),
+                store: AuditStore::Memory(Vec::new()),
+            }),
+        }
+    }
+
+    /// Open a JSON-lines log at `path`, verifying any existing entries
+    pub fn open(path: &Path) -> Result<Self, ProverError> {
+        let mut chain = ChainVerifier::new();
+        if path.exists() {
+            read_entries(path, |entry| chain.check(&entry))?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            state: Mutex::new(AuditState {
+                next_seq: chain.next_seq,
+                last_hash: chain.prev_hash,
+                store: AuditStore::File {
+                    path: path.to_path_buf(),
+                    file,
+                },
+            }),
+        })
+    }
+
+    /// Append a record to the chain
+    pub fn append(&self, record: AuditRecord) -> Result<AuditEntry, ProverError> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let seq = state.next_seq;
+        let prev_hash = state.last_hash.clone();
+        let hash = AuditEntry::compute_hash(seq, &record, &prev_hash)?;
+        let entry = AuditEntry {
+            seq,
+            record,
+            prev_hash,
+            hash,
+        };
+
+        match &mut state.store {
+            AuditStore::Memory(entries) => entries.push(entry.clone()),
+            AuditStore::File { file, .. } => {
+                let mut line = serde_json::to_vec(&entry)?;
+                line.push(b'\n');
+                file.write_all(&line)?;
+                file.flush()?;
+            }
+        }
+        state.next_seq = seq + 1;
+        state.last_hash = entry.hash.clone();
+        Ok(entry)
+    }
+
+    /// Record `operation` by `caller` on `program`, logging rather than
+    /// returning a failure to append
+    pub fn record(
+        &self,
+        operation: AuditOperation,
+        caller: &str,
+        program: &[u8],
+        input: &[u8],
+        started_at_ms: u64,
+        outcome: AuditOutcome,
+    ) {
+        let record = AuditRecord {
+            operation,
+            caller: caller.to_string(),
+            program_hash: program_hash(program),
+            input_digest: input_digest(input),
+            outcome,
+            started_at_ms,
+            finished_at_ms: now_ms(),
+        };
+        if let Err(e) = self.append(record) {
+            tracing::error!("failed to append audit entry: {:?}", e);
+        }
+    }
+
+    /// All entries, oldest first. File-backed logs are read back from disk.
+    pub fn entries(&self) -> Result<Vec<AuditEntry>, ProverError> {
+        let mut entries = Vec::new();
+        self.for_each(|entry| {
+            entries.push(entry);
+            Ok(())
+        })?;
+        Ok(entries)
+    }
+
+    /// Check the whole chain for tampering
+    pub fn verify(&self) -> Result<(), ProverError> {
+        let mut chain = ChainVerifier::new();
+        self.for_each(|entry| chain.check(&entry))
+    }
+
+    /// Export the chain as JSON lines for compliance review
+    pub fn export<W: Write>(&self, mut writer: W) -> Result<(), ProverError> {
+        self.for_each(|entry| {
+            serde_json::to_writer(&mut writer, &entry)?;
+            writer.write_all(b"\n")?;
+            Ok(())
+        })
+    }
+
+    /// Visit every entry in order, holding the lock so no append interleaves
+    fn for_each(&self, visit: impl FnMut(AuditEntry) -> Result<(), ProverError>) -> Result<(), ProverError> {
+        let state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        match &state.store {
+            AuditStore::Memory(entries) => entries.iter().cloned().try_for_each(visit),
+            AuditStore::File { path, .. } => read_entries(path, visit),
+        }
+    }
+}
+
+/// Stream the JSON-lines entries stored at `path`
+fn read_entries(path: &Path, mut visit: impl FnMut(AuditEntry) -> Result<(), ProverError>) -> Result<(), ProverError> {
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            visit(serde_json::from_str(&line)?)?;
+        }
+    }
+    Ok(())
+}
+
+/// Checks entries one at a time against the chain built so far
+struct ChainVerifier {
+    next_seq: u64,
+    prev_hash: String,
+}
+
+impl ChainVerifier {
+    fn new() -> Self {
+        Self {
+            next_seq: 0,
+            prev_hash: GENESIS_HASH.to_string(),
+        }
+    }
+
+    fn check(&mut self, entry: &AuditEntry) -> Result<(), ProverError> {
+        let expected = AuditEntry::compute_hash(self.next_seq, &entry.record, &self.prev_hash)?;
+        if entry.seq != self.next_seq
+            || !ct_eq(entry.prev_hash.as_bytes(), self.prev_hash.as_bytes())
+            || !ct_eq(entry.hash.as_bytes(), expected.as_bytes())
+        {
+            return Err(ProverError::Other(format!(
+                "Audit log chain broken at entry {}",
+                self.next_seq
+            )));
+        }
+        self.next_seq += 1;
+        self.prev_hash = entry.hash.clone();
+        Ok(())
+    }
+}
+
+/// Records the proofs and verifications reported on an
+/// [`EventBus`](crate::events::EventBus), with the backend as the caller.
+/// Events carry no inputs, so their entries have an empty input digest.
+impl EventSubscriber for AuditLog {
+    fn on_event(&self, event: &LifecycleEvent) {
+        let finished_at_ms = now_ms();
+        let (operation, backend, program_hash, outcome, started_at_ms) = match event {
+            LifecycleEvent::ProofCompleted {
+                backend,
+                program_hash,
+                duration,
+                ..
+            } => (
+                AuditOperation::Prove,
+                backend,
+                program_hash,
+                AuditOutcome::Success,
+                finished_at_ms.saturating_sub(duration.as_millis() as u64),
+            ),
+            LifecycleEvent::ProofFailed {
+                backend,
+                program_hash,
+                error,
+                ..
+            } => (
+                AuditOperation::Prove,
+                backend,
+                program_hash,
+                AuditOutcome::Failure(error.clone()),
+                finished_at_ms,
+            ),
+            LifecycleEvent::ProofVerified {
+                backend,
+                program_hash,
+                valid,
+                error,
+            } => {
+                let outcome = match (valid, error) {
+                    (_, Some(error)) => AuditOutcome::Failure(error.clone()),
+                    (true, None) => AuditOutcome::Success,
+                    (false, None) => AuditOutcome::Failure("proof rejected".to_string()),
+                };
+                (AuditOperation::Verify, backend, program_hash, outcome, finished_at_ms)
+            }
+            _ => return,
+        };
+        let record = AuditRecord {
+            operation,
+            caller: backend.clone(),
+            program_hash: program_hash.clone(),
+            input_digest: String::new(),
+            outcome,
+            started_at_ms,
+            finished_at_ms,
+        };
+        if let Err(e) = self.append(record) {
+            tracing::error!("failed to append audit entry: {:?}", e);
+        }
+    }
+}
+
+/// Check that entries are consecutive and each hash covers its predecessor
+pub fn verify_chain(entries: &[AuditEntry]) -> Result<(), ProverError> {
+    let mut chain = ChainVerifier::new();
+    entries.iter().try_for_each(|entry| chain.check(entry))
+}
+
+/// Digest recorded in place of raw inputs
+pub fn input_digest(input: &[u8]) -> String {
+    hex::encode(Sha3_256::digest(input))
+}
+
+/// Milliseconds since the Unix epoch
+pub(crate) fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Caller recorded for calls made through the plain [`ZkBackend`] interface
+pub const ANONYMOUS_CALLER: &str = "anonymous";
+
+/// Backend wrapper recording every prove and verify call
+pub struct AuditedBackend {
+    inner: Arc<dyn ZkBackend>,
+    log: Arc<AuditLog>,
+}
+
+impl AuditedBackend {
+    pub fn new(inner: Arc<dyn ZkBackend>, log: Arc<AuditLog>) -> Self {
+        Self { inner, log }
+    }
+
+    /// Prove on behalf of `caller`
+    pub fn prove_as(&self, caller: &str, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        let started_at_ms = now_ms();
+        let result = self.inner.prove(program, input);
+        let outcome = match &result {
+            Ok(_) => AuditOutcome::Success,
+            Err(e) => AuditOutcome::Failure(format!("{:?}", e)),
+        };
+        self.log.record(AuditOperation::Prove, caller, program, input, started_at_ms, outcome);
+        result
+    }
+
+    /// Verify on behalf of `caller`
+    pub fn verify_as(&self, caller: &str, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        let started_at_ms = now_ms();
+        let result = self.inner.verify(program, proof);
+        let outcome = match &result {
+            Ok(true) => AuditOutcome::Success,
+            Ok(false) => AuditOutcome::Failure("proof rejected".to_string()),
+            Err(e) => AuditOutcome::Failure(format!("{:?}", e)),
+        };
+        self.log.record(AuditOperation::Verify, caller, program, proof, started_at_ms, outcome);
+        result
+    }
+}
+
+impl ZkBackend for AuditedBackend {
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        self.prove_as(ANONYMOUS_CALLER, program, input)
+    }
+
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        self.verify_as(ANONYMOUS_CALLER, program, proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockBackend;
+
+    fn record(caller: &str) -> AuditRecord {
+        AuditRecord {
+            operation: AuditOperation::Prove,
+            caller: caller.to_string(),
+            program_hash: program_hash(b"elf"),
+            input_digest: input_digest(b"input"),
+            outcome: AuditOutcome::Success,
+            started_at_ms: 1,
+            finished_at_ms: 2,
+        }
+    }
+
+    #[test]
+    fn test_records_bus_events() {
+        let bus = Arc::new(crate::events::EventBus::new());
+        let log = Arc::new(AuditLog::in_memory());
+        bus.subscribe(log.clone());
+        let backend = crate::events::EventedBackend::new(Arc::new(MockBackend::default()), bus.clone(), "mock");
+        let proof = backend.prove(b"elf", b"input").unwrap();
+        assert!(!backend.verify(b"elf", b"forged").unwrap());
+        assert!(backend.verify(b"elf", &proof).unwrap());
+        bus.emit(LifecycleEvent::CacheEvicted {
+            cache: "programs".to_string(),
+            key: "k".to_string(),
+        });
+
+        let entries = log.entries().unwrap();
+        let records: Vec<_> = entries.iter().map(|e| (e.record.operation, e.record.outcome.clone())).collect();
+        assert_eq!(
+            records,
+            [
+                (AuditOperation::Prove, AuditOutcome::Success),
+                (AuditOperation::Verify, AuditOutcome::Failure("proof rejected".to_string())),
+                (AuditOperation::Verify, AuditOutcome::Success),
+            ]
+        );
+        assert_eq!(entries[0].record.caller, "mock");
+        assert_eq!(entries[0].record.program_hash, program_hash(b"elf"));
+        log.verify().unwrap();
+    }
+
+    #[test]
+    fn test_chain_detects_tampering() {
+        let log = AuditLog::in_memory();
+        log.append(record("relayer-a")).unwrap();
+        log.append(record("relayer-b")).unwrap();
+        log.verify().unwrap();
+
+        let mut entries = log.entries().unwrap();
+        assert_eq!(entries[1].prev_hash, entries[0].hash);
+
+        entries[0].record.caller = "mallory".to_string();
+        assert!(verify_chain(&entries).is_err());
+
+        let mut truncated = log.entries().unwrap();
+        truncated.remove(0);
+        assert!(verify_chain(&truncated).is_err());
+    }
+
+    #[test]
+    fn test_file_log_reopens_and_streams() {
+        let path = std::env::temp_dir().join(format!("frostgate-audit-{}.jsonl", uuid::Uuid::new_v4()));
+        let log = AuditLog::open(&path).unwrap();
+        log.append(record("relayer-a")).unwrap();
+        drop(log);
+
+        let log = AuditLog::open(&path).unwrap();
+        let entry = log.append(record("relayer-b")).unwrap();
+        assert_eq!(entry.seq, 1);
+        log.verify().unwrap();
+        let mut exported = Vec::new();
+        log.export(&mut exported).unwrap();
+        assert_eq!(exported, std::fs::read(&path).unwrap());
+        assert_eq!(log.entries().unwrap()[1], entry);
+
+        std::fs::write(&path, String::from_utf8(exported).unwrap().replace("relayer-a", "mallory")).unwrap();
+        assert!(log.verify().is_err());
+        assert!(AuditLog::open(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_caller_per_call() {
+        let log = Arc::new(AuditLog::in_memory());
+        let backend = AuditedBackend::new(Arc::new(MockBackend::default()), log.clone());
+        let proof = backend.prove_as("relayer-a", b"elf", b"input").unwrap();
+        backend.verify_as("relayer-b", b"elf", &proof).unwrap();
+        backend.prove(b"elf", b"input").unwrap();
+
+        let callers: Vec<_> = log.entries().unwrap().into_iter().map(|e| e.record.caller).collect();
+        assert_eq!(callers, ["relayer-a", "relayer-b", ANONYMOUS_CALLER]);
+        assert_eq!(log.entries().unwrap()[0].record.input_digest, input_digest(b"input"));
+    }
+}
@@ -0,0 +1,205 @@
+use crate::registry::{self, BackendProfile};
+use crate::types::{ProgramHash, ProverError, program_hash};
+use frostgate_zkip::{ZkBackend, ZkError};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// How [`LoadBalancedBackend`] ranks the backends in its pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BalanceStrategy {
+    /// Fewest proofs currently dispatched by this balancer
+    #[default]
+    LeastOutstanding,
+    /// Lowest load reported to the registry, e.g. by health checks
+    LeastLoaded,
+}
+
+/// Spreads prove requests over a pool of registry backends, e.g. several
+/// SP1 network accounts and a local GPU box. Unregistered and unhealthy
+/// backends are skipped.
+///
+/// Requests for a program go back to the backend that proved it last, whose
+/// key cache is warm, unless that backend has more than `sticky_slack`
+/// requests outstanding beyond the least busy one.
+pub struct LoadBalancedBackend {
+    backend_ids: Vec<String>,
+    strategy: BalanceStrategy,
+    sticky_slack: usize,
+    outstanding: Vec<AtomicUsize>,
+    affinity: Mutex<HashMap<ProgramHash, usize>>,
+}
+
+struct Outstanding<'a>(&'a AtomicUsize);
+
+impl Drop for Outstanding<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl LoadBalancedBackend {
+    /// `backend_ids` are registry IDs forming the pool
+    pub fn new(backend_ids: Vec<String>) -> Self {
+        Self {
+            outstanding: backend_ids.iter().map(|_| AtomicUsize::new(0)).collect(),
+            backend_ids,
+            strategy: BalanceStrategy::default(),
+            sticky_slack: 2,
+            affinity: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_strategy(mut self, strategy: BalanceStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Extra outstanding requests tolerated on a program's previous backend
+    /// before it is routed elsewhere; `0` routes by load alone
+    pub fn with_sticky_slack(mut self, sticky_slack: usize) -> Self {
+        self.sticky_slack = sticky_slack;
+        self
+    }
+
+    /// Proofs currently dispatched to backend `id` by this balancer
+    pub fn outstanding(&self, id: &str) -> Option<usize> {
+        let index = self.backend_ids.iter().position(|backend_id| backend_id == id)?;
+        Some(self.outstanding[index].load(Ordering::SeqCst))
+    }
+
+    /// Prove, returning the proof and the ID of the backend that produced it
+    pub fn prove_with_source(&self, program: &[u8], input: &[u8]) -> Result<(Vec<u8>, String), ProverError> {
+        let hash = program_hash(program);
+        let (index, backend) = self.dispatch(Some(&hash))?;
+        let id = &self.backend_ids[index];
+        let result = {
+            let _outstanding = Outstanding(&self.outstanding[index]);
+            backend.prove(program, input)
+        };
+        let mut affinity = self.affinity.lock().unwrap_or_else(PoisonError::into_inner);
+        match result {
+            Ok(proof) => {
+                affinity.insert(hash, index);
+                Ok((proof, id.clone()))
+            }
+            Err(e) => {
+                if affinity.get(&hash) == Some(&index) {
+                    affinity.remove(&hash);
+                }
+                tracing::warn!("backend {} failed to prove: {:?}", id, e);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Choose a backend and count the request as outstanding on it. The
+    /// affinity lock is held throughout, so concurrent requests see each
+    /// other's choices.
+    fn dispatch(&self, program: Option<&ProgramHash>) -> Result<(usize, Arc<dyn ZkBackend>), ProverError> {
+        let affinity = self.affinity.lock().unwrap_or_else(PoisonError::into_inner);
+        let candidates: Vec<_> = self
+            .backend_ids
+            .iter()
+            .enumerate()
+            .filter_map(|(index, id)| {
+                let profile = registry::backend_profile(id).unwrap_or_default();
+                if !profile.healthy {
+                    return None;
+                }
+                let backend = registry::get_backend(id)?;
+                Some((index, profile, backend))
+            })
+            .collect();
+        let rank = |(index, profile): (usize, &BackendProfile)| {
+            let outstanding = self.outstanding[index].load(Ordering::SeqCst);
+            match self.strategy {
+                BalanceStrategy::LeastOutstanding => (outstanding, profile.load, index),
+                BalanceStrategy::LeastLoaded => (0, profile.load, index),
+            }
+        };
+        let best = candidates
+            .iter()
+            .min_by(|a, b| {
+                let (a, b) = (rank((a.0, &a.1)), rank((b.0, &b.1)));
+                a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)).then(a.2.cmp(&b.2))
+            })
+            .ok_or_else(|| ProverError::Other("no healthy backend in the pool".to_string()))?;
+
+        let min_outstanding = self.outstanding[best.0].load(Ordering::SeqCst);
+        let chosen = program
+            .and_then(|hash| affinity.get(hash))
+            .and_then(|previous| candidates.iter().find(|(index, ..)| index == previous))
+            .filter(|(index, ..)| self.outstanding[*index].load(Ordering::SeqCst) <= min_outstanding + self.sticky_slack)
+            .unwrap_or(best);
+        self.outstanding[chosen.0].fetch_add(1, Ordering::SeqCst);
+        Ok((chosen.0, chosen.2.clone()))
+    }
+}
+
+impl ZkBackend for LoadBalancedBackend {
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        self.prove_with_source(program, input)
+            .map(|(proof, _)| proof)
+            .map_err(ProverError::into_zk_error)
+    }
+
+    /// Verify on the least busy healthy backend
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        let (index, backend) = self.dispatch(None).map_err(ProverError::into_zk_error)?;
+        let _outstanding = Outstanding(&self.outstanding[index]);
+        backend.verify(program, proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockBackend, MockConfig};
+    use std::time::Duration;
+
+    #[test]
+    fn test_least_outstanding_and_sticky() {
+        let ids: Vec<String> = ["balance-a", "balance-b", "balance-c"].iter().map(|id| id.to_string()).collect();
+        let mocks: Vec<_> = ids
+            .iter()
+            .map(|id| {
+                let mock = Arc::new(MockBackend::new(MockConfig {
+                    prove_latency: Duration::from_millis(100),
+                    ..MockConfig::default()
+                }));
+                registry::register_backend(id.clone(), mock.clone()).unwrap();
+                mock
+            })
+            .collect();
+        let balancer = Arc::new(LoadBalancedBackend::new(ids.clone()).with_sticky_slack(0));
+
+        let workers: Vec<_> = (0..3u8)
+            .map(|i| {
+                let balancer = balancer.clone();
+                std::thread::spawn(move || balancer.prove_with_source(&[b'p', i], b"input").unwrap().1)
+            })
+            .collect();
+        let mut sources: Vec<String> = workers.into_iter().map(|worker| worker.join().unwrap()).collect();
+        sources.sort();
+        assert_eq!(sources, ids);
+        assert!(mocks.iter().all(|mock| mock.prove_calls() == 1));
+
+        let sticky = LoadBalancedBackend::new(ids.clone()).with_strategy(BalanceStrategy::LeastLoaded);
+        registry::report_backend_health("balance-a", true, 0.5).unwrap();
+        let (_, first) = sticky.prove_with_source(b"elf", b"1").unwrap();
+        assert_eq!(first, "balance-b");
+        registry::report_backend_health("balance-b", true, 0.9).unwrap();
+        assert_eq!(sticky.prove_with_source(b"elf", b"2").unwrap().1, first);
+        assert_eq!(sticky.prove_with_source(b"other", b"2").unwrap().1, "balance-c");
+
+        registry::report_backend_health(&first, false, 0.0).unwrap();
+        let (proof, moved) = sticky.prove_with_source(b"elf", b"3").unwrap();
+        assert_ne!(moved, first);
+        assert!(sticky.verify(b"elf", &proof).unwrap());
+
+        for id in &ids {
+            registry::unregister_backend(id);
+        }
+    }
+}
@@ -0,0 +1,275 @@
+use crate::binding::{PublicValuesExtractor, check_bindable, check_committed};
+use crate::cancel::{AbortableBackend, CancellationToken, Unabortable, prove_with_cancel};
+use crate::policies::InputPolicies;
+use crate::types::{ProgramHash, ProveRequest, ProverError, VerifyRequest, program_hash};
+use frostgate_zkip::ZkBackend;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+/// Prepares a backend for one program (key setup, artifact loading). Called
+/// once per distinct program in a batch; the result is shared by every
+/// request for that program.
+pub type ProgramSetup = Arc<dyn Fn(&[u8]) -> Result<Arc<dyn AbortableBackend>, ProverError> + Send + Sync>;
+
+/// Proves or verifies many requests concurrently, bounded by a semaphore
+pub struct BatchProver {
+    backend: Arc<dyn AbortableBackend>,
+    setup: Option<ProgramSetup>,
+    permits: Arc<Semaphore>,
+    policies: Option<Arc<InputPolicies>>,
+    extract: Option<PublicValuesExtractor>,
+}
+
+impl BatchProver {
+    /// Run at most `max_concurrent` proofs at a time. Cancelled proofs run
+    /// to the end and keep their permits until then.
+    pub fn new(backend: Arc<dyn ZkBackend>, max_concurrent: usize) -> Self {
+        Self::abortable(Arc::new(Unabortable(backend)), max_concurrent)
+    }
+
+    /// Like [`BatchProver::new`], stopping each cancelled proof at once
+    pub fn abortable(backend: Arc<dyn AbortableBackend>, max_concurrent: usize) -> Self {
+        Self {
+            backend,
+            setup: None,
+            permits: Arc::new(Semaphore::new(max_concurrent.max(1))),
+            policies: None,
+            extract: None,
+        }
+    }
+
+    /// Share a semaphore with other provers so they draw from the same limit
+    pub fn with_semaphore(mut self, permits: Arc<Semaphore>) -> Self {
+        self.permits = permits;
+        self
+    }
+
+    /// Set up each distinct program once per batch instead of once per proof
+    pub fn with_setup(mut self, setup: ProgramSetup) -> Self {
+        self.setup = Some(setup);
+        self
+    }
+
+    /// Reject requests that break their program's input policy before
+    /// they take a permit
+    pub fn with_policies(mut self, policies: Arc<InputPolicies>) -> Self {
+        self.policies = Some(policies);
+        self
+    }
+
+    /// Require proofs of requests with public inputs to commit to exactly
+    /// those, read with `extract`. Without it such requests are rejected.
+    pub fn with_extractor(mut self, extract: PublicValuesExtractor) -> Self {
+        self.extract = Some(extract);
+        self
+    }
+
+    /// Prove every request and return the results in request order
+    pub async fn prove_batch(&self, requests: &[ProveRequest]) -> Vec<Result<Vec<u8>, ProverError>> {
+        self.prove_batch_with_cancel(requests, CancellationToken::new()).await
+    }
+
+    /// Like [`BatchProver::prove_batch`], but requests still queued or
+    /// running when `token` is cancelled fail with [`ProverError::Cancelled`]
+    pub async fn prove_batch_with_cancel(
+        &self,
+        requests: &[ProveRequest],
+        token: CancellationToken,
+    ) -> Vec<Result<Vec<u8>, ProverError>> {
+        let mut prepared: HashMap<ProgramHash, Result<Arc<dyn AbortableBackend>, String>> = HashMap::new();
+        let mut handles = Vec::with_capacity(requests.len());
+
+        for request in requests {
+            let hash = program_hash(&request.program);
+            let backend = prepared
+                .entry(hash)
+                .or_insert_with(|| self.prepare(&request.program))
+                .clone();
+            let permits = self.permits.clone();
+            let request = request.clone();
+            let token = token.clone();
+            let policies = self.policies.clone();
+            let extract = self.extract.clone();
+            handles.push(tokio::spawn(async move {
+                let backend = backend.map_err(ProverError::Other)?;
+                if let Some(policies) = policies {
+                    policies.check_request(&request)?;
+                }
+                check_bindable(&request.public_inputs, extract.as_ref())?;
+                let permit = tokio::select! {
+                    _ = token.cancelled() => return Err(ProverError::Cancelled),
+                    permit = permits.acquire_owned() => permit
+                        .map_err(|_| ProverError::Other("batch semaphore closed".to_string()))?,
+                };
+                let proof =
+                    prove_with_cancel(backend, request.program, request.stdin_inputs, token, Some(permit)).await?;
+                check_committed(&request.public_inputs, &proof, extract.as_ref())?;
+                Ok(proof)
+            }));
+        }
+
+        let mut results = Vec::with_capacity(handles.len());
+        for handle in handles {
+            results.push(
+                handle
+                    .await
+                    .unwrap_or_else(|e| Err(ProverError::Other(format!("batch task failed: {}", e)))),
+            );
+        }
+        results
+    }
+
+    /// Verify every proof and return the results in request order. Proofs
+    /// for the same program share one prepared backend, so verifier
+    /// artifacts are loaded once per program rather than once per proof.
+    pub async fn verify_batch(&self, requests: &[VerifyRequest]) -> Vec<Result<bool, ProverError>> {
+        let mut prepared: HashMap<ProgramHash, Result<Arc<dyn AbortableBackend>, String>> = HashMap::new();
+        let mut handles = Vec::with_capacity(requests.len());
+
+        for request in requests {
+            let backend = prepared
+                .entry(request.program_hash())
+                .or_insert_with(|| self.prepare(&request.program))
+                .clone();
+            let permits = self.permits.clone();
+            let request = request.clone();
+            handles.push(tokio::spawn(async move {
+                let backend = backend.map_err(ProverError::Other)?;
+                let _permit = permits
+                    .acquire_owned()
+                    .await
+                    .map_err(|_| ProverError::Other("batch semaphore closed".to_string()))?;
+                tokio::task::spawn_blocking(move || backend.verify(&request.program, &request.proof))
+                    .await
+                    .map_err(|e| ProverError::Other(format!("verify task failed: {}", e)))?
+                    .map_err(ProverError::from)
+            }));
+        }
+
+        let mut results = Vec::with_capacity(handles.len());
+        for handle in handles {
+            results.push(
+                handle
+                    .await
+                    .unwrap_or_else(|e| Err(ProverError::Other(format!("batch task failed: {}", e)))),
+            );
+        }
+        results
+    }
+
+    fn prepare(&self, program: &[u8]) -> Result<Arc<dyn AbortableBackend>, String> {
+        match &self.setup {
+            Some(setup) => setup(program).map_err(|e| format!("program setup failed: {:?}", e)),
+            None => Ok(self.backend.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policies::InputPolicy;
+    use crate::testing::{MockBackend, mock_public_values};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_results_in_order_with_shared_setup() {
+        let backend: Arc<dyn ZkBackend> = Arc::new(MockBackend::default());
+        let setups = Arc::new(AtomicUsize::new(0));
+        let counter = setups.clone();
+        let shared: Arc<dyn AbortableBackend> = Arc::new(Unabortable(backend.clone()));
+        let prover = BatchProver::new(backend.clone(), 2).with_setup(Arc::new(move |_program| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok(shared.clone())
+        }));
+
+        let requests = vec![
+            ProveRequest::new(b"a".to_vec(), b"1".to_vec()),
+            ProveRequest::new(b"b".to_vec(), b"2".to_vec()),
+            ProveRequest::new(b"a".to_vec(), b"3".to_vec()),
+        ];
+        let results = prover.prove_batch(&requests).await;
+
+        assert_eq!(setups.load(Ordering::SeqCst), 2);
+        for (request, result) in requests.iter().zip(results) {
+            assert_eq!(result.unwrap(), backend.prove(&request.program, &request.stdin_inputs).unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_policies_checked_per_request() {
+        let policies = Arc::new(InputPolicies::new());
+        policies.set(
+            program_hash(b"a"),
+            InputPolicy {
+                max_stdin_bytes: Some(2),
+                ..InputPolicy::default()
+            },
+        );
+        let prover = BatchProver::new(Arc::new(MockBackend::default()), 2).with_policies(policies);
+
+        let results = prover
+            .prove_batch(&[
+                ProveRequest::new(b"a".to_vec(), b"1".to_vec()),
+                ProveRequest::new(b"a".to_vec(), b"123".to_vec()),
+                ProveRequest::new(b"b".to_vec(), b"123".to_vec()),
+            ])
+            .await;
+
+        assert!(results[0].is_ok());
+        assert!(matches!(results[1], Err(ProverError::PolicyViolation(_))));
+        assert!(results[2].is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_public_inputs_bound() {
+        // Guest committing its input
+        let guest = Arc::new(MockBackend::default().with_guest(Arc::new(|_, input| Ok(input.to_vec()))));
+        let requests = [
+            ProveRequest::new(b"a".to_vec(), b"1".to_vec()).with_public_inputs(b"1".to_vec()),
+            ProveRequest::new(b"a".to_vec(), b"1".to_vec()).with_public_inputs(b"2".to_vec()),
+            ProveRequest::new(b"a".to_vec(), b"1".to_vec()),
+        ];
+
+        let bound = BatchProver::new(guest.clone(), 2).with_extractor(mock_public_values());
+        let results = bound.prove_batch(&requests).await;
+        assert!(results[0].is_ok());
+        assert!(matches!(results[1], Err(ProverError::PublicInputsMismatch)));
+        assert!(results[2].is_ok());
+
+        // Without an extractor, declared public inputs can't be honoured
+        let unbound = BatchProver::new(guest.clone(), 2);
+        let results = unbound.prove_batch(&requests).await;
+        assert!(matches!(results[0], Err(ProverError::PolicyViolation(_))));
+        assert!(results[2].is_ok());
+        assert_eq!(guest.prove_calls(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_verify_batch() {
+        let backend: Arc<dyn ZkBackend> = Arc::new(MockBackend::default());
+        let setups = Arc::new(AtomicUsize::new(0));
+        let counter = setups.clone();
+        let shared: Arc<dyn AbortableBackend> = Arc::new(Unabortable(backend.clone()));
+        let prover = BatchProver::new(backend.clone(), 4).with_setup(Arc::new(move |_program| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok(shared.clone())
+        }));
+
+        let requests = vec![
+            VerifyRequest::new(b"a".to_vec(), backend.prove(b"a", b"1").unwrap()),
+            VerifyRequest::new(b"b".to_vec(), backend.prove(b"b", b"2").unwrap()),
+            VerifyRequest::new(b"a".to_vec(), b"garbage".to_vec()),
+        ];
+        let results: Vec<bool> = prover
+            .verify_batch(&requests)
+            .await
+            .into_iter()
+            .map(|r| r.unwrap_or(false))
+            .collect();
+
+        assert_eq!(setups.load(Ordering::SeqCst), 2);
+        assert_eq!(results, vec![true, true, false]);
+    }
+}
@@ -0,0 +1,146 @@
+use clap::Parser;
+use frostgate_prover::admission::AdmissionMiddleware;
+use frostgate_prover::config::{ConfigLayer, ProverConfig};
+use frostgate_prover::isolation::IsolatedBackend;
+use frostgate_prover::memory::MemoryBudgetBackend;
+use frostgate_prover::program_limits::ProgramLimitMiddleware;
+use frostgate_prover::quotas::TenantQuotas;
+use frostgate_prover::registry;
+use frostgate_prover::server_state::ServerState;
+use frostgate_prover::service::ProverService;
+use frostgate_prover::signatures::SignatureMiddleware;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[derive(Parser)]
+#[command(name = "frostgate-proverd", version, about = "Frostgate prover gRPC service")]
+struct Cli {
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:50051")]
+    listen: SocketAddr,
+    /// Config file, layered under `FROSTGATE_*` variables; defaults to
+    /// prover.toml if it exists. Its key holder is registered under its
+    /// `backend` id.
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// Further backend to register, as `id=/path/to/key-holder`; may be repeated
+    #[arg(long = "backend", value_parser = parse_backend)]
+    backends: Vec<(String, PathBuf)>,
+    /// Largest program accepted by SubmitProgram, in bytes
+    #[arg(long, default_value_t = 64 * 1024 * 1024)]
+    max_program_bytes: usize,
+    /// Also serve the REST API on this address
+    #[cfg(feature = "rest")]
+    #[arg(long)]
+    http: Option<SocketAddr>,
+}
+
+fn parse_backend(s: &str) -> Result<(String, PathBuf), String> {
+    let (id, path) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected id=/path/to/key-holder, got '{}'", s))?;
+    Ok((id.to_string(), PathBuf::from(path)))
+}
+
+/// Log to stderr at the levels in `RUST_LOG`, `info` by default
+fn init_tracing() {
+    let filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info"));
+    tracing_subscriber::fmt().with_env_filter(filter).with_writer(std::io::stderr).init();
+}
+
+/// Launch the key holder `config` names and register it as `id`, within
+/// the configured memory budget
+fn register_key_holder(id: &str, config: &ProverConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let key_holder = IsolatedBackend::spawn(&config.key_holder_config()?).map_err(|e| format!("backend {}: {:?}", id, e))?;
+    let key_holder = Arc::new(key_holder);
+    match config.memory_budget() {
+        Some(budget) => registry::register_backend(
+            id.to_string(),
+            Arc::new(MemoryBudgetBackend::for_key_holder(key_holder, budget)),
+        )?,
+        None => registry::register_backend(id.to_string(), key_holder)?,
+    }
+    tracing::info!("registered backend {}", id);
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+    let config = ProverConfig::load(cli.config.as_deref(), ConfigLayer::default())?;
+    #[cfg(feature = "otel")]
+    let _otlp = match frostgate_prover::telemetry::init_otlp_from_config("frostgate-proverd", &config)? {
+        Some(guard) => Some(guard),
+        None => {
+            init_tracing();
+            None
+        }
+    };
+    #[cfg(not(feature = "otel"))]
+    {
+        init_tracing();
+        if let Some(endpoint) = &config.otlp_endpoint {
+            tracing::warn!("built without the otel feature, not exporting spans to {}", endpoint);
+        }
+    }
+
+    // Middleware applies to every backend, whenever it was registered. The
+    // first added is outermost, so bad signatures never take an admission slot
+    // and requests waiting on their program's limit don't hold one meanwhile.
+    let signatures = config.program_signatures()?;
+    if let Some(signatures) = &signatures {
+        registry::add_backend_middleware(Arc::new(SignatureMiddleware::new(signatures.clone())));
+    }
+    if let Some(limiter) = config.program_limiter() {
+        registry::add_backend_middleware(Arc::new(ProgramLimitMiddleware::new(limiter)));
+    }
+    registry::add_backend_middleware(Arc::new(AdmissionMiddleware::new().with_default(config.admission_config())));
+
+    if config.key_holder.is_some() {
+        register_key_holder(&config.backend, &config)?;
+    }
+    for (id, program) in cli.backends {
+        let backend_config = ProverConfig {
+            key_holder: Some(program),
+            key_holder_args: Vec::new(),
+            ..config.clone()
+        };
+        register_key_holder(&id, &backend_config)?;
+    }
+
+    // Both APIs share one state, so jobs submitted through either can be
+    // followed through the other
+    let mut state = ServerState::new().with_max_program_bytes(cli.max_program_bytes);
+    if let Some(access) = config.access_control() {
+        state = state.with_access_control(access);
+    }
+    if let Some(quotas) = config.quota_manager() {
+        state = state.with_quotas(TenantQuotas::new(quotas));
+    }
+    if let Some(signatures) = signatures {
+        state = state.with_signatures(signatures);
+    }
+    let state = Arc::new(state);
+
+    #[cfg(feature = "rest")]
+    if let Some(http) = cli.http {
+        let listener = tokio::net::TcpListener::bind(http).await?;
+        let server = frostgate_prover::rest::RestServer::with_state(state.clone());
+        tracing::info!("serving REST API on {}", http);
+        tokio::spawn(async move {
+            if let Err(e) = server.serve(listener).await {
+                tracing::error!("REST API stopped: {}", e);
+            }
+        });
+    }
+
+    let service = ProverService::with_state(state);
+    tracing::info!("listening on {}", cli.listen);
+    tonic::transport::Server::builder()
+        .add_service(service.into_server())
+        .serve(cli.listen)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,142 @@
+use crate::types::{ProveRequest, ProverError, ct_eq};
+use frostgate_zkip::{ZkBackend, ZkError};
+use std::sync::Arc;
+
+/// Extracts the public values a proof commits to
+pub type PublicValuesExtractor = Arc<dyn Fn(&[u8]) -> Result<Vec<u8>, ZkError> + Send + Sync>;
+
+/// Verify `proof` and require that it commits to exactly `public_inputs`.
+///
+/// The comparison runs before the backend's verifier, so a proof for the
+/// right program but the wrong statement is rejected without paying for a
+/// full verification.
+pub fn verify_with_public_inputs(
+    backend: &dyn ZkBackend,
+    program: &[u8],
+    proof: &[u8],
+    public_inputs: &[u8],
+    extract: &PublicValuesExtractor,
+) -> Result<bool, ProverError> {
+    let committed = extract(proof)?;
+    if !ct_eq(&committed, public_inputs) {
+        tracing::warn!(
+            "public inputs mismatch: proof commits to {} bytes, caller supplied {}",
+            committed.len(),
+            public_inputs.len()
+        );
+        return Err(ProverError::PublicInputsMismatch);
+    }
+    Ok(backend.verify(program, proof)?)
+}
+
+/// Prove `request` with its stdin inputs and, if it carries public inputs,
+/// require the resulting proof to commit to exactly those values
+pub fn prove_with_public_inputs(
+    backend: &dyn ZkBackend,
+    request: &ProveRequest,
+    extract: &PublicValuesExtractor,
+) -> Result<Vec<u8>, ProverError> {
+    let proof = backend.prove(&request.program, &request.stdin_inputs)?;
+    check_committed(&request.public_inputs, &proof, Some(extract))?;
+    Ok(proof)
+}
+
+/// Reject a request declaring `public_inputs` before proving it, when
+/// there is no extractor to bind them with
+pub fn check_bindable(public_inputs: &[u8], extract: Option<&PublicValuesExtractor>) -> Result<(), ProverError> {
+    if !public_inputs.is_empty() && extract.is_none() {
+        return Err(ProverError::PolicyViolation(
+            "request declares public inputs, but no extractor is configured to bind them".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Require `proof` to commit to exactly `public_inputs`, unless they are
+/// empty
+pub fn check_committed(
+    public_inputs: &[u8],
+    proof: &[u8],
+    extract: Option<&PublicValuesExtractor>,
+) -> Result<(), ProverError> {
+    check_bindable(public_inputs, extract)?;
+    let Some(extract) = extract.filter(|_| !public_inputs.is_empty()) else {
+        return Ok(());
+    };
+    if !ct_eq(&extract(proof)?, public_inputs) {
+        tracing::warn!("proof commits to different public values than requested");
+        return Err(ProverError::PublicInputsMismatch);
+    }
+    Ok(())
+}
+
+/// Backend paired with the extractor for its proof format
+pub struct BoundVerifier {
+    backend: Arc<dyn ZkBackend>,
+    extract: PublicValuesExtractor,
+}
+
+impl BoundVerifier {
+    pub fn new(backend: Arc<dyn ZkBackend>, extract: PublicValuesExtractor) -> Self {
+        Self { backend, extract }
+    }
+
+    /// Prove `request`, binding the proof to its public inputs
+    pub fn prove(&self, request: &ProveRequest) -> Result<Vec<u8>, ProverError> {
+        prove_with_public_inputs(self.backend.as_ref(), request, &self.extract)
+    }
+
+    /// Verify `proof`, binding it to `public_inputs`
+    pub fn verify(&self, program: &[u8], proof: &[u8], public_inputs: &[u8]) -> Result<bool, ProverError> {
+        verify_with_public_inputs(self.backend.as_ref(), program, proof, public_inputs, &self.extract)
+    }
+
+    /// Public values committed by `proof`
+    pub fn public_values(&self, proof: &[u8]) -> Result<Vec<u8>, ProverError> {
+        Ok((self.extract)(proof)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockBackend, mock_public_values};
+
+    /// Guest that commits the first byte of its input, or nothing
+    fn bound_verifier() -> (Arc<MockBackend>, BoundVerifier) {
+        let guest = MockBackend::default().with_guest(Arc::new(|_, input| Ok(input.iter().take(1).copied().collect())));
+        let backend = Arc::new(guest);
+        (backend.clone(), BoundVerifier::new(backend, mock_public_values()))
+    }
+
+    fn request(input: &[u8], public_inputs: &[u8]) -> ProveRequest {
+        ProveRequest::new(b"elf".to_vec(), input.to_vec()).with_public_inputs(public_inputs.to_vec())
+    }
+
+    #[test]
+    fn test_mismatched_commitment() {
+        let (backend, verifier) = bound_verifier();
+        let proof = verifier.prove(&request(b"ab", b"a")).unwrap();
+        assert_eq!(verifier.public_values(&proof).unwrap(), b"a");
+        assert!(verifier.verify(b"elf", &proof, b"a").unwrap());
+
+        assert!(matches!(verifier.verify(b"elf", &proof, b"b"), Err(ProverError::PublicInputsMismatch)));
+        // Rejected before the backend's verifier runs
+        assert_eq!(backend.verify_calls(), 1);
+        assert!(matches!(verifier.prove(&request(b"ab", b"b")), Err(ProverError::PublicInputsMismatch)));
+    }
+
+    #[test]
+    fn test_empty_commitment() {
+        let (_, verifier) = bound_verifier();
+        let proof = verifier.prove(&request(&[], &[])).unwrap();
+        assert!(verifier.public_values(&proof).unwrap().is_empty());
+        assert!(verifier.verify(b"elf", &proof, &[]).unwrap());
+        assert!(matches!(verifier.verify(b"elf", &proof, b"a"), Err(ProverError::PublicInputsMismatch)));
+
+        // A proof committing values doesn't pass for one committing none
+        let committing = verifier.prove(&request(b"a", &[])).unwrap();
+        assert!(matches!(verifier.verify(b"elf", &committing, &[]), Err(ProverError::PublicInputsMismatch)));
+        assert!(matches!(verifier.prove(&request(&[], b"a")), Err(ProverError::PublicInputsMismatch)));
+    }
+}
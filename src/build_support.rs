@@ -0,0 +1,167 @@
+//! Helpers for downstream `build.rs` scripts that compile SP1 guest programs.
+//!
+//! ```ignore
+//! // build.rs
+//! let artifact = frostgate_prover::build_support::build_guest(&GuestBuildConfig::new("../guest", "guest"))?;
+//! frostgate_prover::build_support::write_program_constants(&[("GUEST", &artifact)], "guest_programs.rs")?;
+//!
+//! // lib.rs
+//! include!(concat!(env!("OUT_DIR"), "/guest_programs.rs"));
+//! ```
+
+use crate::types::{ProgramHash, ProverError, program_hash};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// How to build one guest crate
+#[derive(Debug, Clone)]
+pub struct GuestBuildConfig {
+    /// Directory containing the guest's Cargo.toml
+    pub crate_dir: PathBuf,
+    /// Name of the produced ELF
+    pub elf_name: String,
+    /// Where the ELF is written; defaults to `$OUT_DIR/guests`
+    pub output_dir: Option<PathBuf>,
+    pub features: Vec<String>,
+    /// Build inside SP1's docker image for reproducible ELFs
+    pub docker: bool,
+}
+
+impl GuestBuildConfig {
+    pub fn new(crate_dir: impl Into<PathBuf>, elf_name: &str) -> Self {
+        Self {
+            crate_dir: crate_dir.into(),
+            elf_name: elf_name.to_string(),
+            output_dir: None,
+            features: Vec::new(),
+            docker: false,
+        }
+    }
+}
+
+/// A compiled guest program
+#[derive(Debug, Clone)]
+pub struct GuestArtifact {
+    pub elf_path: PathBuf,
+    pub program_hash: ProgramHash,
+}
+
+/// Compile a guest crate with `cargo prove build` and hash the resulting ELF
+pub fn build_guest(config: &GuestBuildConfig) -> Result<GuestArtifact, ProverError> {
+    let output_dir = match &config.output_dir {
+        Some(dir) => dir.clone(),
+        None => out_dir()?.join("guests"),
+    };
+    fs::create_dir_all(&output_dir)?;
+
+    println!("cargo:rerun-if-changed={}", config.crate_dir.join("src").display());
+    println!("cargo:rerun-if-changed={}", config.crate_dir.join("Cargo.toml").display());
+
+    let mut command = Command::new("cargo");
+    command
+        .args(["prove", "build", "--elf-name", &config.elf_name, "--output-directory"])
+        .arg(&output_dir)
+        .current_dir(&config.crate_dir)
+        // The outer build's flags target the host, not the guest
+        .env_remove("RUSTFLAGS")
+        .env_remove("CARGO_ENCODED_RUSTFLAGS");
+    if !config.features.is_empty() {
+        command.args(["--features", &config.features.join(",")]);
+    }
+    if config.docker {
+        command.arg("--docker");
+    }
+
+    let status = command.status()?;
+    if !status.success() {
+        return Err(ProverError::Other(format!(
+            "guest build in {} failed with {}",
+            config.crate_dir.display(),
+            status
+        )));
+    }
+
+    let elf_path = output_dir.join(&config.elf_name);
+    let elf = fs::read(&elf_path)?;
+    Ok(GuestArtifact {
+        elf_path,
+        program_hash: program_hash(&elf),
+    })
+}
+
+/// Write `{NAME}_ELF` and `{NAME}_PROGRAM_HASH` constants for each artifact
+/// to `$OUT_DIR/file_name`
+pub fn write_program_constants(artifacts: &[(&str, &GuestArtifact)], file_name: &str) -> Result<PathBuf, ProverError> {
+    let path = out_dir()?.join(file_name);
+    fs::write(&path, program_constants(artifacts))?;
+    Ok(path)
+}
+
+fn program_constants(artifacts: &[(&str, &GuestArtifact)]) -> String {
+    let mut source = String::from("// @generated by frostgate_prover::build_support\n");
+    for (name, artifact) in artifacts {
+        let name = name.to_uppercase();
+        let _ = writeln!(
+            source,
+            "pub const {}_ELF: &[u8] = include_bytes!({:?});",
+            name,
+            artifact.elf_path.display().to_string()
+        );
+        let _ = writeln!(
+            source,
+            "pub const {}_PROGRAM_HASH: &str = {:?};",
+            name, artifact.program_hash
+        );
+    }
+    source
+}
+
+fn out_dir() -> Result<PathBuf, ProverError> {
+    std::env::var_os("OUT_DIR")
+        .map(PathBuf::from)
+        .ok_or_else(|| ProverError::Other("OUT_DIR is not set; call from a build script".to_string()))
+}
+
+/// Hash of an ELF already on disk
+pub fn hash_elf(path: &Path) -> Result<ProgramHash, ProverError> {
+    Ok(program_hash(&fs::read(path)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn artifact(elf_path: &str, elf: &[u8]) -> GuestArtifact {
+        GuestArtifact {
+            elf_path: PathBuf::from(elf_path),
+            program_hash: program_hash(elf),
+        }
+    }
+
+    #[test]
+    fn test_program_constants() {
+        let bridge = artifact("/out/guests/bridge", b"bridge elf");
+        let oracle = artifact("/out/guests/oracle", b"oracle elf");
+        let source = program_constants(&[("bridge", &bridge), ("Oracle", &oracle)]);
+
+        let expected = format!(
+            "// @generated by frostgate_prover::build_support\n\
+             pub const BRIDGE_ELF: &[u8] = include_bytes!(\"/out/guests/bridge\");\n\
+             pub const BRIDGE_PROGRAM_HASH: &str = \"{}\";\n\
+             pub const ORACLE_ELF: &[u8] = include_bytes!(\"/out/guests/oracle\");\n\
+             pub const ORACLE_PROGRAM_HASH: &str = \"{}\";\n",
+            bridge.program_hash, oracle.program_hash
+        );
+        assert_eq!(source, expected);
+    }
+
+    #[test]
+    fn test_program_constants_escape_paths() {
+        // Windows paths and quotes must stay valid string literals
+        let source = program_constants(&[("guest", &artifact(r#"C:\out\"guests"\guest"#, b"elf"))]);
+        assert!(source.contains(r#"include_bytes!("C:\\out\\\"guests\"\\guest")"#));
+        assert_eq!(program_constants(&[]), "// @generated by frostgate_prover::build_support\n");
+    }
+}
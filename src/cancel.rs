@@ -0,0 +1,228 @@
+use crate::types::ProverError;
+use frostgate_zkip::{ZkBackend, ZkError};
+use std::sync::{Arc, Mutex, PoisonError};
+use tokio::sync::OwnedSemaphorePermit;
+
+pub use tokio_util::sync::CancellationToken;
+
+/// Stops work in flight on a backend, e.g. by killing the process it runs in
+pub type Abort = Arc<dyn Fn() + Send + Sync>;
+
+/// Backend whose calls can be stopped one at a time. `started` is called
+/// once a call is running, not while it waits its turn, with an [`Abort`]
+/// that stops that call and none queued behind it.
+pub trait AbortableBackend: ZkBackend {
+    fn prove_abortable(
+        &self,
+        program: &[u8],
+        input: &[u8],
+        started: &(dyn Fn(Abort) + Sync),
+    ) -> Result<Vec<u8>, ZkError>;
+
+    fn verify_abortable(
+        &self,
+        program: &[u8],
+        proof: &[u8],
+        started: &(dyn Fn(Abort) + Sync),
+    ) -> Result<bool, ZkError>;
+}
+
+/// Calls on a backend that can't stop them. They are never reported as
+/// started, so cancelling one leaves it running to the end.
+pub struct Unabortable(pub Arc<dyn ZkBackend>);
+
+impl ZkBackend for Unabortable {
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        self.0.prove(program, input)
+    }
+
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        self.0.verify(program, proof)
+    }
+}
+
+impl AbortableBackend for Unabortable {
+    fn prove_abortable(
+        &self,
+        program: &[u8],
+        input: &[u8],
+        _started: &(dyn Fn(Abort) + Sync),
+    ) -> Result<Vec<u8>, ZkError> {
+        self.0.prove(program, input)
+    }
+
+    fn verify_abortable(
+        &self,
+        program: &[u8],
+        proof: &[u8],
+        _started: &(dyn Fn(Abort) + Sync),
+    ) -> Result<bool, ZkError> {
+        self.0.verify(program, proof)
+    }
+}
+
+/// Stops one call of an [`AbortableBackend`], whether it is running yet or
+/// still waiting its turn
+#[derive(Clone, Default)]
+struct CallAbort(Arc<Mutex<CallState>>);
+
+#[derive(Default)]
+struct CallState {
+    abort: Option<Abort>,
+    aborted: bool,
+}
+
+impl CallAbort {
+    fn started(&self, abort: Abort) {
+        let mut state = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if state.aborted {
+            abort();
+        }
+        state.abort = Some(abort);
+    }
+
+    fn abort(&self) {
+        let mut state = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        state.aborted = true;
+        if let Some(abort) = &state.abort {
+            abort();
+        }
+    }
+}
+
+/// Aborts the call unless disarmed by its completion
+struct AbortOnDrop(Option<CallAbort>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        if let Some(call) = self.0.take() {
+            call.abort();
+        }
+    }
+}
+
+/// Prove on a blocking thread, returning [`ProverError::Cancelled`] as soon
+/// as `token` is cancelled.
+///
+/// Cancelling `token` or dropping the future stops this prove only: one
+/// still queued on the backend stops as it starts, and others sharing the
+/// backend are untouched. `permit`, if given, is held for as long as the
+/// backend is busy, so a prove that can't be stopped (see [`Unabortable`])
+/// keeps it until it ends and no other proof is started on top of it.
+pub async fn prove_with_cancel(
+    backend: Arc<dyn AbortableBackend>,
+    program: Vec<u8>,
+    input: Vec<u8>,
+    token: CancellationToken,
+    permit: Option<OwnedSemaphorePermit>,
+) -> Result<Vec<u8>, ProverError> {
+    if token.is_cancelled() {
+        return Err(ProverError::Cancelled);
+    }
+    let call = CallAbort::default();
+    let started = call.clone();
+    let task = tokio::task::spawn_blocking(move || {
+        let _permit = permit;
+        backend.prove_abortable(&program, &input, &|abort| started.started(abort))
+    });
+    let mut guard = AbortOnDrop(Some(call));
+    tokio::select! {
+        _ = token.cancelled() => Err(ProverError::Cancelled),
+        joined = task => {
+            guard.0 = None;
+            joined
+                .map_err(|e| ProverError::Other(format!("proving task failed: {}", e)))?
+                .map_err(ProverError::from)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::isolation::IsolatedBackend;
+    use crate::testing::{MockBackend, MockConfig, stalling_key_holder};
+    use std::time::{Duration, Instant};
+    use tokio::sync::Semaphore;
+
+    fn cancel_after(token: &CancellationToken, delay: Duration) {
+        let canceller = token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            canceller.cancel();
+        });
+    }
+
+    #[tokio::test]
+    async fn test_cancel_keeps_permit_until_prove_ends() {
+        let backend = Arc::new(Unabortable(Arc::new(MockBackend::new(MockConfig {
+            prove_latency: Duration::from_millis(300),
+            ..MockConfig::default()
+        }))));
+        let permits = Arc::new(Semaphore::new(1));
+        let permit = permits.clone().acquire_owned().await.unwrap();
+        let token = CancellationToken::new();
+
+        cancel_after(&token, Duration::from_millis(20));
+        let start = Instant::now();
+        let result = prove_with_cancel(backend, b"elf".to_vec(), b"input".to_vec(), token, Some(permit)).await;
+
+        assert!(matches!(result, Err(ProverError::Cancelled)));
+        assert!(start.elapsed() < Duration::from_millis(300));
+        assert_eq!(permits.available_permits(), 0);
+        let _permit = permits.acquire().await.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(300));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_aborts_key_holder() {
+        let key_holder = Arc::new(IsolatedBackend::spawn(&stalling_key_holder()).unwrap());
+        let permits = Arc::new(Semaphore::new(1));
+        let permit = permits.clone().acquire_owned().await.unwrap();
+        let token = CancellationToken::new();
+
+        cancel_after(&token, Duration::from_millis(20));
+        let result = prove_with_cancel(key_holder, b"elf".to_vec(), b"input".to_vec(), token, Some(permit)).await;
+
+        assert!(matches!(result, Err(ProverError::Cancelled)));
+        // The killed prove gives its permit back promptly
+        let _permit = tokio::time::timeout(Duration::from_secs(5), permits.acquire()).await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_dropped_prove_aborts() {
+        let key_holder = Arc::new(IsolatedBackend::spawn(&stalling_key_holder()).unwrap());
+        let permits = Arc::new(Semaphore::new(1));
+        let permit = permits.clone().acquire_owned().await.unwrap();
+
+        let token = CancellationToken::new();
+        let prove = prove_with_cancel(key_holder, b"elf".to_vec(), b"input".to_vec(), token, Some(permit));
+        assert!(tokio::time::timeout(Duration::from_millis(20), prove).await.is_err());
+        let _permit = tokio::time::timeout(Duration::from_secs(5), permits.acquire()).await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cancel_spares_other_requests() {
+        let key_holder = Arc::new(IsolatedBackend::spawn(&stalling_key_holder()).unwrap());
+        let permits = Arc::new(Semaphore::new(2));
+        let prove = |token: &CancellationToken, permit| {
+            let backend = key_holder.clone();
+            tokio::spawn(prove_with_cancel(backend, b"elf".to_vec(), b"input".to_vec(), token.clone(), Some(permit)))
+        };
+        let (running, queued) = (CancellationToken::new(), CancellationToken::new());
+        let first = prove(&running, permits.clone().acquire_owned().await.unwrap());
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let second = prove(&queued, permits.clone().acquire_owned().await.unwrap());
+
+        // Cancelling the queued request leaves the running one alone
+        queued.cancel();
+        assert!(matches!(second.await.unwrap(), Err(ProverError::Cancelled)));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!first.is_finished());
+
+        // The queued prove stops as soon as it gets its turn
+        running.cancel();
+        assert!(matches!(first.await.unwrap(), Err(ProverError::Cancelled)));
+        let _permits = tokio::time::timeout(Duration::from_secs(5), permits.acquire_many(2)).await.unwrap().unwrap();
+    }
+}
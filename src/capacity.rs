@@ -0,0 +1,287 @@
+use crate::types::ProverError;
+use std::sync::{Arc, Mutex, PoisonError};
+use tokio::sync::Notify;
+
+/// Proving capacity needed by a job or batch
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResourceRequirements {
+    pub permits: u32,
+    pub memory_bytes: u64,
+}
+
+impl ResourceRequirements {
+    pub fn new(permits: u32, memory_bytes: u64) -> Self {
+        Self { permits, memory_bytes }
+    }
+
+    fn fits_in(&self, other: &ResourceRequirements) -> bool {
+        self.permits <= other.permits && self.memory_bytes <= other.memory_bytes
+    }
+
+    fn take(&mut self, amount: &ResourceRequirements) {
+        self.permits -= amount.permits;
+        self.memory_bytes -= amount.memory_bytes;
+    }
+
+    fn give(&mut self, amount: &ResourceRequirements) {
+        self.permits += amount.permits;
+        self.memory_bytes += amount.memory_bytes;
+    }
+}
+
+/// Shared proving capacity: concurrency permits plus a memory budget.
+///
+/// Opportunistic work leases directly from the pool; scheduled high-priority
+/// work reserves capacity up front and leases from its [`Reservation`], so
+/// other traffic can't starve it.
+pub struct CapacityPool {
+    total: ResourceRequirements,
+    free: Mutex<ResourceRequirements>,
+    released: Notify,
+}
+
+impl CapacityPool {
+    pub fn new(total: ResourceRequirements) -> Arc<Self> {
+        Arc::new(Self {
+            total,
+            free: Mutex::new(total),
+            released: Notify::new(),
+        })
+    }
+
+    pub fn total(&self) -> ResourceRequirements {
+        self.total
+    }
+
+    /// Capacity neither leased nor reserved
+    pub fn available(&self) -> ResourceRequirements {
+        *self.free.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Lease capacity if it is free right now
+    pub fn try_acquire(self: &Arc<Self>, req: ResourceRequirements) -> Option<Lease> {
+        self.try_take(&req).then(|| Lease {
+            amount: req,
+            source: LeaseSource::Pool(self.clone()),
+        })
+    }
+
+    /// Lease capacity, waiting for it to become free
+    pub async fn acquire(self: &Arc<Self>, req: ResourceRequirements) -> Result<Lease, ProverError> {
+        self.check_satisfiable(&req)?;
+        loop {
+            let released = self.released.notified();
+            if let Some(lease) = self.try_acquire(req) {
+                return Ok(lease);
+            }
+            released.await;
+        }
+    }
+
+    /// Set aside capacity for an upcoming batch, failing if it isn't free now
+    pub fn reserve(self: &Arc<Self>, req: ResourceRequirements) -> Result<Reservation, ProverError> {
+        self.check_satisfiable(&req)?;
+        if !self.try_take(&req) {
+            return Err(ProverError::InsufficientCapacity(format!(
+                "cannot reserve {:?}, only {:?} is free",
+                req,
+                self.available()
+            )));
+        }
+        Ok(self.reservation(req))
+    }
+
+    /// Set aside capacity for an upcoming batch, waiting for it to become free
+    pub async fn reserve_wait(self: &Arc<Self>, req: ResourceRequirements) -> Result<Reservation, ProverError> {
+        self.check_satisfiable(&req)?;
+        loop {
+            let released = self.released.notified();
+            if self.try_take(&req) {
+                return Ok(self.reservation(req));
+            }
+            released.await;
+        }
+    }
+
+    fn reservation(self: &Arc<Self>, req: ResourceRequirements) -> Reservation {
+        Reservation {
+            inner: Arc::new(ReservationInner {
+                pool: self.clone(),
+                reserved: req,
+                free: Mutex::new(req),
+                released: Notify::new(),
+            }),
+        }
+    }
+
+    fn check_satisfiable(&self, req: &ResourceRequirements) -> Result<(), ProverError> {
+        if req.fits_in(&self.total) {
+            Ok(())
+        } else {
+            Err(ProverError::InsufficientCapacity(format!(
+                "{:?} exceeds total capacity {:?}",
+                req, self.total
+            )))
+        }
+    }
+
+    fn try_take(&self, req: &ResourceRequirements) -> bool {
+        let mut free = self.free.lock().unwrap_or_else(PoisonError::into_inner);
+        if req.fits_in(&free) {
+            free.take(req);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn give_back(&self, amount: &ResourceRequirements) {
+        self.free
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .give(amount);
+        self.released.notify_waiters();
+    }
+}
+
+struct ReservationInner {
+    pool: Arc<CapacityPool>,
+    reserved: ResourceRequirements,
+    free: Mutex<ResourceRequirements>,
+    released: Notify,
+}
+
+impl Drop for ReservationInner {
+    fn drop(&mut self) {
+        self.pool.give_back(&self.reserved);
+    }
+}
+
+/// Capacity set aside in a [`CapacityPool`]; returned to the pool once the
+/// reservation and all its leases are dropped
+#[derive(Clone)]
+pub struct Reservation {
+    inner: Arc<ReservationInner>,
+}
+
+impl Reservation {
+    /// Total capacity reserved
+    pub fn reserved(&self) -> ResourceRequirements {
+        self.inner.reserved
+    }
+
+    /// Reserved capacity not currently leased
+    pub fn available(&self) -> ResourceRequirements {
+        *self.inner.free.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Lease from the reservation if capacity is free right now
+    pub fn try_acquire(&self, req: ResourceRequirements) -> Option<Lease> {
+        let mut free = self.inner.free.lock().unwrap_or_else(PoisonError::into_inner);
+        if !req.fits_in(&free) {
+            return None;
+        }
+        free.take(&req);
+        Some(Lease {
+            amount: req,
+            source: LeaseSource::Reservation(self.inner.clone()),
+        })
+    }
+
+    /// Lease from the reservation, waiting for earlier leases to finish
+    pub async fn acquire(&self, req: ResourceRequirements) -> Result<Lease, ProverError> {
+        if !req.fits_in(&self.inner.reserved) {
+            return Err(ProverError::InsufficientCapacity(format!(
+                "{:?} exceeds reservation {:?}",
+                req, self.inner.reserved
+            )));
+        }
+        loop {
+            let released = self.inner.released.notified();
+            if let Some(lease) = self.try_acquire(req) {
+                return Ok(lease);
+            }
+            released.await;
+        }
+    }
+}
+
+enum LeaseSource {
+    Pool(Arc<CapacityPool>),
+    Reservation(Arc<ReservationInner>),
+}
+
+/// Capacity held by one running job, returned on drop
+pub struct Lease {
+    amount: ResourceRequirements,
+    source: LeaseSource,
+}
+
+impl Lease {
+    pub fn amount(&self) -> ResourceRequirements {
+        self.amount
+    }
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        match &self.source {
+            LeaseSource::Pool(pool) => pool.give_back(&self.amount),
+            LeaseSource::Reservation(reservation) => {
+                reservation
+                    .free
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .give(&self.amount);
+                reservation.released.notify_waiters();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GB: u64 = 1 << 30;
+
+    #[test]
+    fn test_reservation_protects_capacity() {
+        let pool = CapacityPool::new(ResourceRequirements::new(4, 64 * GB));
+        let reservation = pool.reserve(ResourceRequirements::new(2, 32 * GB)).unwrap();
+
+        // Opportunistic traffic only sees the unreserved half
+        let a = pool.try_acquire(ResourceRequirements::new(2, 16 * GB)).unwrap();
+        assert!(pool.try_acquire(ResourceRequirements::new(1, GB)).is_none());
+
+        // Reserved work still gets its capacity
+        let b = reservation.try_acquire(ResourceRequirements::new(2, 32 * GB)).unwrap();
+        assert!(reservation.try_acquire(ResourceRequirements::new(1, GB)).is_none());
+        drop(b);
+        assert_eq!(reservation.available(), reservation.reserved());
+
+        drop(a);
+        drop(reservation);
+        assert_eq!(pool.available(), pool.total());
+    }
+
+    #[test]
+    fn test_lease_outlives_reservation_handle() {
+        let pool = CapacityPool::new(ResourceRequirements::new(2, GB));
+        let reservation = pool.reserve(ResourceRequirements::new(1, GB)).unwrap();
+        let lease = reservation.try_acquire(ResourceRequirements::new(1, GB)).unwrap();
+        drop(reservation);
+        assert_eq!(pool.available(), ResourceRequirements::new(1, 0));
+        drop(lease);
+        assert_eq!(pool.available(), pool.total());
+    }
+
+    #[test]
+    fn test_rejects_impossible_requests() {
+        let pool = CapacityPool::new(ResourceRequirements::new(1, GB));
+        assert!(matches!(
+            pool.reserve(ResourceRequirements::new(2, GB)),
+            Err(ProverError::InsufficientCapacity(_))
+        ));
+    }
+}
@@ -0,0 +1,276 @@
+use crate::aggregation::AggregatedProof;
+use crate::binding::PublicValuesExtractor;
+use crate::envelope::{ProofEnvelope, hex_bytes};
+use crate::types::{ProgramHash, ProverError, ct_eq, program_hash};
+use frostgate_zkip::ZkBackend;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+use std::sync::Arc;
+
+/// Commitment the first link of every chain builds on
+pub const GENESIS_COMMITMENT: [u8; 32] = [0; 32];
+
+/// Metadata key holding a link's position in its chain
+pub const CHAIN_INDEX_KEY: &str = "chain_index";
+
+/// Digest linking `message` at position `index` to the link before it,
+/// whose commitment is `previous`
+pub fn chain_commitment(previous: &[u8], index: u64, message: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha3_256::new();
+    hasher.update(previous);
+    hasher.update(index.to_le_bytes());
+    hasher.update((message.len() as u64).to_le_bytes());
+    hasher.update(message);
+    hasher.finalize().to_vec()
+}
+
+/// Stdin of the chain guest. The guest verifies `previous` recursively,
+/// checks that its public values are `previous_commitment`, and commits
+/// `commitment`, so each proof vouches for the whole chain before it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainInput {
+    pub index: u64,
+    /// The previous link's proof, absent for the first link
+    pub previous: Option<AggregatedProof>,
+    #[serde(with = "hex_bytes")]
+    pub previous_commitment: Vec<u8>,
+    #[serde(with = "hex_bytes")]
+    pub message: Vec<u8>,
+    /// Digest the guest must commit, see [`chain_commitment`]
+    #[serde(with = "hex_bytes")]
+    pub commitment: Vec<u8>,
+}
+
+/// One message and the proof that appends it to the chain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainLink {
+    #[serde(with = "hex_bytes")]
+    pub message: Vec<u8>,
+    pub proof: ProofEnvelope,
+}
+
+/// An ordered, tamper-evident sequence of message proofs, e.g. the messages
+/// a bridge relays from one chain to another. Each link's public values are
+/// a commitment over the previous link's commitment and its own message, so
+/// dropping, reordering or altering a message breaks every later link.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofChain {
+    /// Chain guest every link is proven with
+    pub program_hash: ProgramHash,
+    pub links: Vec<ChainLink>,
+}
+
+impl ProofChain {
+    pub fn new(program_hash: ProgramHash) -> Self {
+        Self {
+            program_hash,
+            links: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.links.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.links.is_empty()
+    }
+
+    /// Commitment of the last link, which the next link builds on
+    pub fn head(&self) -> Vec<u8> {
+        match self.links.last() {
+            Some(link) => link.proof.public_values.clone(),
+            None => GENESIS_COMMITMENT.to_vec(),
+        }
+    }
+}
+
+/// Extends and verifies [`ProofChain`]s by proving a guest that checks the
+/// previous link recursively. `extract` reads the commitment out of the
+/// backend's proofs, so each payload is bound to the link it sits in.
+pub struct ChainProver {
+    backend: Arc<dyn ZkBackend>,
+    backend_id: String,
+    chain_program: Vec<u8>,
+    extract: PublicValuesExtractor,
+}
+
+impl ChainProver {
+    pub fn new(
+        backend: Arc<dyn ZkBackend>,
+        backend_id: &str,
+        chain_program: Vec<u8>,
+        extract: PublicValuesExtractor,
+    ) -> Self {
+        Self {
+            backend,
+            backend_id: backend_id.to_string(),
+            chain_program,
+            extract,
+        }
+    }
+
+    /// An empty chain for this prover's guest
+    pub fn start(&self) -> ProofChain {
+        ProofChain::new(program_hash(&self.chain_program))
+    }
+
+    /// Prove `message` as the next link of `chain` and append it
+    pub fn extend<'a>(&self, chain: &'a mut ProofChain, message: Vec<u8>) -> Result<&'a ChainLink, ProverError> {
+        self.check_program(chain)?;
+        let index = chain.len() as u64;
+        let previous_commitment = chain.head();
+        let commitment = chain_commitment(&previous_commitment, index, &message);
+        let input = ChainInput {
+            index,
+            previous: chain.links.last().map(|link| AggregatedProof {
+                program_hash: link.proof.program_hash.clone(),
+                public_values: link.proof.public_values.clone(),
+                proof: link.proof.payload.clone(),
+            }),
+            previous_commitment,
+            message: message.clone(),
+            commitment: commitment.clone(),
+        };
+        let payload = self
+            .backend
+            .prove(&self.chain_program, &serde_json::to_vec(&input)?)?;
+        if !ct_eq(&(self.extract)(&payload)?, &commitment) {
+            tracing::warn!("chain guest committed something other than link {}'s commitment", index);
+            return Err(ProverError::PublicInputsMismatch);
+        }
+        tracing::info!("extended proof chain to {} links", index + 1);
+
+        let mut proof = ProofEnvelope::new(&self.backend_id, chain.program_hash.clone(), payload, commitment);
+        proof.insert_metadata(CHAIN_INDEX_KEY, index.to_string());
+        chain.links.push(ChainLink { message, proof });
+        Ok(&chain.links[chain.links.len() - 1])
+    }
+
+    /// Check every link: its envelope, its commitment against the link
+    /// before it and its message, that its proof commits to that
+    /// commitment, and the proof itself
+    pub fn verify(&self, chain: &ProofChain) -> Result<(), ProverError> {
+        self.check_program(chain)?;
+        let mut previous = GENESIS_COMMITMENT.to_vec();
+        for (index, link) in chain.links.iter().enumerate() {
+            if !link.proof.checksum_valid() {
+                return Err(ProverError::MalformedEnvelope(format!(
+                    "link {} fails its checksum",
+                    index
+                )));
+            }
+            if !ct_eq(link.proof.program_hash.as_bytes(), chain.program_hash.as_bytes()) {
+                return Err(ProverError::Other(format!(
+                    "link {} is for program {}, not the chain's {}",
+                    index, link.proof.program_hash, chain.program_hash
+                )));
+            }
+            let expected = chain_commitment(&previous, index as u64, &link.message);
+            let committed = (self.extract)(&link.proof.payload)?;
+            if !ct_eq(&link.proof.public_values, &expected) || !ct_eq(&committed, &expected) {
+                tracing::warn!("link {} does not commit to its message and predecessor", index);
+                return Err(ProverError::PublicInputsMismatch);
+            }
+            if !self.backend.verify(&self.chain_program, &link.proof.payload)? {
+                return Err(ProverError::Other(format!("proof of link {} is invalid", index)));
+            }
+            previous = expected;
+        }
+        Ok(())
+    }
+
+    fn check_program(&self, chain: &ProofChain) -> Result<(), ProverError> {
+        let hash = program_hash(&self.chain_program);
+        if !ct_eq(chain.program_hash.as_bytes(), hash.as_bytes()) {
+            return Err(ProverError::Other(format!(
+                "chain is for program {}, this prover runs {}",
+                chain.program_hash, hash
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockBackend, mock_public_values};
+    use frostgate_zkip::ZkError;
+
+    /// Mock chain guest committing the commitment its input asks for
+    fn chain_prover(program: &[u8]) -> ChainProver {
+        let guest = MockBackend::default().with_guest(Arc::new(|_, input| {
+            let input: ChainInput = serde_json::from_slice(input).map_err(|e| ZkError::Config(e.to_string()))?;
+            Ok(input.commitment)
+        }));
+        ChainProver::new(Arc::new(guest), "mock", program.to_vec(), mock_public_values())
+    }
+
+    fn chain_of(prover: &ChainProver, messages: &[&[u8]]) -> ProofChain {
+        let mut chain = prover.start();
+        for message in messages {
+            prover.extend(&mut chain, message.to_vec()).unwrap();
+        }
+        chain
+    }
+
+    #[test]
+    fn test_extend_and_verify() {
+        let prover = chain_prover(b"chain");
+        let chain = chain_of(&prover, &[b"deposit", b"transfer", b"withdraw"]);
+
+        assert_eq!(chain.len(), 3);
+        assert_eq!(chain.links[2].proof.metadata.get(CHAIN_INDEX_KEY).map(String::as_str), Some("2"));
+        assert_eq!(
+            chain.head(),
+            chain_commitment(&chain.links[1].proof.public_values, 2, b"withdraw")
+        );
+        prover.verify(&chain).unwrap();
+
+        let other = chain_prover(b"other");
+        assert!(other.verify(&chain).is_err());
+    }
+
+    #[test]
+    fn test_tampering_detected() {
+        let prover = chain_prover(b"chain");
+        let chain = chain_of(&prover, &[b"a", b"b", b"c"]);
+
+        let mut altered = chain.clone();
+        altered.links[1].message = b"B".to_vec();
+        assert!(matches!(prover.verify(&altered), Err(ProverError::PublicInputsMismatch)));
+
+        let mut reordered = chain.clone();
+        reordered.links.swap(0, 1);
+        assert!(prover.verify(&reordered).is_err());
+
+        let mut dropped = chain.clone();
+        dropped.links.remove(1);
+        assert!(prover.verify(&dropped).is_err());
+
+        // Truncating the tail leaves a valid, shorter chain
+        let mut truncated = chain;
+        truncated.links.pop();
+        prover.verify(&truncated).unwrap();
+    }
+
+    #[test]
+    fn test_spliced_payload_detected() {
+        let prover = chain_prover(b"chain");
+        let chain = chain_of(&prover, &[b"a", b"b"]);
+        let other = chain_of(&prover, &[b"x", b"y"]);
+
+        // A valid proof of another chain's link, under a forged message
+        // whose commitment the envelope claims
+        let mut spliced = chain;
+        let forged = chain_commitment(&spliced.links[0].proof.public_values, 1, b"forged");
+        let link = &mut spliced.links[1];
+        link.message = b"forged".to_vec();
+        link.proof.payload = other.links[1].proof.payload.clone();
+        link.proof.public_values = forged;
+        link.proof.seal();
+        assert!(prover.backend.verify(b"chain", &link.proof.payload).unwrap());
+        assert!(matches!(prover.verify(&spliced), Err(ProverError::PublicInputsMismatch)));
+    }
+}
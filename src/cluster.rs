@@ -0,0 +1,424 @@
+use crate::envelope::hex_bytes;
+use crate::progress::{ProgressBackend, ProgressSink, ProveProgress};
+use crate::types::{ProgramHash, ProverError, program_hash};
+use frostgate_zkip::{ZkBackend, ZkError};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// One shard of a core proof, handed to a worker
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShardTask {
+    pub program_hash: ProgramHash,
+    /// Zero-based position of this shard in the execution
+    pub index: usize,
+    pub total: usize,
+    #[serde(with = "hex_bytes")]
+    pub program: Vec<u8>,
+    /// Execution checkpoint the shard starts from
+    #[serde(with = "hex_bytes")]
+    pub checkpoint: Vec<u8>,
+}
+
+/// Worker reply to a [`ShardTask`]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkerResponse {
+    ShardProof(#[serde(with = "hex_bytes")] Vec<u8>),
+    Error(String),
+}
+
+/// Executes a program and splits the trace into shard checkpoints, in order
+pub type ShardSplitter = Arc<dyn Fn(&[u8], &[u8]) -> Result<Vec<Vec<u8>>, ZkError> + Send + Sync>;
+
+/// Proves one shard of `program` from its checkpoint
+pub type ShardProver = Arc<dyn Fn(&[u8], &[u8]) -> Result<Vec<u8>, ZkError> + Send + Sync>;
+
+/// Recursively combines shard proofs, given in shard order, into one proof
+pub type ShardRecursor = Arc<dyn Fn(&[u8], &[Vec<u8>]) -> Result<Vec<u8>, ZkError> + Send + Sync>;
+
+/// Proves shards on behalf of a [`Coordinator`]
+pub trait ShardWorker: Send + Sync {
+    /// Name used in logs
+    fn name(&self) -> &str;
+
+    fn prove_shard(&self, task: &ShardTask) -> Result<Vec<u8>, ProverError>;
+}
+
+/// Worker proving shards in this process
+pub struct LocalWorker {
+    name: String,
+    prove: ShardProver,
+}
+
+impl LocalWorker {
+    pub fn new(name: &str, prove: ShardProver) -> Self {
+        Self {
+            name: name.to_string(),
+            prove,
+        }
+    }
+}
+
+impl ShardWorker for LocalWorker {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn prove_shard(&self, task: &ShardTask) -> Result<Vec<u8>, ProverError> {
+        Ok((self.prove)(&task.program, &task.checkpoint)?)
+    }
+}
+
+/// Worker on another process or machine running [`serve_worker`]. Each
+/// shard is sent over its own TCP connection as one line of JSON.
+pub struct RemoteWorker {
+    addr: String,
+    connect_timeout: Duration,
+    shard_timeout: Duration,
+}
+
+impl RemoteWorker {
+    pub fn new(addr: &str) -> Self {
+        Self {
+            addr: addr.to_string(),
+            connect_timeout: Duration::from_secs(5),
+            shard_timeout: Duration::from_secs(30 * 60),
+        }
+    }
+
+    /// Longest a worker may take to prove one shard before it is given up on
+    pub fn with_shard_timeout(mut self, timeout: Duration) -> Self {
+        self.shard_timeout = timeout;
+        self
+    }
+}
+
+impl ShardWorker for RemoteWorker {
+    fn name(&self) -> &str {
+        &self.addr
+    }
+
+    fn prove_shard(&self, task: &ShardTask) -> Result<Vec<u8>, ProverError> {
+        let addr = self
+            .addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| ProverError::NetworkUnavailable(format!("worker {} didn't resolve", self.addr)))?;
+        let mut stream = TcpStream::connect_timeout(&addr, self.connect_timeout)
+            .map_err(|e| ProverError::NetworkUnavailable(format!("worker {}: {}", self.addr, e)))?;
+        stream.set_read_timeout(Some(self.shard_timeout))?;
+
+        let mut line = serde_json::to_vec(task)?;
+        line.push(b'\n');
+        stream.write_all(&line)?;
+        stream.flush()?;
+
+        let mut response = String::new();
+        if BufReader::new(stream).read_line(&mut response)? == 0 {
+            return Err(ProverError::NetworkUnavailable(format!(
+                "worker {} closed the connection",
+                self.addr
+            )));
+        }
+        match serde_json::from_str(&response)? {
+            WorkerResponse::ShardProof(proof) => Ok(proof),
+            WorkerResponse::Error(e) => Err(ProverError::Other(format!("worker {}: {}", self.addr, e))),
+        }
+    }
+}
+
+/// Accept shard tasks on `listener` until it fails, proving each with
+/// `prove`. Connections are served on their own threads so one worker
+/// machine can take shards from several coordinators.
+pub fn serve_worker(listener: TcpListener, prove: ShardProver) -> Result<(), ProverError> {
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let prove = prove.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = handle_connection(stream, &prove) {
+                tracing::warn!("shard connection failed: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
+fn handle_connection(stream: TcpStream, prove: &ShardProver) -> Result<(), ProverError> {
+    let mut output = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<ShardTask>(&line) {
+            Ok(task) => {
+                let start = Instant::now();
+                match prove(&task.program, &task.checkpoint) {
+                    Ok(proof) => {
+                        tracing::info!(
+                            "proved shard {}/{} of program {} in {:?}",
+                            task.index + 1,
+                            task.total,
+                            task.program_hash,
+                            start.elapsed()
+                        );
+                        WorkerResponse::ShardProof(proof)
+                    }
+                    Err(e) => WorkerResponse::Error(format!("{:?}", e)),
+                }
+            }
+            Err(e) => WorkerResponse::Error(format!("Malformed shard task: {}", e)),
+        };
+        serde_json::to_writer(&mut output, &response)?;
+        output.write_all(b"\n")?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+/// Limits of a [`Coordinator`]
+#[derive(Debug, Clone)]
+pub struct CoordinatorConfig {
+    /// Times a shard is tried, each on a different worker where possible,
+    /// before the proof fails
+    pub max_attempts: usize,
+}
+
+impl Default for CoordinatorConfig {
+    fn default() -> Self {
+        Self { max_attempts: 3 }
+    }
+}
+
+/// Spreads core proving across workers: executes and splits the program
+/// locally, proves the shards on workers, then recurses the shard proofs
+/// into one proof locally
+pub struct Coordinator {
+    workers: Vec<Arc<dyn ShardWorker>>,
+    split: ShardSplitter,
+    recurse: ShardRecursor,
+    verifier: Arc<dyn ZkBackend>,
+    config: CoordinatorConfig,
+}
+
+impl Coordinator {
+    /// `verifier` checks the recursed proofs this coordinator produces
+    pub fn new(
+        workers: Vec<Arc<dyn ShardWorker>>,
+        split: ShardSplitter,
+        recurse: ShardRecursor,
+        verifier: Arc<dyn ZkBackend>,
+    ) -> Self {
+        Self {
+            workers,
+            split,
+            recurse,
+            verifier,
+            config: CoordinatorConfig::default(),
+        }
+    }
+
+    pub fn with_config(mut self, config: CoordinatorConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Prove `program` on `input` across the workers
+    pub fn prove_sharded(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ProverError> {
+        self.run(program, input, None)
+    }
+
+    /// Like [`Coordinator::prove_sharded`], reporting each shard into `sink`
+    /// as its proof comes back, in whatever order the workers finish
+    pub fn prove_sharded_with_progress(
+        &self,
+        program: &[u8],
+        input: &[u8],
+        sink: &ProgressSink,
+    ) -> Result<Vec<u8>, ProverError> {
+        self.run(program, input, Some(sink))
+    }
+
+    fn run(&self, program: &[u8], input: &[u8], sink: Option<&ProgressSink>) -> Result<Vec<u8>, ProverError> {
+        if self.workers.is_empty() {
+            return Err(ProverError::InsufficientCapacity("cluster has no workers".to_string()));
+        }
+        let hash = program_hash(program);
+        let _span = tracing::info_span!("cluster_prove", program_hash = %hash).entered();
+        if let Some(sink) = sink {
+            sink(ProveProgress::Started);
+        }
+        let checkpoints = (self.split)(program, input)?;
+        let total = checkpoints.len();
+        let tasks: Vec<ShardTask> = checkpoints
+            .into_iter()
+            .enumerate()
+            .map(|(index, checkpoint)| ShardTask {
+                program_hash: hash.clone(),
+                index,
+                total,
+                program: program.to_vec(),
+                checkpoint,
+            })
+            .collect();
+
+        let proofs = Mutex::new(vec![None; total]);
+        let mut pending: Vec<usize> = (0..total).collect();
+        let mut last_errors: Vec<Option<ProverError>> = (0..total).map(|_| None).collect();
+        for attempt in 0..self.config.max_attempts.max(1) {
+            if pending.is_empty() {
+                break;
+            }
+            // Shift assignments each attempt so a retried shard lands on a
+            // different worker than the one that failed it
+            let mut assigned: Vec<Vec<usize>> = vec![Vec::new(); self.workers.len()];
+            for &shard in &pending {
+                assigned[(shard + attempt) % self.workers.len()].push(shard);
+            }
+            let failures = Mutex::new(Vec::new());
+            std::thread::scope(|scope| {
+                for (worker, shards) in self.workers.iter().zip(assigned) {
+                    let (tasks, proofs, failures) = (&tasks, &proofs, &failures);
+                    scope.spawn(move || {
+                        for shard in shards {
+                            match worker.prove_shard(&tasks[shard]) {
+                                Ok(proof) => {
+                                    proofs.lock().unwrap_or_else(PoisonError::into_inner)[shard] = Some(proof);
+                                    if let Some(sink) = sink {
+                                        sink(ProveProgress::ShardProven { index: shard, total });
+                                    }
+                                }
+                                Err(e) => {
+                                    tracing::warn!("worker {} failed shard {}/{}: {}", worker.name(), shard + 1, total, e);
+                                    failures.lock().unwrap_or_else(PoisonError::into_inner).push((shard, e));
+                                }
+                            }
+                        }
+                    });
+                }
+            });
+            pending.clear();
+            for (shard, e) in failures.into_inner().unwrap_or_else(PoisonError::into_inner) {
+                pending.push(shard);
+                last_errors[shard] = Some(e);
+            }
+        }
+        if let Some(&shard) = pending.first() {
+            let cause = last_errors[shard].take().map(|e| e.to_string()).unwrap_or_default();
+            return Err(ProverError::Other(format!(
+                "shard {}/{} failed after {} attempts: {}",
+                shard + 1,
+                total,
+                self.config.max_attempts.max(1),
+                cause
+            )));
+        }
+
+        let proofs: Vec<Vec<u8>> = proofs
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+            .into_iter()
+            .flatten()
+            .collect();
+        if let Some(sink) = sink {
+            sink(ProveProgress::Compressing);
+        }
+        let proof = (self.recurse)(program, &proofs)?;
+        if let Some(sink) = sink {
+            sink(ProveProgress::Done);
+        }
+        Ok(proof)
+    }
+}
+
+impl ZkBackend for Coordinator {
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        self.prove_sharded(program, input).map_err(ProverError::into_zk_error)
+    }
+
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        self.verifier.verify(program, proof)
+    }
+}
+
+impl ProgressBackend for Coordinator {
+    fn prove_with_progress(&self, program: &[u8], input: &[u8], sink: &ProgressSink) -> Result<Vec<u8>, ZkError> {
+        self.prove_sharded_with_progress(program, input, sink)
+            .map_err(ProverError::into_zk_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockBackend;
+
+    fn split() -> ShardSplitter {
+        Arc::new(|_program, input| Ok(input.chunks(2).map(<[u8]>::to_vec).collect()))
+    }
+
+    fn recurse() -> ShardRecursor {
+        Arc::new(|_program, proofs| Ok(proofs.concat()))
+    }
+
+    #[test]
+    fn test_failed_shards_move_to_other_workers() {
+        let flaky: Arc<dyn ShardWorker> = Arc::new(LocalWorker::new(
+            "flaky",
+            Arc::new(|_program, _checkpoint| Err(ZkError::Config("out of memory".to_string()))),
+        ));
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        std::thread::spawn(move || {
+            serve_worker(
+                listener,
+                Arc::new(|_program, checkpoint| Ok(checkpoint.to_ascii_uppercase())),
+            )
+        });
+        let remote: Arc<dyn ShardWorker> = Arc::new(RemoteWorker::new(&addr));
+
+        let coordinator = Coordinator::new(vec![flaky, remote], split(), recurse(), Arc::new(MockBackend::default()));
+        assert_eq!(coordinator.prove(b"elf", b"abcdefg").unwrap(), b"ABCDEFG");
+    }
+
+    #[test]
+    fn test_gives_up_after_max_attempts() {
+        let broken: Arc<dyn ShardWorker> = Arc::new(LocalWorker::new(
+            "broken",
+            Arc::new(|_program, _checkpoint| Err(ZkError::Config("out of memory".to_string()))),
+        ));
+        let coordinator = Coordinator::new(vec![broken], split(), recurse(), Arc::new(MockBackend::default()))
+            .with_config(CoordinatorConfig { max_attempts: 2 });
+        assert!(coordinator.prove_sharded(b"elf", b"abc").is_err());
+    }
+
+    #[test]
+    fn test_reports_each_shard() {
+        let worker: Arc<dyn ShardWorker> = Arc::new(LocalWorker::new(
+            "local",
+            Arc::new(|_program, checkpoint| Ok(checkpoint.to_vec())),
+        ));
+        let coordinator = Coordinator::new(vec![worker], split(), recurse(), Arc::new(MockBackend::default()));
+        let (sink, mut rx) = crate::progress::progress_channel();
+        assert_eq!(coordinator.prove_with_progress(b"elf", b"abcde", &sink).unwrap(), b"abcde");
+
+        let mut reports = Vec::new();
+        while let Ok(progress) = rx.try_recv() {
+            reports.push(progress);
+        }
+        assert_eq!(
+            reports,
+            [
+                ProveProgress::Started,
+                ProveProgress::ShardProven { index: 0, total: 3 },
+                ProveProgress::ShardProven { index: 1, total: 3 },
+                ProveProgress::ShardProven { index: 2, total: 3 },
+                ProveProgress::Compressing,
+                ProveProgress::Done,
+            ]
+        );
+    }
+}
@@ -0,0 +1,40 @@
+//! Compatibility shim over the supported SP1 SDK major versions.
+//!
+//! Code in this crate refers to SP1 through this module rather than naming
+//! `sp1_sdk` directly, so the SDK version is chosen by the `sp1-v4`/`sp1-v5`
+//! cargo features. `verifier-only` builds enable neither and have no SDK.
+
+#[cfg(all(feature = "sp1-v4", feature = "sp1-v5"))]
+compile_error!("features `sp1-v4` and `sp1-v5` are mutually exclusive");
+
+#[cfg(not(any(feature = "sp1-v4", feature = "sp1-v5", feature = "verifier-only")))]
+compile_error!("one of the features `sp1-v4`, `sp1-v5` or `verifier-only` must be enabled");
+
+#[cfg(feature = "sp1-v5")]
+pub use {sp1_core_machine as core_machine, sp1_prover as prover, sp1_sdk as sdk};
+
+#[cfg(feature = "sp1-v4")]
+pub use {sp1_core_machine_v4 as core_machine, sp1_prover_v4 as prover, sp1_sdk_v4 as sdk};
+
+/// SP1 release the crate is built against
+#[cfg(feature = "sp1-v5")]
+pub const SP1_VERSION: &str = "5.0.0";
+
+#[cfg(feature = "sp1-v4")]
+pub const SP1_VERSION: &str = "4.2.0";
+
+/// Release of `sp1-verifier` used by verifier-only builds
+#[cfg(not(any(feature = "sp1-v4", feature = "sp1-v5")))]
+pub const SP1_VERSION: &str = "5.0.0";
+
+/// Major version of the enabled SP1 SDK; proofs are only compatible within one
+pub const SP1_MAJOR_VERSION: u32 = if cfg!(feature = "sp1-v4") { 4 } else { 5 };
+
+/// Whether a proof produced by SP1 `version` can be verified by this build
+pub fn is_compatible(version: &str) -> bool {
+    version
+        .split('.')
+        .next()
+        .and_then(|major| major.trim_start_matches('v').parse::<u32>().ok())
+        == Some(SP1_MAJOR_VERSION)
+}
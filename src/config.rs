@@ -0,0 +1,814 @@
+use crate::access::{AccessRules, ProgramAccessControl};
+use crate::admission::AdmissionConfig;
+use crate::dedup::{DedupCache, DedupConfig};
+use crate::gpu::ProverMode;
+use crate::isolation::KeyHolderConfig;
+use crate::memory::MemoryBudget;
+use crate::pipeline::ProofMode;
+use crate::program_limits::{ProgramLimit, ProgramLimiter};
+use crate::quotas::{QuotaLimits, QuotaManager, QuotaScope};
+use crate::secrets::SecretString;
+use crate::signatures::{ProgramSignatures, SignatureMode};
+use crate::types::ProgramHash;
+use ed25519_dalek::VerifyingKey;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Config file deployments read when none is named explicitly
+pub const DEFAULT_CONFIG_FILE: &str = "prover.toml";
+
+/// Prefix of the environment variables read by [`ConfigLayer::from_env`]
+pub const ENV_PREFIX: &str = "FROSTGATE_";
+
+/// Environment variable telling a key holder which proof mode to produce
+pub const PROOF_MODE_ENV: &str = "FROSTGATE_PROOF_MODE";
+
+/// Environment variable telling a key holder where to keep proving keys
+/// and other setup artifacts
+pub const ARTIFACTS_DIR_ENV: &str = "FROSTGATE_ARTIFACTS_DIR";
+
+/// Environment variable holding the prover network's private key, as read by SP1
+pub const NETWORK_PRIVATE_KEY_ENV: &str = "NETWORK_PRIVATE_KEY";
+
+/// Why a configuration couldn't be loaded
+#[derive(Debug)]
+pub enum ConfigError {
+    Io { path: PathBuf, source: std::io::Error },
+    /// The file isn't valid TOML or has unknown or mistyped keys
+    Parse { path: PathBuf, message: String },
+    /// A setting has a value outside what it accepts. `field` names the
+    /// file key or environment variable it came from.
+    InvalidValue { field: String, value: String, expected: String },
+    /// A setting another setting depends on is unset
+    Missing { field: String, required_by: String },
+    /// A `FROSTGATE_*` variable that names no setting, e.g. a misspelling
+    Unknown { field: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io { path, source } => write!(f, "can't read {}: {}", path.display(), source),
+            ConfigError::Parse { path, message } => write!(f, "invalid config {}: {}", path.display(), message.trim_end()),
+            ConfigError::InvalidValue { field, value, expected } => {
+                write!(f, "invalid {} '{}': expected {}", field, value, expected)
+            }
+            ConfigError::Missing { field, required_by } => write!(f, "{} must be set {}", field, required_by),
+            ConfigError::Unknown { field } => write!(f, "unknown setting {}", field),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// `[access]`: programs every tenant may prove, and tighter rules per tenant
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AccessSettings {
+    pub global: AccessRules,
+    pub tenants: BTreeMap<String, AccessRules>,
+}
+
+/// `[quotas]`: daily limits per tenant and per program hash
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QuotaSettings {
+    pub tenants: BTreeMap<String, QuotaLimits>,
+    pub programs: BTreeMap<ProgramHash, QuotaLimits>,
+}
+
+/// `[signatures]`: how program signatures are checked, and the signers
+/// accepted, as hex ed25519 public keys by name
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SignatureSettings {
+    pub mode: SignatureMode,
+    pub signers: BTreeMap<String, String>,
+}
+
+/// One source of settings. Unset fields fall through to lower layers when
+/// layers are combined with [`ConfigLayer::over`]. Tables are taken whole
+/// from the highest layer that sets them.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigLayer {
+    pub backend: Option<String>,
+    pub key_holder: Option<PathBuf>,
+    pub key_holder_args: Option<Vec<String>>,
+    pub pass_env: Option<Vec<String>>,
+    pub proof_mode: Option<String>,
+    pub prover_mode: Option<String>,
+    pub max_concurrent: Option<usize>,
+    pub memory_budget_bytes: Option<u64>,
+    pub dedup_ttl_secs: Option<u64>,
+    pub dedup_max_entries: Option<usize>,
+    pub dedup_max_bytes: Option<u64>,
+    pub artifacts_dir: Option<PathBuf>,
+    pub use_network: Option<bool>,
+    pub network_endpoint: Option<String>,
+    pub network_private_key: Option<SecretString>,
+    pub otlp_endpoint: Option<String>,
+    pub access: Option<AccessSettings>,
+    pub quotas: Option<QuotaSettings>,
+    pub signatures: Option<SignatureSettings>,
+    pub admission: Option<AdmissionConfig>,
+    pub program_limits: Option<BTreeMap<ProgramHash, ProgramLimit>>,
+}
+
+impl ConfigLayer {
+    /// Read a TOML file
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        toml::from_str(&text).map_err(|e| ConfigError::Parse {
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        })
+    }
+
+    /// Read `FROSTGATE_*` variables from the process environment, plus
+    /// `NETWORK_PRIVATE_KEY`
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::from_vars(std::env::vars())
+    }
+
+    /// Read settings from `(name, value)` pairs named like environment
+    /// variables. `FROSTGATE_KEY_HOLDER_ARGS` is split on whitespace and
+    /// `FROSTGATE_PASS_ENV` on commas. Like unknown file keys, `FROSTGATE_*`
+    /// names that match no setting are rejected.
+    pub fn from_vars(vars: impl IntoIterator<Item = (String, String)>) -> Result<Self, ConfigError> {
+        let mut layer = Self::default();
+        for (name, value) in vars {
+            if name == NETWORK_PRIVATE_KEY_ENV {
+                layer.network_private_key = Some(SecretString::from(value));
+                continue;
+            }
+            let Some(key) = name.strip_prefix(ENV_PREFIX) else {
+                continue;
+            };
+            match key {
+                "BACKEND" => layer.backend = Some(value),
+                "KEY_HOLDER" => layer.key_holder = Some(PathBuf::from(value)),
+                "KEY_HOLDER_ARGS" => layer.key_holder_args = Some(value.split_whitespace().map(str::to_string).collect()),
+                "PASS_ENV" => {
+                    layer.pass_env = Some(
+                        value
+                            .split(',')
+                            .map(str::trim)
+                            .filter(|s| !s.is_empty())
+                            .map(str::to_string)
+                            .collect(),
+                    )
+                }
+                "PROOF_MODE" => layer.proof_mode = Some(value),
+                "PROVER_MODE" => layer.prover_mode = Some(value),
+                "MAX_CONCURRENT" => layer.max_concurrent = Some(parse_number(&name, &value)?),
+                "MEMORY_BUDGET_BYTES" => layer.memory_budget_bytes = Some(parse_number(&name, &value)?),
+                "DEDUP_TTL_SECS" => layer.dedup_ttl_secs = Some(parse_number(&name, &value)?),
+                "DEDUP_MAX_ENTRIES" => layer.dedup_max_entries = Some(parse_number(&name, &value)?),
+                "DEDUP_MAX_BYTES" => layer.dedup_max_bytes = Some(parse_number(&name, &value)?),
+                "ARTIFACTS_DIR" => layer.artifacts_dir = Some(PathBuf::from(value)),
+                "USE_NETWORK" => layer.use_network = Some(parse_bool(&name, &value)?),
+                "NETWORK_ENDPOINT" => layer.network_endpoint = Some(value),
+                "OTLP_ENDPOINT" => layer.otlp_endpoint = Some(value),
+                _ => return Err(ConfigError::Unknown { field: name }),
+            }
+        }
+        Ok(layer)
+    }
+
+    /// Combine with `lower`, keeping this layer's value wherever both set one
+    pub fn over(self, lower: ConfigLayer) -> ConfigLayer {
+        ConfigLayer {
+            backend: self.backend.or(lower.backend),
+            key_holder: self.key_holder.or(lower.key_holder),
+            key_holder_args: self.key_holder_args.or(lower.key_holder_args),
+            pass_env: self.pass_env.or(lower.pass_env),
+            proof_mode: self.proof_mode.or(lower.proof_mode),
+            prover_mode: self.prover_mode.or(lower.prover_mode),
+            max_concurrent: self.max_concurrent.or(lower.max_concurrent),
+            memory_budget_bytes: self.memory_budget_bytes.or(lower.memory_budget_bytes),
+            dedup_ttl_secs: self.dedup_ttl_secs.or(lower.dedup_ttl_secs),
+            dedup_max_entries: self.dedup_max_entries.or(lower.dedup_max_entries),
+            dedup_max_bytes: self.dedup_max_bytes.or(lower.dedup_max_bytes),
+            artifacts_dir: self.artifacts_dir.or(lower.artifacts_dir),
+            use_network: self.use_network.or(lower.use_network),
+            network_endpoint: self.network_endpoint.or(lower.network_endpoint),
+            network_private_key: self.network_private_key.or(lower.network_private_key),
+            otlp_endpoint: self.otlp_endpoint.or(lower.otlp_endpoint),
+            access: self.access.or(lower.access),
+            quotas: self.quotas.or(lower.quotas),
+            signatures: self.signatures.or(lower.signatures),
+            admission: self.admission.or(lower.admission),
+            program_limits: self.program_limits.or(lower.program_limits),
+        }
+    }
+
+    /// Fill unset fields with defaults and [validate](ProverConfig::validate)
+    /// the result
+    pub fn resolve(self) -> Result<ProverConfig, ConfigError> {
+        let defaults = ProverConfig::default();
+        let proof_mode = match self.proof_mode {
+            Some(name) => ProofMode::parse(&name).ok_or_else(|| ConfigError::InvalidValue {
+                field: "proof_mode".to_string(),
+                value: name,
+                expected: "one of core, compressed, plonk, groth16".to_string(),
+            })?,
+            None => defaults.proof_mode,
+        };
+        let prover_mode = match self.prover_mode {
+            Some(name) => ProverMode::parse(&name).ok_or_else(|| ConfigError::InvalidValue {
+                field: "prover_mode".to_string(),
+                value: name,
+                expected: "one of cpu, cuda, cuda:<device>, network".to_string(),
+            })?,
+            None => defaults.prover_mode,
+        };
+        let config = ProverConfig {
+            backend: self.backend.unwrap_or(defaults.backend),
+            key_holder: self.key_holder,
+            key_holder_args: self.key_holder_args.unwrap_or_default(),
+            pass_env: self.pass_env.unwrap_or_default(),
+            proof_mode,
+            prover_mode,
+            max_concurrent: self.max_concurrent.unwrap_or(defaults.max_concurrent),
+            memory_budget_bytes: self.memory_budget_bytes,
+            dedup: DedupConfig {
+                ttl: self.dedup_ttl_secs.map_or(defaults.dedup.ttl, Duration::from_secs),
+                max_entries: self.dedup_max_entries.unwrap_or(defaults.dedup.max_entries),
+                max_bytes: self.dedup_max_bytes.unwrap_or(defaults.dedup.max_bytes),
+            },
+            artifacts_dir: self.artifacts_dir,
+            use_network: self.use_network.unwrap_or(defaults.use_network),
+            network_endpoint: self.network_endpoint,
+            network_private_key: self.network_private_key,
+            otlp_endpoint: self.otlp_endpoint,
+            access: self.access,
+            quotas: self.quotas,
+            signatures: self.signatures,
+            admission: self.admission,
+            program_limits: self.program_limits.unwrap_or_default(),
+        };
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+fn validate_endpoint(field: &str, endpoint: &str) -> Result<(), ConfigError> {
+    let invalid = |expected: &str| ConfigError::InvalidValue {
+        field: field.to_string(),
+        value: endpoint.to_string(),
+        expected: expected.to_string(),
+    };
+    let (scheme, rest) = endpoint
+        .split_once("://")
+        .ok_or_else(|| invalid("a URL such as https://rpc.example.com"))?;
+    if scheme != "http" && scheme != "https" {
+        return Err(invalid("an http or https URL"));
+    }
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    // IPv6 hosts are bracketed, e.g. [::1]:8080
+    let (host, port) = match authority.strip_prefix('[') {
+        Some(bracketed) => {
+            let (host, rest) = bracketed.split_once(']').ok_or_else(|| invalid("a closing ] after an IPv6 host"))?;
+            (host, rest.strip_prefix(':'))
+        }
+        None => match authority.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        },
+    };
+    if let Some(port) = port {
+        port.parse::<u16>().map_err(|_| invalid("a port between 0 and 65535"))?;
+    }
+    if host.is_empty() || host.contains(char::is_whitespace) {
+        return Err(invalid("a URL with a host name"));
+    }
+    Ok(())
+}
+
+fn parse_signer(name: &str, key: &str) -> Result<VerifyingKey, ConfigError> {
+    let invalid = || ConfigError::InvalidValue {
+        field: format!("signatures.signers.{}", name),
+        value: key.to_string(),
+        expected: "a hex ed25519 public key".to_string(),
+    };
+    let bytes: [u8; 32] = hex::decode(key)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(invalid)?;
+    VerifyingKey::from_bytes(&bytes).map_err(|_| invalid())
+}
+
+fn parse_number<T: std::str::FromStr>(field: &str, value: &str) -> Result<T, ConfigError> {
+    value.trim().parse().map_err(|_| ConfigError::InvalidValue {
+        field: field.to_string(),
+        value: value.to_string(),
+        expected: "a whole number".to_string(),
+    })
+}
+
+fn parse_bool(field: &str, value: &str) -> Result<bool, ConfigError> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" => Ok(false),
+        _ => Err(ConfigError::InvalidValue {
+            field: field.to_string(),
+            value: value.to_string(),
+            expected: "true or false".to_string(),
+        }),
+    }
+}
+
+/// Deployment settings for a prover process
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProverConfig {
+    /// Backend id recorded in proof envelopes
+    pub backend: String,
+    /// Key-holder executable that runs setup, execution and proving
+    pub key_holder: Option<PathBuf>,
+    pub key_holder_args: Vec<String>,
+    /// Extra environment variables passed to the key holder, on top of
+    /// `SP1_*` and `NETWORK_PRIVATE_KEY`
+    pub pass_env: Vec<String>,
+    pub proof_mode: ProofMode,
+    /// Where the key holder proves; `use_network` overrides it
+    pub prover_mode: ProverMode,
+    /// Proofs each registered backend runs at once
+    pub max_concurrent: usize,
+    /// Memory local proofs may use at once, by their estimated peak; unset
+    /// leaves them unlimited. See [`crate::memory::MemoryBudget`].
+    pub memory_budget_bytes: Option<u64>,
+    /// Lifetime and size of the cache answering repeated prove requests
+    pub dedup: DedupConfig,
+    /// Where the key holder keeps proving keys and other setup artifacts
+    pub artifacts_dir: Option<PathBuf>,
+    /// Prove on the SP1 prover network instead of locally
+    pub use_network: bool,
+    pub network_endpoint: Option<String>,
+    pub network_private_key: Option<SecretString>,
+    /// OTLP collector spans are exported to, e.g. `http://localhost:4317`;
+    /// unset only logs them. Needs the `otel` feature.
+    pub otlp_endpoint: Option<String>,
+    /// Program allowlists the services enforce; unset allows every program
+    pub access: Option<AccessSettings>,
+    /// Daily quotas the services charge tenants; unset leaves them unmetered
+    pub quotas: Option<QuotaSettings>,
+    /// Program signature checks; unset disables them
+    pub signatures: Option<SignatureSettings>,
+    /// Rate and queue limits applied to each registered backend. Their
+    /// concurrency is `max_concurrent`, whatever the table says.
+    pub admission: Option<AdmissionConfig>,
+    /// Concurrency caps for particular programs, by program hash, held
+    /// across every backend
+    pub program_limits: BTreeMap<ProgramHash, ProgramLimit>,
+}
+
+impl Default for ProverConfig {
+    fn default() -> Self {
+        Self {
+            backend: "sp1".to_string(),
+            key_holder: None,
+            key_holder_args: Vec::new(),
+            pass_env: Vec::new(),
+            proof_mode: ProofMode::Core,
+            prover_mode: ProverMode::Cpu,
+            max_concurrent: 1,
+            memory_budget_bytes: None,
+            dedup: DedupConfig::default(),
+            artifacts_dir: None,
+            use_network: false,
+            network_endpoint: None,
+            network_private_key: None,
+            otlp_endpoint: None,
+            access: None,
+            quotas: None,
+            signatures: None,
+            admission: None,
+            program_limits: BTreeMap::new(),
+        }
+    }
+}
+
+impl ProverConfig {
+    /// Settings from a TOML file alone
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        ConfigLayer::from_file(path.as_ref())?.resolve()
+    }
+
+    /// Settings from the environment alone
+    pub fn from_env() -> Result<Self, ConfigError> {
+        ConfigLayer::from_env()?.resolve()
+    }
+
+    /// Layer `explicit` over the environment over the file at `path`. With
+    /// no `path`, [`DEFAULT_CONFIG_FILE`] is read if it exists.
+    pub fn load(path: Option<&Path>, explicit: ConfigLayer) -> Result<Self, ConfigError> {
+        let file = match path {
+            Some(path) => ConfigLayer::from_file(path)?,
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => ConfigLayer::from_file(Path::new(DEFAULT_CONFIG_FILE))?,
+            None => ConfigLayer::default(),
+        };
+        explicit.over(ConfigLayer::from_env()?).over(file).resolve()
+    }
+
+    /// Check the settings against each other and the filesystem: concurrency,
+    /// program limits, the memory budget and the dedup cache's size are
+    /// positive, the network and OTLP endpoints are http(s) URLs, network
+    /// proving has a private key, the artifacts dir is a directory, and
+    /// signer keys are ed25519 public keys
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.max_concurrent == 0 {
+            return Err(ConfigError::InvalidValue {
+                field: "max_concurrent".to_string(),
+                value: "0".to_string(),
+                expected: "at least 1".to_string(),
+            });
+        }
+        if self.memory_budget_bytes == Some(0) {
+            return Err(ConfigError::InvalidValue {
+                field: "memory_budget_bytes".to_string(),
+                value: "0".to_string(),
+                expected: "at least 1".to_string(),
+            });
+        }
+        for (field, size) in [
+            ("dedup_max_entries", self.dedup.max_entries as u64),
+            ("dedup_max_bytes", self.dedup.max_bytes),
+        ] {
+            if size == 0 {
+                return Err(ConfigError::InvalidValue {
+                    field: field.to_string(),
+                    value: "0".to_string(),
+                    expected: "at least 1".to_string(),
+                });
+            }
+        }
+        for (hash, limit) in &self.program_limits {
+            if limit.max_concurrent == 0 {
+                return Err(ConfigError::InvalidValue {
+                    field: format!("program_limits.{}.max_concurrent", hash),
+                    value: "0".to_string(),
+                    expected: "at least 1".to_string(),
+                });
+            }
+        }
+        if let Some(endpoint) = &self.network_endpoint {
+            validate_endpoint("network_endpoint", endpoint)?;
+        }
+        if let Some(endpoint) = &self.otlp_endpoint {
+            validate_endpoint("otlp_endpoint", endpoint)?;
+        }
+        if self.effective_prover_mode() == ProverMode::Network && self.network_private_key.as_ref().is_none_or(SecretString::is_blank) {
+            return Err(ConfigError::Missing {
+                field: "network_private_key".to_string(),
+                required_by: "to prove on the network".to_string(),
+            });
+        }
+        if let Some(dir) = &self.artifacts_dir
+            && !dir.is_dir()
+        {
+            return Err(ConfigError::InvalidValue {
+                field: "artifacts_dir".to_string(),
+                value: dir.display().to_string(),
+                expected: "an existing directory".to_string(),
+            });
+        }
+        if let Some(signatures) = &self.signatures {
+            for (name, key) in &signatures.signers {
+                parse_signer(name, key)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Where proofs are produced: on the network if `use_network` is set,
+    /// otherwise as `prover_mode` says
+    pub fn effective_prover_mode(&self) -> ProverMode {
+        if self.use_network {
+            ProverMode::Network
+        } else {
+            self.prover_mode
+        }
+    }
+
+    /// The configured program allowlists
+    pub fn access_control(&self) -> Option<Arc<ProgramAccessControl>> {
+        let settings = self.access.as_ref()?;
+        let control = ProgramAccessControl::new();
+        control.set_global(settings.global.clone());
+        for (tenant, rules) in &settings.tenants {
+            control.set_tenant(tenant, rules.clone());
+        }
+        Some(Arc::new(control))
+    }
+
+    /// The configured quotas, with no usage recorded yet
+    pub fn quota_manager(&self) -> Option<Arc<QuotaManager>> {
+        let settings = self.quotas.as_ref()?;
+        let quotas = QuotaManager::new();
+        for (tenant, limits) in &settings.tenants {
+            quotas.set_limits(QuotaScope::Tenant(tenant.clone()), *limits);
+        }
+        for (hash, limits) in &settings.programs {
+            quotas.set_limits(QuotaScope::Program(hash.clone()), *limits);
+        }
+        Some(Arc::new(quotas))
+    }
+
+    /// The configured per-program limits, shared by every backend
+    pub fn program_limiter(&self) -> Option<Arc<ProgramLimiter>> {
+        if self.program_limits.is_empty() {
+            return None;
+        }
+        let limiter = self
+            .program_limits
+            .iter()
+            .fold(ProgramLimiter::new(), |limiter, (hash, limit)| limiter.with_limit(hash.clone(), *limit));
+        Some(Arc::new(limiter))
+    }
+
+    /// The configured signature checks, allowing the configured signers
+    pub fn program_signatures(&self) -> Result<Option<Arc<ProgramSignatures>>, ConfigError> {
+        let Some(settings) = &self.signatures else {
+            return Ok(None);
+        };
+        let mut signatures = ProgramSignatures::new(settings.mode);
+        for (name, key) in &settings.signers {
+            signatures = signatures.allow_signer(name, parse_signer(name, key)?);
+        }
+        Ok(Some(Arc::new(signatures)))
+    }
+
+    /// Admission limits for each registered backend: the `admission` table,
+    /// or no rate or queue limit without one, running `max_concurrent`
+    /// proofs at once
+    pub fn admission_config(&self) -> AdmissionConfig {
+        let limits = self.admission.clone().unwrap_or(AdmissionConfig {
+            rate_per_sec: f64::MAX,
+            burst: u32::MAX,
+            max_concurrent: 1,
+            max_queue_depth: usize::MAX,
+        });
+        AdmissionConfig {
+            max_concurrent: self.max_concurrent,
+            ..limits
+        }
+    }
+
+    /// The configured memory budget, shared by the local proofs it covers
+    pub fn memory_budget(&self) -> Option<Arc<MemoryBudget>> {
+        self.memory_budget_bytes.map(MemoryBudget::new)
+    }
+
+    /// A dedup cache with the configured limits
+    pub fn dedup_cache(&self) -> Arc<DedupCache> {
+        Arc::new(DedupCache::new(self.dedup.clone()))
+    }
+
+    /// How to launch the configured key holder. Its environment carries the
+    /// proof mode, network settings and the variables named in `pass_env`.
+    pub fn key_holder_config(&self) -> Result<KeyHolderConfig, ConfigError> {
+        let program = self.key_holder.clone().ok_or_else(|| ConfigError::Missing {
+            field: "key_holder".to_string(),
+            required_by: "to launch a key holder".to_string(),
+        })?;
+        let mut env: Vec<(String, String)> = std::env::vars()
+            .filter(|(key, _)| key.starts_with("SP1_") || self.pass_env.contains(key))
+            .collect();
+        env.push((PROOF_MODE_ENV.to_string(), self.proof_mode.name().to_string()));
+        if let Some(dir) = &self.artifacts_dir {
+            env.push((ARTIFACTS_DIR_ENV.to_string(), dir.display().to_string()));
+        }
+        match self.effective_prover_mode() {
+            ProverMode::Cpu => {}
+            ProverMode::Cuda { device } => {
+                env.push(("SP1_PROVER".to_string(), "cuda".to_string()));
+                env.push(("CUDA_VISIBLE_DEVICES".to_string(), device.to_string()));
+            }
+            ProverMode::Network => env.push(("SP1_PROVER".to_string(), "network".to_string())),
+        }
+        if let Some(endpoint) = &self.network_endpoint {
+            env.push(("NETWORK_RPC_URL".to_string(), endpoint.clone()));
+        }
+        if let Some(key) = &self.network_private_key {
+            env.push((NETWORK_PRIVATE_KEY_ENV.to_string(), key.expose_secret().to_string()));
+        }
+        Ok(KeyHolderConfig {
+            program,
+            args: self.key_holder_args.clone(),
+            env,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_layering() {
+        let file: ConfigLayer = toml::from_str(
+            r#"
+            backend = "sp1-cluster"
+            proof_mode = "compressed"
+            max_concurrent = 2
+            dedup_ttl_secs = 60
+            dedup_max_entries = 16
+            "#,
+        )
+        .unwrap();
+        let env = ConfigLayer::from_vars(vars(&[
+            ("FROSTGATE_PROOF_MODE", "plonk"),
+            ("FROSTGATE_MAX_CONCURRENT", "4"),
+            ("FROSTGATE_MEMORY_BUDGET_BYTES", "68719476736"),
+            ("FROSTGATE_USE_NETWORK", "yes"),
+            ("FROSTGATE_DEDUP_MAX_ENTRIES", "64"),
+            ("NETWORK_PRIVATE_KEY", "0xabc"),
+            ("UNRELATED", "x"),
+        ]))
+        .unwrap();
+        let explicit = ConfigLayer {
+            proof_mode: Some("groth16".to_string()),
+            ..ConfigLayer::default()
+        };
+
+        let config = explicit.over(env).over(file).resolve().unwrap();
+        assert_eq!(config.backend, "sp1-cluster");
+        assert_eq!(config.proof_mode, ProofMode::Groth16);
+        assert_eq!(config.max_concurrent, 4);
+        assert_eq!(config.memory_budget_bytes, Some(64 << 30));
+        assert_eq!(
+            config.dedup,
+            DedupConfig {
+                ttl: Duration::from_secs(60),
+                max_entries: 64,
+                ..DedupConfig::default()
+            }
+        );
+        assert!(config.use_network);
+        assert!(!format!("{:?}", config).contains("0xabc"));
+
+        let key_holder = ProverConfig {
+            key_holder: Some(PathBuf::from("key-holder")),
+            artifacts_dir: Some(PathBuf::from("/var/lib/frostgate")),
+            ..ProverConfig::default()
+        }
+        .key_holder_config()
+        .unwrap();
+        assert!(key_holder.env.contains(&(ARTIFACTS_DIR_ENV.to_string(), "/var/lib/frostgate".to_string())));
+    }
+
+    #[test]
+    fn test_prover_mode() {
+        let layer = ConfigLayer::from_vars(vars(&[("FROSTGATE_PROVER_MODE", "cuda:1")])).unwrap();
+        let config = ProverConfig {
+            key_holder: Some(PathBuf::from("key-holder")),
+            ..layer.resolve().unwrap()
+        };
+        assert_eq!(config.prover_mode, ProverMode::Cuda { device: 1 });
+        let env = config.key_holder_config().unwrap().env;
+        assert!(env.contains(&("SP1_PROVER".to_string(), "cuda".to_string())));
+        assert!(env.contains(&("CUDA_VISIBLE_DEVICES".to_string(), "1".to_string())));
+
+        let network: ConfigLayer = toml::from_str(r#"prover_mode = "network""#).unwrap();
+        assert!(matches!(network.resolve(), Err(ConfigError::Missing { field, .. }) if field == "network_private_key"));
+        let bad: ConfigLayer = toml::from_str(r#"prover_mode = "tpu""#).unwrap();
+        assert!(matches!(bad.resolve(), Err(ConfigError::InvalidValue { field, .. }) if field == "prover_mode"));
+    }
+
+    #[test]
+    fn test_bad_values() {
+        let err = ConfigLayer::from_vars(vars(&[("FROSTGATE_MAX_CONCURRENT", "lots")])).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid FROSTGATE_MAX_CONCURRENT 'lots': expected a whole number"
+        );
+        let err = ConfigLayer::from_vars(vars(&[("FROSTGATE_MAX_CONCURENT", "2")])).unwrap_err();
+        assert_eq!(err.to_string(), "unknown setting FROSTGATE_MAX_CONCURENT");
+
+        let layer = ConfigLayer {
+            proof_mode: Some("stark".to_string()),
+            ..ConfigLayer::default()
+        };
+        assert!(matches!(layer.resolve(), Err(ConfigError::InvalidValue { field, .. }) if field == "proof_mode"));
+        assert!(toml::from_str::<ConfigLayer>("max_concurent = 2").is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        let config = ProverConfig {
+            use_network: true,
+            network_endpoint: Some("https://rpc.example.com:8443/v1".to_string()),
+            ..ProverConfig::default()
+        };
+        assert!(matches!(config.validate(), Err(ConfigError::Missing { field, .. }) if field == "network_private_key"));
+
+        let config = ProverConfig {
+            network_private_key: Some(SecretString::from("0xabc")),
+            ..config
+        };
+        assert!(config.validate().is_ok());
+
+        let config = ProverConfig {
+            otlp_endpoint: Some("localhost:4317".to_string()),
+            ..ProverConfig::default()
+        };
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidValue { field, .. }) if field == "otlp_endpoint"));
+
+        for endpoint in ["rpc.example.com", "ftp://rpc.example.com", "https://:443", "https://host:port", "https://[::1"] {
+            let config = ProverConfig {
+                network_endpoint: Some(endpoint.to_string()),
+                ..config.clone()
+            };
+            assert!(config.validate().is_err(), "{} accepted", endpoint);
+        }
+
+        let config = ProverConfig {
+            max_concurrent: 0,
+            ..ProverConfig::default()
+        };
+        assert!(config.validate().is_err());
+
+        let config = ProverConfig {
+            dedup: DedupConfig {
+                max_entries: 0,
+                ..DedupConfig::default()
+            },
+            ..ProverConfig::default()
+        };
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidValue { field, .. }) if field == "dedup_max_entries"));
+    }
+
+    #[test]
+    fn test_service_tables() {
+        let signer = ed25519_dalek::SigningKey::from_bytes(&[7; 32]).verifying_key();
+        let layer: ConfigLayer = toml::from_str(&format!(
+            r#"
+            [access.global]
+            deny = ["bad"]
+            [access.tenants.relayer]
+            allow = ["bridge"]
+
+            [quotas.tenants.relayer]
+            proofs_per_day = 2
+
+            [signatures]
+            mode = "enforce"
+            signers = {{ release = "{}" }}
+
+            [admission]
+            max_queue_depth = 4
+
+            [program_limits.hungry]
+            max_concurrent = 2
+            "#,
+            hex::encode(signer.as_bytes())
+        ))
+        .unwrap();
+        let config = layer.resolve().unwrap();
+
+        let control = config.access_control().unwrap();
+        control.check("relayer", "bridge").unwrap();
+        assert!(control.check("relayer", "other").is_err());
+        assert!(control.check("research", "bad").is_err());
+        let quotas = config.quota_manager().unwrap();
+        assert_eq!(quotas.remaining(&QuotaScope::Tenant("relayer".to_string())).proofs, Some(2));
+        assert_eq!(config.program_signatures().unwrap().unwrap().mode(), SignatureMode::Enforce);
+        assert_eq!(config.admission_config().max_queue_depth, 4);
+        assert_eq!(config.program_limiter().unwrap().limit("hungry"), Some(ProgramLimit::new(2)));
+        assert!(ProverConfig::default().program_limiter().is_none());
+        let unlimited = ProverConfig {
+            max_concurrent: 3,
+            ..ProverConfig::default()
+        }
+        .admission_config();
+        assert_eq!(unlimited.max_concurrent, 3);
+        let controller = crate::admission::AdmissionController::new(unlimited);
+        let permits: Vec<_> = (0..3).map(|_| controller.admit().unwrap()).collect();
+        assert_eq!(controller.running(), permits.len());
+        assert!(ProverConfig::default().access_control().is_none());
+
+        let bad: ConfigLayer = toml::from_str("[program_limits.hungry]\nmax_concurrent = 0").unwrap();
+        assert!(matches!(bad.resolve(), Err(ConfigError::InvalidValue { field, .. }) if field == "program_limits.hungry.max_concurrent"));
+        let bad: ConfigLayer = toml::from_str("[signatures]\nsigners = { release = \"00\" }").unwrap();
+        assert!(matches!(bad.resolve(), Err(ConfigError::InvalidValue { field, .. }) if field == "signatures.signers.release"));
+    }
+}
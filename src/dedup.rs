@@ -0,0 +1,302 @@
+use crate::events::{EventBus, LifecycleEvent};
+use crate::pipeline::ProofMode;
+use crate::types::{ProgramHash, ProverError, program_hash};
+use frostgate_zkip::{ZkBackend, ZkError};
+use sha3::{Digest, Sha3_256};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Identifies a prove request by content
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DedupKey {
+    pub program_hash: ProgramHash,
+    pub stdin_hash: String,
+    pub proof_mode: ProofMode,
+}
+
+impl DedupKey {
+    pub fn new(program: &[u8], stdin: &[u8], proof_mode: ProofMode) -> Self {
+        Self {
+            program_hash: program_hash(program),
+            stdin_hash: hex::encode(Sha3_256::digest(stdin)),
+            proof_mode,
+        }
+    }
+}
+
+/// Limits of a [`DedupCache`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DedupConfig {
+    /// How long a proof is served from the cache
+    pub ttl: Duration,
+    pub max_entries: usize,
+    /// Maximum total size of cached proofs
+    pub max_bytes: u64,
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        Self {
+            ttl: Duration::from_secs(60 * 60),
+            max_entries: 1024,
+            max_bytes: 256 * 1024 * 1024,
+        }
+    }
+}
+
+struct CachedProof {
+    proof: Arc<Vec<u8>>,
+    inserted: Instant,
+}
+
+#[derive(Default)]
+struct DedupState {
+    entries: HashMap<DedupKey, CachedProof>,
+    /// Keys in insertion order, oldest first
+    order: VecDeque<DedupKey>,
+    total_bytes: u64,
+}
+
+/// Content-addressed cache of finished proofs, so identical requests (e.g.
+/// replayed messages) are answered without proving again
+pub struct DedupCache {
+    config: DedupConfig,
+    state: Mutex<DedupState>,
+    /// One lock per request being proven, so concurrent duplicates wait for
+    /// the first instead of proving in parallel
+    in_flight: Mutex<HashMap<DedupKey, Arc<Mutex<()>>>>,
+    events: Option<Arc<EventBus>>,
+}
+
+impl DedupCache {
+    pub fn new(config: DedupConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(DedupState::default()),
+            in_flight: Mutex::new(HashMap::new()),
+            events: None,
+        }
+    }
+
+    /// Emit [`LifecycleEvent::CacheEvicted`] for proofs dropped to make room
+    /// or on expiry
+    pub fn with_events(mut self, bus: Arc<EventBus>) -> Self {
+        self.events = Some(bus);
+        self
+    }
+
+    /// Cached proof for `key`, if it hasn't expired
+    pub fn get(&self, key: &DedupKey) -> Option<Arc<Vec<u8>>> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let expired = self.expire(&mut state);
+        let proof = state.entries.get(key).map(|entry| entry.proof.clone());
+        drop(state);
+        self.report(expired);
+        proof
+    }
+
+    pub fn insert(&self, key: DedupKey, proof: Arc<Vec<u8>>) {
+        let bytes = proof.len() as u64;
+        if bytes > self.config.max_bytes {
+            return;
+        }
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let entry = CachedProof {
+            proof,
+            inserted: Instant::now(),
+        };
+        if let Some(old) = state.entries.insert(key.clone(), entry) {
+            state.total_bytes -= old.proof.len() as u64;
+            state.order.retain(|k| k != &key);
+        }
+        state.order.push_back(key);
+        state.total_bytes += bytes;
+        let mut evicted = Vec::new();
+        while state.entries.len() > self.config.max_entries || state.total_bytes > self.config.max_bytes {
+            let Some(oldest) = state.order.pop_front() else {
+                break;
+            };
+            if let Some(entry) = state.entries.remove(&oldest) {
+                state.total_bytes -= entry.proof.len() as u64;
+                evicted.push(oldest);
+            }
+        }
+        drop(state);
+        self.report(evicted);
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner).entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Cached proof for `key`, or the result of `prove`, which runs at most
+    /// once at a time per key
+    pub fn get_or_prove<F>(&self, key: DedupKey, prove: F) -> Result<Arc<Vec<u8>>, ProverError>
+    where
+        F: FnOnce() -> Result<Vec<u8>, ProverError>,
+    {
+        if let Some(proof) = self.get(&key) {
+            return Ok(proof);
+        }
+        let lock = self
+            .in_flight
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(key.clone())
+            .or_default()
+            .clone();
+        let _proving = lock.lock().unwrap_or_else(PoisonError::into_inner);
+
+        // A duplicate may have finished while this one waited
+        let result = match self.get(&key) {
+            Some(proof) => Ok(proof),
+            None => prove().map(|proof| {
+                let proof = Arc::new(proof);
+                self.insert(key.clone(), proof.clone());
+                proof
+            }),
+        };
+        self.in_flight
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&key);
+        result
+    }
+
+    /// Drop expired proofs, returning their keys
+    fn expire(&self, state: &mut DedupState) -> Vec<DedupKey> {
+        let ttl = self.config.ttl;
+        let mut expired_keys = Vec::new();
+        while let Some(oldest) = state.order.front() {
+            let expired = state
+                .entries
+                .get(oldest)
+                .is_none_or(|entry| entry.inserted.elapsed() >= ttl);
+            if !expired {
+                break;
+            }
+            if let Some(key) = state.order.pop_front()
+                && let Some(entry) = state.entries.remove(&key)
+            {
+                state.total_bytes -= entry.proof.len() as u64;
+                expired_keys.push(key);
+            }
+        }
+        expired_keys
+    }
+
+    /// Emit an event per dropped proof, outside the cache lock
+    fn report(&self, evicted: Vec<DedupKey>) {
+        if let Some(bus) = &self.events {
+            for key in evicted {
+                bus.emit(LifecycleEvent::CacheEvicted {
+                    cache: "proofs".to_string(),
+                    key: format!("{}/{}/{}", key.program_hash, key.stdin_hash, key.proof_mode.name()),
+                });
+            }
+        }
+    }
+}
+
+/// Backend wrapper answering repeated prove requests from a [`DedupCache`].
+/// `proof_mode` is the mode the wrapped backend produces.
+pub struct DedupBackend {
+    inner: Arc<dyn ZkBackend>,
+    cache: Arc<DedupCache>,
+    proof_mode: ProofMode,
+}
+
+impl DedupBackend {
+    pub fn new(inner: Arc<dyn ZkBackend>, cache: Arc<DedupCache>, proof_mode: ProofMode) -> Self {
+        Self {
+            inner,
+            cache,
+            proof_mode,
+        }
+    }
+}
+
+impl ZkBackend for DedupBackend {
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        let key = DedupKey::new(program, input, self.proof_mode);
+        let proof = self
+            .cache
+            .get_or_prove(key, || Ok(self.inner.prove(program, input)?))
+            .map_err(ProverError::into_zk_error)?;
+        Ok(proof.as_ref().clone())
+    }
+
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        self.inner.verify(program, proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockBackend, MockConfig};
+
+    #[test]
+    fn test_duplicates_prove_once() {
+        let inner = Arc::new(MockBackend::new(MockConfig {
+            prove_latency: Duration::from_millis(50),
+            ..MockConfig::default()
+        }));
+        let backend = Arc::new(DedupBackend::new(
+            inner.clone(),
+            Arc::new(DedupCache::new(DedupConfig::default())),
+            ProofMode::Groth16,
+        ));
+
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let backend = backend.clone();
+                std::thread::spawn(move || backend.prove(b"elf", b"input").unwrap())
+            })
+            .collect();
+        let proofs: Vec<_> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+        assert!(proofs.windows(2).all(|w| w[0] == w[1]));
+        assert_eq!(inner.prove_calls(), 1);
+
+        backend.prove(b"elf", b"other input").unwrap();
+        assert_eq!(inner.prove_calls(), 2);
+    }
+
+    #[test]
+    fn test_ttl_and_limits() {
+        let bus = Arc::new(EventBus::new());
+        let mut events = bus.subscribe_channel();
+        let cache = DedupCache::new(DedupConfig {
+            ttl: Duration::from_millis(20),
+            max_entries: 2,
+            max_bytes: 1024,
+        })
+        .with_events(bus);
+        let key = |input: &[u8]| DedupKey::new(b"elf", input, ProofMode::Core);
+        cache.insert(key(b"a"), Arc::new(vec![1]));
+        cache.insert(key(b"b"), Arc::new(vec![2]));
+        cache.insert(key(b"c"), Arc::new(vec![3]));
+        assert!(cache.get(&key(b"a")).is_none());
+        assert_eq!(cache.len(), 2);
+        let evicted = key(b"a");
+        assert_eq!(
+            events.try_recv().unwrap(),
+            LifecycleEvent::CacheEvicted {
+                cache: "proofs".to_string(),
+                key: format!("{}/{}/core", evicted.program_hash, evicted.stdin_hash),
+            }
+        );
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(cache.get(&key(b"c")).is_none());
+        assert!(cache.is_empty());
+        // Both remaining proofs expired
+        assert!(events.try_recv().is_ok() && events.try_recv().is_ok());
+        assert!(events.try_recv().is_err());
+    }
+}
@@ -0,0 +1,341 @@
+use crate::types::{ProverError, carried_error, mentions};
+use frostgate_zkip::{ZkBackend, ZkError};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Resource levels observed on the node
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PressureReading {
+    pub available_memory_bytes: Option<u64>,
+    pub free_disk_bytes: Option<u64>,
+}
+
+/// Samples the node's resource levels
+pub type PressureProbe = Arc<dyn Fn() -> PressureReading + Send + Sync>;
+
+/// When to enter and leave verify-only mode
+#[derive(Debug, Clone)]
+pub struct DegradationThresholds {
+    pub min_available_memory_bytes: u64,
+    pub min_free_disk_bytes: u64,
+    /// Consecutive out-of-memory failures that trigger degradation
+    pub max_consecutive_ooms: u32,
+    /// How long after the last out-of-memory failure proving is tried
+    /// again. One more failure degrades the node at once; a success ends
+    /// the streak.
+    pub oom_cooldown: Duration,
+    /// Resources must exceed the thresholds by this factor to recover
+    pub recovery_factor: f64,
+}
+
+impl Default for DegradationThresholds {
+    fn default() -> Self {
+        Self {
+            min_available_memory_bytes: 4 << 30,
+            min_free_disk_bytes: 10 << 30,
+            max_consecutive_ooms: 3,
+            oom_cooldown: Duration::from_secs(10 * 60),
+            recovery_factor: 1.5,
+        }
+    }
+}
+
+#[derive(Default)]
+struct DegradationState {
+    reason: Option<String>,
+    consecutive_ooms: u32,
+    last_oom: Option<Instant>,
+}
+
+/// Switches the node into verify-only mode under resource pressure and back
+/// out once pressure subsides
+pub struct DegradationMonitor {
+    thresholds: DegradationThresholds,
+    probe: PressureProbe,
+    state: Mutex<DegradationState>,
+}
+
+impl DegradationMonitor {
+    /// Monitor using `/proc/meminfo` for available memory and the
+    /// filesystem holding `workdir` for free disk
+    pub fn new(thresholds: DegradationThresholds, workdir: impl Into<PathBuf>) -> Self {
+        Self::with_probe(thresholds, node_probe(workdir.into()))
+    }
+
+    pub fn with_probe(thresholds: DegradationThresholds, probe: PressureProbe) -> Self {
+        Self {
+            thresholds,
+            probe,
+            state: Mutex::new(DegradationState::default()),
+        }
+    }
+
+    /// Why the node is degraded, if it is
+    pub fn degraded_reason(&self) -> Option<String> {
+        self.state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .reason
+            .clone()
+    }
+
+    pub fn is_degraded(&self) -> bool {
+        self.degraded_reason().is_some()
+    }
+
+    /// Sample resources and update the mode
+    pub fn evaluate(&self) {
+        let reading = (self.probe)();
+        let t = &self.thresholds;
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let pressure = match reading {
+            PressureReading {
+                available_memory_bytes: Some(memory),
+                ..
+            } if memory < t.min_available_memory_bytes => Some(format!("available memory is {} bytes", memory)),
+            PressureReading {
+                free_disk_bytes: Some(disk),
+                ..
+            } if disk < t.min_free_disk_bytes => Some(format!("free disk is {} bytes", disk)),
+            _ => None,
+        };
+
+        if let Some(reason) = pressure {
+            if state.reason.is_none() {
+                tracing::warn!("entering verify-only mode: {}", reason);
+            }
+            state.reason = Some(reason);
+            return;
+        }
+
+        if state.consecutive_ooms >= t.max_consecutive_ooms
+            && state.last_oom.is_some_and(|last| last.elapsed() >= t.oom_cooldown)
+        {
+            tracing::info!("out-of-memory cooldown over, trying proofs again");
+            state.consecutive_ooms = t.max_consecutive_ooms - 1;
+        }
+        let recovered = |value: Option<u64>, threshold: u64| {
+            value.is_none_or(|v| v as f64 >= threshold as f64 * t.recovery_factor)
+        };
+        if state.reason.is_some()
+            && state.consecutive_ooms < t.max_consecutive_ooms
+            && recovered(reading.available_memory_bytes, t.min_available_memory_bytes)
+            && recovered(reading.free_disk_bytes, t.min_free_disk_bytes)
+        {
+            tracing::info!("leaving verify-only mode");
+            state.reason = None;
+        }
+    }
+
+    /// Record the outcome of a proof attempt
+    pub fn record_prove_result(&self, result: &Result<Vec<u8>, ZkError>) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        match result {
+            Err(e) if is_oom_error(e) => {
+                state.consecutive_ooms += 1;
+                state.last_oom = Some(Instant::now());
+                if state.consecutive_ooms >= self.thresholds.max_consecutive_ooms {
+                    let reason = format!("{} consecutive out-of-memory failures", state.consecutive_ooms);
+                    tracing::warn!("entering verify-only mode: {}", reason);
+                    state.reason = Some(reason);
+                }
+            }
+            Ok(_) => state.consecutive_ooms = 0,
+            Err(_) => {}
+        }
+    }
+
+    /// Reset the OOM streak, e.g. after an operator intervened
+    pub fn reset_oom_streak(&self) {
+        self.state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .consecutive_ooms = 0;
+    }
+
+    /// Reject prove requests while degraded
+    pub fn check_prove(&self) -> Result<(), ProverError> {
+        match self.degraded_reason() {
+            Some(reason) => Err(ProverError::Degraded(reason)),
+            None => Ok(()),
+        }
+    }
+
+    /// Spawn a task that re-evaluates pressure every `interval`
+    pub fn spawn_monitor(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                self.evaluate();
+            }
+        })
+    }
+}
+
+/// Whether a backend error reports memory exhaustion. Errors converted
+/// from a [`ProverError`] never do, whatever their message says.
+pub fn is_oom_error(e: &ZkError) -> bool {
+    if carried_error(e).is_some() {
+        return false;
+    }
+    let msg = format!("{:?}", e);
+    ["out of memory", "oom", "memory allocation", "cannot allocate memory"]
+        .iter()
+        .any(|phrase| mentions(&msg, phrase))
+}
+
+/// [`proc_meminfo_probe`] for memory and [`free_disk_bytes`] of `workdir`
+pub fn node_probe(workdir: PathBuf) -> PressureProbe {
+    Arc::new(move || PressureReading {
+        free_disk_bytes: free_disk_bytes(&workdir),
+        ..proc_meminfo_probe()
+    })
+}
+
+/// Bytes available to unprivileged users on the filesystem holding `path`
+pub fn free_disk_bytes(path: &Path) -> Option<u64> {
+    let stat = rustix::fs::statvfs(path).ok()?;
+    Some(stat.f_bavail.saturating_mul(stat.f_frsize))
+}
+
+/// Read `MemAvailable` from `/proc/meminfo`
+pub fn proc_meminfo_probe() -> PressureReading {
+    let available_memory_bytes = std::fs::read_to_string("/proc/meminfo").ok().and_then(|info| {
+        info.lines()
+            .find_map(|line| line.strip_prefix("MemAvailable:"))
+            .and_then(|rest| rest.trim().trim_end_matches("kB").trim().parse::<u64>().ok())
+            .map(|kb| kb * 1024)
+    });
+    PressureReading {
+        available_memory_bytes,
+        free_disk_bytes: None,
+    }
+}
+
+/// Backend wrapper that rejects proving in verify-only mode but keeps
+/// serving verification
+pub struct DegradableBackend {
+    inner: Arc<dyn ZkBackend>,
+    monitor: Arc<DegradationMonitor>,
+}
+
+impl DegradableBackend {
+    pub fn new(inner: Arc<dyn ZkBackend>, monitor: Arc<DegradationMonitor>) -> Self {
+        Self { inner, monitor }
+    }
+}
+
+impl ZkBackend for DegradableBackend {
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        self.monitor.check_prove().map_err(ProverError::into_zk_error)?;
+        let result = self.inner.prove(program, input);
+        self.monitor.record_prove_result(&result);
+        result
+    }
+
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        self.inner.verify(program, proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    #[test]
+    fn test_memory_pressure_with_hysteresis() {
+        let memory = Arc::new(AtomicU64::new(8 << 30));
+        let reading = memory.clone();
+        let probe: PressureProbe = Arc::new(move || PressureReading {
+            available_memory_bytes: Some(reading.load(Ordering::SeqCst)),
+            free_disk_bytes: None,
+        });
+        let monitor = DegradationMonitor::with_probe(DegradationThresholds::default(), probe);
+
+        monitor.evaluate();
+        monitor.check_prove().unwrap();
+
+        memory.store(1 << 30, Ordering::SeqCst);
+        monitor.evaluate();
+        assert!(matches!(monitor.check_prove(), Err(ProverError::Degraded(_))));
+
+        // Just above the threshold isn't enough to recover
+        memory.store(5 << 30, Ordering::SeqCst);
+        monitor.evaluate();
+        assert!(monitor.is_degraded());
+
+        memory.store(7 << 30, Ordering::SeqCst);
+        monitor.evaluate();
+        assert!(!monitor.is_degraded());
+    }
+
+    #[test]
+    fn test_repeated_ooms() {
+        let monitor = DegradationMonitor::with_probe(DegradationThresholds::default(), Arc::new(PressureReading::default));
+        let oom = Err(ZkError::Config("out of memory".to_string()));
+        for _ in 0..3 {
+            monitor.record_prove_result(&oom);
+        }
+        assert!(monitor.is_degraded());
+
+        // Stays degraded until the streak is cleared
+        monitor.evaluate();
+        assert!(monitor.is_degraded());
+        monitor.reset_oom_streak();
+        monitor.evaluate();
+        assert!(!monitor.is_degraded());
+    }
+
+    #[test]
+    fn test_oom_streak_cools_down() {
+        let thresholds = DegradationThresholds {
+            oom_cooldown: Duration::from_millis(20),
+            ..DegradationThresholds::default()
+        };
+        let monitor = DegradationMonitor::with_probe(thresholds, Arc::new(PressureReading::default));
+        let oom = Err(ZkError::Config("out of memory".to_string()));
+        for _ in 0..3 {
+            monitor.record_prove_result(&oom);
+        }
+        monitor.evaluate();
+        assert!(monitor.is_degraded());
+
+        // After the cooldown one trial proof is let through, and degrades
+        // the node again at once if it runs out of memory
+        std::thread::sleep(Duration::from_millis(30));
+        monitor.evaluate();
+        assert!(!monitor.is_degraded());
+        monitor.record_prove_result(&oom);
+        assert!(monitor.is_degraded());
+
+        // A successful trial ends the streak
+        std::thread::sleep(Duration::from_millis(30));
+        monitor.evaluate();
+        monitor.record_prove_result(&Ok(Vec::new()));
+        for _ in 0..2 {
+            monitor.record_prove_result(&oom);
+        }
+        assert!(!monitor.is_degraded());
+    }
+
+    #[test]
+    fn test_free_disk_probed() {
+        let dir = std::env::temp_dir();
+        assert!(free_disk_bytes(&dir).is_some_and(|bytes| bytes > 0));
+        assert!(free_disk_bytes(&dir.join("frostgate-missing-dir")).is_none());
+        assert!(node_probe(dir)().free_disk_bytes.is_some());
+    }
+
+    #[test]
+    fn test_oom_errors() {
+        assert!(is_oom_error(&ZkError::Config("prover OOM-killed".to_string())));
+        assert!(is_oom_error(&ZkError::Config("memory allocation of 4096 bytes failed".to_string())));
+        assert!(!is_oom_error(&ZkError::Config("no room in the bloom filter".to_string())));
+        let rejected = ProverError::PolicyViolation("program oom-test is denied".to_string());
+        assert!(!is_oom_error(&rejected.into_zk_error()));
+    }
+}
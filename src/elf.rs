@@ -0,0 +1,158 @@
+use crate::types::ProverError;
+
+const ELF_MAGIC: [u8; 4] = [0x7f, b'E', b'L', b'F'];
+const ELFCLASS32: u8 = 1;
+const ELFDATA2LSB: u8 = 1;
+const ET_EXEC: u16 = 2;
+const EM_RISCV: u16 = 0xf3;
+const PT_LOAD: u32 = 1;
+const EHDR_SIZE: usize = 52;
+const PHDR_SIZE: usize = 32;
+
+/// Summary of a validated guest ELF
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElfInfo {
+    pub entry: u32,
+    pub load_segments: usize,
+}
+
+/// Check that `program` is a 32-bit little-endian RISC-V executable whose
+/// entrypoint lies in a loadable segment, before spending time on key
+/// generation. When `expected_entry` is given the entrypoint must match it.
+pub fn validate_elf(program: &[u8], expected_entry: Option<u32>) -> Result<ElfInfo, ProverError> {
+    if program.len() < EHDR_SIZE || program[..4] != ELF_MAGIC {
+        return Err(invalid("not an ELF file"));
+    }
+    if program[4] != ELFCLASS32 {
+        return Err(invalid("expected a 32-bit ELF"));
+    }
+    if program[5] != ELFDATA2LSB {
+        return Err(invalid("expected a little-endian ELF"));
+    }
+
+    let e_type = read_u16(program, 16);
+    if e_type != ET_EXEC {
+        return Err(invalid(&format!("expected an executable, found ELF type {}", e_type)));
+    }
+    let machine = read_u16(program, 18);
+    if machine != EM_RISCV {
+        return Err(invalid(&format!("expected RISC-V, found machine {:#x}", machine)));
+    }
+
+    let entry = read_u32(program, 24);
+    if let Some(expected) = expected_entry
+        && entry != expected
+    {
+        return Err(invalid(&format!(
+            "entrypoint {:#x} does not match expected {:#x}",
+            entry, expected
+        )));
+    }
+
+    let phoff = read_u32(program, 28) as usize;
+    let phentsize = read_u16(program, 42) as usize;
+    let phnum = read_u16(program, 44) as usize;
+    if phnum == 0 {
+        return Err(invalid("no program headers"));
+    }
+    if phentsize < PHDR_SIZE {
+        return Err(invalid(&format!("program header size {} is too small", phentsize)));
+    }
+    let table_end = phentsize
+        .checked_mul(phnum)
+        .and_then(|size| size.checked_add(phoff))
+        .filter(|end| *end <= program.len())
+        .ok_or_else(|| invalid("program header table is out of bounds"))?;
+
+    let mut load_segments = 0;
+    let mut entry_mapped = false;
+    for offset in (phoff..table_end).step_by(phentsize) {
+        if read_u32(program, offset) != PT_LOAD {
+            continue;
+        }
+        load_segments += 1;
+
+        let p_offset = read_u32(program, offset + 4) as u64;
+        let p_vaddr = read_u32(program, offset + 8) as u64;
+        let p_filesz = read_u32(program, offset + 16) as u64;
+        let p_memsz = read_u32(program, offset + 20) as u64;
+        if p_offset + p_filesz > program.len() as u64 {
+            return Err(invalid(&format!("segment at offset {:#x} is truncated", p_offset)));
+        }
+        if (p_vaddr..p_vaddr + p_memsz).contains(&(entry as u64)) {
+            entry_mapped = true;
+        }
+    }
+
+    if load_segments == 0 {
+        return Err(invalid("no loadable segments"));
+    }
+    if !entry_mapped {
+        return Err(invalid(&format!("entrypoint {:#x} is not in a loadable segment", entry)));
+    }
+
+    Ok(ElfInfo { entry, load_segments })
+}
+
+fn invalid(reason: &str) -> ProverError {
+    ProverError::InvalidProgram(reason.to_string())
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a minimal RISC-V executable with one segment mapped at 0x1000
+    fn riscv_elf(entry: u32) -> Vec<u8> {
+        let mut elf = vec![0u8; EHDR_SIZE + PHDR_SIZE + 16];
+        elf[..4].copy_from_slice(&ELF_MAGIC);
+        elf[4] = ELFCLASS32;
+        elf[5] = ELFDATA2LSB;
+        elf[6] = 1;
+        elf[16..18].copy_from_slice(&ET_EXEC.to_le_bytes());
+        elf[18..20].copy_from_slice(&EM_RISCV.to_le_bytes());
+        elf[24..28].copy_from_slice(&entry.to_le_bytes());
+        elf[28..32].copy_from_slice(&(EHDR_SIZE as u32).to_le_bytes());
+        elf[42..44].copy_from_slice(&(PHDR_SIZE as u16).to_le_bytes());
+        elf[44..46].copy_from_slice(&1u16.to_le_bytes());
+
+        let ph = EHDR_SIZE;
+        elf[ph..ph + 4].copy_from_slice(&PT_LOAD.to_le_bytes());
+        elf[ph + 4..ph + 8].copy_from_slice(&((EHDR_SIZE + PHDR_SIZE) as u32).to_le_bytes());
+        elf[ph + 8..ph + 12].copy_from_slice(&0x1000u32.to_le_bytes());
+        elf[ph + 16..ph + 20].copy_from_slice(&16u32.to_le_bytes());
+        elf[ph + 20..ph + 24].copy_from_slice(&16u32.to_le_bytes());
+        elf
+    }
+
+    #[test]
+    fn test_valid_elf() {
+        let info = validate_elf(&riscv_elf(0x1004), Some(0x1004)).unwrap();
+        assert_eq!(info.entry, 0x1004);
+        assert_eq!(info.load_segments, 1);
+    }
+
+    #[test]
+    fn test_invalid_elf() {
+        assert!(matches!(validate_elf(b"not an elf", None), Err(ProverError::InvalidProgram(_))));
+
+        let mut wrong_machine = riscv_elf(0x1004);
+        wrong_machine[18..20].copy_from_slice(&0x3eu16.to_le_bytes());
+        assert!(matches!(validate_elf(&wrong_machine, None), Err(ProverError::InvalidProgram(_))));
+
+        // Entrypoint outside the loadable segment
+        assert!(validate_elf(&riscv_elf(0x2000), None).is_err());
+        assert!(validate_elf(&riscv_elf(0x1004), Some(0x1008)).is_err());
+
+        let truncated = riscv_elf(0x1004);
+        assert!(validate_elf(&truncated[..EHDR_SIZE + 8], None).is_err());
+    }
+}
@@ -0,0 +1,258 @@
+use crate::types::ProverError;
+use std::collections::HashMap;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Weight given to the newest latency sample
+const LATENCY_SMOOTHING: f64 = 0.3;
+
+/// A proving network endpoint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoint {
+    pub name: String,
+    pub url: String,
+    pub region: Option<String>,
+}
+
+/// Measures round-trip latency to an endpoint, `None` if it is unreachable
+pub type LatencyProbe = Arc<dyn Fn(&Endpoint) -> Option<Duration> + Send + Sync>;
+
+/// Latest probe results for an endpoint
+#[derive(Debug, Clone)]
+pub struct EndpointStats {
+    pub available: bool,
+    pub latency: Option<Duration>,
+    pub last_probe: Instant,
+}
+
+/// Picks the best network endpoint for each submission
+pub struct EndpointSelector {
+    endpoints: Vec<Endpoint>,
+    probe: LatencyProbe,
+    stats: Mutex<HashMap<String, EndpointStats>>,
+    pinned: Mutex<Option<String>>,
+}
+
+impl EndpointSelector {
+    /// Create a selector probing endpoints over TCP
+    pub fn new(endpoints: Vec<Endpoint>) -> Self {
+        Self::with_probe(endpoints, Arc::new(tcp_connect_probe))
+    }
+
+    /// Create a selector with a custom probe
+    pub fn with_probe(endpoints: Vec<Endpoint>, probe: LatencyProbe) -> Self {
+        Self {
+            endpoints,
+            probe,
+            stats: Mutex::new(HashMap::new()),
+            pinned: Mutex::new(None),
+        }
+    }
+
+    /// Configured endpoints, in configuration order
+    pub fn endpoints(&self) -> &[Endpoint] {
+        &self.endpoints
+    }
+
+    /// Probe every endpoint once and update its stats
+    pub fn probe_all(&self) {
+        for endpoint in &self.endpoints {
+            let sample = (self.probe)(endpoint);
+            let mut stats = self.stats.lock().unwrap_or_else(PoisonError::into_inner);
+            let latency = match (sample, stats.get(&endpoint.name).and_then(|s| s.latency)) {
+                (Some(new), Some(old)) => Some(old.mul_f64(1.0 - LATENCY_SMOOTHING) + new.mul_f64(LATENCY_SMOOTHING)),
+                (sample, _) => sample,
+            };
+            stats.insert(
+                endpoint.name.clone(),
+                EndpointStats {
+                    available: sample.is_some(),
+                    latency,
+                    last_probe: Instant::now(),
+                },
+            );
+        }
+    }
+
+    /// Latest stats for an endpoint
+    pub fn stats(&self, name: &str) -> Option<EndpointStats> {
+        self.stats.lock().unwrap_or_else(PoisonError::into_inner).get(name).cloned()
+    }
+
+    /// Always route to `name`, regardless of probe results
+    pub fn pin(&self, name: &str) -> Result<(), ProverError> {
+        if !self.endpoints.iter().any(|e| e.name == name) {
+            return Err(ProverError::Other(format!("Unknown endpoint '{}'", name)));
+        }
+        *self.pinned.lock().unwrap_or_else(PoisonError::into_inner) = Some(name.to_string());
+        Ok(())
+    }
+
+    /// Return to latency-based selection
+    pub fn unpin(&self) {
+        *self.pinned.lock().unwrap_or_else(PoisonError::into_inner) = None;
+    }
+
+    /// Currently pinned endpoint, if any
+    pub fn pinned(&self) -> Option<String> {
+        self.pinned.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /// Pick the endpoint to use for the next submission.
+    ///
+    /// A pinned endpoint always wins. Otherwise the available endpoint with the
+    /// lowest smoothed latency is chosen, falling back to configuration order
+    /// for endpoints that have not been probed yet.
+    pub fn select(&self) -> Option<Endpoint> {
+        if let Some(name) = self.pinned.lock().unwrap_or_else(PoisonError::into_inner).as_ref() {
+            return self.endpoints.iter().find(|e| &e.name == name).cloned();
+        }
+
+        let stats = self.stats.lock().unwrap_or_else(PoisonError::into_inner);
+        let probed_best = self
+            .endpoints
+            .iter()
+            .filter_map(|e| {
+                let s = stats.get(&e.name)?;
+                if s.available { s.latency.map(|l| (e, l)) } else { None }
+            })
+            .min_by_key(|(_, latency)| *latency)
+            .map(|(e, _)| e.clone());
+
+        probed_best.or_else(|| {
+            self.endpoints
+                .iter()
+                .find(|e| !stats.contains_key(&e.name))
+                .cloned()
+        })
+    }
+
+    /// Spawn a task that re-probes all endpoints every `interval`
+    pub fn spawn_probe_loop(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let selector = self.clone();
+                if let Err(e) = tokio::task::spawn_blocking(move || selector.probe_all()).await {
+                    tracing::error!("endpoint probe round failed: {}", e);
+                }
+            }
+        })
+    }
+}
+
+/// Probe an endpoint by timing a TCP connect to its host
+pub fn tcp_connect_probe(endpoint: &Endpoint) -> Option<Duration> {
+    let (scheme, rest) = endpoint.url.split_once("://").unwrap_or(("https", &endpoint.url));
+    let authority = rest.split('/').next().unwrap_or(rest);
+    let default_port = if scheme == "http" { 80 } else { 443 };
+    let target = if authority.contains(':') {
+        authority.to_string()
+    } else {
+        format!("{}:{}", authority, default_port)
+    };
+
+    let addr = target.to_socket_addrs().ok()?.next()?;
+    let start = Instant::now();
+    TcpStream::connect_timeout(&addr, Duration::from_secs(5)).ok()?;
+    Some(start.elapsed())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoint(name: &str) -> Endpoint {
+        Endpoint {
+            name: name.to_string(),
+            url: format!("https://{}.example", name),
+            region: None,
+        }
+    }
+
+    /// Selector over endpoints `a`, `b` and `c` whose probe answers the
+    /// latencies in the returned map, treating missing ones as unreachable
+    fn selector() -> (EndpointSelector, Arc<Mutex<HashMap<String, Duration>>>) {
+        let latencies = Arc::new(Mutex::new(HashMap::new()));
+        let probe: LatencyProbe = {
+            let latencies = latencies.clone();
+            Arc::new(move |endpoint| latencies.lock().unwrap().get(&endpoint.name).copied())
+        };
+        let endpoints = ["a", "b", "c"].map(endpoint).to_vec();
+        (EndpointSelector::with_probe(endpoints, probe), latencies)
+    }
+
+    fn set(latencies: &Mutex<HashMap<String, Duration>>, name: &str, millis: Option<u64>) {
+        let mut latencies = latencies.lock().unwrap();
+        match millis {
+            Some(millis) => latencies.insert(name.to_string(), Duration::from_millis(millis)),
+            None => latencies.remove(name),
+        };
+    }
+
+    #[test]
+    fn test_selects_fastest_available() {
+        let (selector, latencies) = selector();
+        // Nothing probed yet: configuration order
+        assert_eq!(selector.select(), Some(endpoint("a")));
+
+        set(&latencies, "a", Some(80));
+        set(&latencies, "b", Some(20));
+        set(&latencies, "c", None);
+        selector.probe_all();
+        assert_eq!(selector.select(), Some(endpoint("b")));
+        assert!(!selector.stats("c").unwrap().available);
+
+        set(&latencies, "b", None);
+        selector.probe_all();
+        assert_eq!(selector.select(), Some(endpoint("a")));
+
+        set(&latencies, "a", None);
+        selector.probe_all();
+        assert_eq!(selector.select(), None);
+    }
+
+    #[test]
+    fn test_pinning() {
+        let (selector, latencies) = selector();
+        set(&latencies, "a", Some(10));
+        set(&latencies, "c", Some(50));
+        selector.probe_all();
+
+        assert!(selector.pin("missing").is_err());
+        assert_eq!(selector.pinned(), None);
+        // Pinned endpoints win even when slower or unreachable
+        selector.pin("b").unwrap();
+        assert_eq!(selector.pinned().as_deref(), Some("b"));
+        assert_eq!(selector.select(), Some(endpoint("b")));
+
+        selector.unpin();
+        assert_eq!(selector.select(), Some(endpoint("a")));
+    }
+
+    #[test]
+    fn test_latency_smoothing() {
+        let (selector, latencies) = selector();
+        set(&latencies, "a", Some(100));
+        set(&latencies, "b", Some(120));
+        selector.probe_all();
+        assert_eq!(selector.stats("a").unwrap().latency, Some(Duration::from_millis(100)));
+
+        // One slow sample moves the average only part of the way
+        set(&latencies, "a", Some(200));
+        selector.probe_all();
+        let smoothed = selector.stats("a").unwrap().latency.unwrap();
+        assert!(smoothed.abs_diff(Duration::from_millis(130)) < Duration::from_micros(1));
+        assert_eq!(selector.select(), Some(endpoint("b")));
+
+        // An outage drops the history
+        set(&latencies, "a", None);
+        selector.probe_all();
+        set(&latencies, "a", Some(50));
+        selector.probe_all();
+        assert_eq!(selector.stats("a").unwrap().latency, Some(Duration::from_millis(50)));
+        assert_eq!(selector.select(), Some(endpoint("a")));
+    }
+}
@@ -0,0 +1,546 @@
+use crate::binding::{PublicValuesExtractor, verify_with_public_inputs};
+use crate::inspect::PROOF_SYSTEM_KEY;
+use crate::receipts::ReceiptSigner;
+use crate::replay::{ReplayGuard, ReplayTag};
+use crate::types::{ProgramHash, ProverError, ct_eq, program_hash};
+use frostgate_zkip::{ZkBackend, ZkError};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Current envelope format version. Version 2 extends the checksum to
+/// cover the backend and program hash, version 3 to cover the metadata.
+pub const ENVELOPE_VERSION: u32 = 3;
+
+/// Oldest envelope format still accepted by the decoder
+pub const MIN_ENVELOPE_VERSION: u32 = 1;
+
+/// Self-describing container for a serialized proof. The checksum guards
+/// against corruption, not forgery: anyone can recompute it, so fields
+/// that matter for trust are checked against the proof or a receipt.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofEnvelope {
+    pub version: u32,
+    pub backend: String,
+    pub program_hash: ProgramHash,
+    #[serde(with = "hex_bytes")]
+    pub payload: Vec<u8>,
+    #[serde(with = "hex_bytes")]
+    pub public_values: Vec<u8>,
+    pub metadata: BTreeMap<String, String>,
+    pub checksum: String,
+}
+
+impl ProofEnvelope {
+    /// Wrap a proof produced by `backend` for `program_hash`
+    pub fn new(backend: &str, program_hash: ProgramHash, payload: Vec<u8>, public_values: Vec<u8>) -> Self {
+        let mut envelope = Self {
+            version: ENVELOPE_VERSION,
+            backend: backend.to_string(),
+            program_hash,
+            payload,
+            public_values,
+            metadata: BTreeMap::new(),
+            checksum: String::new(),
+        };
+        envelope.checksum = envelope.expected_checksum();
+        envelope
+    }
+
+    /// Set a metadata entry and reseal the checksum over it
+    pub fn insert_metadata(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.metadata.insert(key.into(), value.into());
+        self.seal();
+    }
+
+    /// Recompute the checksum after changing fields directly
+    pub fn seal(&mut self) {
+        self.checksum = self.expected_checksum();
+    }
+
+    /// Whether the checksum covers the current contents, as defined by the
+    /// envelope's format version
+    pub fn checksum_valid(&self) -> bool {
+        ct_eq(self.checksum.as_bytes(), self.expected_checksum().as_bytes())
+    }
+
+    /// Upgrade to the current format version, recomputing the checksum. The
+    /// old checksum is verified first so corrupted envelopes aren't blessed.
+    pub fn migrate(mut self) -> Result<Self, ProverError> {
+        if self.version == ENVELOPE_VERSION {
+            return Ok(self);
+        }
+        if !(MIN_ENVELOPE_VERSION..ENVELOPE_VERSION).contains(&self.version) {
+            return Err(ProverError::UnsupportedProofVersion(format!(
+                "envelope version {}",
+                self.version
+            )));
+        }
+        if !self.checksum_valid() {
+            return Err(malformed("checksum mismatch".to_string()));
+        }
+        tracing::debug!("migrating envelope from version {} to {}", self.version, ENVELOPE_VERSION);
+        self.version = ENVELOPE_VERSION;
+        self.checksum = self.expected_checksum();
+        Ok(self)
+    }
+
+    /// Serialize for storage or transport
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ProverError> {
+        Ok(serde_json::to_vec(self)?)
+    }
+}
+
+impl ProofEnvelope {
+    fn expected_checksum(&self) -> String {
+        let mut hasher = Sha3_256::new();
+        if self.version >= 2 {
+            for field in [self.backend.as_bytes(), self.program_hash.as_bytes()] {
+                hasher.update((field.len() as u64).to_le_bytes());
+                hasher.update(field);
+            }
+        }
+        hasher.update((self.payload.len() as u64).to_le_bytes());
+        hasher.update(&self.payload);
+        hasher.update(&self.public_values);
+        if self.version >= 3 {
+            hasher.update((self.metadata.len() as u64).to_le_bytes());
+            for (key, value) in &self.metadata {
+                for field in [key, value] {
+                    hasher.update((field.len() as u64).to_le_bytes());
+                    hasher.update(field.as_bytes());
+                }
+            }
+        }
+        hex::encode(hasher.finalize())
+    }
+}
+
+/// Bounds applied when decoding envelopes from untrusted sources
+#[derive(Debug, Clone, Copy)]
+pub struct DecodeLimits {
+    /// Maximum size of the serialized envelope
+    pub max_bytes: usize,
+    /// Maximum nesting depth of the serialized document
+    pub max_depth: usize,
+    /// Maximum number of metadata entries
+    pub max_metadata_entries: usize,
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self {
+            max_bytes: 64 * 1024 * 1024,
+            max_depth: 8,
+            max_metadata_entries: 256,
+        }
+    }
+}
+
+/// Decode an envelope from untrusted bytes, enforcing `limits` before any
+/// large allocation happens
+pub fn decode_envelope(bytes: &[u8], limits: &DecodeLimits) -> Result<ProofEnvelope, ProverError> {
+    if bytes.len() > limits.max_bytes {
+        return Err(malformed(format!(
+            "envelope is {} bytes, limit is {}",
+            bytes.len(),
+            limits.max_bytes
+        )));
+    }
+    let depth = nesting_depth(bytes);
+    if depth > limits.max_depth {
+        return Err(malformed(format!(
+            "envelope nesting depth {} exceeds {}",
+            depth, limits.max_depth
+        )));
+    }
+
+    let envelope: ProofEnvelope =
+        serde_json::from_slice(bytes).map_err(|e| malformed(format!("invalid envelope: {}", e)))?;
+    if !(MIN_ENVELOPE_VERSION..=ENVELOPE_VERSION).contains(&envelope.version) {
+        return Err(ProverError::UnsupportedProofVersion(format!(
+            "envelope version {}",
+            envelope.version
+        )));
+    }
+    if envelope.metadata.len() > limits.max_metadata_entries {
+        return Err(malformed(format!(
+            "{} metadata entries exceed limit of {}",
+            envelope.metadata.len(),
+            limits.max_metadata_entries
+        )));
+    }
+    if !envelope.checksum_valid() {
+        return Err(malformed("checksum mismatch".to_string()));
+    }
+    Ok(envelope)
+}
+
+fn malformed(reason: String) -> ProverError {
+    ProverError::MalformedEnvelope(reason)
+}
+
+/// Decode an envelope of any supported version and upgrade it to the
+/// current format
+pub fn decode_and_migrate(bytes: &[u8], limits: &DecodeLimits) -> Result<ProofEnvelope, ProverError> {
+    decode_envelope(bytes, limits)?.migrate()
+}
+
+/// Maximum bracket nesting in a JSON document, ignoring string contents
+fn nesting_depth(bytes: &[u8]) -> usize {
+    let (mut depth, mut max_depth) = (0usize, 0usize);
+    let (mut in_string, mut escaped) = (false, false);
+    for &b in bytes {
+        if in_string {
+            match b {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match b {
+            b'"' => in_string = true,
+            b'{' | b'[' => {
+                depth += 1;
+                max_depth = max_depth.max(depth);
+            }
+            b'}' | b']' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    max_depth
+}
+
+/// Wraps a backend's native proofs in envelopes tagged with its proof
+/// system, so proofs from different zkVMs registered side by side (e.g. an
+/// SP1 backend and a Nexus key-holder process) are self-describing
+pub struct EnvelopedBackend {
+    inner: Arc<dyn ZkBackend>,
+    backend_id: String,
+    proof_system: String,
+    extract: Option<PublicValuesExtractor>,
+    limits: DecodeLimits,
+    signer: Option<Arc<ReceiptSigner>>,
+    replay_guard: Option<Arc<ReplayGuard>>,
+}
+
+impl EnvelopedBackend {
+    pub fn new(inner: Arc<dyn ZkBackend>, backend_id: &str, proof_system: &str) -> Self {
+        Self {
+            inner,
+            backend_id: backend_id.to_string(),
+            proof_system: proof_system.to_string(),
+            extract: None,
+            limits: DecodeLimits::default(),
+            signer: None,
+            replay_guard: None,
+        }
+    }
+
+    /// Record the public values of each native proof in its envelope, and
+    /// check on verify that the payload commits to them. Without an
+    /// extractor only envelopes with empty public values verify.
+    pub fn with_extractor(mut self, extract: PublicValuesExtractor) -> Self {
+        self.extract = Some(extract);
+        self
+    }
+
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Sign a receipt for every proof this backend produces
+    pub fn with_signer(mut self, signer: Arc<ReceiptSigner>) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    /// Only verify envelopes carrying a [`ReplayTag`], and each tag once.
+    /// Tags are consumed when a proof verifies, so share the guard between
+    /// every backend that verifies for the same consumer.
+    pub fn with_replay_guard(mut self, guard: Arc<ReplayGuard>) -> Self {
+        self.replay_guard = Some(guard);
+        self
+    }
+
+    /// Prove with a guest that commits `tag`'s digest ahead of its public
+    /// values, recording the tag in the envelope. Needs an extractor.
+    pub fn prove_tagged(&self, program: &[u8], input: &[u8], tag: &ReplayTag) -> Result<Vec<u8>, ProverError> {
+        if self.extract.is_none() {
+            return Err(ProverError::Other(
+                "replay tags need an extractor to bind them to the proof".to_string(),
+            ));
+        }
+        let mut envelope = self.envelope(program, input)?;
+        tag.check(&envelope.public_values)?;
+        tag.stamp(&mut envelope);
+        self.sign_and_encode(envelope)
+    }
+
+    /// Verify a proof, requiring it to be tagged with `tag`, e.g. the domain
+    /// and id of the message the caller is about to act on
+    pub fn verify_tagged(&self, program: &[u8], proof: &[u8], tag: &ReplayTag) -> Result<bool, ProverError> {
+        self.verify_envelope(program, proof, Some(tag))
+    }
+
+    fn envelope(&self, program: &[u8], input: &[u8]) -> Result<ProofEnvelope, ProverError> {
+        let native = self.inner.prove(program, input)?;
+        let public_values = match &self.extract {
+            Some(extract) => extract(&native)?,
+            None => Vec::new(),
+        };
+        let mut envelope = ProofEnvelope::new(&self.backend_id, program_hash(program), native, public_values);
+        envelope.insert_metadata(PROOF_SYSTEM_KEY, self.proof_system.clone());
+        Ok(envelope)
+    }
+
+    fn sign_and_encode(&self, mut envelope: ProofEnvelope) -> Result<Vec<u8>, ProverError> {
+        if let Some(signer) = &self.signer {
+            signer.sign(&mut envelope);
+        }
+        envelope.to_bytes()
+    }
+
+    fn verify_envelope(&self, program: &[u8], proof: &[u8], expected: Option<&ReplayTag>) -> Result<bool, ProverError> {
+        let envelope = decode_envelope(proof, &self.limits)?;
+        let proof_system = envelope.metadata.get(PROOF_SYSTEM_KEY).map(String::as_str);
+        if proof_system != Some(self.proof_system.as_str()) {
+            tracing::warn!(
+                "expected a {} proof, got {}",
+                self.proof_system,
+                proof_system.unwrap_or("an untagged one")
+            );
+            return Ok(false);
+        }
+        if !ct_eq(envelope.program_hash.as_bytes(), program_hash(program).as_bytes()) {
+            return Ok(false);
+        }
+
+        // Tags are checked against the committed values before the proof,
+        // and consumed only once it verifies
+        let tag = ReplayTag::from_envelope(&envelope)?;
+        if let Some(expected) = expected
+            && !tag.as_ref().is_some_and(|tag| ct_eq(&tag.digest(), &expected.digest()))
+        {
+            return Err(ProverError::ReplayDetected(format!(
+                "proof is not tagged for domain '{}' and nonce {}",
+                expected.domain,
+                hex::encode(&expected.nonce)
+            )));
+        }
+        match &tag {
+            Some(tag) => {
+                tag.check(&envelope.public_values)?;
+            }
+            None if self.replay_guard.is_some() => {
+                return Err(ProverError::ReplayDetected("proof carries no replay tag".to_string()));
+            }
+            None => {}
+        }
+
+        let valid = match &self.extract {
+            None if !envelope.public_values.is_empty() => {
+                tracing::warn!("envelope claims public values this backend can't bind to its proof");
+                false
+            }
+            None => self.inner.verify(program, &envelope.payload)?,
+            Some(extract) => match verify_with_public_inputs(
+                self.inner.as_ref(),
+                program,
+                &envelope.payload,
+                &envelope.public_values,
+                extract,
+            ) {
+                Err(ProverError::PublicInputsMismatch) => false,
+                result => result?,
+            },
+        };
+        if valid && let (Some(guard), Some(tag)) = (&self.replay_guard, &tag) {
+            guard.consume(tag)?;
+        }
+        Ok(valid)
+    }
+}
+
+impl ZkBackend for EnvelopedBackend {
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        self.envelope(program, input)
+            .and_then(|envelope| self.sign_and_encode(envelope))
+            .map_err(ProverError::into_zk_error)
+    }
+
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        self.verify_envelope(program, proof, None)
+            .map_err(ProverError::into_zk_error)
+    }
+}
+
+/// Serde helper encoding byte vectors as hex strings
+pub(crate) mod hex_bytes {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        hex::decode(encoded).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let envelope = ProofEnvelope::new("sp1", "hash".to_string(), vec![1, 2, 3], vec![4]);
+        let bytes = envelope.to_bytes().unwrap();
+        assert_eq!(decode_envelope(&bytes, &DecodeLimits::default()).unwrap(), envelope);
+    }
+
+    #[test]
+    fn test_migrates_v1_envelopes() {
+        let mut v1 = ProofEnvelope::new("sp1", "hash".to_string(), vec![1, 2, 3], vec![4]);
+        v1.version = 1;
+        v1.checksum = v1.expected_checksum();
+        let bytes = v1.to_bytes().unwrap();
+
+        let decoded = decode_envelope(&bytes, &DecodeLimits::default()).unwrap();
+        assert_eq!(decoded.version, 1);
+        let migrated = decode_and_migrate(&bytes, &DecodeLimits::default()).unwrap();
+        assert_eq!(migrated.version, ENVELOPE_VERSION);
+        assert!(migrated.checksum_valid());
+        assert_eq!(migrated.payload, v1.payload);
+
+        // Version 2 checksums bind the program hash
+        let mut retargeted = migrated.clone();
+        retargeted.program_hash = "other".to_string();
+        assert!(!retargeted.checksum_valid());
+
+        // Version 3 checksums bind the metadata
+        let mut retagged = migrated;
+        retagged.insert_metadata(PROOF_SYSTEM_KEY, "sp1");
+        assert!(retagged.checksum_valid());
+        retagged.metadata.insert(PROOF_SYSTEM_KEY.to_string(), "nexus".to_string());
+        assert!(!retagged.checksum_valid());
+        let mut v2 = retagged.clone();
+        v2.version = 2;
+        v2.seal();
+        v2.metadata.insert(PROOF_SYSTEM_KEY.to_string(), "sp1".to_string());
+        assert!(v2.checksum_valid());
+        assert_eq!(v2.migrate().unwrap().version, ENVELOPE_VERSION);
+
+        let mut future = v1;
+        future.version = ENVELOPE_VERSION + 1;
+        assert!(matches!(
+            decode_envelope(&future.to_bytes().unwrap(), &DecodeLimits::default()),
+            Err(ProverError::UnsupportedProofVersion(_))
+        ));
+    }
+
+    #[test]
+    fn test_rejects_hostile_input() {
+        let limits = DecodeLimits {
+            max_bytes: 1024,
+            ..Default::default()
+        };
+        let huge = vec![b' '; 2048];
+        assert!(matches!(decode_envelope(&huge, &limits), Err(ProverError::MalformedEnvelope(_))));
+
+        let deep = "[".repeat(100);
+        assert!(decode_envelope(deep.as_bytes(), &limits).is_err());
+
+        let mut tampered = ProofEnvelope::new("sp1", "hash".to_string(), vec![1, 2, 3], vec![4]);
+        tampered.payload[0] = 9;
+        assert!(decode_envelope(&tampered.to_bytes().unwrap(), &limits).is_err());
+    }
+
+    #[test]
+    fn test_enveloped_backend_tags_proof_system() {
+        let inner = Arc::new(crate::testing::MockBackend::default());
+        let nexus = EnvelopedBackend::new(inner.clone(), "nexus-local", "nexus");
+        let sp1 = EnvelopedBackend::new(inner, "sp1-local", "sp1");
+
+        let proof = nexus.prove(b"elf", b"input").unwrap();
+        let envelope = decode_envelope(&proof, &DecodeLimits::default()).unwrap();
+        assert_eq!(envelope.metadata.get(PROOF_SYSTEM_KEY).map(String::as_str), Some("nexus"));
+        assert!(nexus.verify(b"elf", &proof).unwrap());
+        assert!(!sp1.verify(b"elf", &proof).unwrap());
+        assert!(!nexus.verify(b"other", &proof).unwrap());
+    }
+
+    #[test]
+    fn test_enveloped_backend_binds_public_values() {
+        use crate::testing::{MockBackend, mock_public_values};
+
+        let guest = MockBackend::default().with_guest(Arc::new(|_, input| Ok(input.to_vec())));
+        let backend = EnvelopedBackend::new(Arc::new(guest), "mock", "mock").with_extractor(mock_public_values());
+        let proof = backend.prove(b"elf", b"committed").unwrap();
+        let envelope = decode_envelope(&proof, &DecodeLimits::default()).unwrap();
+        assert_eq!(envelope.public_values, b"committed");
+        assert!(backend.verify(b"elf", &proof).unwrap());
+
+        // A resealed envelope claiming other values no longer verifies
+        let mut forged = envelope.clone();
+        forged.public_values = b"forged".to_vec();
+        forged.seal();
+        assert!(!backend.verify(b"elf", &forged.to_bytes().unwrap()).unwrap());
+
+        // Without an extractor, claimed values can't be checked at all
+        let unbound = EnvelopedBackend::new(Arc::new(MockBackend::default()), "mock", "mock");
+        assert!(!unbound.verify(b"elf", &proof).unwrap());
+    }
+
+    #[test]
+    fn test_enveloped_backend_replay_tags() {
+        use crate::testing::{MockBackend, mock_public_values};
+
+        // Guest committing the tag digest its input starts with, then the rest
+        let guest = MockBackend::default().with_guest(Arc::new(|_, input| Ok(input.to_vec())));
+        let guard = Arc::new(ReplayGuard::new());
+        let backend = EnvelopedBackend::new(Arc::new(guest), "mock", "mock")
+            .with_extractor(mock_public_values())
+            .with_replay_guard(guard);
+        let tag = ReplayTag::new("frostgate:test", b"message-1");
+        let other = ReplayTag::new("frostgate:test", b"message-2");
+
+        let proof = backend.prove_tagged(b"elf", &tag.bind(b"payload"), &tag).unwrap();
+        let envelope = decode_envelope(&proof, &DecodeLimits::default()).unwrap();
+        assert_eq!(ReplayTag::from_envelope(&envelope).unwrap(), Some(tag.clone()));
+        // A guest that didn't commit the tag can't be tagged with it
+        assert!(matches!(
+            backend.prove_tagged(b"elf", &other.bind(b"payload"), &tag),
+            Err(ProverError::ReplayDetected(_))
+        ));
+
+        // Presented for another message, then accepted once for its own
+        assert!(matches!(
+            backend.verify_tagged(b"elf", &proof, &other),
+            Err(ProverError::ReplayDetected(_))
+        ));
+        assert!(backend.verify_tagged(b"elf", &proof, &tag).unwrap());
+        assert!(matches!(
+            backend.verify_tagged(b"elf", &proof, &tag),
+            Err(ProverError::ReplayDetected(_))
+        ));
+        assert!(backend.verify(b"elf", &proof).is_err());
+
+        // Restamping the envelope for another message breaks the binding
+        let mut retagged = envelope;
+        other.stamp(&mut retagged);
+        assert!(matches!(
+            backend.verify_tagged(b"elf", &retagged.to_bytes().unwrap(), &other),
+            Err(ProverError::ReplayDetected(_))
+        ));
+
+        // With a guard, untagged proofs are refused
+        let untagged = backend.prove(b"elf", b"payload").unwrap();
+        assert!(backend.verify(b"elf", &untagged).is_err());
+    }
+}
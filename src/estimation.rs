@@ -0,0 +1,309 @@
+use crate::pipeline::ProofMode;
+use crate::types::{ProgramHash, ProverError, program_hash};
+use frostgate_zkip::ZkError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::Duration;
+
+/// Weight kept by older samples each time a new one arrives, so the model
+/// follows hardware and software changes
+const DECAY: f64 = 0.98;
+
+/// Weighted least-squares fit of `seconds = intercept + slope * cycles`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DurationModel {
+    weight: f64,
+    sum_x: f64,
+    sum_y: f64,
+    sum_xx: f64,
+    sum_xy: f64,
+    samples: u64,
+}
+
+impl DurationModel {
+    /// Add an observed job
+    pub fn observe(&mut self, cycles: u64, duration: Duration) {
+        let (x, y) = (cycles as f64, duration.as_secs_f64());
+        self.weight = self.weight * DECAY + 1.0;
+        self.sum_x = self.sum_x * DECAY + x;
+        self.sum_y = self.sum_y * DECAY + y;
+        self.sum_xx = self.sum_xx * DECAY + x * x;
+        self.sum_xy = self.sum_xy * DECAY + x * y;
+        self.samples += 1;
+    }
+
+    /// Number of jobs observed
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    /// Predicted duration for `cycles`, once at least one job was observed
+    pub fn predict(&self, cycles: u64) -> Option<Duration> {
+        if self.samples == 0 {
+            return None;
+        }
+        let mean_x = self.sum_x / self.weight;
+        let mean_y = self.sum_y / self.weight;
+        let var_x = self.sum_xx / self.weight - mean_x * mean_x;
+
+        let seconds = if var_x > 1e-9 * mean_x * mean_x {
+            let slope = (self.sum_xy / self.weight - mean_x * mean_y) / var_x;
+            mean_y + slope * (cycles as f64 - mean_x)
+        } else if mean_x > 0.0 {
+            // All samples at one size: scale proportionally
+            mean_y * cycles as f64 / mean_x
+        } else {
+            mean_y
+        };
+        Some(Duration::from_secs_f64(seconds.max(0.0)))
+    }
+}
+
+/// Learns per-program proving time on this hardware from completed jobs
+#[derive(Default)]
+pub struct DurationEstimator {
+    models: RwLock<HashMap<ProgramHash, DurationModel>>,
+    overall: RwLock<DurationModel>,
+}
+
+impl DurationEstimator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a completed job
+    pub fn record(&self, program_hash: &str, cycles: u64, duration: Duration) {
+        self.models
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(program_hash.to_string())
+            .or_default()
+            .observe(cycles, duration);
+        self.overall
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .observe(cycles, duration);
+    }
+
+    /// Estimated proving time for `cycles` of `program_hash`, falling back to
+    /// the model across all programs when this one has no history
+    pub fn estimate_duration(&self, program_hash: &str, cycles: u64) -> Option<Duration> {
+        let models = self.models.read().unwrap_or_else(PoisonError::into_inner);
+        match models.get(program_hash) {
+            Some(model) => model.predict(cycles),
+            None => self
+                .overall
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .predict(cycles),
+        }
+    }
+
+    /// Save the learned models
+    pub fn save(&self, path: &Path) -> Result<(), ProverError> {
+        let models = self.models.read().unwrap_or_else(PoisonError::into_inner);
+        let overall = self.overall.read().unwrap_or_else(PoisonError::into_inner);
+        std::fs::write(path, serde_json::to_vec(&(&*models, &*overall))?)?;
+        Ok(())
+    }
+
+    /// Load models saved with [`DurationEstimator::save`]
+    pub fn load(path: &Path) -> Result<Self, ProverError> {
+        let (models, overall): (HashMap<ProgramHash, DurationModel>, DurationModel) =
+            serde_json::from_slice(&std::fs::read(path)?)?;
+        Ok(Self {
+            models: RwLock::new(models),
+            overall: RwLock::new(overall),
+        })
+    }
+}
+
+/// Executes a program without proving it and returns the cycle count
+pub type CycleCounter = Arc<dyn Fn(&[u8], &[u8]) -> Result<u64, ZkError> + Send + Sync>;
+
+/// Calibrated proving cost of one proof mode on the reference hardware
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModeCost {
+    /// Time paid regardless of program size (setup, wrapping)
+    pub fixed_secs: f64,
+    pub secs_per_mcycle: f64,
+}
+
+impl ModeCost {
+    fn predict(&self, cycles: u64) -> Duration {
+        Duration::from_secs_f64(self.fixed_secs + self.secs_per_mcycle * cycles as f64 / 1e6)
+    }
+}
+
+/// Constants used to price a request before proving it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CostModel {
+    pub core: ModeCost,
+    pub compressed: ModeCost,
+    pub plonk: ModeCost,
+    pub groth16: ModeCost,
+    /// Network prover fee charged per request, in the market's smallest unit
+    pub network_base_fee: u64,
+    /// Network prover fee per million cycles
+    pub network_fee_per_mcycle: u64,
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        // Measured on a 32-core CPU prover
+        Self {
+            core: ModeCost {
+                fixed_secs: 2.0,
+                secs_per_mcycle: 1.0,
+            },
+            compressed: ModeCost {
+                fixed_secs: 20.0,
+                secs_per_mcycle: 1.3,
+            },
+            plonk: ModeCost {
+                fixed_secs: 110.0,
+                secs_per_mcycle: 1.3,
+            },
+            groth16: ModeCost {
+                fixed_secs: 80.0,
+                secs_per_mcycle: 1.3,
+            },
+            network_base_fee: 1_000,
+            network_fee_per_mcycle: 50,
+        }
+    }
+}
+
+impl CostModel {
+    pub fn mode_cost(&self, mode: ProofMode) -> ModeCost {
+        match mode {
+            ProofMode::Core => self.core,
+            ProofMode::Compressed => self.compressed,
+            ProofMode::Plonk => self.plonk,
+            ProofMode::Groth16 => self.groth16,
+        }
+    }
+
+    /// Network prover fee for `cycles`
+    pub fn network_cost(&self, cycles: u64) -> u64 {
+        self.network_base_fee
+            .saturating_add(self.network_fee_per_mcycle.saturating_mul(cycles.div_ceil(1_000_000)))
+    }
+}
+
+/// Predicted cost of proving a request
+#[derive(Debug, Clone, PartialEq)]
+pub struct CostEstimate {
+    pub program_hash: ProgramHash,
+    pub cycles: u64,
+    pub core: Duration,
+    pub compressed: Duration,
+    pub plonk: Duration,
+    pub groth16: Duration,
+    /// Fee a network prover would charge, in the market's smallest unit
+    pub network_cost: u64,
+}
+
+impl CostEstimate {
+    /// Predicted local proving time for `mode`
+    pub fn proving_time(&self, mode: ProofMode) -> Duration {
+        match mode {
+            ProofMode::Core => self.core,
+            ProofMode::Compressed => self.compressed,
+            ProofMode::Plonk => self.plonk,
+            ProofMode::Groth16 => self.groth16,
+        }
+    }
+}
+
+/// Prices requests by executing them, without proving
+pub struct CostEstimator {
+    count_cycles: CycleCounter,
+    model: CostModel,
+    learned: Option<Arc<DurationEstimator>>,
+}
+
+impl CostEstimator {
+    pub fn new(count_cycles: CycleCounter, model: CostModel) -> Self {
+        Self {
+            count_cycles,
+            model,
+            learned: None,
+        }
+    }
+
+    /// Prefer proving times learned on this node over the calibrated core
+    /// constants once the estimator has history
+    pub fn with_learned(mut self, learned: Arc<DurationEstimator>) -> Self {
+        self.learned = Some(learned);
+        self
+    }
+
+    pub fn estimate(&self, program: &[u8], input: &[u8]) -> Result<CostEstimate, ProverError> {
+        let hash = program_hash(program);
+        let span = tracing::info_span!("execute", program_hash = %hash, cycles = tracing::field::Empty);
+        let cycles = span.in_scope(|| (self.count_cycles)(program, input))?;
+        span.record("cycles", cycles);
+        let core = self
+            .learned
+            .as_ref()
+            .and_then(|learned| learned.estimate_duration(&hash, cycles))
+            .unwrap_or_else(|| self.model.core.predict(cycles));
+        // Wrapped modes cost the core proof plus their own overhead
+        let wrapped = |mode: ProofMode| {
+            let extra = self.model.mode_cost(mode).predict(cycles);
+            core + extra.saturating_sub(self.model.core.predict(cycles))
+        };
+        Ok(CostEstimate {
+            program_hash: hash,
+            cycles,
+            core,
+            compressed: wrapped(ProofMode::Compressed),
+            plonk: wrapped(ProofMode::Plonk),
+            groth16: wrapped(ProofMode::Groth16),
+            network_cost: self.model.network_cost(cycles),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_linear_fit() {
+        let estimator = DurationEstimator::new();
+        assert!(estimator.estimate_duration("program", 1_000).is_none());
+
+        // 2s fixed setup plus 1s per million cycles
+        for millions in [1, 2, 4, 8] {
+            estimator.record("program", millions * 1_000_000, Duration::from_secs(2 + millions));
+        }
+        let estimate = estimator.estimate_duration("program", 16_000_000).unwrap();
+        assert!((estimate.as_secs_f64() - 18.0).abs() < 0.5, "{:?}", estimate);
+
+        // Unknown programs use the overall model
+        assert!(estimator.estimate_duration("other", 16_000_000).is_some());
+    }
+
+    #[test]
+    fn test_single_size_scales_proportionally() {
+        let mut model = DurationModel::default();
+        model.observe(1_000, Duration::from_secs(10));
+        assert_eq!(model.predict(2_000), Some(Duration::from_secs(20)));
+    }
+
+    #[test]
+    fn test_cost_estimate() {
+        let counter: CycleCounter = Arc::new(|_, input| Ok(input.len() as u64 * 1_000_000));
+        let estimator = CostEstimator::new(counter, CostModel::default());
+
+        let estimate = estimator.estimate(b"elf", &[0; 10]).unwrap();
+        assert_eq!(estimate.cycles, 10_000_000);
+        assert_eq!(estimate.core, Duration::from_secs(12));
+        assert!(estimate.core < estimate.compressed && estimate.groth16 < estimate.plonk);
+        assert_eq!(estimate.network_cost, 1_500);
+    }
+}
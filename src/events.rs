@@ -0,0 +1,335 @@
+use crate::isolation::KeySetup;
+use crate::pipeline::ProofMode;
+use crate::types::{ProgramHash, program_hash};
+use frostgate_zkip::{ZkBackend, ZkError};
+use serde::{Serialize, Serializer};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+/// Capacity of the broadcast channel handed to async subscribers
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// Lifecycle events emitted across the crate. Events serialize as JSON
+/// objects tagged with an `event` field, with durations in milliseconds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum LifecycleEvent {
+    ProgramRegistered {
+        program_hash: ProgramHash,
+    },
+    /// Proving and verifying keys were generated for a program
+    ProgramSetup {
+        program_hash: ProgramHash,
+        vkey_hash: String,
+        #[serde(rename = "duration_ms", serialize_with = "as_millis")]
+        duration: Duration,
+    },
+    ProofStarted {
+        backend: String,
+        program_hash: ProgramHash,
+    },
+    ProofCompleted {
+        backend: String,
+        program_hash: ProgramHash,
+        mode: ProofMode,
+        #[serde(rename = "duration_ms", serialize_with = "as_millis")]
+        duration: Duration,
+        proof_bytes: usize,
+    },
+    ProofFailed {
+        backend: String,
+        program_hash: ProgramHash,
+        mode: ProofMode,
+        error: String,
+    },
+    /// A proof was checked; `valid` is false for rejected proofs, while
+    /// verifier errors are reported in `error`
+    ProofVerified {
+        backend: String,
+        program_hash: ProgramHash,
+        valid: bool,
+        error: Option<String>,
+    },
+    BackendQuarantined {
+        backend: String,
+        reason: String,
+    },
+    BackendReadmitted {
+        backend: String,
+    },
+    CacheEvicted {
+        cache: String,
+        key: String,
+    },
+}
+
+impl LifecycleEvent {
+    /// One-line description for chat notifications and logs
+    pub fn summary(&self) -> String {
+        let short = |hash: &str| hash.chars().take(12).collect::<String>();
+        match self {
+            LifecycleEvent::ProgramRegistered { program_hash } => format!("program {} registered", short(program_hash)),
+            LifecycleEvent::ProgramSetup {
+                program_hash, duration, ..
+            } => format!("program {} set up in {:?}", short(program_hash), duration),
+            LifecycleEvent::ProofStarted { backend, program_hash } => {
+                format!("{}: proving program {}", backend, short(program_hash))
+            }
+            LifecycleEvent::ProofCompleted {
+                backend,
+                program_hash,
+                duration,
+                ..
+            } => format!("{}: proved program {} in {:?}", backend, short(program_hash), duration),
+            LifecycleEvent::ProofFailed {
+                backend,
+                program_hash,
+                error,
+                ..
+            } => format!("{}: proving program {} failed: {}", backend, short(program_hash), error),
+            LifecycleEvent::ProofVerified {
+                backend,
+                program_hash,
+                valid,
+                error,
+            } => match error {
+                Some(error) => format!("{}: verifying a proof of {} failed: {}", backend, short(program_hash), error),
+                None => format!(
+                    "{}: proof of {} is {}",
+                    backend,
+                    short(program_hash),
+                    if *valid { "valid" } else { "invalid" }
+                ),
+            },
+            LifecycleEvent::BackendQuarantined { backend, reason } => format!("{} quarantined: {}", backend, reason),
+            LifecycleEvent::BackendReadmitted { backend } => format!("{} readmitted", backend),
+            LifecycleEvent::CacheEvicted { cache, key } => format!("{} cache evicted {}", cache, key),
+        }
+    }
+
+    /// Whether the event reports something going wrong
+    pub fn is_failure(&self) -> bool {
+        match self {
+            LifecycleEvent::ProofFailed { .. } | LifecycleEvent::BackendQuarantined { .. } => true,
+            LifecycleEvent::ProofVerified { valid, .. } => !valid,
+            _ => false,
+        }
+    }
+}
+
+fn as_millis<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_millis() as u64)
+}
+
+/// Receives lifecycle events synchronously, in emission order
+pub trait EventSubscriber: Send + Sync {
+    fn on_event(&self, event: &LifecycleEvent);
+}
+
+impl<F> EventSubscriber for F
+where
+    F: Fn(&LifecycleEvent) + Send + Sync,
+{
+    fn on_event(&self, event: &LifecycleEvent) {
+        self(event)
+    }
+}
+
+/// Handle for removing a subscriber
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+/// Crate-wide event bus that metrics, audit logging and webhooks subscribe to
+pub struct EventBus {
+    subscribers: RwLock<Vec<(SubscriptionId, Arc<dyn EventSubscriber>)>>,
+    channel: broadcast::Sender<LifecycleEvent>,
+    next_id: AtomicU64,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self {
+            subscribers: RwLock::new(Vec::new()),
+            channel: broadcast::channel(CHANNEL_CAPACITY).0,
+            next_id: AtomicU64::new(0),
+        }
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a synchronous subscriber
+    pub fn subscribe(&self, subscriber: Arc<dyn EventSubscriber>) -> SubscriptionId {
+        let id = SubscriptionId(self.next_id.fetch_add(1, Ordering::SeqCst));
+        self.subscribers
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push((id, subscriber));
+        id
+    }
+
+    /// Remove a synchronous subscriber
+    pub fn unsubscribe(&self, id: SubscriptionId) -> bool {
+        let mut subscribers = self.subscribers.write().unwrap_or_else(PoisonError::into_inner);
+        let before = subscribers.len();
+        subscribers.retain(|(sub_id, _)| *sub_id != id);
+        subscribers.len() != before
+    }
+
+    /// Receive events on a channel; slow receivers miss the oldest events
+    pub fn subscribe_channel(&self) -> broadcast::Receiver<LifecycleEvent> {
+        self.channel.subscribe()
+    }
+
+    /// Deliver an event to every subscriber
+    pub fn emit(&self, event: LifecycleEvent) {
+        let subscribers = self.subscribers.read().unwrap_or_else(PoisonError::into_inner).clone();
+        for (_, subscriber) in subscribers {
+            subscriber.on_event(&event);
+        }
+        // No receivers is not an error
+        let _ = self.channel.send(event);
+    }
+}
+
+/// Backend wrapper emitting proof lifecycle events
+pub struct EventedBackend {
+    inner: Arc<dyn ZkBackend>,
+    bus: Arc<EventBus>,
+    backend_id: String,
+    mode: ProofMode,
+}
+
+impl EventedBackend {
+    pub fn new(inner: Arc<dyn ZkBackend>, bus: Arc<EventBus>, backend_id: &str) -> Self {
+        Self {
+            inner,
+            bus,
+            backend_id: backend_id.to_string(),
+            mode: ProofMode::Core,
+        }
+    }
+
+    /// Mode of the proofs `inner` produces, reported with every proof.
+    /// Defaults to [`ProofMode::Core`].
+    pub fn with_mode(mut self, mode: ProofMode) -> Self {
+        self.mode = mode;
+        self
+    }
+}
+
+impl ZkBackend for EventedBackend {
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        let hash = program_hash(program);
+        self.bus.emit(LifecycleEvent::ProofStarted {
+            backend: self.backend_id.clone(),
+            program_hash: hash.clone(),
+        });
+        let start = Instant::now();
+        let result = self.inner.prove(program, input);
+        self.bus.emit(match &result {
+            Ok(proof) => LifecycleEvent::ProofCompleted {
+                backend: self.backend_id.clone(),
+                program_hash: hash,
+                mode: self.mode,
+                duration: start.elapsed(),
+                proof_bytes: proof.len(),
+            },
+            Err(e) => LifecycleEvent::ProofFailed {
+                backend: self.backend_id.clone(),
+                program_hash: hash,
+                mode: self.mode,
+                error: format!("{:?}", e),
+            },
+        });
+        result
+    }
+
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        let result = self.inner.verify(program, proof);
+        self.bus.emit(LifecycleEvent::ProofVerified {
+            backend: self.backend_id.clone(),
+            program_hash: program_hash(program),
+            valid: matches!(result, Ok(true)),
+            error: result.as_ref().err().map(|e| format!("{:?}", e)),
+        });
+        result
+    }
+}
+
+/// Wrap a key setup so each successful run emits
+/// [`LifecycleEvent::ProgramSetup`]
+pub fn evented_setup(setup: KeySetup, bus: Arc<EventBus>) -> KeySetup {
+    Arc::new(move |program| {
+        let start = Instant::now();
+        let (vkey_hash, vk) = setup(program)?;
+        bus.emit(LifecycleEvent::ProgramSetup {
+            program_hash: program_hash(program),
+            vkey_hash: vkey_hash.clone(),
+            duration: start.elapsed(),
+        });
+        Ok((vkey_hash, vk))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockBackend, MockConfig};
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_subscribers_see_proof_events() {
+        let bus = Arc::new(EventBus::new());
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        let id = bus.subscribe(Arc::new(move |event: &LifecycleEvent| {
+            sink.lock().unwrap().push(event.clone());
+        }));
+        let mut channel = bus.subscribe_channel();
+
+        let flaky = Arc::new(MockBackend::new(MockConfig {
+            fail_first_proves: 1,
+            ..Default::default()
+        }));
+        let backend = EventedBackend::new(flaky, bus.clone(), "mock");
+        assert!(backend.prove(b"elf", b"input").is_err());
+        assert!(backend.prove(b"elf", b"input").is_ok());
+
+        let events = seen.lock().unwrap().clone();
+        assert_eq!(events.len(), 4);
+        assert!(matches!(events[1], LifecycleEvent::ProofFailed { .. }));
+        assert!(matches!(events[3], LifecycleEvent::ProofCompleted { .. }));
+        assert!(matches!(channel.try_recv(), Ok(LifecycleEvent::ProofStarted { .. })));
+
+        assert!(bus.unsubscribe(id));
+        bus.emit(LifecycleEvent::CacheEvicted {
+            cache: "proofs".to_string(),
+            key: "k".to_string(),
+        });
+        assert_eq!(seen.lock().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_verify_and_setup_events() {
+        let bus = Arc::new(EventBus::new());
+        let mut channel = bus.subscribe_channel();
+        let backend = EventedBackend::new(Arc::new(MockBackend::default()), bus.clone(), "mock");
+        assert!(!backend.verify(b"elf", b"forged").unwrap());
+        let event = channel.try_recv().unwrap();
+        assert!(event.is_failure());
+        assert!(matches!(event, LifecycleEvent::ProofVerified { valid: false, error: None, .. }));
+
+        let setup = evented_setup(Arc::new(|_| Ok(("0x01".to_string(), vec![1]))), bus.clone());
+        setup(b"elf").unwrap();
+        let json = serde_json::to_value(channel.try_recv().unwrap()).unwrap();
+        assert_eq!(json["event"], "program_setup");
+        assert_eq!(json["vkey_hash"], "0x01");
+        assert!(json["duration_ms"].is_u64());
+    }
+}
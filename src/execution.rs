@@ -0,0 +1,216 @@
+use crate::estimation::CycleCounter;
+use crate::types::ProverError;
+use frostgate_zkip::{ZkBackend, ZkError};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Syscalls that are part of the zkVM's runtime rather than precompiles
+const RUNTIME_SYSCALLS: &[&str] = &[
+    "HALT",
+    "WRITE",
+    "ENTER_UNCONSTRAINED",
+    "EXIT_UNCONSTRAINED",
+    "COMMIT",
+    "COMMIT_DEFERRED_PROOFS",
+    "VERIFY_SP1_PROOF",
+    "HINT_LEN",
+    "HINT_READ",
+];
+
+/// What running a guest produced: its cycle count and committed public
+/// values, plus the proof when one was requested
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExecutionResult {
+    pub cycles: u64,
+    pub public_values: Vec<u8>,
+    pub proof: Option<Vec<u8>>,
+    /// Instruction-level counts, when the executor collected them
+    pub profile: Option<ExecutionProfile>,
+}
+
+/// Counts from SP1's execution report, keyed by SP1's names for opcodes,
+/// syscalls and `cycle-tracker` regions
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionProfile {
+    pub opcodes: BTreeMap<String, u64>,
+    pub syscalls: BTreeMap<String, u64>,
+    /// Cycles spent in each region marked with `cycle-tracker-start`/`end`
+    pub regions: BTreeMap<String, u64>,
+}
+
+#[cfg(any(feature = "sp1-v4", feature = "sp1-v5"))]
+impl From<&crate::compat::sdk::ExecutionReport> for ExecutionProfile {
+    fn from(report: &crate::compat::sdk::ExecutionReport) -> Self {
+        let nonzero = |(name, count): (String, u64)| (count > 0).then_some((name, count));
+        Self {
+            opcodes: report
+                .opcode_counts
+                .iter()
+                .map(|(opcode, count)| (format!("{:?}", opcode), *count))
+                .filter_map(nonzero)
+                .collect(),
+            syscalls: report
+                .syscall_counts
+                .iter()
+                .map(|(syscall, count)| (format!("{:?}", syscall), *count))
+                .filter_map(nonzero)
+                .collect(),
+            regions: report.cycle_tracker.iter().map(|(name, cycles)| (name.clone(), *cycles)).collect(),
+        }
+    }
+}
+
+/// Share of the guest's cycles spent in one tracked region
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RegionReport {
+    pub name: String,
+    pub cycles: u64,
+    /// Fraction of the total cycle count; nested regions overlap
+    pub share: f64,
+}
+
+/// Profile of one execution for circuit engineers, serialized as JSON by
+/// `frostgate-prover execute --json`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExecutionReport {
+    pub cycles: u64,
+    pub public_values_hex: String,
+    /// Instructions executed, summed over all opcodes
+    pub instructions: u64,
+    pub opcodes: BTreeMap<String, u64>,
+    pub syscalls: BTreeMap<String, u64>,
+    /// The subset of `syscalls` that invoke precompiles
+    pub precompiles: BTreeMap<String, u64>,
+    /// Tracked regions, most expensive first
+    pub regions: Vec<RegionReport>,
+}
+
+impl ExecutionResult {
+    /// Summarize the execution. Counts are empty if the executor didn't
+    /// collect a profile.
+    pub fn to_report(&self) -> ExecutionReport {
+        let profile = self.profile.clone().unwrap_or_default();
+        let precompiles = profile
+            .syscalls
+            .iter()
+            .filter(|(name, _)| !RUNTIME_SYSCALLS.contains(&name.as_str()))
+            .map(|(name, count)| (name.clone(), *count))
+            .collect();
+        let mut regions: Vec<RegionReport> = profile
+            .regions
+            .into_iter()
+            .map(|(name, cycles)| RegionReport {
+                name,
+                cycles,
+                share: if self.cycles == 0 { 0.0 } else { cycles as f64 / self.cycles as f64 },
+            })
+            .collect();
+        regions.sort_by(|a, b| b.cycles.cmp(&a.cycles).then_with(|| a.name.cmp(&b.name)));
+        ExecutionReport {
+            cycles: self.cycles,
+            public_values_hex: hex::encode(&self.public_values),
+            instructions: profile.opcodes.values().sum(),
+            opcodes: profile.opcodes,
+            syscalls: profile.syscalls,
+            precompiles,
+            regions,
+        }
+    }
+}
+
+/// Runs a program on an input without proving it
+pub type Executor = Arc<dyn Fn(&[u8], &[u8]) -> Result<ExecutionResult, ZkError> + Send + Sync>;
+
+/// Pairs a backend with an executor, so callers that only need cycle counts
+/// or outputs don't pay for a proof
+pub struct ExecutingProver {
+    backend: Arc<dyn ZkBackend>,
+    execute: Executor,
+}
+
+impl ExecutingProver {
+    pub fn new(backend: Arc<dyn ZkBackend>, execute: Executor) -> Self {
+        Self { backend, execute }
+    }
+
+    /// Run the program without generating a proof
+    pub fn execute(&self, program: &[u8], input: &[u8]) -> Result<ExecutionResult, ProverError> {
+        let _span = tracing::info_span!("execute", program_bytes = program.len()).entered();
+        let mut result = (self.execute)(program, input)?;
+        result.proof = None;
+        tracing::debug!("executed program in {} cycles", result.cycles);
+        Ok(result)
+    }
+
+    /// Run the program, then prove it. Execution fails fast on guest errors
+    /// before any proving time is spent.
+    pub fn execute_and_prove(&self, program: &[u8], input: &[u8]) -> Result<ExecutionResult, ProverError> {
+        let mut result = self.execute(program, input)?;
+        result.proof = Some(self.backend.prove(program, input)?);
+        Ok(result)
+    }
+
+    /// Cycle counts from the executor, for estimators and pipelines
+    pub fn cycle_counter(&self) -> CycleCounter {
+        let execute = self.execute.clone();
+        Arc::new(move |program, input| execute(program, input).map(|result| result.cycles))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockBackend;
+
+    #[test]
+    fn test_execute_skips_proving() {
+        let backend = Arc::new(MockBackend::default());
+        let prover = ExecutingProver::new(
+            backend.clone(),
+            Arc::new(|_program, input| {
+                Ok(ExecutionResult {
+                    cycles: input.len() as u64,
+                    public_values: input.to_vec(),
+                    ..ExecutionResult::default()
+                })
+            }),
+        );
+
+        let executed = prover.execute(b"elf", b"input").unwrap();
+        assert_eq!(executed.cycles, 5);
+        assert_eq!(executed.public_values, b"input");
+        assert!(executed.proof.is_none());
+        assert_eq!(backend.prove_calls(), 0);
+
+        let proved = prover.execute_and_prove(b"elf", b"input").unwrap();
+        assert_eq!(proved.proof.unwrap(), backend.prove(b"elf", b"input").unwrap());
+        assert_eq!((prover.cycle_counter())(b"elf", b"abc").unwrap(), 3);
+    }
+
+    #[test]
+    fn test_report() {
+        let counts = |entries: &[(&str, u64)]| entries.iter().map(|(k, v)| (k.to_string(), *v)).collect();
+        let result = ExecutionResult {
+            cycles: 1000,
+            public_values: vec![0xab],
+            profile: Some(ExecutionProfile {
+                opcodes: counts(&[("ADD", 600), ("ECALL", 3)]),
+                syscalls: counts(&[("WRITE", 1), ("KECCAK_PERMUTE", 2)]),
+                regions: counts(&[("hash", 250), ("verify", 500)]),
+            }),
+            ..ExecutionResult::default()
+        };
+
+        let report = result.to_report();
+        assert_eq!(report.instructions, 603);
+        assert_eq!(report.precompiles, counts(&[("KECCAK_PERMUTE", 2)]));
+        assert_eq!(report.regions[0].name, "verify");
+        assert_eq!(report.regions[0].share, 0.5);
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["public_values_hex"], "ab");
+        assert_eq!(json["syscalls"]["WRITE"], 1);
+        assert_eq!(json["regions"][1]["cycles"], 250);
+    }
+}
@@ -0,0 +1,75 @@
+use crate::envelope::EnvelopedBackend;
+use crate::isolation::{IsolatedBackend, KeyHolderConfig};
+use crate::registry::{self, ZkCapability};
+use crate::types::ProverError;
+use frostgate_zkip::{ZkBackend, ZkError};
+use std::sync::Arc;
+
+/// Capability advertised by external backends in the registry
+pub const EXTERNAL_CAPABILITY: ZkCapability = ZkCapability::Custom("external");
+
+/// Backend proving with a proof system other than SP1, through a host
+/// program that speaks the key-holder protocol (see
+/// [`serve_key_holder`](crate::isolation::serve_key_holder)). Proofs are
+/// wrapped in envelopes tagged with the host's proof system, so they can't
+/// be mistaken for proofs of backends registered alongside, and latencies
+/// can be compared through the registry.
+pub struct ExternalBackend {
+    inner: EnvelopedBackend,
+}
+
+impl ExternalBackend {
+    /// Wrap a backend producing native `proof_system` proofs
+    pub fn new(host: Arc<dyn ZkBackend>, backend_id: &str, proof_system: &str) -> Self {
+        Self {
+            inner: EnvelopedBackend::new(host, backend_id, proof_system),
+        }
+    }
+
+    /// Launch a host program as a key holder and prove through it
+    pub fn spawn(config: &KeyHolderConfig, backend_id: &str, proof_system: &str) -> Result<Self, ProverError> {
+        Ok(Self::new(Arc::new(IsolatedBackend::spawn(config)?), backend_id, proof_system))
+    }
+
+    /// Register in the global registry as a local CPU backend with the
+    /// [`EXTERNAL_CAPABILITY`]
+    pub fn register(self, id: &str) -> Result<(), ZkError> {
+        registry::register_backend_with_capabilities(
+            id.to_string(),
+            Arc::new(self),
+            &[ZkCapability::LocalCpu, EXTERNAL_CAPABILITY],
+        )
+    }
+}
+
+impl ZkBackend for ExternalBackend {
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        self.inner.prove(program, input)
+    }
+
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        self.inner.verify(program, proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::envelope::{DecodeLimits, decode_envelope};
+    use crate::inspect::PROOF_SYSTEM_KEY;
+    use crate::testing::MockBackend;
+
+    #[test]
+    fn test_proofs_are_tagged() {
+        let host: Arc<dyn ZkBackend> = Arc::new(MockBackend::default());
+        let external = ExternalBackend::new(host.clone(), "risc0-local", "risc0");
+        let proof = external.prove(b"elf", b"input").unwrap();
+
+        let envelope = decode_envelope(&proof, &DecodeLimits::default()).unwrap();
+        assert_eq!(envelope.metadata.get(PROOF_SYSTEM_KEY).map(String::as_str), Some("risc0"));
+        assert!(external.verify(b"elf", &proof).unwrap());
+
+        let sp1 = EnvelopedBackend::new(host, "sp1-local", "sp1");
+        assert!(!external.verify(b"elf", &sp1.prove(b"elf", b"input").unwrap()).unwrap());
+    }
+}
@@ -0,0 +1,150 @@
+use crate::registry;
+use crate::types::ProverError;
+use frostgate_zkip::{ZkBackend, ZkError};
+
+/// Tries registry backends in order, moving on when one fails with a
+/// retryable error or reports itself unhealthy, e.g. network proving with a
+/// local fallback. Fatal errors, such as a bad program, are returned as they
+/// are, since every backend would fail the same way.
+pub struct FailoverBackend {
+    backend_ids: Vec<String>,
+    reverify_with_primary: bool,
+}
+
+impl FailoverBackend {
+    /// `backend_ids` are registry IDs in order of preference
+    pub fn new(backend_ids: Vec<String>) -> Self {
+        Self {
+            backend_ids,
+            reverify_with_primary: false,
+        }
+    }
+
+    /// Check proofs produced by a fallback with the first backend's verifier
+    /// before returning them
+    pub fn with_reverify(mut self, reverify: bool) -> Self {
+        self.reverify_with_primary = reverify;
+        self
+    }
+
+    /// Prove, returning the proof and the ID of the backend that produced it
+    pub fn prove_with_source(&self, program: &[u8], input: &[u8]) -> Result<(Vec<u8>, String), ProverError> {
+        let mut failures = Vec::new();
+        for (position, id) in self.backend_ids.iter().enumerate() {
+            let Some(backend) = registry::get_backend(id) else {
+                failures.push(format!("{}: not registered", id));
+                continue;
+            };
+            if registry::backend_profile(id).is_some_and(|profile| !profile.healthy) {
+                failures.push(format!("{}: unhealthy", id));
+                continue;
+            }
+            match backend.prove(program, input) {
+                Ok(proof) => {
+                    if position > 0 {
+                        tracing::warn!("proved on fallback backend {} after: {}", id, failures.join("; "));
+                        if self.reverify_with_primary {
+                            self.reverify(program, &proof, id)?;
+                        }
+                    }
+                    return Ok((proof, id.clone()));
+                }
+                Err(e) => {
+                    let e = ProverError::from(e);
+                    if !e.is_retryable() {
+                        tracing::warn!("backend {} failed to prove, not failing over: {:?}", id, e);
+                        return Err(e);
+                    }
+                    tracing::warn!("backend {} failed to prove: {:?}", id, e);
+                    failures.push(format!("{}: {:?}", id, e));
+                }
+            }
+        }
+        Err(ProverError::Other(format!(
+            "all backends failed: {}",
+            failures.join("; ")
+        )))
+    }
+
+    fn reverify(&self, program: &[u8], proof: &[u8], source: &str) -> Result<(), ProverError> {
+        let primary_id = self
+            .backend_ids
+            .first()
+            .ok_or_else(|| ProverError::Other("no backends configured".to_string()))?;
+        let primary = registry::get_backend(primary_id)
+            .ok_or_else(|| ProverError::Other(format!("primary backend {} not registered", primary_id)))?;
+        if primary.verify(program, proof)? {
+            Ok(())
+        } else {
+            Err(ProverError::Other(format!(
+                "proof from fallback {} rejected by primary {}",
+                source, primary_id
+            )))
+        }
+    }
+}
+
+impl ZkBackend for FailoverBackend {
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        self.prove_with_source(program, input)
+            .map(|(proof, _)| proof)
+            .map_err(ProverError::into_zk_error)
+    }
+
+    /// Verify with the first registered, healthy backend
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        let backend = self
+            .backend_ids
+            .iter()
+            .filter(|id| registry::backend_profile(id).is_none_or(|profile| profile.healthy))
+            .find_map(|id| registry::get_backend(id))
+            .ok_or_else(|| ZkError::Config("no healthy backend to verify with".to_string()))?;
+        backend.verify(program, proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockBackend;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_falls_over_to_next_backend() {
+        let failing = Arc::new(MockBackend::default());
+        for _ in 0..2 {
+            failing.inject_failure(ProverError::NetworkUnavailable("prover network".to_string()).into_zk_error());
+        }
+        registry::register_backend("failover-network".to_string(), failing).unwrap();
+        registry::register_backend("failover-local".to_string(), Arc::new(MockBackend::default())).unwrap();
+
+        let backend = FailoverBackend::new(vec!["failover-network".to_string(), "failover-local".to_string()])
+            .with_reverify(true);
+        let (proof, source) = backend.prove_with_source(b"elf", b"input").unwrap();
+        assert_eq!(source, "failover-local");
+        assert!(backend.verify(b"elf", &proof).unwrap());
+
+        registry::report_backend_health("failover-local", false, 0.0).unwrap();
+        assert!(backend.prove(b"elf", b"input").is_err());
+
+        registry::unregister_backend("failover-network");
+        registry::unregister_backend("failover-local");
+    }
+
+    #[test]
+    fn test_fatal_error_not_failed_over() {
+        let primary = Arc::new(MockBackend::default());
+        primary.inject_failure(ProverError::InvalidProgram("not an ELF".to_string()).into_zk_error());
+        let fallback = Arc::new(MockBackend::default());
+        registry::register_backend("fatal-primary".to_string(), primary).unwrap();
+        registry::register_backend("fatal-fallback".to_string(), fallback.clone()).unwrap();
+
+        let backend = FailoverBackend::new(vec!["fatal-primary".to_string(), "fatal-fallback".to_string()]);
+        let err = backend.prove_with_source(b"elf", b"input").unwrap_err();
+        assert_eq!(err.code(), ProverError::InvalidProgram(String::new()).code());
+        assert_eq!(fallback.prove_calls(), 0);
+
+        registry::unregister_backend("fatal-primary");
+        registry::unregister_backend("fatal-fallback");
+    }
+}
@@ -0,0 +1,188 @@
+use crate::envelope::{DecodeLimits, ProofEnvelope, decode_envelope};
+use crate::isolation::KeySetup;
+use crate::types::{ProgramHash, ProverError, program_hash};
+use crate::vkeys::VkeyStore;
+use frostgate_zkip::ZkBackend;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File listing the fixtures in a fixtures directory
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// A guest program and input to generate a golden proof for
+#[derive(Debug, Clone)]
+pub struct FixtureSpec {
+    pub name: String,
+    pub program: Vec<u8>,
+    pub input: Vec<u8>,
+}
+
+/// Manifest entry describing one generated fixture
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FixtureEntry {
+    pub name: String,
+    pub backend: String,
+    pub program_hash: ProgramHash,
+    pub program_file: String,
+    pub proof_file: String,
+    /// Verifying key exported with [`VkeyStore::export_vk`], if key setup ran
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vk_file: Option<String>,
+}
+
+/// A fixture loaded back from disk
+#[derive(Debug, Clone)]
+pub struct Fixture {
+    pub entry: FixtureEntry,
+    pub program: Vec<u8>,
+    pub envelope: ProofEnvelope,
+    /// Exported verifying key, for [`VkeyStore::import_vk`]
+    pub vk: Option<Vec<u8>>,
+}
+
+/// Prove every spec with `backend` and write programs, proof envelopes and a
+/// manifest to `dir`, so downstream crates can test verification without a
+/// prover. With `setup`, each program's verifying key is written too.
+pub fn generate_fixtures(
+    backend: &dyn ZkBackend,
+    backend_id: &str,
+    setup: Option<&KeySetup>,
+    specs: &[FixtureSpec],
+    dir: &Path,
+) -> Result<Vec<FixtureEntry>, ProverError> {
+    fs::create_dir_all(dir)?;
+
+    let vkeys = VkeyStore::new();
+    let mut entries = Vec::with_capacity(specs.len());
+    for spec in specs {
+        tracing::info!("generating fixture '{}'", spec.name);
+        let proof = backend.prove(&spec.program, &spec.input)?;
+        if !backend.verify(&spec.program, &proof)? {
+            return Err(ProverError::Other(format!(
+                "fixture '{}' does not verify",
+                spec.name
+            )));
+        }
+
+        let hash = program_hash(&spec.program);
+        let envelope = ProofEnvelope::new(backend_id, hash.clone(), proof, Vec::new());
+        let vk = match setup {
+            Some(setup) => {
+                let (vkey_hash, vk) = setup(&spec.program)?;
+                vkeys.insert(hash.clone(), vkey_hash, vk);
+                Some(vkeys.export_vk(&hash)?)
+            }
+            None => None,
+        };
+        let entry = FixtureEntry {
+            name: spec.name.clone(),
+            backend: backend_id.to_string(),
+            program_hash: hash,
+            program_file: format!("{}.elf", spec.name),
+            proof_file: format!("{}.proof.json", spec.name),
+            vk_file: vk.as_ref().map(|_| format!("{}.vk", spec.name)),
+        };
+        fs::write(dir.join(&entry.program_file), &spec.program)?;
+        fs::write(dir.join(&entry.proof_file), envelope.to_bytes()?)?;
+        if let (Some(vk_file), Some(vk)) = (&entry.vk_file, vk) {
+            fs::write(dir.join(vk_file), vk)?;
+        }
+        entries.push(entry);
+    }
+
+    fs::write(dir.join(MANIFEST_FILE), serde_json::to_vec_pretty(&entries)?)?;
+    Ok(entries)
+}
+
+/// List the fixtures in `dir`
+pub fn read_manifest(dir: &Path) -> Result<Vec<FixtureEntry>, ProverError> {
+    Ok(serde_json::from_slice(&fs::read(dir.join(MANIFEST_FILE))?)?)
+}
+
+/// Load the fixture called `name` from `dir`
+pub fn load_fixture(dir: &Path, name: &str) -> Result<Fixture, ProverError> {
+    let entry = read_manifest(dir)?
+        .into_iter()
+        .find(|e| e.name == name)
+        .ok_or(ProverError::ProgramNotFound)?;
+    let program = fs::read(dir.join(&entry.program_file))?;
+    let envelope = decode_envelope(&fs::read(dir.join(&entry.proof_file))?, &DecodeLimits::default())?;
+    let vk = entry.vk_file.as_ref().map(|vk_file| fs::read(dir.join(vk_file))).transpose()?;
+    Ok(Fixture {
+        entry,
+        program,
+        envelope,
+        vk,
+    })
+}
+
+/// Default fixtures directory of a crate, `<manifest dir>/fixtures`
+pub fn default_fixtures_dir(manifest_dir: &str) -> PathBuf {
+    Path::new(manifest_dir).join("fixtures")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockBackend, MockConfig, mock_vkey_hash, mock_vkeys};
+    use std::sync::Arc;
+
+    fn specs() -> Vec<FixtureSpec> {
+        ["fibonacci", "sha"]
+            .iter()
+            .map(|name| FixtureSpec {
+                name: name.to_string(),
+                program: format!("{}.elf", name).into_bytes(),
+                input: b"input".to_vec(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_generate_and_load() {
+        let dir = std::env::temp_dir().join(format!("frostgate-fixtures-{}", uuid::Uuid::new_v4()));
+        let backend = MockBackend::default();
+        let entries = generate_fixtures(&backend, "mock", None, &specs(), &dir).unwrap();
+        assert_eq!(read_manifest(&dir).unwrap(), entries);
+
+        let fixture = load_fixture(&dir, "sha").unwrap();
+        assert_eq!(fixture.program, b"sha.elf");
+        assert_eq!(fixture.entry.program_hash, program_hash(b"sha.elf"));
+        assert_eq!(fixture.envelope.program_hash, fixture.entry.program_hash);
+        assert!(fixture.envelope.checksum_valid());
+        assert!(backend.verify(&fixture.program, &fixture.envelope.payload).unwrap());
+        assert!(matches!(load_fixture(&dir, "missing"), Err(ProverError::ProgramNotFound)));
+        assert_eq!(fixture.vk, None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_verifying_keys_written() {
+        let dir = std::env::temp_dir().join(format!("frostgate-fixtures-{}", uuid::Uuid::new_v4()));
+        let setup: KeySetup = Arc::new(|program| Ok((mock_vkey_hash(program), program.to_vec())));
+        let entries = generate_fixtures(&MockBackend::default(), "mock", Some(&setup), &specs(), &dir).unwrap();
+        assert_eq!(entries[1].vk_file.as_deref(), Some("sha.vk"));
+
+        let fixture = load_fixture(&dir, "sha").unwrap();
+        let vkeys = mock_vkeys();
+        assert_eq!(vkeys.import_vk(&fixture.vk.unwrap()).unwrap(), fixture.entry.program_hash);
+        assert_eq!(vkeys.get(&fixture.entry.program_hash).unwrap().vk, b"sha.elf");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_unverifiable_proof_rejected() {
+        let dir = std::env::temp_dir().join(format!("frostgate-fixtures-{}", uuid::Uuid::new_v4()));
+        let backend = MockBackend::new(MockConfig {
+            fail_verify: true,
+            ..MockConfig::default()
+        });
+        assert!(generate_fixtures(&backend, "mock", None, &specs(), &dir).is_err());
+        assert!(read_manifest(&dir).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
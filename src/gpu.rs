@@ -0,0 +1,233 @@
+use crate::types::{carried_error, mentions};
+use frostgate_zkip::{ZkBackend, ZkError};
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// Where local proofs are produced
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProverMode {
+    #[default]
+    Cpu,
+    /// SP1's CUDA prover on the given device index
+    Cuda { device: u32 },
+    Network,
+}
+
+impl ProverMode {
+    /// Parse `cpu`, `network`, `cuda` or `cuda:<device>`
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "cpu" => Some(ProverMode::Cpu),
+            "network" => Some(ProverMode::Network),
+            "cuda" | "gpu" => Some(ProverMode::Cuda { device: 0 }),
+            other => other
+                .strip_prefix("cuda:")
+                .and_then(|device| device.parse().ok())
+                .map(|device| ProverMode::Cuda { device }),
+        }
+    }
+
+    /// Inverse of [`ProverMode::parse`]
+    pub fn name(&self) -> String {
+        match self {
+            ProverMode::Cpu => "cpu".to_string(),
+            ProverMode::Cuda { device } => format!("cuda:{}", device),
+            ProverMode::Network => "network".to_string(),
+        }
+    }
+}
+
+/// State of one GPU
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GpuStatus {
+    pub device: u32,
+    pub total_memory_bytes: u64,
+    pub free_memory_bytes: u64,
+}
+
+/// Reports the GPUs present on the node
+pub type GpuProbe = Arc<dyn Fn() -> Vec<GpuStatus> + Send + Sync>;
+
+/// Query GPUs through `nvidia-smi`; no GPUs if it is missing or fails
+pub fn nvidia_smi_probe() -> Vec<GpuStatus> {
+    let output = Command::new("nvidia-smi")
+        .args(["--query-gpu=index,memory.total,memory.free", "--format=csv,noheader,nounits"])
+        .output();
+    match output {
+        Ok(output) if output.status.success() => parse_nvidia_smi(&String::from_utf8_lossy(&output.stdout)),
+        _ => Vec::new(),
+    }
+}
+
+/// Parse `index, total MiB, free MiB` lines
+fn parse_nvidia_smi(output: &str) -> Vec<GpuStatus> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(',').map(|field| field.trim().parse::<u64>().ok());
+            let device = u32::try_from(fields.next()??).ok()?;
+            let total = fields.next()??;
+            let free = fields.next()??;
+            Some(GpuStatus {
+                device,
+                total_memory_bytes: total * 1024 * 1024,
+                free_memory_bytes: free * 1024 * 1024,
+            })
+        })
+        .collect()
+}
+
+/// Health of the configured GPU as seen by the last check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GpuHealth {
+    Ready(GpuStatus),
+    LowMemory(GpuStatus),
+    Missing,
+}
+
+/// Proves on a CUDA backend while its device is present with enough free
+/// memory, falling back to a CPU backend otherwise or when the GPU prove
+/// fails with a device error. The device is probed when the backend is
+/// built and again after a device error or an explicit
+/// [`refresh`](GpuBackend::refresh), not on every proof.
+pub struct GpuBackend {
+    gpu: Arc<dyn ZkBackend>,
+    cpu: Arc<dyn ZkBackend>,
+    device: u32,
+    min_free_memory_bytes: u64,
+    probe: GpuProbe,
+    status: RwLock<Option<GpuStatus>>,
+}
+
+impl GpuBackend {
+    pub fn new(gpu: Arc<dyn ZkBackend>, cpu: Arc<dyn ZkBackend>, device: u32) -> Self {
+        Self::with_probe(gpu, cpu, device, Arc::new(nvidia_smi_probe))
+    }
+
+    /// Like [`GpuBackend::new`], probing the device with `probe` instead of
+    /// `nvidia-smi`
+    pub fn with_probe(gpu: Arc<dyn ZkBackend>, cpu: Arc<dyn ZkBackend>, device: u32, probe: GpuProbe) -> Self {
+        let backend = Self {
+            gpu,
+            cpu,
+            device,
+            min_free_memory_bytes: 8 << 30,
+            probe,
+            status: RwLock::new(None),
+        };
+        backend.refresh();
+        backend
+    }
+
+    /// Free device memory required to prove on the GPU
+    pub fn with_min_free_memory(mut self, bytes: u64) -> Self {
+        self.min_free_memory_bytes = bytes;
+        self
+    }
+
+    /// Probe the device again, e.g. from a periodic health check
+    pub fn refresh(&self) -> GpuHealth {
+        let status = (self.probe)().into_iter().find(|gpu| gpu.device == self.device);
+        *self.status.write().unwrap_or_else(PoisonError::into_inner) = status;
+        self.health_check()
+    }
+
+    /// Health as of the last probe
+    pub fn health_check(&self) -> GpuHealth {
+        match *self.status.read().unwrap_or_else(PoisonError::into_inner) {
+            Some(status) if status.free_memory_bytes >= self.min_free_memory_bytes => GpuHealth::Ready(status),
+            Some(status) => GpuHealth::LowMemory(status),
+            None => GpuHealth::Missing,
+        }
+    }
+}
+
+/// Whether a prover error comes from the GPU rather than the program or
+/// the host. Host memory exhaustion isn't one: retrying on CPU would hit
+/// it again.
+fn is_device_error(e: &ZkError) -> bool {
+    if carried_error(e).is_some() {
+        return false;
+    }
+    let msg = format!("{:?}", e);
+    ["cuda", "cublas", "gpu", "device lost"]
+        .iter()
+        .any(|phrase| mentions(&msg, phrase))
+}
+
+impl ZkBackend for GpuBackend {
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        match self.health_check() {
+            GpuHealth::Ready(_) => {}
+            health => {
+                tracing::warn!("GPU {} unavailable ({:?}), proving on CPU", self.device, health);
+                return self.cpu.prove(program, input);
+            }
+        }
+        match self.gpu.prove(program, input) {
+            Err(e) if is_device_error(&e) => {
+                tracing::warn!("GPU prove failed ({:?}), retrying on CPU", e);
+                self.refresh();
+                self.cpu.prove(program, input)
+            }
+            result => result,
+        }
+    }
+
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        // Verification is cheap; keep the GPU free for proving
+        self.cpu.verify(program, proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockBackend;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    #[test]
+    fn test_falls_back_to_cpu() {
+        let gpu = Arc::new(MockBackend::default());
+        gpu.inject_failure(ZkError::Config("CUDA error: device lost".to_string()));
+        let cpu = Arc::new(MockBackend::default());
+        let free = Arc::new(AtomicU64::new(16 << 30));
+        let reading = free.clone();
+        let probes = Arc::new(AtomicU64::new(0));
+        let probed = probes.clone();
+        let backend = GpuBackend::with_probe(
+            gpu.clone(),
+            cpu.clone(),
+            0,
+            Arc::new(move || {
+                probed.fetch_add(1, Ordering::SeqCst);
+                parse_nvidia_smi(&format!("0, 24576, {}\n", reading.load(Ordering::SeqCst) >> 20))
+            }),
+        );
+
+        assert!(matches!(backend.health_check(), GpuHealth::Ready(_)));
+        backend.prove(b"elf", b"input").unwrap();
+        assert_eq!((gpu.prove_calls(), cpu.prove_calls()), (1, 1));
+        // Probed when built and again after the device error
+        assert_eq!(probes.load(Ordering::SeqCst), 2);
+
+        free.store(1 << 30, Ordering::SeqCst);
+        assert!(matches!(backend.health_check(), GpuHealth::Ready(_)));
+        assert!(matches!(backend.refresh(), GpuHealth::LowMemory(_)));
+        backend.prove(b"elf", b"input").unwrap();
+        assert_eq!((gpu.prove_calls(), cpu.prove_calls()), (1, 2));
+        assert_eq!(ProverMode::parse("cuda:1"), Some(ProverMode::Cuda { device: 1 }));
+    }
+
+    #[test]
+    fn test_device_errors() {
+        assert!(is_device_error(&ZkError::Config("CUDA error: device lost".to_string())));
+        assert!(is_device_error(&ZkError::Config("GPU out of memory".to_string())));
+        assert!(is_device_error(&ZkError::Config("CUDA_ERROR_OUT_OF_MEMORY".to_string())));
+        assert!(!is_device_error(&ZkError::Config("memory allocation failed: out of memory".to_string())));
+        assert!(!is_device_error(&ZkError::Config("No space left on device".to_string())));
+        assert!(!is_device_error(&ZkError::Config("invalid input: debug build".to_string())));
+    }
+}
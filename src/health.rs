@@ -0,0 +1,179 @@
+use crate::events::{EventBus, LifecycleEvent};
+use crate::registry;
+use frostgate_zkip::ZkBackend;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+/// Checks whether a backend is able to serve requests, e.g. by verifying a
+/// known-good proof. Returns the reason on failure.
+pub type HealthProbe = Arc<dyn Fn(&str, &dyn ZkBackend) -> Result<(), String> + Send + Sync>;
+
+/// When to quarantine and re-admit backends
+#[derive(Debug, Clone)]
+pub struct HealthMonitorConfig {
+    pub interval: Duration,
+    /// Consecutive failed probes before a backend is quarantined
+    pub failure_threshold: u32,
+    /// Consecutive successful probes before a quarantined backend is re-admitted
+    pub recovery_threshold: u32,
+}
+
+impl Default for HealthMonitorConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(30),
+            failure_threshold: 3,
+            recovery_threshold: 2,
+        }
+    }
+}
+
+#[derive(Default)]
+struct ProbeState {
+    quarantined: bool,
+    consecutive_failures: u32,
+    consecutive_successes: u32,
+}
+
+/// Periodically probes every registered backend, quarantining failing ones
+/// (they are reported unhealthy and excluded from routing) and re-admitting
+/// them after enough successful probes
+pub struct HealthMonitor {
+    config: HealthMonitorConfig,
+    probe: HealthProbe,
+    events: Option<Arc<EventBus>>,
+    states: Mutex<HashMap<String, ProbeState>>,
+}
+
+impl HealthMonitor {
+    pub fn new(config: HealthMonitorConfig, probe: HealthProbe) -> Self {
+        Self {
+            config,
+            probe,
+            events: None,
+            states: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Emit `BackendQuarantined` and `BackendReadmitted` on `events`
+    pub fn with_events(mut self, events: Arc<EventBus>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Whether `id` is currently quarantined
+    pub fn is_quarantined(&self, id: &str) -> bool {
+        self.states
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(id)
+            .is_some_and(|state| state.quarantined)
+    }
+
+    /// Probe every registered backend once
+    pub fn check_all(&self) {
+        for id in registry::list_backends() {
+            let Some(backend) = registry::get_backend(&id) else {
+                continue;
+            };
+            let result = (self.probe)(&id, backend.as_ref());
+            self.record(&id, result);
+        }
+        let registered = registry::list_backends();
+        self.states
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|id, _| registered.contains(id));
+    }
+
+    /// Record a probe outcome for `id`, quarantining or re-admitting it when a
+    /// threshold is crossed
+    pub fn record(&self, id: &str, result: Result<(), String>) {
+        let mut states = self.states.lock().unwrap_or_else(PoisonError::into_inner);
+        let state = states.entry(id.to_string()).or_default();
+        let event = match result {
+            Err(reason) => {
+                state.consecutive_failures += 1;
+                state.consecutive_successes = 0;
+                if state.quarantined || state.consecutive_failures < self.config.failure_threshold {
+                    return;
+                }
+                state.quarantined = true;
+                tracing::warn!("quarantining backend {}: {}", id, reason);
+                LifecycleEvent::BackendQuarantined {
+                    backend: id.to_string(),
+                    reason,
+                }
+            }
+            Ok(()) => {
+                state.consecutive_successes += 1;
+                state.consecutive_failures = 0;
+                if !state.quarantined || state.consecutive_successes < self.config.recovery_threshold {
+                    return;
+                }
+                state.quarantined = false;
+                tracing::info!("re-admitting backend {}", id);
+                LifecycleEvent::BackendReadmitted { backend: id.to_string() }
+            }
+        };
+        let healthy = !state.quarantined;
+        drop(states);
+
+        let load = registry::backend_profile(id).map_or(0.0, |profile| profile.load);
+        if let Err(e) = registry::report_backend_health(id, healthy, load) {
+            tracing::debug!("backend {} left the registry: {:?}", id, e);
+        }
+        if let Some(events) = &self.events {
+            events.emit(event);
+        }
+    }
+
+    /// Spawn a task that probes all backends every `interval`
+    pub fn spawn_monitor(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.config.interval);
+            loop {
+                ticker.tick().await;
+                let monitor = self.clone();
+                if let Err(e) = tokio::task::spawn_blocking(move || monitor.check_all()).await {
+                    tracing::error!("health check task failed: {}", e);
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockBackend;
+
+    #[test]
+    fn test_quarantine_and_readmission() {
+        registry::register_backend("health-mock".to_string(), Arc::new(MockBackend::default())).unwrap();
+        let events = Arc::new(EventBus::new());
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        events.subscribe(Arc::new(move |event: &LifecycleEvent| {
+            sink.lock().unwrap().push(event.clone());
+        }));
+        let probe: HealthProbe = Arc::new(|_, _| Ok(()));
+        let monitor = HealthMonitor::new(HealthMonitorConfig::default(), probe).with_events(events);
+
+        for _ in 0..3 {
+            monitor.record("health-mock", Err("probe failed".to_string()));
+        }
+        assert!(monitor.is_quarantined("health-mock"));
+        assert!(!registry::backend_profile("health-mock").unwrap().healthy);
+
+        monitor.record("health-mock", Ok(()));
+        assert!(monitor.is_quarantined("health-mock"));
+        monitor.record("health-mock", Ok(()));
+        assert!(!monitor.is_quarantined("health-mock"));
+        assert!(registry::backend_profile("health-mock").unwrap().healthy);
+        assert_eq!(seen.lock().unwrap().len(), 2);
+
+        registry::unregister_backend("health-mock");
+    }
+}
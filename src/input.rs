@@ -0,0 +1,142 @@
+use crate::compat::sdk::{SP1Proof, SP1ProofWithPublicValues, SP1Stdin, SP1VerifyingKey};
+use crate::streaming::InputChunks;
+use crate::types::ProverError;
+use frostgate_zkip::ZkError;
+use serde::{Deserialize, Serialize};
+
+/// A proof handed to the guest for recursive verification
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecursionProof {
+    /// bincode-serialized `SP1ProofWithPublicValues`, which must be compressed
+    pub proof: Vec<u8>,
+    /// bincode-serialized `SP1VerifyingKey` of the proven program
+    pub vkey: Vec<u8>,
+}
+
+/// Guest stdin with SP1's multi-value semantics: each `write` becomes a
+/// separate `sp1_zkvm::io::read` on the guest side
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sp1Input {
+    pub buffer: Vec<Vec<u8>>,
+    pub proofs: Vec<RecursionProof>,
+}
+
+impl Sp1Input {
+    /// Serialize for a backend's `input` parameter
+    pub fn encode(&self) -> Result<Vec<u8>, ProverError> {
+        bincode::serialize(self).map_err(|e| ProverError::Other(format!("encoding stdin: {}", e)))
+    }
+
+    /// Inverse of [`Sp1Input::encode`]
+    pub fn decode(bytes: &[u8]) -> Result<Self, ProverError> {
+        bincode::deserialize(bytes).map_err(|e| ProverError::Other(format!("decoding stdin: {}", e)))
+    }
+
+    /// Build the SDK's stdin
+    pub fn to_sp1_stdin(&self) -> Result<SP1Stdin, ProverError> {
+        let mut stdin = SP1Stdin::new();
+        for item in &self.buffer {
+            stdin.write_slice(item);
+        }
+        for recursion in &self.proofs {
+            let proof: SP1ProofWithPublicValues = bincode::deserialize(&recursion.proof)
+                .map_err(|e| ProverError::Other(format!("decoding recursion proof: {}", e)))?;
+            let vkey: SP1VerifyingKey = bincode::deserialize(&recursion.vkey)
+                .map_err(|e| ProverError::Other(format!("decoding recursion vkey: {}", e)))?;
+            let SP1Proof::Compressed(reduced) = proof.proof else {
+                return Err(ProverError::Other(
+                    "only compressed proofs can be verified recursively".to_string(),
+                ));
+            };
+            stdin.write_proof(*reduced, vkey.vk);
+        }
+        Ok(stdin)
+    }
+}
+
+/// Builds an [`Sp1Input`] value by value instead of pre-flattening
+/// everything into one byte slice
+#[derive(Debug, Clone, Default)]
+pub struct Sp1InputBuilder {
+    input: Sp1Input,
+}
+
+impl Sp1InputBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write a value the guest reads with `sp1_zkvm::io::read::<T>()`
+    pub fn write<T: Serialize>(mut self, value: &T) -> Result<Self, ProverError> {
+        let bytes = bincode::serialize(value).map_err(|e| ProverError::Other(format!("encoding stdin value: {}", e)))?;
+        self.input.buffer.push(bytes);
+        Ok(self)
+    }
+
+    /// Write raw bytes the guest reads with `sp1_zkvm::io::read_vec()`
+    pub fn write_slice(mut self, bytes: &[u8]) -> Self {
+        self.input.buffer.push(bytes.to_vec());
+        self
+    }
+
+    /// Write each item as a separate value
+    pub fn write_all<T: Serialize>(self, values: &[T]) -> Result<Self, ProverError> {
+        values.iter().try_fold(self, |builder, value| builder.write(value))
+    }
+
+    /// Queue a compressed proof for `sp1_zkvm::lib::verify::verify_sp1_proof`
+    pub fn write_proof(mut self, proof: RecursionProof) -> Self {
+        self.input.proofs.push(proof);
+        self
+    }
+
+    pub fn build(self) -> Sp1Input {
+        self.input
+    }
+
+    /// Shorthand for `build().encode()`
+    pub fn encode(self) -> Result<Vec<u8>, ProverError> {
+        self.input.encode()
+    }
+}
+
+/// Build stdin from streamed input: each chunk becomes a value the guest
+/// reads with `sp1_zkvm::io::read_vec()`, followed by an empty value marking
+/// the end of the stream
+pub fn stdin_from_chunks(chunks: &mut InputChunks<'_>) -> Result<SP1Stdin, ZkError> {
+    let mut stdin = SP1Stdin::new();
+    for chunk in chunks {
+        stdin.write_slice(&chunk?);
+    }
+    stdin.write_slice(&[]);
+    Ok(stdin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_values_stay_separate() {
+        let input = Sp1InputBuilder::new()
+            .write(&42u32)
+            .unwrap()
+            .write_slice(b"raw")
+            .write_all(&["a".to_string(), "b".to_string()])
+            .unwrap()
+            .build();
+
+        assert_eq!(input.buffer.len(), 4);
+        assert_eq!(input.buffer[0], 42u32.to_le_bytes());
+        assert_eq!(input.buffer[1], b"raw");
+        assert_eq!(Sp1Input::decode(&input.encode().unwrap()).unwrap(), input);
+        assert_eq!(input.to_sp1_stdin().unwrap().buffer.len(), 4);
+    }
+
+    #[test]
+    fn test_stdin_from_chunks() {
+        let mut chunks = [b"ab".to_vec(), b"c".to_vec()].into_iter().map(Ok);
+        let stdin = stdin_from_chunks(&mut chunks).unwrap();
+        assert_eq!(stdin.buffer, vec![b"ab".to_vec(), b"c".to_vec(), Vec::new()]);
+    }
+}
@@ -0,0 +1,194 @@
+use crate::envelope::ProofEnvelope;
+use crate::policies::PublicInputSchema;
+use frostgate_zkip::ZkBackend;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Metadata key naming the proof system, e.g. `"sp1"`
+pub const PROOF_SYSTEM_KEY: &str = "proof_system";
+/// Metadata key naming the proof mode, e.g. `"groth16"`
+pub const PROOF_MODE_KEY: &str = "proof_mode";
+
+/// Outcome of the optional verification step
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerificationStatus {
+    NotChecked,
+    Valid,
+    Invalid,
+    ChecksumMismatch,
+    Error(String),
+}
+
+/// Byte sizes of each envelope component
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ComponentSizes {
+    pub payload: usize,
+    pub public_values: usize,
+    pub serialized: usize,
+}
+
+/// Structured description of a proof envelope
+#[derive(Debug, Clone, Serialize)]
+pub struct InspectionReport {
+    pub version: u32,
+    pub backend: String,
+    pub proof_system: Option<String>,
+    pub proof_mode: Option<String>,
+    pub program_hash: String,
+    pub sizes: ComponentSizes,
+    pub public_values_hex: String,
+    /// Public values split by the schema, when one was supplied and matched
+    pub public_value_fields: Option<Vec<String>>,
+    pub metadata: BTreeMap<String, String>,
+    pub checksum_valid: bool,
+    pub verification: VerificationStatus,
+}
+
+/// Options controlling what [`inspect`] does beyond decoding
+#[derive(Default)]
+pub struct InspectOptions<'a> {
+    pub schema: Option<&'a PublicInputSchema>,
+    /// Backend and program to verify the proof against
+    pub verify_with: Option<(&'a dyn ZkBackend, &'a [u8])>,
+}
+
+/// Describe an envelope for debugging, optionally decoding public values
+/// against a schema and verifying the proof
+pub fn inspect(envelope: &ProofEnvelope, options: &InspectOptions<'_>) -> InspectionReport {
+    let checksum_valid = envelope.checksum_valid();
+    let serialized = envelope.to_bytes().map(|b| b.len()).unwrap_or(0);
+
+    let public_value_fields = options
+        .schema
+        .filter(|schema| schema.len() == envelope.public_values.len())
+        .map(|schema| {
+            let mut offset = 0;
+            schema
+                .fields
+                .iter()
+                .map(|width| {
+                    let field = hex::encode(&envelope.public_values[offset..offset + width]);
+                    offset += width;
+                    field
+                })
+                .collect()
+        });
+
+    let verification = match options.verify_with {
+        None => VerificationStatus::NotChecked,
+        Some(_) if !checksum_valid => VerificationStatus::ChecksumMismatch,
+        Some((backend, program)) => match backend.verify(program, &envelope.payload) {
+            Ok(true) => VerificationStatus::Valid,
+            Ok(false) => VerificationStatus::Invalid,
+            Err(e) => VerificationStatus::Error(format!("{:?}", e)),
+        },
+    };
+
+    InspectionReport {
+        version: envelope.version,
+        backend: envelope.backend.clone(),
+        proof_system: envelope.metadata.get(PROOF_SYSTEM_KEY).cloned(),
+        proof_mode: envelope.metadata.get(PROOF_MODE_KEY).cloned(),
+        program_hash: envelope.program_hash.clone(),
+        sizes: ComponentSizes {
+            payload: envelope.payload.len(),
+            public_values: envelope.public_values.len(),
+            serialized,
+        },
+        public_values_hex: hex::encode(&envelope.public_values),
+        public_value_fields,
+        metadata: envelope.metadata.clone(),
+        checksum_valid,
+        verification,
+    }
+}
+
+/// A field that differs between two envelopes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EnvelopeDifference {
+    pub field: String,
+    pub left: String,
+    pub right: String,
+}
+
+/// List the fields that differ between two envelopes, most significant first
+pub fn diff(left: &ProofEnvelope, right: &ProofEnvelope) -> Vec<EnvelopeDifference> {
+    let mut differences = Vec::new();
+    let mut compare = |field: &str, l: String, r: String| {
+        if l != r {
+            differences.push(EnvelopeDifference {
+                field: field.to_string(),
+                left: l,
+                right: r,
+            });
+        }
+    };
+
+    compare("program_hash", left.program_hash.clone(), right.program_hash.clone());
+    compare("backend", left.backend.clone(), right.backend.clone());
+    compare("version", left.version.to_string(), right.version.to_string());
+    for key in [PROOF_SYSTEM_KEY, PROOF_MODE_KEY] {
+        compare(
+            key,
+            left.metadata.get(key).cloned().unwrap_or_default(),
+            right.metadata.get(key).cloned().unwrap_or_default(),
+        );
+    }
+    compare(
+        "public_values",
+        hex::encode(&left.public_values),
+        hex::encode(&right.public_values),
+    );
+    compare(
+        "payload_size",
+        left.payload.len().to_string(),
+        right.payload.len().to_string(),
+    );
+    compare("checksum", left.checksum.clone(), right.checksum.clone());
+
+    let keys: std::collections::BTreeSet<_> = left.metadata.keys().chain(right.metadata.keys()).collect();
+    for key in keys {
+        if key == PROOF_SYSTEM_KEY || key == PROOF_MODE_KEY {
+            continue;
+        }
+        compare(
+            &format!("metadata.{}", key),
+            left.metadata.get(key).cloned().unwrap_or_default(),
+            right.metadata.get(key).cloned().unwrap_or_default(),
+        );
+    }
+    differences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockBackend, canned_envelope};
+
+    #[test]
+    fn test_inspect() {
+        let mut envelope = canned_envelope(b"elf", &[0xaa, 0xbb, 0xcc]);
+        envelope.insert_metadata(PROOF_MODE_KEY, "core");
+        let schema = PublicInputSchema::new(vec![1, 2]);
+        // Verifies proofs committing public values, as canned ones do
+        let backend = MockBackend::default().with_guest(std::sync::Arc::new(|_, input| Ok(input.to_vec())));
+
+        let report = inspect(
+            &envelope,
+            &InspectOptions {
+                schema: Some(&schema),
+                verify_with: Some((&backend, b"elf")),
+            },
+        );
+        assert_eq!(report.proof_mode.as_deref(), Some("core"));
+        assert_eq!(report.public_values_hex, "aabbcc");
+        assert_eq!(report.public_value_fields, Some(vec!["aa".to_string(), "bbcc".to_string()]));
+        assert_eq!(report.verification, VerificationStatus::Valid);
+
+        envelope.payload.push(0);
+        let report = inspect(&envelope, &InspectOptions::default());
+        assert!(!report.checksum_valid);
+        assert_eq!(report.verification, VerificationStatus::NotChecked);
+    }
+}
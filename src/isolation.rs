@@ -0,0 +1,780 @@
+use crate::cancel::{Abort, AbortableBackend};
+use crate::config::{ConfigError, ProverConfig};
+use crate::execution::{ExecutionProfile, ExecutionResult, Executor};
+use crate::memory::{ProofWorker, RssProbe};
+use crate::resources::read_process_rss_bytes;
+use crate::streaming::{InputChunks, StreamingBackend};
+use crate::types::{ProverError, program_hash};
+use crate::vkeys::VkeyStore;
+use frostgate_zkip::{ZkBackend, ZkError};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Request sent to the key-holder process
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum KeyHolderRequest {
+    Prove { program: String, input: String },
+    Verify { program: String, proof: String },
+    Setup { program: String },
+    Execute { program: String, input: String },
+    /// Prove with the input sent in the `Chunk` lines that follow, up to
+    /// an `EndStream` line; answered once, after the end
+    ProveStream { program: String },
+    Chunk { data: String },
+    EndStream,
+}
+
+/// Response from the key-holder process
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyHolderResponse {
+    Proof(String),
+    Verified(bool),
+    Vkey { vkey_hash: String, vk: String },
+    Executed {
+        cycles: u64,
+        public_values: String,
+        #[serde(default)]
+        profile: Option<ExecutionProfile>,
+    },
+    Error(String),
+}
+
+/// Runs key setup for a program, returning its vkey hash and serialized
+/// verifying key
+pub type KeySetup = Arc<dyn Fn(&[u8]) -> Result<(String, Vec<u8>), ZkError> + Send + Sync>;
+
+/// Operations a key holder supports beyond proving and verifying. Requests
+/// for a missing operation are answered with an error, except streamed
+/// proofs: without `streaming` their chunks are collected and proved whole.
+#[derive(Clone, Default)]
+pub struct KeyHolderOps {
+    pub setup: Option<KeySetup>,
+    pub execute: Option<Executor>,
+    pub streaming: Option<Arc<dyn StreamingBackend>>,
+}
+
+/// How to launch the key-holder process
+#[derive(Clone)]
+pub struct KeyHolderConfig {
+    /// Executable that calls [`serve_key_holder`]
+    pub program: PathBuf,
+    pub args: Vec<String>,
+    /// The only environment the child sees; put network credentials here
+    /// rather than in the parent's environment
+    pub env: Vec<(String, String)>,
+}
+
+/// Lists only the names of environment variables, whose values are often
+/// credentials
+impl fmt::Debug for KeyHolderConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyHolderConfig")
+            .field("program", &self.program)
+            .field("args", &self.args)
+            .field("env", &self.env.iter().map(|(name, _)| name).collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+struct KeyHolderPipes {
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+/// The key-holder process and the call it is answering
+struct KeyHolderProcess {
+    child: Child,
+    call: Option<u64>,
+}
+
+impl KeyHolderProcess {
+    fn kill(&mut self) {
+        if let Ok(None) = self.child.try_wait() {
+            tracing::warn!("killing key holder {}", self.child.id());
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+/// Backend that delegates key generation and proving to a separate process,
+/// so proving keys and credentials never enter this process's memory.
+///
+/// Calls are answered one at a time. [`IsolatedBackend::abort`] kills the
+/// process to stop a call in flight; the next call starts a new one. Through
+/// [`AbortableBackend`] a caller can stop its own call without touching the
+/// ones queued behind it.
+pub struct IsolatedBackend {
+    config: KeyHolderConfig,
+    pipes: Mutex<KeyHolderPipes>,
+    /// Kept apart from the pipes, which are locked for a whole call
+    process: Arc<Mutex<KeyHolderProcess>>,
+    calls: AtomicU64,
+    vkeys: Option<Arc<VkeyStore>>,
+}
+
+impl IsolatedBackend {
+    /// Spawn the key-holder process with a scrubbed environment
+    pub fn spawn(config: &KeyHolderConfig) -> Result<Self, ProverError> {
+        let (child, pipes) = Self::launch(config)?;
+        Ok(Self {
+            config: config.clone(),
+            pipes: Mutex::new(pipes),
+            process: Arc::new(Mutex::new(KeyHolderProcess { child, call: None })),
+            calls: AtomicU64::new(0),
+            vkeys: None,
+        })
+    }
+
+    /// Verify proofs of programs with a key in `vkeys` here, against that
+    /// key, rather than in the key holder
+    pub fn with_vkeys(mut self, vkeys: Arc<VkeyStore>) -> Self {
+        self.vkeys = Some(vkeys);
+        self
+    }
+
+    fn launch(config: &KeyHolderConfig) -> Result<(Child, KeyHolderPipes), ProverError> {
+        let mut child = Command::new(&config.program)
+            .args(&config.args)
+            .env_clear()
+            .envs(config.env.iter().cloned())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()?;
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| ProverError::Other("Key holder stdin unavailable".to_string()))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| ProverError::Other("Key holder stdout unavailable".to_string()))?;
+        let pipes = KeyHolderPipes {
+            stdin,
+            stdout: BufReader::new(stdout),
+        };
+        Ok((child, pipes))
+    }
+
+    /// Validate `config` and spawn the key holder it names. Configuration
+    /// problems are reported before any process is started.
+    pub fn try_new(config: &ProverConfig) -> Result<Self, ProverError> {
+        config.validate()?;
+        let key_holder = config.key_holder_config()?;
+        if !key_holder.program.is_file() {
+            return Err(ConfigError::InvalidValue {
+                field: "key_holder".to_string(),
+                value: key_holder.program.display().to_string(),
+                expected: "an existing executable".to_string(),
+            }
+            .into());
+        }
+        Self::spawn(&key_holder)
+    }
+
+    /// Kill the key holder, failing the call in flight if there is one
+    pub fn abort(&self) {
+        self.process.lock().unwrap_or_else(PoisonError::into_inner).kill();
+    }
+
+    /// [`IsolatedBackend::abort`] as an [`Abort`]
+    pub fn abort_handle(self: &Arc<Self>) -> Abort {
+        let backend = self.clone();
+        Arc::new(move || backend.abort())
+    }
+
+    /// Resident memory of the key-holder process, where the proving happens
+    pub fn rss_bytes(&self) -> Option<u64> {
+        let id = self.process.lock().unwrap_or_else(PoisonError::into_inner).child.id();
+        read_process_rss_bytes(id)
+    }
+
+    /// The key holder as a [`ProofWorker`], to measure or stop it
+    pub fn worker(self: &Arc<Self>) -> ProofWorker {
+        let backend = self.clone();
+        let rss_bytes: RssProbe = Arc::new(move || backend.rss_bytes());
+        ProofWorker {
+            rss_bytes,
+            abort: self.abort_handle(),
+        }
+    }
+
+    fn call(&self, request: &KeyHolderRequest) -> Result<KeyHolderResponse, ProverError> {
+        self.call_abortable(request, None, None)
+    }
+
+    /// Make `request`, followed by `chunks` if given, passing `started` an
+    /// abort for it alone once it holds the key holder
+    fn call_abortable(
+        &self,
+        request: &KeyHolderRequest,
+        chunks: Option<&mut InputChunks<'_>>,
+        started: Option<&(dyn Fn(Abort) + Sync)>,
+    ) -> Result<KeyHolderResponse, ProverError> {
+        let mut pipes = self.pipes.lock().unwrap_or_else(PoisonError::into_inner);
+        let call = self.calls.fetch_add(1, Ordering::Relaxed);
+        {
+            let mut process = self.process.lock().unwrap_or_else(PoisonError::into_inner);
+            if !matches!(process.child.try_wait(), Ok(None)) {
+                tracing::info!("key holder exited, starting a new one");
+                (process.child, *pipes) = Self::launch(&self.config)?;
+            }
+            process.call = Some(call);
+        }
+        if let Some(started) = started {
+            let process = self.process.clone();
+            started(Arc::new(move || {
+                let mut process = process.lock().unwrap_or_else(PoisonError::into_inner);
+                if process.call == Some(call) {
+                    process.kill();
+                }
+            }));
+        }
+        let response = Self::exchange(&mut pipes, request, chunks);
+        let mut process = self.process.lock().unwrap_or_else(PoisonError::into_inner);
+        process.call = None;
+        // Cut off mid-request, e.g. by a failing input stream; the key
+        // holder can't be brought back in step with the protocol
+        if response.is_err() {
+            process.kill();
+        }
+        response
+    }
+
+    fn exchange(
+        pipes: &mut KeyHolderPipes,
+        request: &KeyHolderRequest,
+        chunks: Option<&mut InputChunks<'_>>,
+    ) -> Result<KeyHolderResponse, ProverError> {
+        let mut send = |request: &KeyHolderRequest| -> Result<(), ProverError> {
+            let mut line = serde_json::to_vec(request)?;
+            line.push(b'\n');
+            pipes.stdin.write_all(&line)?;
+            Ok(())
+        };
+        send(request)?;
+        if let Some(chunks) = chunks {
+            // Each chunk is written before the next is pulled, and writes
+            // block while the key holder is behind, so little is buffered here
+            for chunk in chunks {
+                send(&KeyHolderRequest::Chunk {
+                    data: hex::encode(chunk?),
+                })?;
+            }
+            send(&KeyHolderRequest::EndStream)?;
+        }
+        pipes.stdin.flush()?;
+
+        let mut response = String::new();
+        if pipes.stdout.read_line(&mut response)? == 0 {
+            return Err(ProverError::Other("Key holder process exited".to_string()));
+        }
+        Ok(serde_json::from_str(&response)?)
+    }
+
+    /// Run key setup for `program` in the key holder, returning its vkey
+    /// hash and serialized verifying key
+    pub fn setup(&self, program: &[u8]) -> Result<(String, Vec<u8>), ProverError> {
+        let request = KeyHolderRequest::Setup {
+            program: hex::encode(program),
+        };
+        match self.call(&request)? {
+            KeyHolderResponse::Vkey { vkey_hash, vk } => {
+                let vk = hex::decode(vk).map_err(|e| ProverError::Other(format!("Malformed vkey from key holder: {}", e)))?;
+                Ok((vkey_hash, vk))
+            }
+            KeyHolderResponse::Error(e) => Err(ProverError::Other(e)),
+            other => Err(ProverError::Other(format!("Unexpected key holder response: {:?}", other))),
+        }
+    }
+
+    /// [`IsolatedBackend::setup`] as a [`KeySetup`]
+    pub fn key_setup(self: &Arc<Self>) -> KeySetup {
+        let backend = self.clone();
+        Arc::new(move |program| backend.setup(program).map_err(ProverError::into_zk_error))
+    }
+
+    /// Execute `program` on `input` without proving
+    pub fn execute(&self, program: &[u8], input: &[u8]) -> Result<ExecutionResult, ProverError> {
+        let request = KeyHolderRequest::Execute {
+            program: hex::encode(program),
+            input: hex::encode(input),
+        };
+        match self.call(&request)? {
+            KeyHolderResponse::Executed {
+                cycles,
+                public_values,
+                profile,
+            } => Ok(ExecutionResult {
+                cycles,
+                public_values: hex::decode(public_values)
+                    .map_err(|e| ProverError::Other(format!("Malformed public values from key holder: {}", e)))?,
+                proof: None,
+                profile,
+            }),
+            KeyHolderResponse::Error(e) => Err(ProverError::Other(e)),
+            other => Err(ProverError::Other(format!("Unexpected key holder response: {:?}", other))),
+        }
+    }
+
+    /// [`IsolatedBackend::execute`] as an [`Executor`]
+    pub fn executor(self: &Arc<Self>) -> Executor {
+        let backend = self.clone();
+        Arc::new(move |program, input| backend.execute(program, input).map_err(ProverError::into_zk_error))
+    }
+}
+
+impl Drop for IsolatedBackend {
+    fn drop(&mut self) {
+        let mut process = self.process.lock().unwrap_or_else(PoisonError::into_inner);
+        let _ = process.child.kill();
+        let _ = process.child.wait();
+    }
+}
+
+impl ZkBackend for IsolatedBackend {
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        self.prove_call(program, input, None)
+    }
+
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        self.verify_call(program, proof, None)
+    }
+}
+
+impl AbortableBackend for IsolatedBackend {
+    fn prove_abortable(
+        &self,
+        program: &[u8],
+        input: &[u8],
+        started: &(dyn Fn(Abort) + Sync),
+    ) -> Result<Vec<u8>, ZkError> {
+        self.prove_call(program, input, Some(started))
+    }
+
+    fn verify_abortable(
+        &self,
+        program: &[u8],
+        proof: &[u8],
+        started: &(dyn Fn(Abort) + Sync),
+    ) -> Result<bool, ZkError> {
+        self.verify_call(program, proof, Some(started))
+    }
+}
+
+impl IsolatedBackend {
+    fn prove_call(
+        &self,
+        program: &[u8],
+        input: &[u8],
+        started: Option<&(dyn Fn(Abort) + Sync)>,
+    ) -> Result<Vec<u8>, ZkError> {
+        let request = KeyHolderRequest::Prove {
+            program: hex::encode(program),
+            input: hex::encode(input),
+        };
+        match self.call_abortable(&request, None, started).map_err(ProverError::into_zk_error)? {
+            KeyHolderResponse::Proof(proof) => {
+                hex::decode(proof).map_err(|e| ZkError::Config(format!("Malformed proof from key holder: {}", e)))
+            }
+            KeyHolderResponse::Error(e) => Err(ZkError::Config(e)),
+            other => Err(ZkError::Config(format!("Unexpected key holder response: {:?}", other))),
+        }
+    }
+
+    fn verify_call(
+        &self,
+        program: &[u8],
+        proof: &[u8],
+        started: Option<&(dyn Fn(Abort) + Sync)>,
+    ) -> Result<bool, ZkError> {
+        if let Some(vkeys) = &self.vkeys
+            && let Some(valid) = vkeys
+                .verify(&program_hash(program), proof)
+                .map_err(ProverError::into_zk_error)?
+        {
+            return Ok(valid);
+        }
+        let request = KeyHolderRequest::Verify {
+            program: hex::encode(program),
+            proof: hex::encode(proof),
+        };
+        match self.call_abortable(&request, None, started).map_err(ProverError::into_zk_error)? {
+            KeyHolderResponse::Verified(valid) => Ok(valid),
+            KeyHolderResponse::Error(e) => Err(ZkError::Config(e)),
+            other => Err(ZkError::Config(format!("Unexpected key holder response: {:?}", other))),
+        }
+    }
+}
+
+/// Streams input chunks to the key holder instead of collecting them here
+impl StreamingBackend for IsolatedBackend {
+    fn prove_chunks(&self, program: &[u8], chunks: &mut InputChunks<'_>) -> Result<Vec<u8>, ZkError> {
+        let request = KeyHolderRequest::ProveStream {
+            program: hex::encode(program),
+        };
+        match self.call_abortable(&request, Some(chunks), None).map_err(ProverError::into_zk_error)? {
+            KeyHolderResponse::Proof(proof) => {
+                hex::decode(proof).map_err(|e| ZkError::Config(format!("Malformed proof from key holder: {}", e)))
+            }
+            KeyHolderResponse::Error(e) => Err(ZkError::Config(e)),
+            other => Err(ZkError::Config(format!("Unexpected key holder response: {:?}", other))),
+        }
+    }
+}
+
+/// Serve key-holder requests on `input`/`output` until `input` closes.
+/// Called from the key-holder binary with its stdin, e.g. as
+/// `BufReader::new(std::io::stdin())`, and its stdout.
+pub fn serve_key_holder<R, W>(backend: &dyn ZkBackend, input: R, output: W) -> Result<(), ProverError>
+where
+    R: BufRead + Send,
+    W: Write,
+{
+    serve_key_holder_with(backend, &KeyHolderOps::default(), input, output)
+}
+
+/// Like [`serve_key_holder`], also answering setup and execute requests
+/// and streaming proofs with `ops`
+pub fn serve_key_holder_with<R, W>(
+    backend: &dyn ZkBackend,
+    ops: &KeyHolderOps,
+    input: R,
+    mut output: W,
+) -> Result<(), ProverError>
+where
+    R: BufRead + Send,
+    W: Write,
+{
+    let mut lines = input.lines();
+    while let Some(line) = lines.next() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<KeyHolderRequest>(&line) {
+            Ok(KeyHolderRequest::ProveStream { program }) => {
+                let mut chunks = StreamChunks {
+                    lines: &mut lines,
+                    ended: false,
+                };
+                let response = prove_stream(backend, ops, program, &mut chunks);
+                // Skip what a failed prove left unread, up to the end of the stream
+                chunks.for_each(drop);
+                response
+            }
+            Ok(request) => handle_request(backend, ops, request),
+            Err(e) => KeyHolderResponse::Error(format!("Malformed request: {}", e)),
+        };
+        serde_json::to_writer(&mut output, &response)?;
+        output.write_all(b"\n")?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+/// The chunks of a streamed prove request, read off the request lines up to
+/// the end of the stream
+struct StreamChunks<'a, I> {
+    lines: &'a mut I,
+    ended: bool,
+}
+
+impl<I> Iterator for StreamChunks<'_, I>
+where
+    I: Iterator<Item = std::io::Result<String>>,
+{
+    type Item = Result<Vec<u8>, ZkError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.ended {
+            return None;
+        }
+        let line = match self.lines.next()? {
+            Ok(line) => line,
+            Err(e) => return Some(Err(ZkError::Config(format!("reading input stream: {}", e)))),
+        };
+        match serde_json::from_str::<KeyHolderRequest>(&line) {
+            Ok(KeyHolderRequest::Chunk { data }) => {
+                Some(hex::decode(data).map_err(|e| ZkError::Config(format!("Malformed chunk: {}", e))))
+            }
+            Ok(KeyHolderRequest::EndStream) => {
+                self.ended = true;
+                None
+            }
+            _ => Some(Err(ZkError::Config("Expected an input chunk".to_string()))),
+        }
+    }
+}
+
+fn prove_stream(
+    backend: &dyn ZkBackend,
+    ops: &KeyHolderOps,
+    program: String,
+    chunks: &mut InputChunks<'_>,
+) -> KeyHolderResponse {
+    let program = match hex::decode(program) {
+        Ok(program) => program,
+        Err(e) => return KeyHolderResponse::Error(format!("Malformed program: {}", e)),
+    };
+    let proof = match &ops.streaming {
+        Some(streaming) => streaming.prove_chunks(&program, chunks),
+        None => chunks
+            .collect::<Result<Vec<_>, _>>()
+            .and_then(|input| backend.prove(&program, &input.concat())),
+    };
+    match proof {
+        Ok(proof) => KeyHolderResponse::Proof(hex::encode(proof)),
+        Err(e) => KeyHolderResponse::Error(format!("{:?}", e)),
+    }
+}
+
+fn handle_request(backend: &dyn ZkBackend, ops: &KeyHolderOps, request: KeyHolderRequest) -> KeyHolderResponse {
+    let decode = |field: &str, value: String| {
+        hex::decode(value).map_err(|e| KeyHolderResponse::Error(format!("Malformed {}: {}", field, e)))
+    };
+    let result = match request {
+        KeyHolderRequest::Prove { program, input } => decode("program", program).and_then(|program| {
+            let input = decode("input", input)?;
+            Ok(match backend.prove(&program, &input) {
+                Ok(proof) => KeyHolderResponse::Proof(hex::encode(proof)),
+                Err(e) => KeyHolderResponse::Error(format!("{:?}", e)),
+            })
+        }),
+        KeyHolderRequest::Verify { program, proof } => decode("program", program).and_then(|program| {
+            let proof = decode("proof", proof)?;
+            Ok(match backend.verify(&program, &proof) {
+                Ok(valid) => KeyHolderResponse::Verified(valid),
+                Err(e) => KeyHolderResponse::Error(format!("{:?}", e)),
+            })
+        }),
+        KeyHolderRequest::Setup { program } => decode("program", program).map(|program| match &ops.setup {
+            Some(setup) => match setup(&program) {
+                Ok((vkey_hash, vk)) => KeyHolderResponse::Vkey {
+                    vkey_hash,
+                    vk: hex::encode(vk),
+                },
+                Err(e) => KeyHolderResponse::Error(format!("{:?}", e)),
+            },
+            None => KeyHolderResponse::Error("Key holder doesn't support setup".to_string()),
+        }),
+        KeyHolderRequest::Execute { program, input } => decode("program", program).and_then(|program| {
+            let input = decode("input", input)?;
+            Ok(match &ops.execute {
+                Some(execute) => match execute(&program, &input) {
+                    Ok(result) => KeyHolderResponse::Executed {
+                        cycles: result.cycles,
+                        public_values: hex::encode(result.public_values),
+                        profile: result.profile,
+                    },
+                    Err(e) => KeyHolderResponse::Error(format!("{:?}", e)),
+                },
+                None => KeyHolderResponse::Error("Key holder doesn't support execution".to_string()),
+            })
+        }),
+        KeyHolderRequest::ProveStream { .. } | KeyHolderRequest::Chunk { .. } | KeyHolderRequest::EndStream => {
+            Ok(KeyHolderResponse::Error("Input chunk outside a stream".to_string()))
+        }
+    };
+    result.unwrap_or_else(|error| error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct EchoBackend;
+
+    impl ZkBackend for EchoBackend {
+        fn prove(&self, _program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+            Ok(input.to_vec())
+        }
+
+        fn verify(&self, _program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+            Ok(!proof.is_empty())
+        }
+    }
+
+    #[test]
+    fn test_serve_key_holder() {
+        let requests = [
+            serde_json::to_string(&KeyHolderRequest::Prove {
+                program: hex::encode(b"elf"),
+                input: hex::encode(b"input"),
+            })
+            .unwrap(),
+            serde_json::to_string(&KeyHolderRequest::Verify {
+                program: hex::encode(b"elf"),
+                proof: String::new(),
+            })
+            .unwrap(),
+            "not json".to_string(),
+        ]
+        .join("\n");
+
+        let mut output = Vec::new();
+        serve_key_holder(&EchoBackend, requests.as_bytes(), &mut output).unwrap();
+
+        let responses: Vec<KeyHolderResponse> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert!(matches!(&responses[0], KeyHolderResponse::Proof(p) if *p == hex::encode(b"input")));
+        assert!(matches!(responses[1], KeyHolderResponse::Verified(false)));
+        assert!(matches!(responses[2], KeyHolderResponse::Error(_)));
+    }
+
+    #[test]
+    fn test_serve_key_holder_ops() {
+        let ops = KeyHolderOps {
+            setup: Some(Arc::new(|program| Ok((format!("0x{}", hex::encode(program)), vec![7])))),
+            execute: Some(Arc::new(|_program, input| {
+                Ok(ExecutionResult {
+                    cycles: input.len() as u64,
+                    public_values: input.to_vec(),
+                    ..ExecutionResult::default()
+                })
+            })),
+            streaming: None,
+        };
+        let requests = [
+            KeyHolderRequest::Setup {
+                program: hex::encode(b"elf"),
+            },
+            KeyHolderRequest::Execute {
+                program: hex::encode(b"elf"),
+                input: hex::encode(b"input"),
+            },
+        ]
+        .iter()
+        .map(|request| serde_json::to_string(request).unwrap())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+        let mut output = Vec::new();
+        serve_key_holder_with(&EchoBackend, &ops, requests.as_bytes(), &mut output).unwrap();
+
+        let responses: Vec<KeyHolderResponse> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert!(matches!(&responses[0], KeyHolderResponse::Vkey { vkey_hash, vk } if vkey_hash == "0x656c66" && vk == "07"));
+        assert!(matches!(&responses[1], KeyHolderResponse::Executed { cycles: 5, public_values, .. } if *public_values == hex::encode(b"input")));
+    }
+
+    #[test]
+    fn test_serve_streamed_prove() {
+        let requests = [
+            KeyHolderRequest::ProveStream {
+                program: hex::encode(b"elf"),
+            },
+            KeyHolderRequest::Chunk { data: hex::encode(b"ab") },
+            KeyHolderRequest::Chunk { data: hex::encode(b"c") },
+            KeyHolderRequest::EndStream,
+            KeyHolderRequest::ProveStream {
+                program: hex::encode(b"elf"),
+            },
+            KeyHolderRequest::Chunk { data: "zz".to_string() },
+            KeyHolderRequest::Chunk { data: hex::encode(b"d") },
+            KeyHolderRequest::EndStream,
+            KeyHolderRequest::Verify {
+                program: hex::encode(b"elf"),
+                proof: hex::encode(b"abc"),
+            },
+        ]
+        .iter()
+        .map(|request| serde_json::to_string(request).unwrap())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+        let mut output = Vec::new();
+        serve_key_holder(&EchoBackend, requests.as_bytes(), &mut output).unwrap();
+
+        let responses: Vec<KeyHolderResponse> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(responses.len(), 3);
+        assert!(matches!(&responses[0], KeyHolderResponse::Proof(p) if *p == hex::encode(b"abc")));
+        // The bad chunk fails its stream, whose rest is skipped
+        assert!(matches!(responses[1], KeyHolderResponse::Error(_)));
+        assert!(matches!(responses[2], KeyHolderResponse::Verified(true)));
+    }
+
+    fn script_key_holder(script: &str) -> KeyHolderConfig {
+        KeyHolderConfig {
+            program: PathBuf::from("/bin/sh"),
+            args: vec!["-c".to_string(), script.to_string()],
+            env: vec![("PATH".to_string(), "/bin:/usr/bin".to_string())],
+        }
+    }
+
+    #[test]
+    fn test_prove_chunks_streams_to_key_holder() {
+        // Proves a stream as the number of chunks it had
+        let script = r#"while read -r line; do case "$line" in *'"prove_stream"'*) n=0;; *'"chunk"'*) n=$((n+1));; *'"end_stream"'*) printf '{"proof":"%02x"}\n' $n;; esac; done"#;
+        let key_holder = IsolatedBackend::spawn(&script_key_holder(script)).unwrap();
+
+        let mut chunks = (0..3).map(|_| Ok(vec![0u8; 1024]));
+        assert_eq!(key_holder.prove_chunks(b"elf", &mut chunks).unwrap(), vec![3]);
+
+        // A failing stream leaves the key holder ready for the next call
+        let mut failing = [Ok(vec![0u8]), Err(ZkError::Config("read failed".to_string()))].into_iter();
+        assert!(key_holder.prove_chunks(b"elf", &mut failing).is_err());
+        let mut chunks = std::iter::once(Ok(vec![0u8]));
+        assert_eq!(key_holder.prove_chunks(b"elf", &mut chunks).unwrap(), vec![1]);
+    }
+
+    #[test]
+    fn test_verify_with_imported_vk() {
+        use crate::testing::{mock_committing_proof, mock_vkey_hash, mock_vkeys};
+
+        let vkeys = Arc::new(mock_vkeys());
+        let prover = mock_vkeys();
+        prover.insert(program_hash(b"elf"), mock_vkey_hash(b"vk"), b"vk".to_vec());
+        vkeys.import_vk(&prover.export_vk(&program_hash(b"elf")).unwrap()).unwrap();
+        // Rejects everything it is asked to verify
+        let script = r#"while read -r line; do echo '{"verified":false}'; done"#;
+        let key_holder = IsolatedBackend::spawn(&script_key_holder(script)).unwrap().with_vkeys(vkeys);
+
+        let proof = mock_committing_proof(b"vk", b"values");
+        assert!(key_holder.verify(b"elf", &proof).unwrap());
+        assert!(!key_holder.verify(b"elf", b"garbage").unwrap());
+        // No imported key: asked of the key holder
+        assert!(!key_holder.verify(b"other", &proof).unwrap());
+    }
+
+    #[test]
+    fn test_prove_chunks_bounds_buffering() {
+        // Takes the request, then reads nothing more
+        let key_holder = Arc::new(IsolatedBackend::spawn(&script_key_holder("read -r line; exec sleep 600")).unwrap());
+        let pulled = Arc::new(AtomicU64::new(0));
+
+        let prove = {
+            let (key_holder, pulled) = (key_holder.clone(), pulled.clone());
+            std::thread::spawn(move || {
+                let mut endless = std::iter::repeat_with(|| {
+                    pulled.fetch_add(1, Ordering::SeqCst);
+                    Ok(vec![0u8; 1 << 20])
+                });
+                key_holder.prove_chunks(b"elf", &mut endless)
+            })
+        };
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        // Held up by the full pipe rather than reading ahead into memory
+        assert_eq!(pulled.load(Ordering::SeqCst), 1);
+        key_holder.abort();
+        assert!(prove.join().unwrap().is_err());
+    }
+}
@@ -0,0 +1,305 @@
+use crate::jobs::{JobId, JobStatus};
+use crate::types::ProverError;
+use std::time::SystemTime;
+
+/// A job as persisted by a [`JobStore`]: enough to report it after a
+/// restart, or to prove it again if it was interrupted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobRecord {
+    pub id: JobId,
+    pub program: Vec<u8>,
+    pub input: Vec<u8>,
+    /// Values the proof must commit to, empty if unbound
+    pub public_inputs: Vec<u8>,
+    pub idempotency_key: Option<String>,
+    pub status: JobStatus,
+    pub proof: Option<Vec<u8>>,
+    pub submitted_at: SystemTime,
+    pub finished_at: Option<SystemTime>,
+}
+
+/// Durable storage for the jobs of a
+/// [`ProverJobManager`](crate::jobs::ProverJobManager), so scheduled work
+/// survives a crash or restart
+pub trait JobStore: Send + Sync {
+    /// Record a newly submitted or re-enqueued job, replacing any earlier
+    /// record with the same ID
+    fn insert(&self, job: &JobRecord) -> Result<(), ProverError>;
+
+    /// Record that a job finished with `status`, and its proof if it completed
+    fn finish(&self, id: JobId, status: &JobStatus, proof: Option<&[u8]>) -> Result<(), ProverError>;
+
+    /// Every stored job, oldest first
+    fn load(&self) -> Result<Vec<JobRecord>, ProverError>;
+
+    fn remove(&self, ids: &[JobId]) -> Result<(), ProverError>;
+}
+
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteJobStore;
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use super::*;
+    use crate::types::program_hash;
+    use rusqlite::{Connection, params};
+    use std::path::Path;
+    use std::sync::{Mutex, PoisonError};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    const SCHEMA: &str = "
+        CREATE TABLE IF NOT EXISTS programs (
+            hash TEXT PRIMARY KEY,
+            elf BLOB NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS jobs (
+            id TEXT PRIMARY KEY,
+            program_hash TEXT NOT NULL REFERENCES programs(hash),
+            input BLOB NOT NULL,
+            public_inputs BLOB NOT NULL DEFAULT x'',
+            idempotency_key TEXT,
+            state TEXT NOT NULL,
+            error TEXT,
+            proof BLOB,
+            submitted_at_ms INTEGER NOT NULL,
+            finished_at_ms INTEGER
+        );
+    ";
+
+    fn db_error(e: rusqlite::Error) -> ProverError {
+        ProverError::Other(format!("job store: {}", e))
+    }
+
+    fn to_millis(time: SystemTime) -> i64 {
+        time.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as i64
+    }
+
+    fn from_millis(millis: i64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(millis.max(0) as u64)
+    }
+
+    fn state_columns(status: &JobStatus) -> (&'static str, Option<&str>) {
+        match status {
+            JobStatus::Running => ("running", None),
+            JobStatus::Completed => ("completed", None),
+            JobStatus::Failed(e) => ("failed", Some(e)),
+            JobStatus::Cancelled => ("cancelled", None),
+        }
+    }
+
+    fn parse_status(state: &str, error: Option<String>) -> Result<JobStatus, ProverError> {
+        match state {
+            "running" => Ok(JobStatus::Running),
+            "completed" => Ok(JobStatus::Completed),
+            "failed" => Ok(JobStatus::Failed(error.unwrap_or_default())),
+            "cancelled" => Ok(JobStatus::Cancelled),
+            other => Err(ProverError::Other(format!("job store: unknown job state {:?}", other))),
+        }
+    }
+
+    /// [`JobStore`] in a SQLite database. Programs are stored once per hash
+    /// and shared by the jobs that reference them.
+    pub struct SqliteJobStore {
+        conn: Mutex<Connection>,
+    }
+
+    impl SqliteJobStore {
+        /// Open or create the database at `path`
+        pub fn open(path: &Path) -> Result<Self, ProverError> {
+            Self::init(Connection::open(path).map_err(db_error)?)
+        }
+
+        /// A database that lives as long as the store, for tests
+        pub fn in_memory() -> Result<Self, ProverError> {
+            Self::init(Connection::open_in_memory().map_err(db_error)?)
+        }
+
+        fn init(conn: Connection) -> Result<Self, ProverError> {
+            conn.execute_batch(SCHEMA).map_err(db_error)?;
+            Ok(Self { conn: Mutex::new(conn) })
+        }
+    }
+
+    impl JobStore for SqliteJobStore {
+        fn insert(&self, job: &JobRecord) -> Result<(), ProverError> {
+            let hash = program_hash(&job.program);
+            let (state, error) = state_columns(&job.status);
+            let mut conn = self.conn.lock().unwrap_or_else(PoisonError::into_inner);
+            let tx = conn.transaction().map_err(db_error)?;
+            tx.execute(
+                "INSERT OR IGNORE INTO programs (hash, elf) VALUES (?1, ?2)",
+                params![hash, job.program],
+            )
+            .map_err(db_error)?;
+            tx.execute(
+                "INSERT OR REPLACE INTO jobs
+                 (id, program_hash, input, public_inputs, idempotency_key, state, error, proof, submitted_at_ms,
+                  finished_at_ms)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![
+                    job.id.to_string(),
+                    hash,
+                    job.input,
+                    job.public_inputs,
+                    job.idempotency_key,
+                    state,
+                    error,
+                    job.proof,
+                    to_millis(job.submitted_at),
+                    job.finished_at.map(to_millis),
+                ],
+            )
+            .map_err(db_error)?;
+            tx.commit().map_err(db_error)
+        }
+
+        fn finish(&self, id: JobId, status: &JobStatus, proof: Option<&[u8]>) -> Result<(), ProverError> {
+            let (state, error) = state_columns(status);
+            let conn = self.conn.lock().unwrap_or_else(PoisonError::into_inner);
+            let updated = conn
+                .execute(
+                    "UPDATE jobs SET state = ?2, error = ?3, proof = ?4, finished_at_ms = ?5 WHERE id = ?1",
+                    params![id.to_string(), state, error, proof, to_millis(SystemTime::now())],
+                )
+                .map_err(db_error)?;
+            if updated == 0 {
+                return Err(ProverError::Other(format!("job store: unknown job {}", id)));
+            }
+            Ok(())
+        }
+
+        fn load(&self) -> Result<Vec<JobRecord>, ProverError> {
+            let conn = self.conn.lock().unwrap_or_else(PoisonError::into_inner);
+            let mut stmt = conn
+                .prepare(
+                    "SELECT jobs.id, programs.elf, jobs.input, jobs.public_inputs, jobs.idempotency_key, jobs.state,
+                            jobs.error, jobs.proof, jobs.submitted_at_ms, jobs.finished_at_ms
+                     FROM jobs JOIN programs ON programs.hash = jobs.program_hash
+                     ORDER BY jobs.submitted_at_ms, jobs.rowid",
+                )
+                .map_err(db_error)?;
+            let rows = stmt
+                .query_map([], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, Vec<u8>>(1)?,
+                        row.get::<_, Vec<u8>>(2)?,
+                        row.get::<_, Vec<u8>>(3)?,
+                        row.get::<_, Option<String>>(4)?,
+                        row.get::<_, String>(5)?,
+                        row.get::<_, Option<String>>(6)?,
+                        row.get::<_, Option<Vec<u8>>>(7)?,
+                        row.get::<_, i64>(8)?,
+                        row.get::<_, Option<i64>>(9)?,
+                    ))
+                })
+                .map_err(db_error)?;
+            let mut jobs = Vec::new();
+            for row in rows {
+                let (id, program, input, public_inputs, idempotency_key, state, error, proof, submitted_at, finished_at) =
+                    row.map_err(db_error)?;
+                jobs.push(JobRecord {
+                    id: id.parse()?,
+                    program,
+                    input,
+                    public_inputs,
+                    idempotency_key,
+                    status: parse_status(&state, error)?,
+                    proof,
+                    submitted_at: from_millis(submitted_at),
+                    finished_at: finished_at.map(from_millis),
+                });
+            }
+            Ok(jobs)
+        }
+
+        fn remove(&self, ids: &[JobId]) -> Result<(), ProverError> {
+            let mut conn = self.conn.lock().unwrap_or_else(PoisonError::into_inner);
+            let tx = conn.transaction().map_err(db_error)?;
+            for id in ids {
+                tx.execute("DELETE FROM jobs WHERE id = ?1", params![id.to_string()])
+                    .map_err(db_error)?;
+            }
+            tx.execute(
+                "DELETE FROM programs WHERE hash NOT IN (SELECT program_hash FROM jobs)",
+                [],
+            )
+            .map_err(db_error)?;
+            tx.commit().map_err(db_error)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn record(program: &[u8], input: &[u8], submitted_ms: u64) -> JobRecord {
+            JobRecord {
+                id: uuid::Uuid::new_v4().to_string().parse().unwrap(),
+                program: program.to_vec(),
+                input: input.to_vec(),
+                public_inputs: Vec::new(),
+                idempotency_key: None,
+                status: JobStatus::Running,
+                proof: None,
+                submitted_at: UNIX_EPOCH + Duration::from_millis(submitted_ms),
+                finished_at: None,
+            }
+        }
+
+        fn program_count(store: &SqliteJobStore) -> i64 {
+            let conn = store.conn.lock().unwrap_or_else(PoisonError::into_inner);
+            conn.query_row("SELECT COUNT(*) FROM programs", [], |row| row.get(0)).unwrap()
+        }
+
+        #[test]
+        fn test_insert_finish_load() {
+            let store = SqliteJobStore::in_memory().unwrap();
+            let mut first = record(b"elf", b"one", 2_000);
+            first.idempotency_key = Some("key".to_string());
+            let second = record(b"elf", b"two", 1_000);
+            store.insert(&first).unwrap();
+            store.insert(&second).unwrap();
+            // Oldest first, whatever the insertion order
+            assert_eq!(store.load().unwrap(), vec![second.clone(), first.clone()]);
+
+            store.finish(first.id, &JobStatus::Completed, Some(b"proof")).unwrap();
+            store.finish(second.id, &JobStatus::Failed("out of gas".to_string()), None).unwrap();
+            let loaded = store.load().unwrap();
+            assert_eq!(loaded[1].status, JobStatus::Completed);
+            assert_eq!(loaded[1].proof.as_deref(), Some(&b"proof"[..]));
+            assert_eq!(loaded[1].idempotency_key.as_deref(), Some("key"));
+            assert!(loaded[1].finished_at.is_some());
+            assert_eq!(loaded[0].status, JobStatus::Failed("out of gas".to_string()));
+            assert_eq!(loaded[0].proof, None);
+
+            // Re-enqueueing replaces the record
+            store.insert(&first).unwrap();
+            assert_eq!(store.load().unwrap()[1], first);
+
+            let unknown = record(b"elf", b"three", 3_000);
+            assert!(store.finish(unknown.id, &JobStatus::Cancelled, None).is_err());
+        }
+
+        #[test]
+        fn test_programs_shared_and_removed() {
+            let store = SqliteJobStore::in_memory().unwrap();
+            let first = record(b"elf", b"one", 1_000);
+            let second = record(b"elf", b"two", 2_000);
+            let other = record(b"other", b"three", 3_000);
+            for job in [&first, &second, &other] {
+                store.insert(job).unwrap();
+            }
+            assert_eq!(program_count(&store), 2);
+
+            // A program stays while any job references it
+            store.remove(&[first.id, other.id]).unwrap();
+            assert_eq!(store.load().unwrap(), vec![second.clone()]);
+            assert_eq!(program_count(&store), 1);
+
+            store.remove(&[second.id]).unwrap();
+            assert!(store.load().unwrap().is_empty());
+            assert_eq!(program_count(&store), 0);
+        }
+    }
+}
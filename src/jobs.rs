@@ -0,0 +1,665 @@
+use crate::binding::{PublicValuesExtractor, check_bindable, check_committed};
+use crate::envelope::{DecodeLimits, decode_envelope};
+use crate::job_store::{JobRecord, JobStore};
+use crate::policies::InputPolicies;
+use crate::store::{ProofId, ProofStore};
+use crate::types::{ProveRequest, ProverError, program_hash};
+use frostgate_zkip::ZkBackend;
+use sha3::{Digest, Sha3_256};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::watch;
+use uuid::Uuid;
+
+/// Identifier returned by [`ProverJobManager::submit_prove`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct JobId(Uuid);
+
+impl JobId {
+    fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl FromStr for JobId {
+    type Err = ProverError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Uuid::parse_str(s)
+            .map(Self)
+            .map_err(|e| ProverError::Other(format!("invalid job id '{}': {}", s, e)))
+    }
+}
+
+impl fmt::Display for JobId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Lifecycle state of a job
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobStatus {
+    Running,
+    Completed,
+    Failed(String),
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, JobStatus::Running)
+    }
+}
+
+struct JobEntry {
+    status: watch::Sender<JobStatus>,
+    /// What is being proven, kept until the job finishes
+    request: Option<ProveRequest>,
+    proof: Option<Vec<u8>>,
+    submitted_at: Instant,
+    finished_at: Option<Instant>,
+}
+
+/// What an idempotency key was first submitted with
+struct IdempotencyRecord {
+    /// Hash of the program and input, to detect a key reused for another request
+    fingerprint: String,
+    job: JobId,
+    /// Where the proof was stored, so the key outlives the job's retention
+    proof_id: Option<ProofId>,
+}
+
+type IdempotencyKeys = Arc<Mutex<HashMap<String, IdempotencyRecord>>>;
+
+/// Identifies a request by content. Program, input and public inputs are
+/// hashed apart so no two requests can produce the same concatenation.
+fn request_fingerprint(program: &[u8], input: &[u8], public_inputs: &[u8]) -> String {
+    let fingerprint = format!("{}:{}", program_hash(program), hex::encode(Sha3_256::digest(input)));
+    match public_inputs {
+        [] => fingerprint,
+        public_inputs => format!("{}:{}", fingerprint, hex::encode(Sha3_256::digest(public_inputs))),
+    }
+}
+
+/// Where a key's original job can still be found
+enum KeyedJob {
+    /// Running or completed in the job table
+    Live(JobId),
+    /// Pruned, but its proof may be in the proof store
+    Stored(JobId, ProofId),
+    /// Failed, cancelled or lost: the key may be submitted afresh
+    Gone,
+}
+
+/// Runs proofs in the background and tracks them by [`JobId`]
+pub struct ProverJobManager {
+    backend: Arc<dyn ZkBackend>,
+    jobs: Arc<Mutex<HashMap<JobId, JobEntry>>>,
+    retention: Duration,
+    keys: IdempotencyKeys,
+    store: Option<Arc<dyn ProofStore>>,
+    job_store: Option<Arc<dyn JobStore>>,
+    policies: Option<Arc<InputPolicies>>,
+    extract: Option<PublicValuesExtractor>,
+}
+
+impl ProverJobManager {
+    pub fn new(backend: Arc<dyn ZkBackend>) -> Self {
+        Self {
+            backend,
+            jobs: Arc::new(Mutex::new(HashMap::new())),
+            retention: Duration::from_secs(60 * 60),
+            keys: Arc::new(Mutex::new(HashMap::new())),
+            store: None,
+            job_store: None,
+            policies: None,
+            extract: None,
+        }
+    }
+
+    /// Persist every job in `job_store` as it is submitted and finishes, so
+    /// [`ProverJobManager::resume`] can pick them up after a restart
+    pub fn with_job_store(mut self, job_store: Arc<dyn JobStore>) -> Self {
+        self.job_store = Some(job_store);
+        self
+    }
+
+    /// Load the jobs persisted by a previous run. Finished jobs are restored
+    /// with their status and proof; jobs that were interrupted while running
+    /// are proven again from the start under their original ID. Idempotency
+    /// keys are restored with their jobs. Returns the number of jobs
+    /// re-enqueued. Must be called from within a tokio runtime.
+    pub fn resume(&self) -> Result<usize, ProverError> {
+        let Some(job_store) = &self.job_store else {
+            return Ok(0);
+        };
+        let mut requeued = 0;
+        for record in job_store.load()? {
+            if self.jobs.lock().unwrap_or_else(PoisonError::into_inner).contains_key(&record.id) {
+                continue;
+            }
+            if let Some(key) = &record.idempotency_key {
+                let mut keys = self.keys.lock().unwrap_or_else(PoisonError::into_inner);
+                keys.entry(key.clone()).or_insert_with(|| IdempotencyRecord {
+                    fingerprint: request_fingerprint(&record.program, &record.input, &record.public_inputs),
+                    job: record.id,
+                    proof_id: None,
+                });
+            }
+            let submitted_at = instant_of(record.submitted_at);
+            if record.status.is_finished() {
+                let (status, _) = watch::channel(record.status);
+                let finished_at = record.finished_at.map(instant_of).unwrap_or(submitted_at);
+                self.jobs.lock().unwrap_or_else(PoisonError::into_inner).insert(
+                    record.id,
+                    JobEntry {
+                        status,
+                        request: None,
+                        proof: record.proof,
+                        submitted_at,
+                        finished_at: Some(finished_at),
+                    },
+                );
+            } else {
+                tracing::info!("re-enqueuing interrupted job {}", record.id);
+                let request = ProveRequest::new(record.program, record.input).with_public_inputs(record.public_inputs);
+                self.start(record.id, request, record.idempotency_key, submitted_at);
+                requeued += 1;
+            }
+        }
+        Ok(requeued)
+    }
+
+    /// Keep the proofs of jobs submitted with an idempotency key in `store`,
+    /// so resubmissions are answered from it after the job itself is pruned.
+    /// Proofs must be envelopes to be stored.
+    pub fn with_store(mut self, store: Arc<dyn ProofStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// How long finished jobs are kept before [`ProverJobManager::prune`] drops them
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Start proving in the background and return immediately. Must be called
+    /// from within a tokio runtime.
+    pub fn submit_prove(&self, program: &[u8], input: &[u8]) -> JobId {
+        let id = JobId::new();
+        self.spawn(id, &ProveRequest::new(program, input), None);
+        id
+    }
+
+    /// Check requests against `policies` in [`ProverJobManager::submit_request`]
+    pub fn with_policies(mut self, policies: Arc<InputPolicies>) -> Self {
+        self.policies = Some(policies);
+        self
+    }
+
+    /// Require the proofs of requests with public inputs to commit to
+    /// exactly those, read with `extract`. Without it
+    /// [`ProverJobManager::submit_request`] rejects such requests.
+    pub fn with_extractor(mut self, extract: PublicValuesExtractor) -> Self {
+        self.extract = Some(extract);
+        self
+    }
+
+    /// Submit `request`, under `key` if given, after checking it against the
+    /// program's input policy. Requests that break it never become jobs.
+    pub fn submit_request(&self, request: &ProveRequest, key: Option<&str>) -> Result<JobId, ProverError> {
+        if let Some(policies) = &self.policies {
+            policies.check_request(request)?;
+        }
+        check_bindable(&request.public_inputs, self.extract.as_ref())?;
+        match key {
+            Some(key) => self.submit_request_with_key(key, request),
+            None => {
+                let id = JobId::new();
+                self.spawn(id, request, None);
+                Ok(id)
+            }
+        }
+    }
+
+    /// Like [`ProverJobManager::submit_prove`], but a key already submitted
+    /// for the same program and input returns the original job instead of
+    /// proving again, e.g. when a relayer retries after a timeout. Keys of
+    /// failed or cancelled jobs are reused for a fresh attempt. Reusing a
+    /// key for a different request is an error.
+    pub fn submit_prove_with_key(&self, key: &str, program: &[u8], input: &[u8]) -> Result<JobId, ProverError> {
+        self.submit_request_with_key(key, &ProveRequest::new(program, input))
+    }
+
+    fn submit_request_with_key(&self, key: &str, request: &ProveRequest) -> Result<JobId, ProverError> {
+        let fingerprint = request_fingerprint(&request.program, &request.stdin_inputs, &request.public_inputs);
+        loop {
+            let mut keys = self.keys.lock().unwrap_or_else(PoisonError::into_inner);
+            if let Some(record) = keys.get(key) {
+                if record.fingerprint != fingerprint {
+                    return Err(ProverError::IdempotencyConflict { key: key.to_string() });
+                }
+                match self.keyed_job(record) {
+                    KeyedJob::Live(id) => {
+                        tracing::debug!("idempotency key {} resolved to job {}", key, id);
+                        return Ok(id);
+                    }
+                    KeyedJob::Stored(id, proof_id) => {
+                        // Don't hold up every other submission on the store
+                        drop(keys);
+                        if self.restore_from_store(id, &proof_id)? {
+                            tracing::debug!("idempotency key {} resolved to stored job {}", key, id);
+                            return Ok(id);
+                        }
+                        keys = self.keys.lock().unwrap_or_else(PoisonError::into_inner);
+                        if keys.get(key).is_some_and(|record| record.job != id) {
+                            // Resubmitted meanwhile; resolve against that
+                            continue;
+                        }
+                    }
+                    KeyedJob::Gone => {}
+                }
+            }
+            let id = JobId::new();
+            keys.insert(
+                key.to_string(),
+                IdempotencyRecord {
+                    fingerprint,
+                    job: id,
+                    proof_id: None,
+                },
+            );
+            self.spawn(id, request, Some(key.to_string()));
+            return Ok(id);
+        }
+    }
+
+    fn keyed_job(&self, record: &IdempotencyRecord) -> KeyedJob {
+        let jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(entry) = jobs.get(&record.job) {
+            return match *entry.status.borrow() {
+                JobStatus::Running | JobStatus::Completed => KeyedJob::Live(record.job),
+                _ => KeyedJob::Gone,
+            };
+        }
+        match (&self.store, &record.proof_id) {
+            (Some(_), Some(proof_id)) => KeyedJob::Stored(record.job, proof_id.clone()),
+            _ => KeyedJob::Gone,
+        }
+    }
+
+    /// Put a pruned job back in the job table from its stored proof.
+    /// Returns whether the proof was still there.
+    fn restore_from_store(&self, id: JobId, proof_id: &ProofId) -> Result<bool, ProverError> {
+        let Some(store) = &self.store else {
+            return Ok(false);
+        };
+        let Some(envelope) = store.get(proof_id)? else {
+            return Ok(false);
+        };
+        let proof = envelope.to_bytes()?;
+        let (status, _) = watch::channel(JobStatus::Completed);
+        let now = Instant::now();
+        self.jobs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(id)
+            .or_insert(JobEntry {
+                status,
+                request: None,
+                proof: Some(proof),
+                submitted_at: now,
+                finished_at: Some(now),
+            });
+        Ok(true)
+    }
+
+    fn spawn(&self, id: JobId, request: &ProveRequest, key: Option<String>) {
+        if let Some(job_store) = &self.job_store {
+            let record = JobRecord {
+                id,
+                program: request.program.clone(),
+                input: request.stdin_inputs.clone(),
+                public_inputs: request.public_inputs.clone(),
+                idempotency_key: key.clone(),
+                status: JobStatus::Running,
+                proof: None,
+                submitted_at: SystemTime::now(),
+                finished_at: None,
+            };
+            if let Err(e) = job_store.insert(&record) {
+                tracing::warn!("can't persist job {}: {}", id, e);
+            }
+        }
+        self.start(id, request.clone(), key, Instant::now());
+    }
+
+    fn start(&self, id: JobId, request: ProveRequest, key: Option<String>, submitted_at: Instant) {
+        let (status, _) = watch::channel(JobStatus::Running);
+        self.jobs.lock().unwrap_or_else(PoisonError::into_inner).insert(
+            id,
+            JobEntry {
+                status,
+                request: Some(request.clone()),
+                proof: None,
+                submitted_at,
+                finished_at: None,
+            },
+        );
+
+        let backend = self.backend.clone();
+        let jobs = self.jobs.clone();
+        let keys = self.keys.clone();
+        let store = self.store.clone();
+        let job_store = self.job_store.clone();
+        let extract = self.extract.clone();
+        tokio::task::spawn_blocking(move || {
+            let outcome = backend
+                .prove(&request.program, &request.stdin_inputs)
+                .map_err(ProverError::from)
+                .and_then(|proof| {
+                    check_committed(&request.public_inputs, &proof, extract.as_ref())?;
+                    Ok(proof)
+                });
+            let stored = {
+                let mut jobs = jobs.lock().unwrap_or_else(PoisonError::into_inner);
+                let Some(entry) = jobs.get_mut(&id) else {
+                    return;
+                };
+                // A cancelled job keeps its status; the late result is dropped
+                if entry.status.borrow().is_finished() {
+                    return;
+                }
+                entry.finished_at = Some(Instant::now());
+                entry.request = None;
+                let stored = match outcome {
+                    Ok(proof) => {
+                        let stored = key.zip(store).map(|(key, store)| (key, store, proof.clone()));
+                        entry.proof = Some(proof);
+                        entry.status.send_replace(JobStatus::Completed);
+                        stored
+                    }
+                    Err(e) => {
+                        tracing::warn!("job {} failed: {}", id, e);
+                        entry.status.send_replace(JobStatus::Failed(e.to_string()));
+                        None
+                    }
+                };
+                if let Some(job_store) = &job_store {
+                    let status = entry.status.borrow().clone();
+                    if let Err(e) = job_store.finish(id, &status, entry.proof.as_deref()) {
+                        tracing::warn!("can't persist the outcome of job {}: {}", id, e);
+                    }
+                }
+                stored
+            };
+            // Outside the jobs lock, which submissions take after the keys lock
+            if let Some((key, store, proof)) = stored {
+                match decode_envelope(&proof, &DecodeLimits::default()).and_then(|envelope| store.put(&envelope)) {
+                    Ok(proof_id) => {
+                        let mut keys = keys.lock().unwrap_or_else(PoisonError::into_inner);
+                        if let Some(record) = keys.get_mut(&key).filter(|record| record.job == id) {
+                            record.proof_id = Some(proof_id);
+                        }
+                    }
+                    Err(e) => tracing::warn!("can't store proof of job {}: {}", id, e),
+                }
+            }
+        });
+    }
+
+    /// Current status, or `None` for an unknown job
+    pub fn status(&self, id: JobId) -> Option<JobStatus> {
+        let jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+        jobs.get(&id).map(|entry| entry.status.borrow().clone())
+    }
+
+    /// The proof of a completed job, `Ok(None)` while it is still running
+    pub fn result(&self, id: JobId) -> Result<Option<Vec<u8>>, ProverError> {
+        let jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+        let entry = jobs
+            .get(&id)
+            .ok_or_else(|| ProverError::Other(format!("unknown job {}", id)))?;
+        let status = entry.status.borrow().clone();
+        match status {
+            JobStatus::Running => Ok(None),
+            JobStatus::Completed => Ok(entry.proof.clone()),
+            JobStatus::Failed(e) => Err(ProverError::Other(e)),
+            JobStatus::Cancelled => Err(ProverError::Other(format!("job {} was cancelled", id))),
+        }
+    }
+
+    /// Cancel a running job. Returns false if the job is unknown or already
+    /// finished. The backend call itself is not interrupted; its result is
+    /// discarded.
+    pub fn cancel(&self, id: JobId) -> bool {
+        let mut jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(entry) = jobs.get_mut(&id) else {
+            return false;
+        };
+        if entry.status.borrow().is_finished() {
+            return false;
+        }
+        entry.finished_at = Some(Instant::now());
+        entry.request = None;
+        entry.status.send_replace(JobStatus::Cancelled);
+        if let Some(job_store) = &self.job_store
+            && let Err(e) = job_store.finish(id, &JobStatus::Cancelled, None)
+        {
+            tracing::warn!("can't persist the cancellation of job {}: {}", id, e);
+        }
+        true
+    }
+
+    /// Wait until the job leaves the running state
+    pub async fn wait(&self, id: JobId) -> Option<JobStatus> {
+        let mut rx = {
+            let jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+            jobs.get(&id)?.status.subscribe()
+        };
+        match rx.wait_for(JobStatus::is_finished).await {
+            Ok(status) => Some(status.clone()),
+            Err(_) => self.status(id),
+        }
+    }
+
+    /// Requests of the jobs still running, oldest first
+    pub fn pending_requests(&self) -> Vec<(JobId, ProveRequest)> {
+        let jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut pending: Vec<_> = jobs
+            .iter()
+            .filter_map(|(id, entry)| Some((entry.submitted_at, *id, entry.request.clone()?)))
+            .collect();
+        pending.sort_by_key(|(submitted_at, _, _)| *submitted_at);
+        pending.into_iter().map(|(_, id, request)| (id, request)).collect()
+    }
+
+    /// Time since submission, or total run time for a finished job
+    pub fn elapsed(&self, id: JobId) -> Option<Duration> {
+        let jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+        jobs.get(&id).map(|entry| match entry.finished_at {
+            Some(finished) => finished.duration_since(entry.submitted_at),
+            None => entry.submitted_at.elapsed(),
+        })
+    }
+
+    /// Drop finished jobs older than the retention window, along with
+    /// idempotency keys whose proof wasn't stored
+    pub fn prune(&self) -> usize {
+        let mut keys = self.keys.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+        let retention = self.retention;
+        let expired: Vec<JobId> = jobs
+            .iter()
+            .filter(|(_, entry)| entry.finished_at.is_some_and(|finished| finished.elapsed() >= retention))
+            .map(|(id, _)| *id)
+            .collect();
+        for id in &expired {
+            jobs.remove(id);
+        }
+        keys.retain(|_, record| record.proof_id.is_some() || jobs.contains_key(&record.job));
+        if let Some(job_store) = &self.job_store
+            && let Err(e) = job_store.remove(&expired)
+        {
+            tracing::warn!("can't remove pruned jobs from the job store: {}", e);
+        }
+        expired.len()
+    }
+}
+
+/// The instant `time` was, as near as the clocks allow; times in the future
+/// or before the monotonic clock's origin map to now
+fn instant_of(time: SystemTime) -> Instant {
+    let age = SystemTime::now().duration_since(time).unwrap_or_default();
+    Instant::now().checked_sub(age).unwrap_or_else(Instant::now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockBackend, MockConfig};
+
+    #[tokio::test]
+    async fn test_submit_and_wait() {
+        let manager = ProverJobManager::new(Arc::new(MockBackend::default()));
+        let id = manager.submit_prove(b"elf", b"input");
+
+        assert_eq!(manager.wait(id).await, Some(JobStatus::Completed));
+        assert!(manager.result(id).unwrap().is_some());
+        assert!(!manager.cancel(id));
+    }
+
+    #[tokio::test]
+    async fn test_public_inputs_bound() {
+        let guest = || Arc::new(MockBackend::default().with_guest(Arc::new(|_, input| Ok(input.to_vec()))));
+        let request =
+            |public_inputs: &[u8]| ProveRequest::new(b"elf".to_vec(), b"1".to_vec()).with_public_inputs(public_inputs);
+
+        let manager = ProverJobManager::new(guest()).with_extractor(crate::testing::mock_public_values());
+        let matching = manager.submit_request(&request(b"1"), None).unwrap();
+        let mismatched = manager.submit_request(&request(b"2"), Some("relay-1")).unwrap();
+        assert_eq!(manager.wait(matching).await, Some(JobStatus::Completed));
+        assert!(matches!(manager.wait(mismatched).await, Some(JobStatus::Failed(_))));
+        // The declared values are part of the request under a key
+        assert!(matches!(
+            manager.submit_request(&request(b"1"), Some("relay-1")),
+            Err(ProverError::IdempotencyConflict { .. })
+        ));
+
+        let unbound = ProverJobManager::new(guest());
+        assert!(matches!(
+            unbound.submit_request(&request(b"1"), None),
+            Err(ProverError::PolicyViolation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_key() {
+        let dir = std::env::temp_dir().join(format!("frostgate-jobs-{}", Uuid::new_v4()));
+        let backend = Arc::new(crate::envelope::EnvelopedBackend::new(
+            Arc::new(MockBackend::default()),
+            "mock",
+            "sp1",
+        ));
+        let manager = ProverJobManager::new(backend)
+            .with_retention(Duration::ZERO)
+            .with_store(Arc::new(crate::store::FsProofStore::new(&dir).unwrap()));
+
+        let id = manager.submit_prove_with_key("relay-1", b"elf", b"input").unwrap();
+        assert_eq!(manager.submit_prove_with_key("relay-1", b"elf", b"input").unwrap(), id);
+        assert!(matches!(
+            manager.submit_prove_with_key("relay-1", b"elf", b"other"),
+            Err(ProverError::IdempotencyConflict { .. })
+        ));
+        // Shifting bytes between program and input is another request
+        assert!(matches!(
+            manager.submit_prove_with_key("relay-1", b"el", b"finput"),
+            Err(ProverError::IdempotencyConflict { .. })
+        ));
+        assert_eq!(manager.wait(id).await, Some(JobStatus::Completed));
+        let proof = manager.result(id).unwrap().unwrap();
+
+        // The proof is stored once the job finishes, shortly after its status
+        let stored = |key: &'static str| {
+            let keys = manager.keys.clone();
+            async move {
+                while keys.lock().unwrap()[key].proof_id.is_none() {
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                }
+            }
+        };
+        stored("relay-1").await;
+        assert_eq!(manager.prune(), 1);
+        assert_eq!(manager.status(id), None);
+        assert_eq!(manager.submit_prove_with_key("relay-1", b"elf", b"input").unwrap(), id);
+        assert_eq!(manager.result(id).unwrap().unwrap(), proof);
+        assert_ne!(manager.submit_prove_with_key("relay-2", b"elf", b"input").unwrap(), id);
+        stored("relay-2").await;
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn test_resume_from_job_store() {
+        let path = std::env::temp_dir().join(format!("frostgate-jobs-{}.db", Uuid::new_v4()));
+        let open = || Arc::new(crate::job_store::SqliteJobStore::open(&path).unwrap());
+
+        let before = ProverJobManager::new(Arc::new(MockBackend::default())).with_job_store(open());
+        let done = before.submit_prove(b"elf", b"done");
+        assert_eq!(before.wait(done).await, Some(JobStatus::Completed));
+        let proof = before.result(done).unwrap().unwrap();
+        // A job the previous process was still proving when it died
+        let interrupted = JobId::new();
+        open()
+            .insert(&JobRecord {
+                id: interrupted,
+                program: b"elf".to_vec(),
+                input: b"interrupted".to_vec(),
+                public_inputs: Vec::new(),
+                idempotency_key: Some("relay-1".to_string()),
+                status: JobStatus::Running,
+                proof: None,
+                submitted_at: SystemTime::now(),
+                finished_at: None,
+            })
+            .unwrap();
+
+        let manager = ProverJobManager::new(Arc::new(MockBackend::default())).with_job_store(open());
+        assert_eq!(manager.resume().unwrap(), 1);
+        assert_eq!(manager.result(done).unwrap().unwrap(), proof);
+        assert_eq!(
+            manager.submit_prove_with_key("relay-1", b"elf", b"interrupted").unwrap(),
+            interrupted
+        );
+        assert_eq!(manager.wait(interrupted).await, Some(JobStatus::Completed));
+        assert_eq!(manager.resume().unwrap(), 0);
+
+        let restarted = ProverJobManager::new(Arc::new(MockBackend::default())).with_job_store(open());
+        while open().load().unwrap().iter().any(|job| !job.status.is_finished()) {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        assert_eq!(restarted.resume().unwrap(), 0);
+        assert_eq!(restarted.status(interrupted), Some(JobStatus::Completed));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cancel() {
+        let backend = MockBackend::new(MockConfig {
+            prove_latency: Duration::from_millis(200),
+            ..MockConfig::default()
+        });
+        let manager = ProverJobManager::new(Arc::new(backend));
+        let id = manager.submit_prove(b"elf", b"input");
+
+        assert_eq!(manager.status(id), Some(JobStatus::Running));
+        assert!(manager.cancel(id));
+        assert_eq!(manager.wait(id).await, Some(JobStatus::Cancelled));
+        assert!(manager.result(id).is_err());
+    }
+}
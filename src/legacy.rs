@@ -0,0 +1,103 @@
+use crate::compat::{SP1_MAJOR_VERSION, is_compatible};
+use crate::envelope::ProofEnvelope;
+use crate::provenance::PROVENANCE_PREFIX;
+use crate::types::ProverError;
+use frostgate_zkip::ZkBackend;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Verifies archived proofs by routing each one to a verifier for the SP1
+/// release that produced it.
+///
+/// Verifiers for older releases are typically [`IsolatedBackend`]s running a
+/// verifier binary built against that release.
+///
+/// [`IsolatedBackend`]: crate::isolation::IsolatedBackend
+pub struct ArchiveVerifier {
+    current: Arc<dyn ZkBackend>,
+    legacy: HashMap<u32, Arc<dyn ZkBackend>>,
+}
+
+impl ArchiveVerifier {
+    /// `current` verifies proofs from the SP1 release this crate is built against
+    pub fn new(current: Arc<dyn ZkBackend>) -> Self {
+        Self {
+            current,
+            legacy: HashMap::new(),
+        }
+    }
+
+    /// Verify proofs from SP1 major version `major` with `verifier`
+    pub fn with_legacy(mut self, major: u32, verifier: Arc<dyn ZkBackend>) -> Self {
+        self.legacy.insert(major, verifier);
+        self
+    }
+
+    /// SP1 version recorded in an envelope's provenance metadata
+    pub fn recorded_version(envelope: &ProofEnvelope) -> Option<&str> {
+        envelope
+            .metadata
+            .get(&format!("{}sp1_version", PROVENANCE_PREFIX))
+            .map(String::as_str)
+    }
+
+    /// Verify an archived envelope against `program`'s vkey. Envelopes without
+    /// a recorded version are assumed to come from the current release.
+    pub fn verify(&self, envelope: &ProofEnvelope, program: &[u8]) -> Result<bool, ProverError> {
+        if !envelope.checksum_valid() {
+            return Err(ProverError::MalformedEnvelope("checksum mismatch".to_string()));
+        }
+
+        let verifier = match Self::recorded_version(envelope) {
+            None => &self.current,
+            Some(version) if is_compatible(version) => &self.current,
+            Some(version) => {
+                let major = parse_major(version)
+                    .ok_or_else(|| ProverError::UnsupportedProofVersion(version.to_string()))?;
+                self.legacy.get(&major).ok_or_else(|| {
+                    ProverError::UnsupportedProofVersion(format!(
+                        "no verifier for SP1 {} (current is {})",
+                        version, SP1_MAJOR_VERSION
+                    ))
+                })?
+            }
+        };
+        Ok(verifier.verify(program, &envelope.payload)?)
+    }
+}
+
+fn parse_major(version: &str) -> Option<u32> {
+    version.trim_start_matches('v').split('.').next()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockBackend, MockGuest, canned_envelope};
+
+    fn with_version(version: &str) -> ProofEnvelope {
+        let mut envelope = canned_envelope(b"elf", b"input");
+        envelope.insert_metadata(format!("{}sp1_version", PROVENANCE_PREFIX), version);
+        envelope
+    }
+
+    #[test]
+    fn test_routes_by_recorded_version() {
+        // Verify proofs committing public values, as canned ones do
+        let guest: MockGuest = Arc::new(|_, input| Ok(input.to_vec()));
+        let current = Arc::new(MockBackend::default().with_guest(guest.clone()));
+        let legacy = Arc::new(MockBackend::default().with_guest(guest));
+        let verifier = ArchiveVerifier::new(current.clone()).with_legacy(3, legacy.clone());
+
+        assert!(verifier.verify(&canned_envelope(b"elf", b"input"), b"elf").unwrap());
+        assert_eq!(current.verify_calls(), 1);
+
+        assert!(verifier.verify(&with_version("3.4.0"), b"elf").unwrap());
+        assert_eq!(legacy.verify_calls(), 1);
+
+        assert!(matches!(
+            verifier.verify(&with_version("2.0.0"), b"elf"),
+            Err(ProverError::UnsupportedProofVersion(_))
+        ));
+    }
+}
@@ -1,3 +1,86 @@
+pub mod access;
+pub mod accounting;
+pub mod admission;
+pub mod aggregation;
+pub mod artifacts;
+pub mod audit;
+pub mod balancer;
+pub mod batch;
+pub mod binding;
+pub mod build_support;
+pub mod cancel;
+pub mod capacity;
+pub mod chain;
+pub mod cluster;
+pub mod compat;
+pub mod config;
+pub mod dedup;
+pub mod degradation;
+pub mod elf;
+pub mod endpoints;
+pub mod envelope;
+pub mod estimation;
+pub mod events;
+pub mod execution;
+pub mod external;
+pub mod failover;
+pub mod fixtures;
+pub mod gpu;
+pub mod health;
+#[cfg(any(feature = "sp1-v4", feature = "sp1-v5"))]
+pub mod input;
+pub mod inspect;
+pub mod isolation;
+pub mod job_store;
+pub mod jobs;
+pub mod legacy;
+#[cfg(feature = "light-verifier")]
+pub mod light_verifier;
+pub mod memory;
+pub mod metrics;
+pub mod network_jobs;
+pub mod offline;
+pub mod onchain;
+pub mod payments;
+pub mod pinning;
+pub mod pipeline;
+pub mod plug;
+pub mod policies;
+pub mod program_cache;
+pub mod program_limits;
+pub mod programs;
+pub mod progress;
+pub mod provenance;
 pub mod prover;
+#[cfg(any(feature = "sp1-v4", feature = "sp1-v5"))]
+pub mod public_values;
+pub mod quotas;
+pub mod receipts;
 pub mod registry;
-pub mod types;
\ No newline at end of file
+pub mod replay;
+pub mod resources;
+#[cfg(feature = "rest")]
+pub mod rest;
+pub mod retry;
+pub mod scheduler;
+pub mod secrets;
+#[cfg(any(feature = "grpc", feature = "rest"))]
+pub mod server_state;
+#[cfg(feature = "grpc")]
+pub mod service;
+pub mod signatures;
+pub mod snapshot;
+pub mod stats;
+pub mod store;
+pub mod streaming;
+pub mod telemetry;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod timeout;
+pub mod types;
+pub mod uploads;
+pub mod vkeys;
+pub mod warm_pool;
+pub mod wasm;
+pub mod webhooks;
+pub mod workdirs;
\ No newline at end of file
@@ -0,0 +1,205 @@
+use crate::envelope::{DecodeLimits, ProofEnvelope, decode_envelope};
+use crate::onchain::OnchainProof;
+use crate::pinning::VkeyResolver;
+use crate::pipeline::ProofMode;
+use crate::types::{ProverError, ct_eq, program_hash};
+use frostgate_zkip::{ZkBackend, ZkError};
+use sp1_verifier::{GROTH16_VK_BYTES, Groth16Verifier, PLONK_VK_BYTES, PlonkVerifier};
+
+/// Verify a Plonk or Groth16 proof against SP1's embedded BN254 verifying
+/// keys. Needs only the verifier artifacts compiled into `sp1-verifier`, not
+/// the prover.
+pub fn verify_wrapped(proof: &OnchainProof) -> Result<(), ProverError> {
+    let vkey_hash = format!("0x{}", hex::encode(proof.program_vkey));
+    match proof.mode {
+        ProofMode::Groth16 => {
+            Groth16Verifier::verify(&proof.proof, &proof.public_values, &vkey_hash, *GROTH16_VK_BYTES)
+                .map_err(|e| ProverError::Other(format!("groth16 verification failed: {:?}", e)))
+        }
+        ProofMode::Plonk => PlonkVerifier::verify(&proof.proof, &proof.public_values, &vkey_hash, *PLONK_VK_BYTES)
+            .map_err(|e| ProverError::Other(format!("plonk verification failed: {:?}", e))),
+        mode => Err(ProverError::Other(format!(
+            "{} proofs need the full prover to verify",
+            mode.name()
+        ))),
+    }
+}
+
+/// Standalone verifier for one program's Plonk and Groth16 proofs, for
+/// verification-only deployments. Core and compressed proofs need the full
+/// prover to verify and are rejected.
+pub struct Sp1Verifier {
+    program_vkey: String,
+    limits: DecodeLimits,
+}
+
+impl Sp1Verifier {
+    /// `vkey` is the program's verifying key hash, either as raw 32 bytes or
+    /// as `0x`-prefixed hex text
+    pub fn new(vkey: &[u8]) -> Result<Self, ProverError> {
+        let program_vkey = match vkey.len() {
+            32 => format!("0x{}", hex::encode(vkey)),
+            _ => std::str::from_utf8(vkey)
+                .map_err(|_| ProverError::Other("verifying key is neither 32 bytes nor hex".to_string()))?
+                .trim()
+                .to_string(),
+        };
+        Ok(Self {
+            program_vkey,
+            limits: DecodeLimits::default(),
+        })
+    }
+
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Verify an envelope holding a Plonk or Groth16 proof
+    pub fn verify(&self, envelope: &ProofEnvelope) -> Result<(), ProverError> {
+        verify_wrapped(&OnchainProof::from_envelope(envelope, &self.program_vkey)?)
+    }
+
+    /// Decode and verify a serialized envelope
+    pub fn verify_bytes(&self, bytes: &[u8]) -> Result<(), ProverError> {
+        self.verify(&decode_envelope(bytes, &self.limits)?)
+    }
+
+    /// Verify many envelopes across all available cores, returning results
+    /// in input order. The BN254 verifying keys are embedded in the binary,
+    /// so every proof shares them instead of reloading per call.
+    pub fn verify_batch(&self, envelopes: &[ProofEnvelope]) -> Vec<Result<(), ProverError>> {
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let chunk = envelopes.len().div_ceil(threads).max(1);
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = envelopes
+                .chunks(chunk)
+                .map(|chunk| {
+                    let handle = scope.spawn(move || chunk.iter().map(|e| self.verify(e)).collect::<Vec<_>>());
+                    (chunk.len(), handle)
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|(len, handle)| {
+                    handle.join().unwrap_or_else(|_| {
+                        (0..len)
+                            .map(|_| Err(ProverError::Other("verifier thread panicked".to_string())))
+                            .collect()
+                    })
+                })
+                .collect()
+        })
+    }
+}
+
+/// Verify-only backend for deployments without prover components. Proofs
+/// are serialized envelopes holding Plonk or Groth16 proofs.
+pub struct LightVerifierBackend {
+    resolve_vkey: VkeyResolver,
+    limits: DecodeLimits,
+}
+
+impl LightVerifierBackend {
+    /// `resolve_vkey` maps a program to its vkey hash, e.g. from pinned
+    /// values shipped with the deployment
+    pub fn new(resolve_vkey: VkeyResolver) -> Self {
+        Self {
+            resolve_vkey,
+            limits: DecodeLimits::default(),
+        }
+    }
+
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+}
+
+impl ZkBackend for LightVerifierBackend {
+    fn prove(&self, _program: &[u8], _input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        Err(ZkError::Config("light verifier backend can't prove".to_string()))
+    }
+
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        let envelope = decode_envelope(proof, &self.limits).map_err(ProverError::into_zk_error)?;
+        if !ct_eq(envelope.program_hash.as_bytes(), program_hash(program).as_bytes()) {
+            tracing::warn!("proof is for program {}, not the one supplied", envelope.program_hash);
+            return Ok(false);
+        }
+        let vkey = (self.resolve_vkey)(program)?;
+        let wrapped = OnchainProof::from_envelope(&envelope, &vkey).map_err(ProverError::into_zk_error)?;
+        match verify_wrapped(&wrapped) {
+            Ok(()) => Ok(true),
+            Err(e) => {
+                tracing::warn!("proof for program {} rejected: {:?}", envelope.program_hash, e);
+                Ok(false)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inspect::PROOF_MODE_KEY;
+    use std::sync::Arc;
+
+    const VKEY: &str = "0x00112233445566778899aabbccddeeff00112233445566778899aabbccddeeff";
+
+    fn envelope(program: &[u8], mode: Option<ProofMode>) -> ProofEnvelope {
+        let mut envelope = ProofEnvelope::new("sp1", program_hash(program), b"proof".to_vec(), Vec::new());
+        if let Some(mode) = mode {
+            envelope.insert_metadata(PROOF_MODE_KEY, mode.name());
+        }
+        envelope
+    }
+
+    #[test]
+    fn test_rejects_proofs_needing_the_prover() {
+        let verifier = Sp1Verifier::new(VKEY.as_bytes()).unwrap();
+        for mode in [ProofMode::Core, ProofMode::Compressed] {
+            assert!(verifier.verify(&envelope(b"elf", Some(mode))).is_err());
+        }
+        assert!(matches!(
+            verifier.verify(&envelope(b"elf", None)),
+            Err(ProverError::MalformedEnvelope(_))
+        ));
+        assert!(matches!(
+            verifier.verify_bytes(b"not an envelope"),
+            Err(ProverError::MalformedEnvelope(_))
+        ));
+    }
+
+    #[test]
+    fn test_verify_batch_keeps_order() {
+        let verifier = Sp1Verifier::new(VKEY.as_bytes()).unwrap();
+        // Enough envelopes to span several threads, failing in two
+        // distinguishable ways
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let envelopes: Vec<_> = (0..threads * 3 + 1)
+            .map(|i| envelope(b"elf", (i % 3 == 0).then_some(ProofMode::Core)))
+            .collect();
+
+        let results = verifier.verify_batch(&envelopes);
+        assert_eq!(results.len(), envelopes.len());
+        for (i, result) in results.iter().enumerate() {
+            let missing_mode = matches!(result, Err(ProverError::MalformedEnvelope(_)));
+            assert_eq!(missing_mode, i % 3 != 0, "result {} out of order: {:?}", i, result);
+        }
+        assert!(verifier.verify_batch(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_backend_rejects_other_program_and_mode() {
+        let resolver: VkeyResolver = Arc::new(|_| Ok(VKEY.to_string()));
+        let backend = LightVerifierBackend::new(resolver);
+        assert!(backend.prove(b"elf", b"input").is_err());
+
+        let other_program = envelope(b"other", Some(ProofMode::Groth16)).to_bytes().unwrap();
+        assert!(!backend.verify(b"elf", &other_program).unwrap());
+
+        let core = envelope(b"elf", Some(ProofMode::Core)).to_bytes().unwrap();
+        assert!(backend.verify(b"elf", &core).is_err());
+    }
+}
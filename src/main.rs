@@ -0,0 +1,294 @@
+use clap::{Parser, Subcommand};
+use frostgate_prover::config::{ConfigLayer, ProverConfig};
+use frostgate_prover::elf::validate_elf;
+use frostgate_prover::envelope::{DecodeLimits, ProofEnvelope, decode_and_migrate, decode_envelope};
+use frostgate_prover::fixtures::{FixtureSpec, generate_fixtures};
+use frostgate_prover::inspect::{InspectOptions, PROOF_MODE_KEY, diff, inspect};
+use frostgate_prover::isolation::IsolatedBackend;
+use frostgate_prover::memory::MemoryBudgetBackend;
+use frostgate_prover::pipeline::ProofMode;
+use frostgate_prover::types::{ProverError, ct_eq, program_hash};
+use frostgate_prover::vkeys::VkeyStore;
+use frostgate_zkip::ZkBackend;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+
+#[derive(Parser)]
+#[command(name = "frostgate-prover", version, about = "Frostgate prover tools")]
+struct Cli {
+    /// TOML file configuring the key holder [default: prover.toml]. FROSTGATE_*
+    /// environment variables override its settings.
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Show the contents of a proof envelope
+    Inspect {
+        file: PathBuf,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show the differences between two proof envelopes
+    Diff { left: PathBuf, right: PathBuf },
+    /// Validate a guest ELF and run key setup for it
+    Setup { elf: PathBuf },
+    /// Prove a guest ELF and write the proof envelope
+    Prove {
+        elf: PathBuf,
+        /// File whose contents are written to the guest's stdin
+        #[arg(long)]
+        input: Option<PathBuf>,
+        /// core, compressed, plonk or groth16 [default: from config]
+        #[arg(long, value_parser = parse_mode)]
+        mode: Option<ProofMode>,
+        #[arg(long, default_value = "proof.bin")]
+        out: PathBuf,
+    },
+    /// Verify a proof envelope against the guest ELF it was produced for
+    Verify {
+        proof: PathBuf,
+        #[arg(long)]
+        elf: PathBuf,
+    },
+    /// Run a guest ELF without proving and report its cycle count
+    Execute {
+        elf: PathBuf,
+        #[arg(long)]
+        input: Option<PathBuf>,
+        /// Print the execution report, with opcode, syscall and region
+        /// counts, as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Run key setup and write the verifying key for import elsewhere
+    ExportVk {
+        elf: PathBuf,
+        /// Where to write the key; printed to stdout if omitted
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Prove guest ELFs and write them, their proofs and a manifest as
+    /// golden fixtures, named after the ELF files
+    Fixtures {
+        #[arg(required = true)]
+        elfs: Vec<PathBuf>,
+        /// File whose contents are written to every guest's stdin
+        #[arg(long)]
+        input: Option<PathBuf>,
+        #[arg(long, default_value = "fixtures")]
+        out: PathBuf,
+    },
+}
+
+fn parse_mode(s: &str) -> Result<ProofMode, String> {
+    ProofMode::parse(s).ok_or_else(|| format!("unknown proof mode '{}'", s))
+}
+
+/// Spawn the configured key holder, telling it to produce `mode` proofs
+fn key_holder(config: &ProverConfig, mode: ProofMode) -> Result<IsolatedBackend, ProverError> {
+    let config = ProverConfig {
+        proof_mode: mode,
+        ..config.clone()
+    };
+    IsolatedBackend::try_new(&config)
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    // Only commands that launch a key holder need the config, so a broken
+    // one doesn't stop offline inspection
+    let config = || ProverConfig::load(cli.config.as_deref(), ConfigLayer::default()).map_err(ProverError::from);
+    let result = match cli.command {
+        Command::Inspect { file, json } => run_inspect(&file, json),
+        Command::Diff { left, right } => run_diff(&left, &right),
+        Command::Setup { elf } => config().and_then(|config| run_setup(&config, &elf)),
+        Command::Prove { elf, input, mode, out } => {
+            config().and_then(|config| run_prove(&config, &elf, input.as_deref(), mode, &out))
+        }
+        Command::Verify { proof, elf } => config().and_then(|config| run_verify(&config, &proof, &elf)),
+        Command::Execute { elf, input, json } => {
+            config().and_then(|config| run_execute(&config, &elf, input.as_deref(), json))
+        }
+        Command::ExportVk { elf, out } => config().and_then(|config| run_export_vk(&config, &elf, out.as_deref())),
+        Command::Fixtures { elfs, input, out } => {
+            config().and_then(|config| run_fixtures(&config, &elfs, input.as_deref(), &out))
+        }
+    };
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn load(path: &Path) -> Result<ProofEnvelope, ProverError> {
+    decode_envelope(&std::fs::read(path)?, &DecodeLimits::default())
+}
+
+fn run_inspect(path: &Path, json: bool) -> Result<ExitCode, ProverError> {
+    let report = inspect(&load(path)?, &InspectOptions::default());
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    println!("version:        {}", report.version);
+    println!("backend:        {}", report.backend);
+    println!("proof system:   {}", report.proof_system.as_deref().unwrap_or("-"));
+    println!("proof mode:     {}", report.proof_mode.as_deref().unwrap_or("-"));
+    println!("program hash:   {}", report.program_hash);
+    println!("payload:        {} bytes", report.sizes.payload);
+    println!("public values:  {} bytes", report.sizes.public_values);
+    println!("                {}", report.public_values_hex);
+    println!("serialized:     {} bytes", report.sizes.serialized);
+    println!("checksum:       {}", if report.checksum_valid { "ok" } else { "MISMATCH" });
+    for (key, value) in &report.metadata {
+        println!("metadata:       {} = {}", key, value);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run_diff(left: &Path, right: &Path) -> Result<ExitCode, ProverError> {
+    let differences = diff(&load(left)?, &load(right)?);
+    if differences.is_empty() {
+        println!("envelopes are identical");
+        return Ok(ExitCode::SUCCESS);
+    }
+    for d in &differences {
+        println!("{}:", d.field);
+        println!("  - {}", d.left);
+        println!("  + {}", d.right);
+    }
+    Ok(ExitCode::from(1))
+}
+
+fn read_elf(path: &Path) -> Result<Vec<u8>, ProverError> {
+    let program = std::fs::read(path)?;
+    validate_elf(&program, None)?;
+    Ok(program)
+}
+
+fn read_input(path: Option<&Path>) -> Result<Vec<u8>, ProverError> {
+    Ok(match path {
+        Some(path) => std::fs::read(path)?,
+        None => Vec::new(),
+    })
+}
+
+fn run_setup(config: &ProverConfig, elf: &Path) -> Result<ExitCode, ProverError> {
+    let program = read_elf(elf)?;
+    let (vkey_hash, vk) = key_holder(config, config.proof_mode)?.setup(&program)?;
+    println!("program hash:   {}", program_hash(&program));
+    println!("vkey hash:      {}", vkey_hash);
+    println!("verifying key:  {} bytes", vk.len());
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run_prove(
+    config: &ProverConfig,
+    elf: &Path,
+    input: Option<&Path>,
+    mode: Option<ProofMode>,
+    out: &Path,
+) -> Result<ExitCode, ProverError> {
+    let mode = mode.unwrap_or(config.proof_mode);
+    let program = read_elf(elf)?;
+    let input = read_input(input)?;
+    let key_holder = Arc::new(key_holder(config, mode)?);
+    let backend: Arc<dyn ZkBackend> = match config.memory_budget() {
+        Some(budget) => Arc::new(MemoryBudgetBackend::for_key_holder(key_holder, budget)),
+        None => key_holder,
+    };
+    let proof = backend.prove(&program, &input)?;
+
+    let mut envelope = ProofEnvelope::new(&config.backend, program_hash(&program), proof, Vec::new());
+    envelope.insert_metadata(PROOF_MODE_KEY, mode.name());
+    std::fs::write(out, envelope.to_bytes()?)?;
+    println!("wrote {} proof to {}", mode.name(), out.display());
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run_verify(config: &ProverConfig, proof: &Path, elf: &Path) -> Result<ExitCode, ProverError> {
+    let envelope = decode_and_migrate(&std::fs::read(proof)?, &DecodeLimits::default())?;
+    let program = read_elf(elf)?;
+    if !ct_eq(envelope.program_hash.as_bytes(), program_hash(&program).as_bytes()) {
+        println!("proof is for program {}, not {}", envelope.program_hash, elf.display());
+        return Ok(ExitCode::from(1));
+    }
+    let mode = envelope
+        .metadata
+        .get(PROOF_MODE_KEY)
+        .and_then(|name| ProofMode::parse(name))
+        .unwrap_or(config.proof_mode);
+    if key_holder(config, mode)?.verify(&program, &envelope.payload)? {
+        println!("proof is valid");
+        Ok(ExitCode::SUCCESS)
+    } else {
+        println!("proof is INVALID");
+        Ok(ExitCode::from(1))
+    }
+}
+
+fn run_execute(config: &ProverConfig, elf: &Path, input: Option<&Path>, json: bool) -> Result<ExitCode, ProverError> {
+    let program = read_elf(elf)?;
+    let result = key_holder(config, config.proof_mode)?.execute(&program, &read_input(input)?)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&result.to_report())?);
+        return Ok(ExitCode::SUCCESS);
+    }
+    println!("cycles:         {}", result.cycles);
+    println!("public values:  {} bytes", result.public_values.len());
+    println!("                {}", hex::encode(&result.public_values));
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run_export_vk(config: &ProverConfig, elf: &Path, out: Option<&Path>) -> Result<ExitCode, ProverError> {
+    let program = read_elf(elf)?;
+    let (vkey_hash, vk) = key_holder(config, config.proof_mode)?.setup(&program)?;
+    let store = VkeyStore::new();
+    let hash = program_hash(&program);
+    store.insert(hash.clone(), vkey_hash, vk);
+    let exported = store.export_vk(&hash)?;
+    match out {
+        Some(out) => {
+            std::fs::write(out, exported)?;
+            eprintln!("wrote verifying key for program {} to {}", hash, out.display());
+        }
+        None => println!("{}", String::from_utf8_lossy(&exported)),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run_fixtures(config: &ProverConfig, elfs: &[PathBuf], input: Option<&Path>, out: &Path) -> Result<ExitCode, ProverError> {
+    let input = read_input(input)?;
+    let specs = elfs
+        .iter()
+        .map(|elf| {
+            let name = elf
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .ok_or_else(|| ProverError::Other(format!("{} has no file name", elf.display())))?;
+            Ok(FixtureSpec {
+                name,
+                program: read_elf(elf)?,
+                input: input.clone(),
+            })
+        })
+        .collect::<Result<Vec<_>, ProverError>>()?;
+    let key_holder = Arc::new(key_holder(config, config.proof_mode)?);
+    let setup = key_holder.key_setup();
+    let entries = generate_fixtures(key_holder.as_ref(), &config.backend, Some(&setup), &specs, out)?;
+    for entry in &entries {
+        println!("{:<16}{}", entry.name, entry.program_hash);
+    }
+    println!("wrote {} fixtures to {}", entries.len(), out.display());
+    Ok(ExitCode::SUCCESS)
+}
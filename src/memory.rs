@@ -0,0 +1,502 @@
+use crate::cancel::{Abort, AbortableBackend};
+use crate::estimation::CycleCounter;
+use crate::isolation::IsolatedBackend;
+use crate::types::ProverError;
+use frostgate_zkip::{ZkBackend, ZkError};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::time::Duration;
+
+/// Retry hint given when the budget is taken by running proofs
+const BUDGET_RETRY_AFTER: Duration = Duration::from_secs(5);
+
+/// SP1's default shard size, as a power of two
+const DEFAULT_SHARD_SIZE: u32 = 22;
+
+/// RSS a key holder may use beyond a proof's estimate before the proof is
+/// killed, for what the model leaves out
+pub const DEFAULT_RSS_HEADROOM: u64 = 1 << 30;
+
+/// Linear estimate of a local proof's peak memory. Traces are generated a
+/// shard at a time, so most memory scales with the cycles of one shard;
+/// checkpoints and the execution record add a little per cycle overall.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryModel {
+    /// Fixed cost of the prover: keys, artifacts and the program itself
+    pub base_bytes: u64,
+    /// Per cycle of the largest shard
+    pub bytes_per_shard_cycle: u64,
+    /// Per cycle of the whole execution
+    pub bytes_per_cycle: u64,
+}
+
+impl Default for MemoryModel {
+    /// Rough figures for SP1 on CPU: about 18 GB at the default shard size
+    fn default() -> Self {
+        Self {
+            base_bytes: 2 << 30,
+            bytes_per_shard_cycle: 4 << 10,
+            bytes_per_cycle: 8,
+        }
+    }
+}
+
+impl MemoryModel {
+    /// Peak memory of proving `cycles` cycles with shards of `2^shard_size`
+    /// cycles
+    pub fn estimate(&self, cycles: u64, shard_size: u32) -> u64 {
+        let shard_cycles = 1u64.checked_shl(shard_size).unwrap_or(u64::MAX).min(cycles);
+        self.base_bytes
+            .saturating_add(shard_cycles.saturating_mul(self.bytes_per_shard_cycle))
+            .saturating_add(cycles.saturating_mul(self.bytes_per_cycle))
+    }
+}
+
+/// Reads the resident memory of the process proofs run in
+pub type RssProbe = Arc<dyn Fn() -> Option<u64> + Send + Sync>;
+
+/// Probe of this process, for backends that prove in-process
+pub fn own_rss() -> RssProbe {
+    Arc::new(crate::resources::read_rss_bytes)
+}
+
+/// The process a backend proves in: how to measure it and how to stop it
+#[derive(Clone)]
+pub struct ProofWorker {
+    pub rss_bytes: RssProbe,
+    pub abort: Abort,
+}
+
+/// Memory set aside for local proofs. Each proof leases its estimated peak
+/// for as long as it runs.
+pub struct MemoryBudget {
+    total_bytes: u64,
+    reserved: Mutex<u64>,
+    freed: Condvar,
+}
+
+impl MemoryBudget {
+    pub fn new(total_bytes: u64) -> Arc<Self> {
+        Arc::new(Self {
+            total_bytes,
+            reserved: Mutex::new(0),
+            freed: Condvar::new(),
+        })
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.total_bytes
+    }
+
+    /// Bytes leased by running proofs
+    pub fn reserved_bytes(&self) -> u64 {
+        *self.reserved.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Lease `bytes` if they are free right now, failing with
+    /// [`ProverError::Busy`] otherwise
+    pub fn try_reserve(self: &Arc<Self>, bytes: u64) -> Result<MemoryLease, ProverError> {
+        self.check_satisfiable(bytes)?;
+        let mut reserved = self.reserved.lock().unwrap_or_else(PoisonError::into_inner);
+        if *reserved + bytes > self.total_bytes {
+            return Err(ProverError::Busy {
+                reason: format!(
+                    "proof needs {} bytes, {} of the {} byte memory budget are free",
+                    bytes,
+                    self.total_bytes - *reserved,
+                    self.total_bytes
+                ),
+                retry_after: BUDGET_RETRY_AFTER,
+            });
+        }
+        *reserved += bytes;
+        Ok(self.lease(bytes))
+    }
+
+    /// Lease `bytes`, waiting for running proofs to free them
+    pub fn reserve(self: &Arc<Self>, bytes: u64) -> Result<MemoryLease, ProverError> {
+        self.check_satisfiable(bytes)?;
+        let mut reserved = self.reserved.lock().unwrap_or_else(PoisonError::into_inner);
+        while *reserved + bytes > self.total_bytes {
+            reserved = self.freed.wait(reserved).unwrap_or_else(PoisonError::into_inner);
+        }
+        *reserved += bytes;
+        Ok(self.lease(bytes))
+    }
+
+    fn lease(self: &Arc<Self>, bytes: u64) -> MemoryLease {
+        MemoryLease {
+            budget: self.clone(),
+            bytes,
+        }
+    }
+
+    fn check_satisfiable(&self, bytes: u64) -> Result<(), ProverError> {
+        if bytes > self.total_bytes {
+            return Err(ProverError::InsufficientCapacity(format!(
+                "proof needs an estimated {} bytes, the memory budget is {}",
+                bytes, self.total_bytes
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Memory leased from a [`MemoryBudget`], returned on drop
+pub struct MemoryLease {
+    budget: Arc<MemoryBudget>,
+    bytes: u64,
+}
+
+impl MemoryLease {
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+}
+
+impl Drop for MemoryLease {
+    fn drop(&mut self) {
+        *self.budget.reserved.lock().unwrap_or_else(PoisonError::into_inner) -= self.bytes;
+        self.budget.freed.notify_all();
+    }
+}
+
+/// Backend that only starts a proof once its estimated memory fits in a
+/// shared [`MemoryBudget`]. Each request is executed first to count its
+/// cycles.
+///
+/// With an RSS limit the worker process's RSS is polled while a proof runs
+/// in it. Once the proof's estimate plus headroom is crossed that call is
+/// aborted and the request fails with [`ProverError::InsufficientCapacity`];
+/// the memory is returned as soon as the call has unwound.
+pub struct MemoryBudgetBackend {
+    inner: Arc<dyn ZkBackend>,
+    budget: Arc<MemoryBudget>,
+    count_cycles: CycleCounter,
+    model: MemoryModel,
+    shard_size: u32,
+    queue: bool,
+    rss_limit: Option<RssLimit>,
+    sample_interval: Duration,
+}
+
+/// How a [`MemoryBudgetBackend`] watches the memory of running proofs
+struct RssLimit {
+    headroom_bytes: u64,
+    rss_bytes: RssProbe,
+    backend: Arc<dyn AbortableBackend>,
+}
+
+impl MemoryBudgetBackend {
+    pub fn new(inner: Arc<dyn ZkBackend>, budget: Arc<MemoryBudget>, count_cycles: CycleCounter) -> Self {
+        Self {
+            inner,
+            budget,
+            count_cycles,
+            model: MemoryModel::default(),
+            shard_size: DEFAULT_SHARD_SIZE,
+            queue: true,
+            rss_limit: None,
+            sample_interval: Duration::from_millis(100),
+        }
+    }
+
+    /// Budget the proofs of `key_holder`, counting cycles by executing in
+    /// it and aborting a proof whose RSS outgrows its estimate by more than
+    /// [`DEFAULT_RSS_HEADROOM`]
+    pub fn for_key_holder(key_holder: Arc<IsolatedBackend>, budget: Arc<MemoryBudget>) -> Self {
+        let execute = key_holder.executor();
+        let count_cycles: CycleCounter = Arc::new(move |program, input| Ok(execute(program, input)?.cycles));
+        let rss_bytes = key_holder.worker().rss_bytes;
+        Self::new(key_holder.clone(), budget, count_cycles).with_rss_limit(DEFAULT_RSS_HEADROOM, rss_bytes, key_holder)
+    }
+
+    pub fn with_model(mut self, model: MemoryModel) -> Self {
+        self.model = model;
+        self
+    }
+
+    /// Shard size the backend proves with, as a power of two
+    pub fn with_shard_size(mut self, shard_size: u32) -> Self {
+        self.shard_size = shard_size;
+        self
+    }
+
+    /// Reject requests that don't fit right now with [`ProverError::Busy`]
+    /// instead of waiting for memory to free up
+    pub fn with_rejection(mut self) -> Self {
+        self.queue = false;
+        self
+    }
+
+    /// Prove through `backend`, reading the RSS of the process it proves in
+    /// with `rss_bytes`, and abort a proof once that exceeds its leased
+    /// estimate plus `headroom_bytes`. RSS is only read while the proof's
+    /// own call runs, and the abort stops that call alone: requests queued
+    /// behind it on `backend` go ahead afterwards.
+    pub fn with_rss_limit(
+        mut self,
+        headroom_bytes: u64,
+        rss_bytes: RssProbe,
+        backend: Arc<dyn AbortableBackend>,
+    ) -> Self {
+        self.rss_limit = Some(RssLimit {
+            headroom_bytes,
+            rss_bytes,
+            backend,
+        });
+        self
+    }
+
+    /// Estimated peak memory of proving `program` on `input`
+    pub fn estimate(&self, program: &[u8], input: &[u8]) -> Result<u64, ProverError> {
+        let cycles = (self.count_cycles)(program, input)?;
+        Ok(self.model.estimate(cycles, self.shard_size))
+    }
+
+    /// Prove, keeping [`ProverError::Busy`] and capacity errors intact
+    pub fn prove_within_budget(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ProverError> {
+        let bytes = self.estimate(program, input)?;
+        let lease = if self.queue {
+            self.budget.reserve(bytes)?
+        } else {
+            self.budget.try_reserve(bytes)?
+        };
+        tracing::debug!("leased {} bytes of the memory budget", bytes);
+        let _lease = lease;
+        let Some(limit) = &self.rss_limit else {
+            return Ok(self.inner.prove(program, input)?);
+        };
+        let rss_limit = bytes.saturating_add(limit.headroom_bytes);
+
+        let (tx, rx) = mpsc::channel();
+        let (started_tx, started_rx) = mpsc::channel::<Abort>();
+        let backend = limit.backend.clone();
+        let (program, input) = (program.to_vec(), input.to_vec());
+        std::thread::spawn(move || {
+            let result = backend.prove_abortable(&program, &input, &|abort| {
+                let _ = started_tx.send(abort);
+            });
+            let _ = tx.send(result);
+        });
+        let mut abort = None;
+        loop {
+            match rx.recv_timeout(self.sample_interval) {
+                Ok(result) => return Ok(result?),
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(ProverError::Other("proving thread panicked".to_string()));
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+            // Until the call starts, the worker's memory is someone else's
+            if abort.is_none() {
+                abort = started_rx.try_recv().ok();
+            }
+            let Some(abort) = &abort else {
+                continue;
+            };
+            if let Some(rss) = (limit.rss_bytes)()
+                && rss > rss_limit
+            {
+                tracing::warn!(
+                    "worker RSS of {} bytes exceeds the proof's limit of {}; aborting it",
+                    rss,
+                    rss_limit
+                );
+                abort();
+                // The call fails once it is stopped; wait so the memory
+                // really is free before the lease is returned
+                let _ = rx.recv();
+                return Err(ProverError::InsufficientCapacity(format!(
+                    "RSS of {} bytes exceeded the limit of {} while proving",
+                    rss, rss_limit
+                )));
+            }
+        }
+    }
+}
+
+impl ZkBackend for MemoryBudgetBackend {
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        self.prove_within_budget(program, input)
+            .map_err(ProverError::into_zk_error)
+    }
+
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        self.inner.verify(program, proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockBackend, MockConfig, stalling_key_holder};
+
+    fn backend(budget: &Arc<MemoryBudget>, latency: Duration) -> MemoryBudgetBackend {
+        let mock = MockBackend::new(MockConfig {
+            prove_latency: latency,
+            ..MockConfig::default()
+        });
+        // One byte per input byte
+        let counter: CycleCounter = Arc::new(|_, input| Ok(input.len() as u64));
+        let model = MemoryModel {
+            base_bytes: 0,
+            bytes_per_shard_cycle: 0,
+            bytes_per_cycle: 1,
+        };
+        MemoryBudgetBackend::new(Arc::new(mock), budget.clone(), counter).with_model(model)
+    }
+
+    #[test]
+    fn test_budget() {
+        let budget = MemoryBudget::new(10);
+        let backend = Arc::new(backend(&budget, Duration::from_millis(200)).with_rejection());
+        assert!(matches!(
+            backend.prove_within_budget(b"elf", &[0; 11]),
+            Err(ProverError::InsufficientCapacity(_))
+        ));
+
+        let running = {
+            let backend = backend.clone();
+            std::thread::spawn(move || backend.prove_within_budget(b"elf", &[0; 6]))
+        };
+        while budget.reserved_bytes() == 0 {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert!(matches!(
+            backend.prove_within_budget(b"elf", &[1; 6]),
+            Err(ProverError::Busy { .. })
+        ));
+        assert!(backend.prove_within_budget(b"elf", &[1; 4]).is_ok());
+        assert!(running.join().unwrap().is_ok());
+        assert_eq!(budget.reserved_bytes(), 0);
+    }
+
+    #[test]
+    fn test_rss_limit_kills_worker() {
+        let budget = MemoryBudget::new(10);
+        let key_holder = Arc::new(IsolatedBackend::spawn(&stalling_key_holder()).unwrap());
+        let counter: CycleCounter = Arc::new(|_, input| Ok(input.len() as u64));
+        let backend = MemoryBudgetBackend::new(key_holder.clone(), budget.clone(), counter)
+            .with_model(MemoryModel {
+                base_bytes: 0,
+                bytes_per_shard_cycle: 0,
+                bytes_per_cycle: 1,
+            })
+            .with_rss_limit(0, key_holder.worker().rss_bytes, key_holder.clone());
+
+        assert!(matches!(
+            backend.prove_within_budget(b"elf", &[0; 5]),
+            Err(ProverError::InsufficientCapacity(_))
+        ));
+        // The proof died with its worker, so its memory is back
+        assert_eq!(budget.reserved_bytes(), 0);
+        // and a fresh key holder takes the next request
+        assert!(key_holder.verify(b"elf", b"proof").unwrap());
+    }
+
+    /// Runs one call at a time, each growing the shared RSS by its input
+    /// length until aborted or done
+    struct GrowingWorker {
+        running: Mutex<()>,
+        rss: Arc<std::sync::atomic::AtomicU64>,
+    }
+
+    impl ZkBackend for GrowingWorker {
+        fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+            self.prove_abortable(program, input, &|_| {})
+        }
+
+        fn verify(&self, _program: &[u8], _proof: &[u8]) -> Result<bool, ZkError> {
+            Ok(true)
+        }
+    }
+
+    impl AbortableBackend for GrowingWorker {
+        fn prove_abortable(
+            &self,
+            _program: &[u8],
+            input: &[u8],
+            started: &(dyn Fn(Abort) + Sync),
+        ) -> Result<Vec<u8>, ZkError> {
+            use std::sync::atomic::{AtomicBool, Ordering};
+            let _running = self.running.lock().unwrap_or_else(PoisonError::into_inner);
+            let aborted = Arc::new(AtomicBool::new(false));
+            let flag = aborted.clone();
+            started(Arc::new(move || flag.store(true, Ordering::SeqCst)));
+            self.rss.store(input.len() as u64, Ordering::SeqCst);
+            let result = (|| {
+                for _ in 0..20 {
+                    if aborted.load(Ordering::SeqCst) {
+                        return Err(ZkError::Config("aborted".to_string()));
+                    }
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+                Ok(input.to_vec())
+            })();
+            self.rss.store(0, Ordering::SeqCst);
+            result
+        }
+
+        fn verify_abortable(
+            &self,
+            _program: &[u8],
+            _proof: &[u8],
+            _started: &(dyn Fn(Abort) + Sync),
+        ) -> Result<bool, ZkError> {
+            Ok(true)
+        }
+    }
+
+    #[test]
+    fn test_rss_limit_spares_queued_callers() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        let rss = Arc::new(AtomicU64::new(0));
+        let worker = Arc::new(GrowingWorker {
+            running: Mutex::new(()),
+            rss: rss.clone(),
+        });
+        // Proofs are estimated at a byte per input byte up to 8, so the
+        // second request outgrows its estimate while the others don't
+        let counter: CycleCounter = Arc::new(|_, input| Ok(input.len().min(8) as u64));
+        let probe: RssProbe = Arc::new(move || Some(rss.load(Ordering::SeqCst)));
+        let mut backend = MemoryBudgetBackend::new(worker.clone(), MemoryBudget::new(100), counter)
+            .with_model(MemoryModel {
+                base_bytes: 0,
+                bytes_per_shard_cycle: 0,
+                bytes_per_cycle: 1,
+            })
+            .with_rss_limit(2, probe, worker.clone());
+        backend.sample_interval = Duration::from_millis(5);
+        let backend = Arc::new(backend);
+
+        let first = {
+            let backend = backend.clone();
+            std::thread::spawn(move || backend.prove_within_budget(b"elf", &[0; 4]))
+        };
+        std::thread::sleep(Duration::from_millis(20));
+        // Queued behind the first while it runs within its limit
+        let second = {
+            let backend = backend.clone();
+            std::thread::spawn(move || backend.prove_within_budget(b"elf", &[0; 16]))
+        };
+        let third = {
+            let backend = backend.clone();
+            std::thread::spawn(move || backend.prove_within_budget(b"elf", &[0; 5]))
+        };
+
+        assert_eq!(first.join().unwrap().unwrap(), vec![0; 4]);
+        assert!(matches!(
+            second.join().unwrap(),
+            Err(ProverError::InsufficientCapacity(_))
+        ));
+        assert_eq!(third.join().unwrap().unwrap(), vec![0; 5]);
+    }
+
+    #[test]
+    fn test_model() {
+        let model = MemoryModel::default();
+        assert!(model.estimate(1 << 30, 22) > model.estimate(1 << 30, 20));
+        assert_eq!(model.estimate(0, 22), model.base_bytes);
+    }
+}
@@ -0,0 +1,250 @@
+use crate::events::{EventSubscriber, LifecycleEvent};
+use crate::pipeline::ProofMode;
+use crate::types::{ProgramHash, ProverError};
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use std::collections::HashSet;
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+/// Latency buckets in seconds, from sub-second mock proofs to hour-long
+/// wrapped network proofs
+const LATENCY_BUCKETS: &[f64] = &[0.1, 0.5, 1.0, 5.0, 15.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1800.0, 3600.0];
+
+/// Prometheus metrics for proving. Subscribe it to an [`crate::events::EventBus`]
+/// to record proofs automatically, or call the `observe_*` methods directly.
+pub struct ProverMetrics {
+    registry: Registry,
+    proofs: IntCounterVec,
+    failures: IntCounterVec,
+    proof_latency: HistogramVec,
+    setup_time: Histogram,
+    queue_depth: IntGauge,
+    permits_in_use: IntGauge,
+    permits_total: IntGauge,
+    program_running: IntGaugeVec,
+    program_queued: IntGaugeVec,
+    /// Most programs given their own slot gauges, none unless enabled
+    max_program_labels: usize,
+    labelled_programs: Mutex<HashSet<ProgramHash>>,
+    network_spend: IntCounterVec,
+}
+
+impl ProverMetrics {
+    /// Register every metric in a fresh registry
+    pub fn new() -> Result<Self, ProverError> {
+        Self::with_registry(Registry::new())
+    }
+
+    /// Register every metric in `registry`, e.g. one shared with the host service
+    pub fn with_registry(registry: Registry) -> Result<Self, ProverError> {
+        let proofs = IntCounterVec::new(
+            Opts::new("frostgate_proofs_total", "Proofs completed"),
+            &["backend", "proof_type"],
+        )
+        .map_err(metrics_error)?;
+        let failures = IntCounterVec::new(
+            Opts::new("frostgate_proof_failures_total", "Failed proof attempts"),
+            &["backend", "proof_type"],
+        )
+        .map_err(metrics_error)?;
+        let proof_latency = HistogramVec::new(
+            HistogramOpts::new("frostgate_proof_duration_seconds", "Time to produce a proof")
+                .buckets(LATENCY_BUCKETS.to_vec()),
+            &["backend", "proof_type"],
+        )
+        .map_err(metrics_error)?;
+        let setup_time = Histogram::with_opts(
+            HistogramOpts::new("frostgate_setup_duration_seconds", "Time spent on program setup")
+                .buckets(LATENCY_BUCKETS.to_vec()),
+        )
+        .map_err(metrics_error)?;
+        let queue_depth =
+            IntGauge::new("frostgate_queue_depth", "Proof requests waiting to start").map_err(metrics_error)?;
+        let permits_in_use = IntGauge::new("frostgate_proving_permits_in_use", "Proving slots currently taken")
+            .map_err(metrics_error)?;
+        let permits_total =
+            IntGauge::new("frostgate_proving_permits_total", "Proving slots available").map_err(metrics_error)?;
+        let program_running = IntGaugeVec::new(
+            Opts::new("frostgate_program_proofs_running", "Proofs running per limited program"),
+            &["program_hash"],
+        )
+        .map_err(metrics_error)?;
+        let program_queued = IntGaugeVec::new(
+            Opts::new("frostgate_program_proofs_queued", "Proofs waiting for a limited program's slots"),
+            &["program_hash"],
+        )
+        .map_err(metrics_error)?;
+        let network_spend = IntCounterVec::new(
+            Opts::new("frostgate_network_spend_total", "Fulfilled paid network spend by request label"),
+            &["label", "value"],
+        )
+        .map_err(metrics_error)?;
+
+        registry.register(Box::new(proofs.clone())).map_err(metrics_error)?;
+        registry.register(Box::new(failures.clone())).map_err(metrics_error)?;
+        registry.register(Box::new(proof_latency.clone())).map_err(metrics_error)?;
+        registry.register(Box::new(setup_time.clone())).map_err(metrics_error)?;
+        registry.register(Box::new(queue_depth.clone())).map_err(metrics_error)?;
+        registry.register(Box::new(permits_in_use.clone())).map_err(metrics_error)?;
+        registry.register(Box::new(permits_total.clone())).map_err(metrics_error)?;
+        registry.register(Box::new(program_running.clone())).map_err(metrics_error)?;
+        registry.register(Box::new(program_queued.clone())).map_err(metrics_error)?;
+        registry.register(Box::new(network_spend.clone())).map_err(metrics_error)?;
+
+        Ok(Self {
+            registry,
+            proofs,
+            failures,
+            proof_latency,
+            setup_time,
+            queue_depth,
+            permits_in_use,
+            permits_total,
+            program_running,
+            program_queued,
+            max_program_labels: 0,
+            labelled_programs: Mutex::new(HashSet::new()),
+            network_spend,
+        })
+    }
+
+    /// Export slot gauges labelled with the program hash for up to
+    /// `max_programs` programs. Off by default, since every program adds a
+    /// series; programs beyond the cap are left out.
+    pub fn with_program_labels(mut self, max_programs: usize) -> Self {
+        self.max_program_labels = max_programs;
+        self
+    }
+
+    /// Record a completed proof
+    pub fn observe_proof(&self, backend: &str, mode: ProofMode, duration: Duration) {
+        self.proofs.with_label_values(&[backend, mode.name()]).inc();
+        self.proof_latency
+            .with_label_values(&[backend, mode.name()])
+            .observe(duration.as_secs_f64());
+    }
+
+    /// Record a failed proof attempt
+    pub fn observe_failure(&self, backend: &str, mode: ProofMode) {
+        self.failures.with_label_values(&[backend, mode.name()]).inc();
+    }
+
+    /// Record time spent setting up a program
+    pub fn observe_setup(&self, duration: Duration) {
+        self.setup_time.observe(duration.as_secs_f64());
+    }
+
+    pub fn set_queue_depth(&self, depth: usize) {
+        self.queue_depth.set(depth as i64);
+    }
+
+    /// Record semaphore usage; saturation is `in_use / total`
+    pub fn set_permits(&self, in_use: usize, total: usize) {
+        self.permits_in_use.set(in_use as i64);
+        self.permits_total.set(total as i64);
+    }
+
+    /// Record the slots of one program with a concurrency limit, if program
+    /// labels are enabled and the program fits under their cap
+    pub fn set_program_slots(&self, program_hash: &str, running: usize, queued: usize) {
+        {
+            let mut labelled = self.labelled_programs.lock().unwrap_or_else(PoisonError::into_inner);
+            if !labelled.contains(program_hash) {
+                if labelled.len() >= self.max_program_labels {
+                    return;
+                }
+                labelled.insert(program_hash.to_string());
+            }
+        }
+        self.program_running.with_label_values(&[program_hash]).set(running as i64);
+        self.program_queued.with_label_values(&[program_hash]).set(queued as i64);
+    }
+
+    /// Record `cost` spent on a paid request whose label `label` was `value`
+    pub fn observe_network_spend(&self, label: &str, value: &str, cost: u64) {
+        self.network_spend.with_label_values(&[label, value]).inc_by(cost);
+    }
+
+    /// The registry holding every metric, for custom exporters
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    /// All metrics in the Prometheus text exposition format
+    pub fn gather(&self) -> Result<String, ProverError> {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .map_err(metrics_error)?;
+        String::from_utf8(buffer).map_err(|e| ProverError::Other(format!("metrics are not UTF-8: {}", e)))
+    }
+}
+
+impl EventSubscriber for ProverMetrics {
+    fn on_event(&self, event: &LifecycleEvent) {
+        match event {
+            LifecycleEvent::ProofCompleted {
+                backend, mode, duration, ..
+            } => self.observe_proof(backend, *mode, *duration),
+            LifecycleEvent::ProofFailed { backend, mode, .. } => self.observe_failure(backend, *mode),
+            LifecycleEvent::ProgramSetup { duration, .. } => self.observe_setup(*duration),
+            _ => {}
+        }
+    }
+}
+
+fn metrics_error(e: prometheus::Error) -> ProverError {
+    ProverError::Other(format!("metrics error: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{EventBus, EventedBackend};
+    use crate::testing::MockBackend;
+    use frostgate_zkip::ZkBackend;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_records_proofs_from_events() {
+        let metrics = Arc::new(ProverMetrics::new().unwrap());
+        let bus = Arc::new(EventBus::new());
+        bus.subscribe(metrics.clone());
+        let backend = EventedBackend::new(Arc::new(MockBackend::default()), bus.clone(), "mock");
+        let compressed =
+            EventedBackend::new(Arc::new(MockBackend::default()), bus.clone(), "mock").with_mode(ProofMode::Compressed);
+
+        backend.prove(b"elf", b"input").unwrap();
+        compressed.prove(b"elf", b"input").unwrap();
+        bus.emit(LifecycleEvent::ProgramSetup {
+            program_hash: "ab".repeat(32),
+            vkey_hash: "0x01".to_string(),
+            duration: Duration::from_secs(2),
+        });
+        metrics.set_permits(1, 4);
+
+        let text = metrics.gather().unwrap();
+        assert!(text.contains(r#"frostgate_proofs_total{backend="mock",proof_type="core"} 1"#));
+        assert!(text.contains(r#"frostgate_proofs_total{backend="mock",proof_type="compressed"} 1"#));
+        assert!(text.contains("frostgate_setup_duration_seconds_count 1"));
+        assert!(text.contains("frostgate_proving_permits_total 4"));
+    }
+
+    #[test]
+    fn test_program_labels_capped() {
+        let metrics = ProverMetrics::new().unwrap();
+        metrics.set_program_slots("aa", 1, 0);
+        assert!(!metrics.gather().unwrap().contains("frostgate_program_proofs_running"));
+
+        let metrics = ProverMetrics::new().unwrap().with_program_labels(1);
+        metrics.set_program_slots("aa", 1, 0);
+        metrics.set_program_slots("bb", 1, 0);
+        metrics.set_program_slots("aa", 2, 1);
+        let text = metrics.gather().unwrap();
+        assert!(text.contains(r#"frostgate_program_proofs_running{program_hash="aa"} 2"#));
+        assert!(!text.contains(r#"program_hash="bb""#));
+    }
+}
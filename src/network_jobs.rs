@@ -0,0 +1,273 @@
+use crate::audit::now_ms;
+use crate::resources::ResourceTracker;
+use crate::types::{ProgramHash, ProverError, program_hash};
+use frostgate_zkip::{ZkBackend, ZkError};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+const PENDING_JOBS_FILE: &str = "pending_network_jobs.json";
+
+/// State of a request on a proving network
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RemoteStatus {
+    Pending,
+    Fulfilled(Vec<u8>),
+    Failed(String),
+}
+
+/// A proving network that accepts requests and is polled for their results
+pub trait RemoteProver: Send + Sync {
+    /// Submit a request, returning the network's request id
+    fn submit(&self, program: &[u8], input: &[u8]) -> Result<String, ZkError>;
+
+    fn status(&self, request_id: &str) -> Result<RemoteStatus, ZkError>;
+
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError>;
+}
+
+/// A submitted request that survives restarts
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingRemoteJob {
+    pub request_id: String,
+    pub program_hash: ProgramHash,
+    pub submitted_at_ms: u64,
+}
+
+/// Polling behaviour for network requests
+#[derive(Debug, Clone)]
+pub struct NetworkJobConfig {
+    pub poll_interval: Duration,
+    /// Requests older than this are abandoned, measured from submission
+    pub timeout: Duration,
+}
+
+impl Default for NetworkJobConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(5),
+            timeout: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+/// Submits proofs to a proving network and persists the request ids, so a
+/// restarted process can re-attach to requests still in flight
+pub struct NetworkJobTracker {
+    prover: Arc<dyn RemoteProver>,
+    config: NetworkJobConfig,
+    path: Option<PathBuf>,
+    pending: Mutex<BTreeMap<String, PendingRemoteJob>>,
+    resources: Option<Arc<ResourceTracker>>,
+}
+
+impl NetworkJobTracker {
+    /// Create a tracker that forgets pending requests on restart
+    pub fn in_memory(prover: Arc<dyn RemoteProver>, config: NetworkJobConfig) -> Self {
+        Self {
+            prover,
+            config,
+            path: None,
+            pending: Mutex::new(BTreeMap::new()),
+            resources: None,
+        }
+    }
+
+    /// Open a tracker persisted in `cache_dir`, loading requests left pending
+    /// by a previous process
+    pub fn open(prover: Arc<dyn RemoteProver>, config: NetworkJobConfig, cache_dir: &Path) -> Result<Self, ProverError> {
+        fs::create_dir_all(cache_dir)?;
+        let path = cache_dir.join(PENDING_JOBS_FILE);
+        let pending = if path.exists() {
+            serde_json::from_slice(&fs::read(&path)?)?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(Self {
+            prover,
+            config,
+            path: Some(path),
+            pending: Mutex::new(pending),
+            resources: None,
+        })
+    }
+
+    /// Report pending requests in `resources` snapshots
+    pub fn with_resource_tracker(mut self, resources: Arc<ResourceTracker>) -> Self {
+        self.resources = Some(resources);
+        self
+    }
+
+    /// Requests submitted but not yet completed
+    pub fn pending_jobs(&self) -> Vec<PendingRemoteJob> {
+        self.pending
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// Re-attach to every request left pending by a previous process and
+    /// wait for it, returning each request id with its outcome
+    pub async fn resume_pending(self: &Arc<Self>) -> Vec<(String, Result<Vec<u8>, ProverError>)> {
+        let jobs = self.pending_jobs();
+        if !jobs.is_empty() {
+            tracing::info!("resuming {} pending network requests", jobs.len());
+        }
+        let tasks: Vec<_> = jobs
+            .into_iter()
+            .map(|job| {
+                let tracker = self.clone();
+                let request_id = job.request_id.clone();
+                (request_id, tokio::task::spawn_blocking(move || tracker.wait(&job)))
+            })
+            .collect();
+
+        let mut results = Vec::with_capacity(tasks.len());
+        for (request_id, task) in tasks {
+            let result = task
+                .await
+                .unwrap_or_else(|e| Err(ProverError::Other(format!("resume task failed: {}", e))));
+            results.push((request_id, result));
+        }
+        results
+    }
+
+    /// Poll `job` until it completes, fails or times out, then forget it
+    fn wait(&self, job: &PendingRemoteJob) -> Result<Vec<u8>, ProverError> {
+        let _remote = self.resources.as_ref().map(|resources| resources.track_remote());
+        let deadline = job.submitted_at_ms + self.config.timeout.as_millis() as u64;
+        let result = loop {
+            match self.prover.status(&job.request_id) {
+                Ok(RemoteStatus::Fulfilled(proof)) => break Ok(proof),
+                Ok(RemoteStatus::Failed(reason)) => {
+                    break Err(ProverError::Other(format!(
+                        "network request {} failed: {}",
+                        job.request_id, reason
+                    )));
+                }
+                Ok(RemoteStatus::Pending) => {}
+                // Transient poll failures don't lose the request
+                Err(e) => tracing::warn!("polling network request {} failed: {:?}", job.request_id, e),
+            }
+            if now_ms() >= deadline {
+                break Err(ProverError::Timeout(format!(
+                    "network request {} not fulfilled within {:?}",
+                    job.request_id, self.config.timeout
+                )));
+            }
+            std::thread::sleep(self.config.poll_interval);
+        };
+        self.forget(&job.request_id)?;
+        result
+    }
+
+    fn forget(&self, request_id: &str) -> Result<(), ProverError> {
+        let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+        if pending.remove(request_id).is_some() {
+            self.persist(&pending)?;
+        }
+        Ok(())
+    }
+
+    fn persist(&self, pending: &BTreeMap<String, PendingRemoteJob>) -> Result<(), ProverError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec(pending)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    fn submit(&self, program: &[u8], input: &[u8]) -> Result<PendingRemoteJob, ProverError> {
+        let job = PendingRemoteJob {
+            request_id: self.prover.submit(program, input)?,
+            program_hash: program_hash(program),
+            submitted_at_ms: now_ms(),
+        };
+        let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+        pending.insert(job.request_id.clone(), job.clone());
+        self.persist(&pending)?;
+        Ok(job)
+    }
+}
+
+impl ZkBackend for NetworkJobTracker {
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        let job = self.submit(program, input).map_err(ProverError::into_zk_error)?;
+        self.wait(&job).map_err(ProverError::into_zk_error)
+    }
+
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        self.prover.verify(program, proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    /// Network that fulfils a request on its second poll
+    #[derive(Default)]
+    struct FakeNetwork {
+        next_id: AtomicU64,
+        polls: Mutex<BTreeMap<String, u32>>,
+    }
+
+    impl RemoteProver for FakeNetwork {
+        fn submit(&self, _program: &[u8], _input: &[u8]) -> Result<String, ZkError> {
+            Ok(format!("req-{}", self.next_id.fetch_add(1, Ordering::SeqCst)))
+        }
+
+        fn status(&self, request_id: &str) -> Result<RemoteStatus, ZkError> {
+            let mut polls = self.polls.lock().unwrap();
+            let count = polls.entry(request_id.to_string()).or_default();
+            *count += 1;
+            Ok(match *count {
+                1 => RemoteStatus::Pending,
+                _ => RemoteStatus::Fulfilled(request_id.as_bytes().to_vec()),
+            })
+        }
+
+        fn verify(&self, _program: &[u8], _proof: &[u8]) -> Result<bool, ZkError> {
+            Ok(true)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resume_after_restart() {
+        let dir = std::env::temp_dir().join(format!("frostgate-network-jobs-{}", uuid::Uuid::new_v4()));
+        let network = Arc::new(FakeNetwork::default());
+        let config = NetworkJobConfig {
+            poll_interval: Duration::from_millis(1),
+            ..NetworkJobConfig::default()
+        };
+
+        // A process submits and then dies before the proof arrives
+        let first = NetworkJobTracker::open(network.clone(), config.clone(), &dir).unwrap();
+        first.submit(b"elf", b"input").unwrap();
+        drop(first);
+
+        let resources = Arc::new(ResourceTracker::new());
+        let restarted = Arc::new(
+            NetworkJobTracker::open(network, config, &dir)
+                .unwrap()
+                .with_resource_tracker(resources.clone()),
+        );
+        assert_eq!(restarted.pending_jobs().len(), 1);
+
+        let results = restarted.resume_pending().await;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].1.as_ref().unwrap(), b"req-0");
+        assert!(restarted.pending_jobs().is_empty());
+        assert_eq!(resources.sample().pending_remote_jobs, 0);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,263 @@
+use crate::types::ProverError;
+use frostgate_zkip::{ZkBackend, ZkError};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+
+/// Predicate deciding whether a backend error means the network is unreachable
+pub type ConnectivityCheck = Arc<dyn Fn(&ZkError) -> bool + Send + Sync>;
+
+/// Receiver for the outcome of a parked job
+pub type ParkedResult = oneshot::Receiver<Result<Vec<u8>, ProverError>>;
+
+/// Configuration for the "awaiting network" queue
+#[derive(Debug, Clone)]
+pub struct OfflineQueueConfig {
+    /// Park jobs on connectivity failures instead of failing them
+    pub enabled: bool,
+    /// Maximum number of parked jobs
+    pub max_jobs: usize,
+    /// Maximum time a job may wait for the network before it is failed
+    pub max_age: Duration,
+    /// Delay between retry rounds
+    pub retry_interval: Duration,
+}
+
+impl Default for OfflineQueueConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_jobs: 256,
+            max_age: Duration::from_secs(30 * 60),
+            retry_interval: Duration::from_secs(15),
+        }
+    }
+}
+
+/// Outcome of a submission through the offline queue
+pub enum Submission {
+    /// The backend answered immediately
+    Completed(Vec<u8>),
+    /// The network was unreachable and the job is waiting for a retry
+    Parked(ParkedResult),
+}
+
+struct ParkedJob {
+    program: Vec<u8>,
+    input: Vec<u8>,
+    parked_at: Instant,
+    reply: oneshot::Sender<Result<Vec<u8>, ProverError>>,
+}
+
+/// Submits proofs to a network backend, parking them while the network is down
+pub struct OfflineQueue {
+    backend: Arc<dyn ZkBackend>,
+    config: OfflineQueueConfig,
+    is_offline: ConnectivityCheck,
+    parked: Mutex<VecDeque<ParkedJob>>,
+}
+
+impl OfflineQueue {
+    /// Create a queue in front of `backend`
+    pub fn new(backend: Arc<dyn ZkBackend>, config: OfflineQueueConfig) -> Self {
+        Self {
+            backend,
+            config,
+            is_offline: Arc::new(is_connectivity_error),
+            parked: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Replace the predicate used to detect connectivity failures
+    pub fn with_connectivity_check(mut self, check: ConnectivityCheck) -> Self {
+        self.is_offline = check;
+        self
+    }
+
+    /// Submit a proof, parking it if the network is unreachable
+    pub fn submit(&self, program: &[u8], input: &[u8]) -> Result<Submission, ProverError> {
+        match self.backend.prove(program, input) {
+            Ok(proof) => Ok(Submission::Completed(proof)),
+            Err(e) if self.config.enabled && (self.is_offline)(&e) => {
+                let rx = self.park(program, input)?;
+                tracing::warn!("network unreachable, parked job ({} waiting)", self.len());
+                Ok(Submission::Parked(rx))
+            }
+            Err(e) => Err(ProverError::ZKError(e)),
+        }
+    }
+
+    /// Program and input of every parked job, oldest first
+    pub fn parked_jobs(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.parked
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|job| (job.program.clone(), job.input.clone()))
+            .collect()
+    }
+
+    /// Park a job without attempting it first, e.g. when restoring a snapshot
+    pub fn park(&self, program: &[u8], input: &[u8]) -> Result<ParkedResult, ProverError> {
+        let mut parked = self.parked.lock().unwrap_or_else(PoisonError::into_inner);
+        if parked.len() >= self.config.max_jobs {
+            return Err(ProverError::QueueFull);
+        }
+        let (reply, rx) = oneshot::channel();
+        parked.push_back(ParkedJob {
+            program: program.to_vec(),
+            input: input.to_vec(),
+            parked_at: Instant::now(),
+            reply,
+        });
+        Ok(rx)
+    }
+
+    /// Number of jobs awaiting the network
+    pub fn len(&self) -> usize {
+        self.parked.lock().unwrap_or_else(PoisonError::into_inner).len()
+    }
+
+    /// Whether no jobs are awaiting the network
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Retry parked jobs in order, stopping at the first connectivity failure.
+    /// Returns the number of jobs that left the queue.
+    pub fn retry_parked(&self) -> usize {
+        self.expire();
+
+        let mut resolved = 0;
+        loop {
+            let Some(job) = self.parked.lock().unwrap_or_else(PoisonError::into_inner).pop_front() else {
+                break;
+            };
+            if job.reply.is_closed() {
+                resolved += 1;
+                continue;
+            }
+            match self.backend.prove(&job.program, &job.input) {
+                Ok(proof) => {
+                    let _ = job.reply.send(Ok(proof));
+                }
+                Err(e) if (self.is_offline)(&e) => {
+                    self.parked.lock().unwrap_or_else(PoisonError::into_inner).push_front(job);
+                    break;
+                }
+                Err(e) => {
+                    let _ = job.reply.send(Err(ProverError::ZKError(e)));
+                }
+            }
+            resolved += 1;
+        }
+        resolved
+    }
+
+    /// Spawn a task that retries parked jobs every `retry_interval`
+    pub fn spawn_retry_loop(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.config.retry_interval);
+            loop {
+                ticker.tick().await;
+                if self.is_empty() {
+                    continue;
+                }
+                let queue = self.clone();
+                if let Err(e) = tokio::task::spawn_blocking(move || queue.retry_parked()).await {
+                    tracing::error!("offline queue retry round failed: {}", e);
+                }
+            }
+        })
+    }
+
+    /// Fail jobs that have waited longer than `max_age`
+    fn expire(&self) {
+        let mut parked = self.parked.lock().unwrap_or_else(PoisonError::into_inner);
+        let max_age = self.config.max_age;
+        let (expired, kept): (VecDeque<_>, VecDeque<_>) =
+            parked.drain(..).partition(|job| job.parked_at.elapsed() > max_age);
+        *parked = kept;
+        drop(parked);
+
+        for job in expired {
+            let _ = job.reply.send(Err(ProverError::NetworkUnavailable(format!(
+                "job waited more than {:?} for the network",
+                max_age
+            ))));
+        }
+    }
+}
+
+/// Default connectivity check, based on the error's description
+pub fn is_connectivity_error(e: &ZkError) -> bool {
+    let msg = format!("{:?}", e).to_lowercase();
+    ["network", "connect", "unreachable", "dns", "timed out"]
+        .iter()
+        .any(|needle| msg.contains(needle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct FlakyBackend {
+        failures_left: AtomicUsize,
+    }
+
+    impl ZkBackend for FlakyBackend {
+        fn prove(&self, _program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+            if self.failures_left.load(Ordering::SeqCst) > 0 {
+                self.failures_left.fetch_sub(1, Ordering::SeqCst);
+                return Err(ZkError::Config("network unreachable".to_string()));
+            }
+            Ok(input.to_vec())
+        }
+
+        fn verify(&self, _program: &[u8], _proof: &[u8]) -> Result<bool, ZkError> {
+            Ok(true)
+        }
+    }
+
+    #[test]
+    fn test_parks_and_retries() {
+        let backend = Arc::new(FlakyBackend { failures_left: AtomicUsize::new(2) });
+        let queue = OfflineQueue::new(backend, OfflineQueueConfig::default());
+
+        let mut rx = match queue.submit(b"elf", b"input").unwrap() {
+            Submission::Parked(rx) => rx,
+            Submission::Completed(_) => panic!("expected job to be parked"),
+        };
+        assert_eq!(queue.len(), 1);
+
+        // Still offline on the first retry
+        assert_eq!(queue.retry_parked(), 0);
+        assert_eq!(queue.retry_parked(), 1);
+        assert!(queue.is_empty());
+        assert_eq!(rx.try_recv().unwrap().unwrap(), b"input".to_vec());
+    }
+
+    #[test]
+    fn test_queue_limits() {
+        let backend = Arc::new(FlakyBackend { failures_left: AtomicUsize::new(usize::MAX) });
+        let config = OfflineQueueConfig {
+            max_jobs: 1,
+            max_age: Duration::ZERO,
+            ..Default::default()
+        };
+        let queue = OfflineQueue::new(backend, config);
+
+        let mut rx = match queue.submit(b"elf", b"a").unwrap() {
+            Submission::Parked(rx) => rx,
+            Submission::Completed(_) => panic!("expected job to be parked"),
+        };
+        assert!(matches!(queue.submit(b"elf", b"b"), Err(ProverError::QueueFull)));
+
+        std::thread::sleep(Duration::from_millis(1));
+        queue.retry_parked();
+        assert!(queue.is_empty());
+        assert!(matches!(rx.try_recv().unwrap(), Err(ProverError::NetworkUnavailable(_))));
+    }
+}
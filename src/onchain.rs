@@ -0,0 +1,175 @@
+use crate::envelope::ProofEnvelope;
+use crate::inspect::PROOF_MODE_KEY;
+use crate::pipeline::ProofMode;
+use crate::types::ProverError;
+use sha3::{Digest, Keccak256};
+
+/// Solidity signature of the SP1 verifier gateway entry point
+pub const VERIFY_PROOF_SIGNATURE: &str = "verifyProof(bytes32,bytes,bytes)";
+
+/// Proof ready for submission to an on-chain SP1 verifier
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OnchainProof {
+    /// The program's verifying key as committed on chain (`vk.bytes32()`)
+    pub program_vkey: [u8; 32],
+    pub public_values: Vec<u8>,
+    /// Wrapped proof bytes, including the verifier selector prefix
+    pub proof: Vec<u8>,
+    pub mode: ProofMode,
+}
+
+impl OnchainProof {
+    /// Build from an envelope holding a Plonk or Groth16 proof
+    pub fn from_envelope(envelope: &ProofEnvelope, program_vkey: &str) -> Result<Self, ProverError> {
+        let mode = envelope
+            .metadata
+            .get(PROOF_MODE_KEY)
+            .and_then(|mode| ProofMode::parse(mode))
+            .ok_or_else(|| ProverError::MalformedEnvelope("envelope has no proof mode".to_string()))?;
+        if !mode.is_onchain_verifiable() {
+            return Err(ProverError::Other(format!(
+                "{} proofs can't be verified on chain, wrap into plonk or groth16 first",
+                mode.name()
+            )));
+        }
+        Ok(Self {
+            program_vkey: parse_vkey(program_vkey)?,
+            public_values: envelope.public_values.clone(),
+            proof: envelope.payload.clone(),
+            mode,
+        })
+    }
+
+    /// ABI-encoded call to `verifyProof(bytes32,bytes,bytes)`
+    pub fn calldata(&self) -> Vec<u8> {
+        let mut calldata = selector(VERIFY_PROOF_SIGNATURE).to_vec();
+        calldata.extend_from_slice(&self.program_vkey);
+        let public_values_offset = 3 * 32;
+        let proof_offset = public_values_offset + 32 + padded_len(self.public_values.len());
+        calldata.extend_from_slice(&abi_word(public_values_offset as u64));
+        calldata.extend_from_slice(&abi_word(proof_offset as u64));
+        encode_bytes(&mut calldata, &self.public_values);
+        encode_bytes(&mut calldata, &self.proof);
+        calldata
+    }
+
+    /// Hex calldata with a `0x` prefix, as accepted by `cast` and JSON-RPC
+    pub fn calldata_hex(&self) -> String {
+        format!("0x{}", hex::encode(self.calldata()))
+    }
+}
+
+/// Solidity source of a contract that verifies proofs of one program
+/// through the SP1 verifier gateway, with the program vkey embedded
+pub fn verifier_contract(contract_name: &str, program_vkey: &str) -> Result<String, ProverError> {
+    if contract_name.is_empty()
+        || contract_name.starts_with(|c: char| c.is_ascii_digit())
+        || !contract_name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return Err(ProverError::Other(format!(
+            "'{}' is not a valid Solidity identifier",
+            contract_name
+        )));
+    }
+    let vkey = hex::encode(parse_vkey(program_vkey)?);
+    Ok(format!(
+        r#"// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.20;
+
+import {{ISP1Verifier}} from "@sp1-contracts/ISP1Verifier.sol";
+
+/// Verifies proofs of a single SP1 program. Generated by frostgate-prover.
+contract {name} {{
+    /// SP1 verifier gateway
+    address public immutable verifier;
+
+    /// Verifying key of the program this contract accepts proofs for
+    bytes32 public constant PROGRAM_VKEY = 0x{vkey};
+
+    constructor(address _verifier) {{
+        verifier = _verifier;
+    }}
+
+    /// Reverts if the proof is invalid, otherwise returns the public values
+    function verify(bytes calldata publicValues, bytes calldata proofBytes)
+        external
+        view
+        returns (bytes calldata)
+    {{
+        ISP1Verifier(verifier).verifyProof(PROGRAM_VKEY, publicValues, proofBytes);
+        return publicValues;
+    }}
+}}
+"#,
+        name = contract_name,
+        vkey = vkey,
+    ))
+}
+
+fn parse_vkey(vkey: &str) -> Result<[u8; 32], ProverError> {
+    let bytes = hex::decode(vkey.trim_start_matches("0x"))
+        .map_err(|e| ProverError::Other(format!("invalid program vkey: {}", e)))?;
+    bytes
+        .try_into()
+        .map_err(|bytes: Vec<u8>| ProverError::Other(format!("program vkey is {} bytes, expected 32", bytes.len())))
+}
+
+fn selector(signature: &str) -> [u8; 4] {
+    let hash = Keccak256::digest(signature.as_bytes());
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+fn abi_word(value: u64) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+fn padded_len(len: usize) -> usize {
+    len.div_ceil(32) * 32
+}
+
+fn encode_bytes(out: &mut Vec<u8>, data: &[u8]) {
+    out.extend_from_slice(&abi_word(data.len() as u64));
+    out.extend_from_slice(data);
+    out.resize(out.len() + padded_len(data.len()) - data.len(), 0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn groth16_envelope() -> ProofEnvelope {
+        let mut envelope = ProofEnvelope::new("sp1", "hash".to_string(), vec![0xab; 40], vec![1, 2, 3]);
+        envelope.insert_metadata(PROOF_MODE_KEY, ProofMode::Groth16.name());
+        envelope
+    }
+
+    #[test]
+    fn test_calldata_layout() {
+        let vkey = format!("0x{}", "11".repeat(32));
+        let proof = OnchainProof::from_envelope(&groth16_envelope(), &vkey).unwrap();
+        let calldata = proof.calldata();
+
+        assert_eq!(&calldata[..4], &selector(VERIFY_PROOF_SIGNATURE));
+        assert_eq!(&calldata[4..36], &[0x11; 32]);
+        // selector + 3 head words + (len + 1 word) + (len + 2 words)
+        assert_eq!(calldata.len(), 4 + 3 * 32 + 2 * 32 + 3 * 32);
+        assert_eq!(calldata[4 + 32 + 31], 0x60);
+        assert_eq!(calldata[4 + 64 + 31], 0xa0);
+    }
+
+    #[test]
+    fn test_rejects_core_proofs() {
+        let mut envelope = groth16_envelope();
+        envelope.insert_metadata(PROOF_MODE_KEY, ProofMode::Compressed.name());
+        assert!(OnchainProof::from_envelope(&envelope, &"11".repeat(32)).is_err());
+    }
+
+    #[test]
+    fn test_contract_embeds_vkey() {
+        let source = verifier_contract("BridgeVerifier", &"ab".repeat(32)).unwrap();
+        assert!(source.contains(&format!("0x{}", "ab".repeat(32))));
+        assert!(verifier_contract("1bad", &"ab".repeat(32)).is_err());
+    }
+}
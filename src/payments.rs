@@ -0,0 +1,250 @@
+use crate::accounting::{Labels, SpendLedger, UNLABELED};
+use crate::metrics::ProverMetrics;
+use crate::types::{ProgramHash, ProverError, program_hash};
+use frostgate_zkip::{ZkBackend, ZkError};
+use std::sync::{Arc, Mutex, PoisonError};
+use uuid::Uuid;
+
+/// Prices a submission in the market's smallest unit
+pub type PricingFn = Arc<dyn Fn(&[u8], &[u8]) -> u64 + Send + Sync>;
+
+/// A submission about to be sent to a paid proving market
+#[derive(Debug, Clone)]
+pub struct SubmissionQuote {
+    pub request_id: Uuid,
+    pub program_hash: ProgramHash,
+    pub max_cost: u64,
+    pub labels: Labels,
+}
+
+/// The outcome of a paid submission
+#[derive(Debug, Clone)]
+pub struct Settlement {
+    pub request_id: Uuid,
+    pub program_hash: ProgramHash,
+    pub cost: u64,
+    pub fulfilled: bool,
+    pub labels: Labels,
+}
+
+/// Hook invoked around submissions to a paid proving market
+pub trait PaymentHook: Send + Sync {
+    /// Check the budget and escrow funds; an error blocks the submission
+    fn before_submit(&self, quote: &SubmissionQuote) -> Result<(), ProverError>;
+
+    /// Record settlement, or release escrow if the request was not fulfilled
+    fn after_fulfillment(&self, settlement: &Settlement) -> Result<(), ProverError>;
+}
+
+/// Enforces a fixed spend limit, escrowing each quote until it settles
+pub struct SpendLimit {
+    limit: u64,
+    state: Mutex<SpendState>,
+}
+
+#[derive(Default)]
+struct SpendState {
+    escrowed: u64,
+    spent: u64,
+}
+
+impl SpendLimit {
+    pub fn new(limit: u64) -> Self {
+        Self {
+            limit,
+            state: Mutex::new(SpendState::default()),
+        }
+    }
+
+    /// Amount settled so far
+    pub fn spent(&self) -> u64 {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner).spent
+    }
+
+    /// Amount neither spent nor held in escrow
+    pub fn remaining(&self) -> u64 {
+        let state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        self.limit.saturating_sub(state.spent + state.escrowed)
+    }
+}
+
+impl PaymentHook for SpendLimit {
+    fn before_submit(&self, quote: &SubmissionQuote) -> Result<(), ProverError> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let remaining = self.limit.saturating_sub(state.spent + state.escrowed);
+        if quote.max_cost > remaining {
+            return Err(ProverError::BudgetExceeded {
+                requested: quote.max_cost,
+                remaining,
+            });
+        }
+        state.escrowed += quote.max_cost;
+        Ok(())
+    }
+
+    fn after_fulfillment(&self, settlement: &Settlement) -> Result<(), ProverError> {
+        // Settlements always carry the quoted cost, so the escrow is released in full
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.escrowed = state.escrowed.saturating_sub(settlement.cost);
+        if settlement.fulfilled {
+            state.spent += settlement.cost;
+        }
+        Ok(())
+    }
+}
+
+/// Backend wrapper that runs payment hooks around every proof
+pub struct PaidBackend {
+    inner: Arc<dyn ZkBackend>,
+    hook: Arc<dyn PaymentHook>,
+    pricing: PricingFn,
+    ledger: Option<Arc<SpendLedger>>,
+    metrics: Option<(Arc<ProverMetrics>, Vec<String>)>,
+}
+
+impl PaidBackend {
+    pub fn new(inner: Arc<dyn ZkBackend>, hook: Arc<dyn PaymentHook>, pricing: PricingFn) -> Self {
+        Self {
+            inner,
+            hook,
+            pricing,
+            ledger: None,
+            metrics: None,
+        }
+    }
+
+    /// Record every settlement in `ledger`
+    pub fn with_ledger(mut self, ledger: Arc<SpendLedger>) -> Self {
+        self.ledger = Some(ledger);
+        self
+    }
+
+    /// Count fulfilled spend in `metrics` by the value of each label in
+    /// `keys`. Keep the keys to low-cardinality labels such as team or
+    /// environment; every value seen becomes a time series.
+    pub fn with_metrics(mut self, metrics: Arc<ProverMetrics>, keys: Vec<String>) -> Self {
+        self.metrics = Some((metrics, keys));
+        self
+    }
+
+    /// Prove with labels that are passed to the hook and recorded in the ledger
+    pub fn prove_labeled(&self, program: &[u8], input: &[u8], labels: Labels) -> Result<Vec<u8>, ZkError> {
+        let quote = SubmissionQuote {
+            request_id: Uuid::new_v4(),
+            program_hash: program_hash(program),
+            max_cost: (self.pricing)(program, input),
+            labels,
+        };
+        self.hook.before_submit(&quote).map_err(ProverError::into_zk_error)?;
+
+        let result = self.inner.prove(program, input);
+        let settlement = Settlement {
+            request_id: quote.request_id,
+            program_hash: quote.program_hash,
+            cost: quote.max_cost,
+            fulfilled: result.is_ok(),
+            labels: quote.labels,
+        };
+        if let Err(e) = self.hook.after_fulfillment(&settlement) {
+            tracing::error!("failed to record settlement {}: {:?}", settlement.request_id, e);
+        }
+        if let Some(ledger) = &self.ledger {
+            ledger.record(&settlement);
+        }
+        if let Some((metrics, keys)) = &self.metrics
+            && settlement.fulfilled
+        {
+            for key in keys {
+                let value = settlement.labels.get(key).map_or(UNLABELED, String::as_str);
+                metrics.observe_network_spend(key, value, settlement.cost);
+            }
+        }
+        result
+    }
+}
+
+impl ZkBackend for PaidBackend {
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        self.prove_labeled(program, input, Labels::new())
+    }
+
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        self.inner.verify(program, proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockBackend;
+
+    fn quote(cost: u64) -> SubmissionQuote {
+        SubmissionQuote {
+            request_id: Uuid::new_v4(),
+            program_hash: "hash".to_string(),
+            max_cost: cost,
+            labels: Labels::new(),
+        }
+    }
+
+    fn settle(quote: &SubmissionQuote, fulfilled: bool) -> Settlement {
+        Settlement {
+            request_id: quote.request_id,
+            program_hash: quote.program_hash.clone(),
+            cost: quote.max_cost,
+            fulfilled,
+            labels: quote.labels.clone(),
+        }
+    }
+
+    #[test]
+    fn test_spend_limit() {
+        let limit = SpendLimit::new(100);
+
+        let first = quote(60);
+        limit.before_submit(&first).unwrap();
+        assert_eq!(limit.remaining(), 40);
+
+        // Escrowed funds can't be double-spent
+        assert!(matches!(
+            limit.before_submit(&quote(50)),
+            Err(ProverError::BudgetExceeded { requested: 50, remaining: 40 })
+        ));
+
+        // A failed request releases its escrow
+        limit.after_fulfillment(&settle(&first, false)).unwrap();
+        assert_eq!(limit.remaining(), 100);
+        assert_eq!(limit.spent(), 0);
+
+        let second = quote(70);
+        limit.before_submit(&second).unwrap();
+        limit.after_fulfillment(&settle(&second, true)).unwrap();
+        assert_eq!(limit.spent(), 70);
+        assert_eq!(limit.remaining(), 30);
+    }
+
+    #[test]
+    fn test_labels_reach_ledger_and_metrics() {
+        let mock = Arc::new(MockBackend::default());
+        mock.inject_failure(ZkError::Config("network unavailable".to_string()));
+        let ledger = Arc::new(SpendLedger::new());
+        let metrics = Arc::new(ProverMetrics::new().unwrap());
+        let backend = PaidBackend::new(mock, Arc::new(SpendLimit::new(1_000)), Arc::new(|_, input| input.len() as u64))
+            .with_ledger(ledger.clone())
+            .with_metrics(metrics.clone(), vec!["team".to_string()]);
+        let labels = |team: &str| Labels::from([("team".to_string(), team.to_string())]);
+
+        // The failed request is recorded but not counted as spend
+        assert!(backend.prove_labeled(b"elf", b"123", labels("bridge")).is_err());
+        backend.prove_labeled(b"elf", b"1234", labels("bridge")).unwrap();
+        backend.prove_labeled(b"elf", b"12345", labels("oracle")).unwrap();
+        backend.prove(b"elf", b"12").unwrap();
+
+        assert_eq!(ledger.entries().len(), 4);
+        assert_eq!(ledger.total(), 11);
+        let text = metrics.gather().unwrap();
+        assert!(text.contains(r#"frostgate_network_spend_total{label="team",value="bridge"} 4"#));
+        assert!(text.contains(r#"frostgate_network_spend_total{label="team",value="oracle"} 5"#));
+        assert!(text.contains(r#"frostgate_network_spend_total{label="team",value="unlabeled"} 2"#));
+    }
+}
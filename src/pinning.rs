@@ -0,0 +1,128 @@
+use crate::types::{ProgramHash, ProverError, ct_eq, program_hash};
+use frostgate_zkip::{ZkBackend, ZkError};
+use std::collections::HashMap;
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// How verification treats programs without a pinned vkey
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VerificationMode {
+    /// Verify against whatever key the backend resolves
+    #[default]
+    Permissive,
+    /// Refuse to verify unless the vkey is pinned locally
+    Strict,
+}
+
+/// Locally pinned verifying-key hashes, keyed by program hash
+pub struct VkeyPins {
+    mode: VerificationMode,
+    pins: RwLock<HashMap<ProgramHash, String>>,
+}
+
+impl VkeyPins {
+    pub fn new(mode: VerificationMode) -> Self {
+        Self {
+            mode,
+            pins: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn mode(&self) -> VerificationMode {
+        self.mode
+    }
+
+    /// Pin the vkey hash a program must verify against
+    pub fn pin(&self, program_hash: ProgramHash, vkey_hash: String) {
+        self.pins.write().unwrap_or_else(PoisonError::into_inner).insert(program_hash, vkey_hash);
+    }
+
+    /// Remove a pin
+    pub fn unpin(&self, program_hash: &str) -> Option<String> {
+        self.pins.write().unwrap_or_else(PoisonError::into_inner).remove(program_hash)
+    }
+
+    /// Pinned vkey hash for a program
+    pub fn pinned(&self, program_hash: &str) -> Option<String> {
+        self.pins.read().unwrap_or_else(PoisonError::into_inner).get(program_hash).cloned()
+    }
+
+    /// All pins, keyed by program hash
+    pub fn all(&self) -> HashMap<ProgramHash, String> {
+        self.pins.read().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /// Check that an envelope's vkey hash is the one pinned for its program.
+    /// In permissive mode unpinned programs are accepted.
+    pub fn check(&self, program_hash: &str, vkey_hash: &str) -> Result<(), ProverError> {
+        match self.pinned(program_hash) {
+            Some(pinned) if ct_eq(pinned.as_bytes(), vkey_hash.as_bytes()) => Ok(()),
+            None if self.mode == VerificationMode::Permissive => Ok(()),
+            expected => Err(ProverError::VkeyMismatch {
+                program_hash: program_hash.to_string(),
+                expected,
+                found: vkey_hash.to_string(),
+            }),
+        }
+    }
+}
+
+/// Derives the vkey hash for a program, e.g. from the backend's key cache
+pub type VkeyResolver = Arc<dyn Fn(&[u8]) -> Result<String, ZkError> + Send + Sync>;
+
+/// Backend wrapper that only verifies against pinned vkeys
+pub struct PinnedBackend {
+    inner: Arc<dyn ZkBackend>,
+    pins: Arc<VkeyPins>,
+    resolve_vkey: VkeyResolver,
+}
+
+impl PinnedBackend {
+    pub fn new(inner: Arc<dyn ZkBackend>, pins: Arc<VkeyPins>, resolve_vkey: VkeyResolver) -> Self {
+        Self {
+            inner,
+            pins,
+            resolve_vkey,
+        }
+    }
+}
+
+impl ZkBackend for PinnedBackend {
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        self.inner.prove(program, input)
+    }
+
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        let vkey_hash = (self.resolve_vkey)(program)?;
+        self.pins
+            .check(&program_hash(program), &vkey_hash)
+            .map_err(ProverError::into_zk_error)?;
+        self.inner.verify(program, proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strict_mode_requires_pin() {
+        let pins = VkeyPins::new(VerificationMode::Strict);
+        assert!(matches!(
+            pins.check("program", "vk"),
+            Err(ProverError::VkeyMismatch { expected: None, .. })
+        ));
+
+        pins.pin("program".to_string(), "vk".to_string());
+        pins.check("program", "vk").unwrap();
+        assert!(pins.check("program", "attacker-vk").is_err());
+    }
+
+    #[test]
+    fn test_permissive_mode_still_enforces_pins() {
+        let pins = VkeyPins::new(VerificationMode::Permissive);
+        pins.check("program", "vk").unwrap();
+
+        pins.pin("program".to_string(), "vk".to_string());
+        assert!(pins.check("program", "attacker-vk").is_err());
+    }
+}
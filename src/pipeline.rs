@@ -0,0 +1,589 @@
+use crate::audit::{AuditLog, AuditOperation, AuditOutcome, now_ms};
+use crate::envelope::ProofEnvelope;
+use crate::estimation::CycleCounter;
+use crate::inspect::PROOF_MODE_KEY;
+use crate::progress::{ProgressSink, ProveProgress};
+use crate::receipts::RECEIPT_PREFIX;
+use crate::types::{ProverError, ct_eq, program_hash};
+use frostgate_zkip::{ZkBackend, ZkError};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Metadata key prefix for per-stage timings, in milliseconds
+pub const STAGE_TIMING_PREFIX: &str = "timing.";
+
+/// Form of a proof, from the raw core STARK to on-chain verifiable wrappers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProofMode {
+    /// Sharded core STARK, as produced by the backend
+    #[default]
+    Core,
+    /// Recursively compressed, constant-size STARK
+    Compressed,
+    /// Compressed STARK wrapped in a PlonkBn254 SNARK
+    Plonk,
+    /// Compressed STARK wrapped in a Groth16Bn254 SNARK
+    Groth16,
+}
+
+impl ProofMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProofMode::Core => "core",
+            ProofMode::Compressed => "compressed",
+            ProofMode::Plonk => "plonk",
+            ProofMode::Groth16 => "groth16",
+        }
+    }
+
+    /// Parse a mode name as produced by [`ProofMode::name`]
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "core" => Some(ProofMode::Core),
+            "compressed" => Some(ProofMode::Compressed),
+            "plonk" => Some(ProofMode::Plonk),
+            "groth16" => Some(ProofMode::Groth16),
+            _ => None,
+        }
+    }
+
+    /// Whether the proof can be verified by an EVM verifier contract
+    pub fn is_onchain_verifiable(&self) -> bool {
+        matches!(self, ProofMode::Plonk | ProofMode::Groth16)
+    }
+
+    /// Wrapping steps needed to turn a `self` proof into a `target` proof,
+    /// or `None` if `target` can't be reached from `self`
+    pub fn path_to(&self, target: ProofMode) -> Option<Vec<ProofMode>> {
+        use ProofMode::*;
+        match (self, target) {
+            (from, to) if *from == to => Some(Vec::new()),
+            (Core, Compressed) => Some(vec![Compressed]),
+            (Core, to @ (Plonk | Groth16)) => Some(vec![Compressed, to]),
+            (Compressed, to @ (Plonk | Groth16)) => Some(vec![to]),
+            _ => None,
+        }
+    }
+}
+
+/// Per-request proving options; unset fields fall back to the pipeline's
+/// defaults
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofConfig {
+    pub proof_mode: Option<ProofMode>,
+    /// Maximum cycles the guest may execute before the request is rejected
+    pub prover_gas_limit: Option<u64>,
+    /// Shard size, as a power of two, for backends that take one
+    pub shard_size: Option<u32>,
+}
+
+impl ProofConfig {
+    /// Parse `proof_mode`, `prover_gas` and `shard_size` from string options,
+    /// ignoring unrelated keys
+    pub fn from_options(options: &BTreeMap<String, String>) -> Result<Self, ProverError> {
+        let number = |key: &str| {
+            options
+                .get(key)
+                .map(|value| {
+                    value
+                        .parse::<u64>()
+                        .map_err(|e| ProverError::Other(format!("invalid {} {:?}: {}", key, value, e)))
+                })
+                .transpose()
+        };
+        let proof_mode = options
+            .get("proof_mode")
+            .map(|name| ProofMode::parse(name).ok_or_else(|| ProverError::Other(format!("unknown proof mode {:?}", name))))
+            .transpose()?;
+        let shard_size = number("shard_size")?
+            .map(|size| u32::try_from(size).map_err(|e| ProverError::Other(format!("invalid shard_size: {}", e))))
+            .transpose()?;
+        Ok(Self {
+            proof_mode,
+            prover_gas_limit: number("prover_gas")?,
+            shard_size,
+        })
+    }
+
+    /// Options asking for a `mode` proof, leaving the rest to the defaults
+    pub fn for_mode(mode: ProofMode) -> Self {
+        Self {
+            proof_mode: Some(mode),
+            ..Self::default()
+        }
+    }
+
+    /// Fill unset fields from `defaults`
+    pub fn or(&self, defaults: &ProofConfig) -> ProofConfig {
+        ProofConfig {
+            proof_mode: self.proof_mode.or(defaults.proof_mode),
+            prover_gas_limit: self.prover_gas_limit.or(defaults.prover_gas_limit),
+            shard_size: self.shard_size.or(defaults.shard_size),
+        }
+    }
+}
+
+/// Performs one wrapping step, e.g. compress a core proof or wrap a compressed
+/// proof into Groth16
+pub trait ProofWrapper: Send + Sync {
+    fn wrap(&self, program: &[u8], proof: &[u8], from: ProofMode, to: ProofMode) -> Result<Vec<u8>, ZkError>;
+}
+
+/// Result of running the pipeline
+#[derive(Debug, Clone)]
+pub struct PipelineOutput {
+    pub proof: Vec<u8>,
+    pub mode: ProofMode,
+    /// Time spent in each stage, in execution order
+    pub stage_timings: Vec<(ProofMode, Duration)>,
+    /// Options the proof was produced with
+    pub config: ProofConfig,
+}
+
+impl PipelineOutput {
+    /// Wrap in an envelope with the mode and stage timings as metadata
+    pub fn into_envelope(self, backend: &str, program: &[u8], public_values: Vec<u8>) -> ProofEnvelope {
+        let mut envelope = ProofEnvelope::new(backend, program_hash(program), self.proof, public_values);
+        envelope.insert_metadata(PROOF_MODE_KEY, self.mode.name());
+        if let Some(shard_size) = self.config.shard_size {
+            envelope.insert_metadata("shard_size", shard_size.to_string());
+        }
+        for (stage, duration) in self.stage_timings {
+            envelope.insert_metadata(
+                format!("{}{}_ms", STAGE_TIMING_PREFIX, stage.name()),
+                duration.as_millis().to_string(),
+            );
+        }
+        envelope
+    }
+}
+
+/// Core-proves a program on an input with shards of `2^shard_size` cycles
+pub type ShardSizedProver = Arc<dyn Fn(&[u8], &[u8], u32) -> Result<Vec<u8>, ZkError> + Send + Sync>;
+
+/// Produces a proof of the requested mode in one call: core proving on the
+/// backend, then each wrapping stage in turn
+pub struct WrappingPipeline {
+    backend: Arc<dyn ZkBackend>,
+    wrapper: Arc<dyn ProofWrapper>,
+    defaults: ProofConfig,
+    count_cycles: Option<CycleCounter>,
+    prove_sharded: Option<ShardSizedProver>,
+    audit: Option<Arc<AuditLog>>,
+}
+
+impl WrappingPipeline {
+    pub fn new(backend: Arc<dyn ZkBackend>, wrapper: Arc<dyn ProofWrapper>) -> Self {
+        Self {
+            backend,
+            wrapper,
+            defaults: ProofConfig::default(),
+            count_cycles: None,
+            prove_sharded: None,
+            audit: None,
+        }
+    }
+
+    /// Options used where a request leaves them unset
+    pub fn with_defaults(mut self, defaults: ProofConfig) -> Self {
+        self.defaults = defaults;
+        self
+    }
+
+    /// Execute requests before proving to enforce their gas limit
+    pub fn with_cycle_counter(mut self, count_cycles: CycleCounter) -> Self {
+        self.count_cycles = Some(count_cycles);
+        self
+    }
+
+    /// Core-prove requests that set a shard size with `prove_sharded`
+    /// instead of the backend. Without one, such requests are rejected
+    /// rather than proven with the backend's own shard size.
+    pub fn with_shard_sized_prover(mut self, prove_sharded: ShardSizedProver) -> Self {
+        self.prove_sharded = Some(prove_sharded);
+        self
+    }
+
+    /// Record every conversion in `log`
+    pub fn with_audit_log(mut self, log: Arc<AuditLog>) -> Self {
+        self.audit = Some(log);
+        self
+    }
+
+    /// Prove with per-request options, falling back to the pipeline defaults
+    pub fn prove_with_config(
+        &self,
+        program: &[u8],
+        input: &[u8],
+        config: &ProofConfig,
+    ) -> Result<PipelineOutput, ProverError> {
+        self.run(program, input, config, None)
+    }
+
+    /// Prove `program` on `input` and wrap the result up to `target`
+    pub fn prove(&self, program: &[u8], input: &[u8], target: ProofMode) -> Result<PipelineOutput, ProverError> {
+        self.run(program, input, &ProofConfig::for_mode(target), None)
+    }
+
+    /// Like [`WrappingPipeline::prove`], reporting each stage into `sink` as
+    /// it starts. With a cycle counter configured the guest is executed first
+    /// so the cycle count is reported before proving.
+    pub fn prove_with_progress(
+        &self,
+        program: &[u8],
+        input: &[u8],
+        target: ProofMode,
+        sink: &ProgressSink,
+    ) -> Result<PipelineOutput, ProverError> {
+        self.run(program, input, &ProofConfig::for_mode(target), Some(sink))
+    }
+
+    fn run(
+        &self,
+        program: &[u8],
+        input: &[u8],
+        config: &ProofConfig,
+        sink: Option<&ProgressSink>,
+    ) -> Result<PipelineOutput, ProverError> {
+        let config = config.or(&self.defaults);
+        let target = config.proof_mode.unwrap_or_default();
+        let span = tracing::info_span!(
+            "pipeline_prove",
+            program_hash = %program_hash(program),
+            proof_type = target.name(),
+        );
+        let _guard = span.enter();
+        if config.shard_size.is_some() && self.prove_sharded.is_none() {
+            return Err(ProverError::Other("this pipeline's backend doesn't take a shard size".to_string()));
+        }
+        if let Some(sink) = sink {
+            sink(ProveProgress::Started);
+        }
+        if let Some(count_cycles) = &self.count_cycles
+            && (sink.is_some() || config.prover_gas_limit.is_some())
+        {
+            let cycles = count_cycles(program, input)?;
+            if let Some(limit) = config.prover_gas_limit
+                && cycles > limit
+            {
+                return Err(ProverError::PolicyViolation(format!(
+                    "execution took {} cycles, gas limit is {}",
+                    cycles, limit
+                )));
+            }
+            if let Some(sink) = sink {
+                sink(ProveProgress::ExecutionDone { cycles });
+            }
+        }
+        let start = Instant::now();
+        let core = tracing::info_span!("stage", proof_type = ProofMode::Core.name()).in_scope(|| {
+            match (config.shard_size, &self.prove_sharded) {
+                (Some(shard_size), Some(prove_sharded)) => prove_sharded(program, input, shard_size),
+                _ => self.backend.prove(program, input),
+            }
+        })?;
+        let core_time = start.elapsed();
+        let mut output = self.wrap_stages(program, core, ProofMode::Core, target, sink)?;
+        output.stage_timings.insert(0, (ProofMode::Core, core_time));
+        output.config = ProofConfig {
+            proof_mode: Some(output.mode),
+            ..config
+        };
+        if let Some(sink) = sink {
+            sink(ProveProgress::Done);
+        }
+        Ok(output)
+    }
+
+    /// Wrap an existing `from` proof up to `target` without re-proving,
+    /// recording the conversion for `caller` in the audit log if one is set
+    pub fn convert(
+        &self,
+        caller: &str,
+        program: &[u8],
+        proof: Vec<u8>,
+        from: ProofMode,
+        target: ProofMode,
+    ) -> Result<PipelineOutput, ProverError> {
+        let input = self.audit.as_ref().map(|_| proof.clone()).unwrap_or_default();
+        self.audited(caller, program, &input, || self.wrap_stages(program, proof, from, target, None))
+    }
+
+    /// Wrap the proof held in `envelope` up to `target`, e.g. to settle a
+    /// stored core proof on-chain without running the guest again. The result
+    /// keeps the envelope's backend, public values and metadata; receipts are
+    /// dropped since they cover the old payload. Envelopes without a proof
+    /// mode are taken to hold core proofs.
+    pub fn convert_envelope(
+        &self,
+        caller: &str,
+        program: &[u8],
+        envelope: &ProofEnvelope,
+        target: ProofMode,
+    ) -> Result<ProofEnvelope, ProverError> {
+        self.audited(caller, program, &envelope.payload, || {
+            self.convert_envelope_unaudited(program, envelope, target)
+        })
+    }
+
+    fn convert_envelope_unaudited(
+        &self,
+        program: &[u8],
+        envelope: &ProofEnvelope,
+        target: ProofMode,
+    ) -> Result<ProofEnvelope, ProverError> {
+        if !envelope.checksum_valid() {
+            return Err(ProverError::MalformedEnvelope("checksum mismatch".to_string()));
+        }
+        let hash = program_hash(program);
+        if !ct_eq(envelope.program_hash.as_bytes(), hash.as_bytes()) {
+            return Err(ProverError::Other(format!(
+                "proof is for program {}, not {}",
+                envelope.program_hash, hash
+            )));
+        }
+        let from = match envelope.metadata.get(PROOF_MODE_KEY) {
+            Some(name) => {
+                ProofMode::parse(name).ok_or_else(|| ProverError::Other(format!("unknown proof mode {:?}", name)))?
+            }
+            None => ProofMode::Core,
+        };
+
+        let output = self.wrap_stages(program, envelope.payload.clone(), from, target, None)?;
+        let mut converted = ProofEnvelope::new(&envelope.backend, hash, output.proof, envelope.public_values.clone());
+        converted.metadata = envelope
+            .metadata
+            .iter()
+            .filter(|(key, _)| !key.starts_with(RECEIPT_PREFIX))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        converted.insert_metadata(PROOF_MODE_KEY, output.mode.name());
+        for (stage, duration) in output.stage_timings {
+            converted.insert_metadata(
+                format!("{}{}_ms", STAGE_TIMING_PREFIX, stage.name()),
+                duration.as_millis().to_string(),
+            );
+        }
+        Ok(converted)
+    }
+
+    fn audited<T>(
+        &self,
+        caller: &str,
+        program: &[u8],
+        proof: &[u8],
+        convert: impl FnOnce() -> Result<T, ProverError>,
+    ) -> Result<T, ProverError> {
+        let Some(log) = &self.audit else {
+            return convert();
+        };
+        let started_at_ms = now_ms();
+        let result = convert();
+        let outcome = match &result {
+            Ok(_) => AuditOutcome::Success,
+            Err(e) => AuditOutcome::Failure(format!("{:?}", e)),
+        };
+        log.record(AuditOperation::Convert, caller, program, proof, started_at_ms, outcome);
+        result
+    }
+
+    fn wrap_stages(
+        &self,
+        program: &[u8],
+        proof: Vec<u8>,
+        from: ProofMode,
+        target: ProofMode,
+        sink: Option<&ProgressSink>,
+    ) -> Result<PipelineOutput, ProverError> {
+        let path = from.path_to(target).ok_or_else(|| {
+            ProverError::Other(format!(
+                "cannot convert a {} proof to {}",
+                from.name(),
+                target.name()
+            ))
+        })?;
+
+        let mut output = PipelineOutput {
+            proof,
+            mode: from,
+            stage_timings: Vec::with_capacity(path.len()),
+            config: ProofConfig {
+                proof_mode: Some(target),
+                ..ProofConfig::default()
+            },
+        };
+        for stage in path {
+            let _stage = tracing::info_span!("stage", proof_type = stage.name()).entered();
+            if let Some(sink) = sink {
+                sink(match stage {
+                    ProofMode::Compressed => ProveProgress::Compressing,
+                    mode => ProveProgress::Wrapping { mode },
+                });
+            }
+            let start = Instant::now();
+            output.proof = self.wrapper.wrap(program, &output.proof, output.mode, stage)?;
+            output.stage_timings.push((stage, start.elapsed()));
+            tracing::debug!(
+                "wrapped {} proof into {} in {:?}",
+                output.mode.name(),
+                stage.name(),
+                start.elapsed()
+            );
+            output.mode = stage;
+        }
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockBackend;
+
+    struct TaggingWrapper;
+
+    impl ProofWrapper for TaggingWrapper {
+        fn wrap(&self, _program: &[u8], proof: &[u8], _from: ProofMode, to: ProofMode) -> Result<Vec<u8>, ZkError> {
+            Ok([proof, to.name().as_bytes()].concat())
+        }
+    }
+
+    #[test]
+    fn test_pipeline_stages() {
+        let pipeline = WrappingPipeline::new(Arc::new(MockBackend::default()), Arc::new(TaggingWrapper));
+        let output = pipeline.prove(b"elf", b"input", ProofMode::Groth16).unwrap();
+
+        assert_eq!(output.mode, ProofMode::Groth16);
+        assert!(output.proof.ends_with(b"compressedgroth16"));
+        let stages: Vec<_> = output.stage_timings.iter().map(|(stage, _)| *stage).collect();
+        assert_eq!(stages, vec![ProofMode::Core, ProofMode::Compressed, ProofMode::Groth16]);
+
+        let envelope = output.into_envelope("mock", b"elf", Vec::new());
+        assert_eq!(envelope.metadata.get(PROOF_MODE_KEY).map(String::as_str), Some("groth16"));
+        assert!(envelope.metadata.contains_key("timing.compressed_ms"));
+    }
+
+    #[test]
+    fn test_request_config_overrides_defaults() {
+        let counter: CycleCounter = Arc::new(|_, input| Ok(input.len() as u64));
+        let pipeline = WrappingPipeline::new(Arc::new(MockBackend::default()), Arc::new(TaggingWrapper))
+            .with_defaults(ProofConfig {
+                proof_mode: Some(ProofMode::Compressed),
+                prover_gas_limit: Some(8),
+                shard_size: Some(22),
+            })
+            .with_cycle_counter(counter)
+            .with_shard_sized_prover(Arc::new(|program, input, _| MockBackend::default().prove(program, input)));
+
+        let output = pipeline
+            .prove_with_config(b"elf", b"input", &ProofConfig::default())
+            .unwrap();
+        assert_eq!(output.mode, ProofMode::Compressed);
+
+        let options = BTreeMap::from([("proof_mode".to_string(), "plonk".to_string())]);
+        let output = pipeline
+            .prove_with_config(b"elf", b"input", &ProofConfig::from_options(&options).unwrap())
+            .unwrap();
+        assert_eq!(output.mode, ProofMode::Plonk);
+        assert_eq!(output.config.shard_size, Some(22));
+
+        assert!(matches!(
+            pipeline.prove_with_config(b"elf", b"too much input", &ProofConfig::default()),
+            Err(ProverError::PolicyViolation(_))
+        ));
+    }
+
+    #[test]
+    fn test_shard_size_reaches_prover() {
+        let pipeline = WrappingPipeline::new(Arc::new(MockBackend::default()), Arc::new(TaggingWrapper));
+        let sharded = ProofConfig {
+            shard_size: Some(20),
+            ..ProofConfig::default()
+        };
+        assert!(pipeline.prove_with_config(b"elf", b"input", &sharded).is_err());
+
+        let pipeline = pipeline.with_shard_sized_prover(Arc::new(|_, _, shard_size| Ok(vec![shard_size as u8])));
+        let output = pipeline.prove_with_config(b"elf", b"input", &sharded).unwrap();
+        assert_eq!(output.proof, [20]);
+        assert_eq!(output.config.shard_size, Some(20));
+        assert_ne!(pipeline.prove(b"elf", b"input", ProofMode::Core).unwrap().proof, [20]);
+    }
+
+    #[test]
+    fn test_progress() {
+        let counter: CycleCounter = Arc::new(|_, input| Ok(input.len() as u64));
+        let pipeline = WrappingPipeline::new(Arc::new(MockBackend::default()), Arc::new(TaggingWrapper))
+            .with_cycle_counter(counter);
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = events.clone();
+        let sink: ProgressSink = Arc::new(move |progress| recorded.lock().unwrap().push(progress));
+
+        pipeline
+            .prove_with_progress(b"elf", b"input", ProofMode::Plonk, &sink)
+            .unwrap();
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                ProveProgress::Started,
+                ProveProgress::ExecutionDone { cycles: 5 },
+                ProveProgress::Compressing,
+                ProveProgress::Wrapping { mode: ProofMode::Plonk },
+                ProveProgress::Done,
+            ]
+        );
+
+        let limited = WrappingPipeline::new(Arc::new(MockBackend::default()), Arc::new(TaggingWrapper))
+            .with_defaults(ProofConfig {
+                prover_gas_limit: Some(4),
+                ..ProofConfig::default()
+            })
+            .with_cycle_counter(Arc::new(|_, input| Ok(input.len() as u64)));
+        assert!(matches!(
+            limited.prove_with_progress(b"elf", b"input", ProofMode::Core, &sink),
+            Err(ProverError::PolicyViolation(_))
+        ));
+    }
+
+    #[test]
+    fn test_convert_envelope() {
+        let log = Arc::new(AuditLog::in_memory());
+        let pipeline = WrappingPipeline::new(Arc::new(MockBackend::default()), Arc::new(TaggingWrapper))
+            .with_audit_log(log.clone());
+        let core = pipeline
+            .prove(b"elf", b"input", ProofMode::Core)
+            .unwrap()
+            .into_envelope("mock", b"elf", b"values".to_vec());
+        let mut stored = core.clone();
+        stored.insert_metadata(format!("{}key", RECEIPT_PREFIX), "sig");
+
+        let groth16 = pipeline.convert_envelope("relayer", b"elf", &stored, ProofMode::Groth16).unwrap();
+        assert!(groth16.checksum_valid());
+        assert_eq!(groth16.payload, [&core.payload[..], b"compressedgroth16"].concat());
+        assert_eq!(groth16.public_values, b"values");
+        assert_eq!(groth16.metadata.get(PROOF_MODE_KEY).map(String::as_str), Some("groth16"));
+        assert!(groth16.metadata.contains_key("timing.core_ms"));
+        assert!(groth16.metadata.contains_key("timing.groth16_ms"));
+        assert!(!groth16.metadata.keys().any(|key| key.starts_with(RECEIPT_PREFIX)));
+
+        assert!(pipeline.convert_envelope("relayer", b"elf", &groth16, ProofMode::Plonk).is_err());
+        assert!(pipeline.convert_envelope("relayer", b"other", &core, ProofMode::Plonk).is_err());
+
+        let entries = log.entries().unwrap();
+        let outcomes: Vec<_> = entries.iter().map(|e| e.record.outcome == AuditOutcome::Success).collect();
+        assert_eq!(outcomes, [true, false, false]);
+        assert!(entries.iter().all(|e| e.record.operation == AuditOperation::Convert));
+        assert_eq!(entries[0].record.caller, "relayer");
+        assert_eq!(entries[0].record.input_digest, crate::audit::input_digest(&stored.payload));
+        log.verify().unwrap();
+    }
+
+    #[test]
+    fn test_paths() {
+        assert_eq!(ProofMode::Compressed.path_to(ProofMode::Plonk), Some(vec![ProofMode::Plonk]));
+        assert_eq!(ProofMode::Groth16.path_to(ProofMode::Plonk), None);
+        assert_eq!(ProofMode::Plonk.path_to(ProofMode::Core), None);
+    }
+}
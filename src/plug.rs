@@ -0,0 +1,139 @@
+use crate::types::ProverError;
+use async_trait::async_trait;
+use frostgate_zkip::types::ZkConfig;
+use frostgate_zkip::zkplug::ZkPlug;
+use frostgate_zkip::{ZkBackend, ZkError};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::runtime::Handle;
+
+/// A [`ZkPlug`] seen as a [`ZkBackend`], so it can be put in the registry.
+/// The program is handed to the plug as its input and the backend input as
+/// its public inputs; typed proofs travel as bincode. Plugs verify proofs
+/// alone, so only plugs whose proofs commit to their program can tell a
+/// proof of another program apart.
+///
+/// The plug's futures are driven on `runtime`, blocking the calling thread,
+/// so call from blocking threads (e.g. inside `spawn_blocking`, as the job
+/// manager does), never from one of the runtime's own workers.
+pub struct PlugAsBackend<P> {
+    plug: Arc<P>,
+    runtime: Handle,
+}
+
+impl<P> PlugAsBackend<P> {
+    pub fn new(plug: Arc<P>, runtime: Handle) -> Self {
+        Self { plug, runtime }
+    }
+}
+
+impl<P> ZkBackend for PlugAsBackend<P>
+where
+    P: ZkPlug,
+    P::Proof: Serialize + DeserializeOwned,
+{
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        let proof = self
+            .runtime
+            .block_on(self.plug.prove(program, Some(input), None))
+            .map_err(|e| ZkError::Config(format!("plug prove failed: {}", e)))?;
+        bincode::serialize(&proof).map_err(|e| ZkError::Config(format!("encoding plug proof: {}", e)))
+    }
+
+    /// Bytes that don't decode as the plug's proof type are rejected rather
+    /// than reported as errors
+    fn verify(&self, _program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        let Ok(proof) = bincode::deserialize::<P::Proof>(proof) else {
+            return Ok(false);
+        };
+        self.runtime
+            .block_on(self.plug.verify(&proof, None, None))
+            .map_err(|e| ZkError::Config(format!("plug verify failed: {}", e)))
+    }
+}
+
+/// Proof made by a [`BackendAsPlug`]: the backend's proof bytes and the
+/// program they prove, which the backend needs to verify them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackendProof {
+    pub program: Vec<u8>,
+    pub proof: Vec<u8>,
+}
+
+/// A [`ZkBackend`] seen as a [`ZkPlug`], for callers written against plugs.
+/// The plug's input is the program and its public inputs the backend input.
+/// Backend calls run on the blocking pool, so the plug must be used within
+/// a Tokio runtime.
+pub struct BackendAsPlug<B: ?Sized> {
+    backend: Arc<B>,
+}
+
+impl<B: ?Sized> BackendAsPlug<B> {
+    pub fn new(backend: Arc<B>) -> Self {
+        Self { backend }
+    }
+}
+
+#[async_trait]
+impl<B: ZkBackend + ?Sized + 'static> ZkPlug for BackendAsPlug<B> {
+    type Proof = BackendProof;
+    type Error = ProverError;
+
+    async fn prove(
+        &self,
+        input: &[u8],
+        public_inputs: Option<&[u8]>,
+        _config: Option<&ZkConfig>,
+    ) -> Result<BackendProof, ProverError> {
+        let backend = self.backend.clone();
+        let program = input.to_vec();
+        let stdin = public_inputs.unwrap_or_default().to_vec();
+        tokio::task::spawn_blocking(move || {
+            let proof = backend.prove(&program, &stdin)?;
+            Ok(BackendProof { program, proof })
+        })
+        .await
+        .map_err(|e| ProverError::Other(format!("prove task failed: {}", e)))?
+    }
+
+    async fn verify(
+        &self,
+        proof: &BackendProof,
+        _public_inputs: Option<&[u8]>,
+        _config: Option<&ZkConfig>,
+    ) -> Result<bool, ProverError> {
+        let backend = self.backend.clone();
+        let proof = proof.clone();
+        tokio::task::spawn_blocking(move || Ok(backend.verify(&proof.program, &proof.proof)?))
+            .await
+            .map_err(|e| ProverError::Other(format!("verify task failed: {}", e)))?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockBackend;
+
+    #[test]
+    fn test_round_trip() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let mock: Arc<dyn ZkBackend> = Arc::new(MockBackend::default());
+        let plug = Arc::new(BackendAsPlug::new(mock.clone()));
+
+        let typed = runtime.block_on(plug.prove(b"elf", Some(b"input"), None)).unwrap();
+        assert_eq!(typed.proof, mock.prove(b"elf", b"input").unwrap());
+        assert!(runtime.block_on(plug.verify(&typed, None, None)).unwrap());
+
+        let backend = PlugAsBackend::new(plug, runtime.handle().clone());
+        let proof = backend.prove(b"elf", b"input").unwrap();
+        assert_eq!(bincode::deserialize::<BackendProof>(&proof).unwrap(), typed);
+        assert!(backend.verify(b"elf", &proof).unwrap());
+        assert!(!backend.verify(b"elf", b"garbage").unwrap());
+
+        let mut forged = typed;
+        forged.proof[0] ^= 1;
+        assert!(!backend.verify(b"elf", &bincode::serialize(&forged).unwrap()).unwrap());
+    }
+}
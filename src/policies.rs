@@ -0,0 +1,213 @@
+use crate::estimation::CycleCounter;
+use crate::types::{ProgramHash, ProveRequest, ProverError, program_hash};
+use frostgate_zkip::{ZkBackend, ZkError};
+use std::collections::HashMap;
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// Expected layout of a program's public inputs, as byte widths of each field
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicInputSchema {
+    pub fields: Vec<usize>,
+}
+
+impl PublicInputSchema {
+    pub fn new(fields: Vec<usize>) -> Self {
+        Self { fields }
+    }
+
+    /// Total number of bytes the schema describes
+    pub fn len(&self) -> usize {
+        self.fields.iter().sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Limits enforced on inputs submitted for a program
+#[derive(Debug, Clone, Default)]
+pub struct InputPolicy {
+    pub max_stdin_bytes: Option<usize>,
+    pub max_cycles: Option<u64>,
+    pub public_inputs: Option<PublicInputSchema>,
+}
+
+/// Input policies attached to registered programs
+#[derive(Default)]
+pub struct InputPolicies {
+    policies: RwLock<HashMap<ProgramHash, InputPolicy>>,
+}
+
+impl InputPolicies {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Attach a policy to a program, replacing any previous one
+    pub fn set(&self, hash: ProgramHash, policy: InputPolicy) {
+        self.policies.write().unwrap_or_else(PoisonError::into_inner).insert(hash, policy);
+    }
+
+    /// Get the policy attached to a program
+    pub fn get(&self, hash: &str) -> Option<InputPolicy> {
+        self.policies.read().unwrap_or_else(PoisonError::into_inner).get(hash).cloned()
+    }
+
+    /// Remove a program's policy
+    pub fn remove(&self, hash: &str) -> Option<InputPolicy> {
+        self.policies.write().unwrap_or_else(PoisonError::into_inner).remove(hash)
+    }
+
+    /// Check a submission against the program's policy.
+    /// Programs without a policy accept any input.
+    pub fn check(&self, hash: &str, stdin: &[u8], public_inputs: &[u8]) -> Result<(), ProverError> {
+        self.check_stdin(hash, stdin)?;
+        self.check_public_inputs(hash, public_inputs)
+    }
+
+    /// Check both the stdin and the public inputs of a request
+    pub fn check_request(&self, request: &ProveRequest) -> Result<(), ProverError> {
+        self.check(&request.program_hash(), &request.stdin_inputs, &request.public_inputs)
+    }
+
+    /// Check the size of a submission's stdin against the program's policy
+    pub fn check_stdin(&self, hash: &str, stdin: &[u8]) -> Result<(), ProverError> {
+        if let Some(max) = self.get(hash).and_then(|p| p.max_stdin_bytes)
+            && stdin.len() > max
+        {
+            return Err(ProverError::PolicyViolation(format!(
+                "stdin is {} bytes, program {} allows at most {}",
+                stdin.len(),
+                hash,
+                max
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check public inputs against the program's schema
+    pub fn check_public_inputs(&self, hash: &str, public_inputs: &[u8]) -> Result<(), ProverError> {
+        if let Some(schema) = self.get(hash).and_then(|p| p.public_inputs)
+            && public_inputs.len() != schema.len()
+        {
+            return Err(ProverError::PolicyViolation(format!(
+                "public inputs are {} bytes, program {} expects {}",
+                public_inputs.len(),
+                hash,
+                schema.len()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check an execution's cycle count against the program's policy
+    pub fn check_cycles(&self, hash: &str, cycles: u64) -> Result<(), ProverError> {
+        match self.get(hash).and_then(|p| p.max_cycles) {
+            Some(max) if cycles > max => Err(ProverError::PolicyViolation(format!(
+                "execution took {} cycles, program {} allows at most {}",
+                cycles, hash, max
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Backend wrapper that enforces input policies before proving
+pub struct PolicyBackend {
+    inner: Arc<dyn ZkBackend>,
+    policies: Arc<InputPolicies>,
+    count_cycles: Option<CycleCounter>,
+}
+
+impl PolicyBackend {
+    pub fn new(inner: Arc<dyn ZkBackend>, policies: Arc<InputPolicies>) -> Self {
+        Self {
+            inner,
+            policies,
+            count_cycles: None,
+        }
+    }
+
+    /// Execute inputs of programs with a cycle limit before proving them.
+    /// Without a counter, `max_cycles` is not enforced.
+    pub fn with_cycle_counter(mut self, count_cycles: CycleCounter) -> Self {
+        self.count_cycles = Some(count_cycles);
+        self
+    }
+
+    /// Check `input` against the program's policy, executing it first if
+    /// the policy limits cycles
+    pub fn check(&self, program: &[u8], input: &[u8]) -> Result<(), ProverError> {
+        let hash = program_hash(program);
+        self.policies.check_stdin(&hash, input)?;
+        if let Some(count_cycles) = &self.count_cycles
+            && self.policies.get(&hash).is_some_and(|p| p.max_cycles.is_some())
+        {
+            self.policies.check_cycles(&hash, count_cycles(program, input)?)?;
+        }
+        Ok(())
+    }
+}
+
+impl ZkBackend for PolicyBackend {
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        self.check(program, input).map_err(ProverError::into_zk_error)?;
+        self.inner.prove(program, input)
+    }
+
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        self.inner.verify(program, proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockBackend;
+
+    fn policies() -> Arc<InputPolicies> {
+        let policies = Arc::new(InputPolicies::new());
+        policies.set(
+            program_hash(b"limited"),
+            InputPolicy {
+                max_stdin_bytes: Some(4),
+                max_cycles: Some(3_000),
+                public_inputs: Some(PublicInputSchema::new(vec![8, 32])),
+            },
+        );
+        policies
+    }
+
+    #[test]
+    fn test_check_request() {
+        let policies = policies();
+        let request = ProveRequest::new(b"limited".to_vec(), b"1234".to_vec()).with_public_inputs(vec![0; 40]);
+        policies.check_request(&request).unwrap();
+
+        let long = ProveRequest::new(b"limited".to_vec(), b"12345".to_vec()).with_public_inputs(vec![0; 40]);
+        assert!(matches!(policies.check_request(&long), Err(ProverError::PolicyViolation(_))));
+        let short = ProveRequest::new(b"limited".to_vec(), b"1234".to_vec()).with_public_inputs(vec![0; 39]);
+        assert!(matches!(policies.check_request(&short), Err(ProverError::PolicyViolation(_))));
+
+        // Programs without a policy accept anything
+        policies.check_request(&ProveRequest::new(b"free".to_vec(), vec![0; 1024])).unwrap();
+        policies.check_cycles(&program_hash(b"free"), u64::MAX).unwrap();
+    }
+
+    #[test]
+    fn test_backend_enforces_cycles() {
+        let counter: CycleCounter = Arc::new(|_, input| Ok(input.len() as u64 * 1_000));
+        let backend = PolicyBackend::new(Arc::new(MockBackend::default()), policies()).with_cycle_counter(counter);
+
+        assert!(backend.prove(b"limited", b"123").is_ok());
+        let err = backend.check(b"limited", b"1234").unwrap_err();
+        assert!(matches!(err, ProverError::PolicyViolation(msg) if msg.contains("4000 cycles")));
+        assert!(backend.prove(b"limited", b"12345").is_err());
+        assert!(backend.prove(b"free", b"12345").is_ok());
+
+        // Without a counter only the stdin limit applies
+        let unmetered = PolicyBackend::new(Arc::new(MockBackend::default()), policies());
+        assert!(unmetered.prove(b"limited", b"1234").is_ok());
+    }
+}
@@ -0,0 +1,349 @@
+use crate::events::{EventBus, LifecycleEvent};
+use crate::types::{ProgramHash, ProverError, program_hash};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Runs per-program setup, returning the result and its size in bytes
+pub type SetupFn<T> = Arc<dyn Fn(&[u8]) -> Result<(T, u64), ProverError> + Send + Sync>;
+
+/// Size limits of a [`ProgramCache`]
+#[derive(Debug, Clone)]
+pub struct ProgramCacheConfig {
+    /// Maximum number of cached programs
+    pub max_entries: usize,
+    /// Maximum total size of cached entries, in bytes
+    pub max_bytes: u64,
+    /// Programs set up eagerly by [`ProgramCache::initialize`]
+    pub preload_paths: Vec<PathBuf>,
+}
+
+impl Default for ProgramCacheConfig {
+    fn default() -> Self {
+        Self {
+            max_entries: 16,
+            max_bytes: 8 * 1024 * 1024 * 1024,
+            preload_paths: Vec::new(),
+        }
+    }
+}
+
+/// A program held in a [`ProgramCache`], as recorded by
+/// [`snapshot`](crate::snapshot::snapshot)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CachedProgram {
+    pub program_hash: ProgramHash,
+    pub pinned: bool,
+}
+
+struct CacheEntry<T> {
+    value: Arc<T>,
+    bytes: u64,
+    last_used: u64,
+}
+
+struct CacheState<T> {
+    entries: HashMap<ProgramHash, CacheEntry<T>>,
+    pinned: HashSet<ProgramHash>,
+    total_bytes: u64,
+    clock: u64,
+}
+
+/// Per-program setup results (proving keys, verifying keys) kept in memory
+/// with LRU eviction. Pinned programs are never evicted.
+pub struct ProgramCache<T> {
+    config: ProgramCacheConfig,
+    state: Mutex<CacheState<T>>,
+    events: Option<Arc<EventBus>>,
+}
+
+impl<T> ProgramCache<T> {
+    pub fn new(config: ProgramCacheConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(CacheState {
+                entries: HashMap::new(),
+                pinned: HashSet::new(),
+                total_bytes: 0,
+                clock: 0,
+            }),
+            events: None,
+        }
+    }
+
+    /// Emit [`LifecycleEvent::CacheEvicted`] for programs evicted to make room
+    pub fn with_events(mut self, bus: Arc<EventBus>) -> Self {
+        self.events = Some(bus);
+        self
+    }
+
+    /// Cached value for `hash`, marking it as recently used
+    pub fn get(&self, hash: &str) -> Option<Arc<T>> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.clock += 1;
+        let now = state.clock;
+        state.entries.get_mut(hash).map(|entry| {
+            entry.last_used = now;
+            entry.value.clone()
+        })
+    }
+
+    /// Cached value for `hash`, running `setup` on a miss. `setup` returns the
+    /// value and its size in bytes. It runs without the cache lock held, so
+    /// concurrent misses on the same program may both run it.
+    pub fn get_or_insert_with<F>(&self, hash: &str, setup: F) -> Result<Arc<T>, ProverError>
+    where
+        F: FnOnce() -> Result<(T, u64), ProverError>,
+    {
+        if let Some(value) = self.get(hash) {
+            return Ok(value);
+        }
+        let (value, bytes) = setup()?;
+        Ok(self.insert(hash.to_string(), value, bytes))
+    }
+
+    /// Add or replace an entry, evicting least recently used unpinned
+    /// programs until the limits hold again
+    pub fn insert(&self, hash: ProgramHash, value: T, bytes: u64) -> Arc<T> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.clock += 1;
+        let value = Arc::new(value);
+        let entry = CacheEntry {
+            value: value.clone(),
+            bytes,
+            last_used: state.clock,
+        };
+        if let Some(old) = state.entries.insert(hash.clone(), entry) {
+            state.total_bytes -= old.bytes;
+        }
+        state.total_bytes += bytes;
+        let evicted = self.evict(&mut state, &hash);
+        drop(state);
+        self.report(evicted);
+        value
+    }
+
+    /// Keep `hash` resident regardless of the limits. Pinning a program that
+    /// isn't cached yet takes effect once it is inserted.
+    pub fn pin_program(&self, hash: &str) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.pinned.insert(hash.to_string());
+    }
+
+    /// Make `hash` evictable again
+    pub fn unpin_program(&self, hash: &str) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.pinned.remove(hash);
+        let evicted = self.evict(&mut state, "");
+        drop(state);
+        self.report(evicted);
+    }
+
+    /// Drop `hash` from the cache, even if pinned
+    pub fn remove(&self, hash: &str) -> Option<Arc<T>> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.pinned.remove(hash);
+        let entry = state.entries.remove(hash)?;
+        state.total_bytes -= entry.bytes;
+        Some(entry.value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner).entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Programs currently cached, most recently used first
+    pub fn cached(&self) -> Vec<CachedProgram> {
+        let state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut entries: Vec<_> = state.entries.iter().collect();
+        entries.sort_by_key(|(_, entry)| std::cmp::Reverse(entry.last_used));
+        entries
+            .into_iter()
+            .map(|(hash, _)| CachedProgram {
+                program_hash: hash.clone(),
+                pinned: state.pinned.contains(hash),
+            })
+            .collect()
+    }
+
+    /// Total size of cached entries, in bytes
+    pub fn total_bytes(&self) -> u64 {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner).total_bytes
+    }
+
+    /// Evict until the limits hold, returning the evicted programs
+    fn evict(&self, state: &mut CacheState<T>, keep: &str) -> Vec<ProgramHash> {
+        let mut evicted = Vec::new();
+        while state.entries.len() > self.config.max_entries || state.total_bytes > self.config.max_bytes {
+            let victim = state
+                .entries
+                .iter()
+                .filter(|(hash, _)| hash.as_str() != keep && !state.pinned.contains(*hash))
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(hash, _)| hash.clone());
+            let Some(victim) = victim else {
+                tracing::warn!(
+                    "program cache over its limits ({} entries, {} bytes) but everything left is pinned",
+                    state.entries.len(),
+                    state.total_bytes
+                );
+                break;
+            };
+            if let Some(entry) = state.entries.remove(&victim) {
+                state.total_bytes -= entry.bytes;
+                tracing::debug!("evicted program {} ({} bytes)", victim, entry.bytes);
+                evicted.push(victim);
+            }
+        }
+        evicted
+    }
+
+    /// Emit an event per evicted program, outside the cache lock
+    fn report(&self, evicted: Vec<ProgramHash>) {
+        if let Some(bus) = &self.events {
+            for key in evicted {
+                bus.emit(LifecycleEvent::CacheEvicted {
+                    cache: "programs".to_string(),
+                    key,
+                });
+            }
+        }
+    }
+}
+
+impl<T: Send + Sync + 'static> ProgramCache<T> {
+    /// Set up `programs` in parallel so the first requests for them don't pay
+    /// setup latency. Returns each program's hash or setup error, in order.
+    pub async fn preload(&self, programs: Vec<Vec<u8>>, setup: SetupFn<T>) -> Vec<Result<ProgramHash, ProverError>> {
+        let tasks: Vec<_> = programs
+            .into_iter()
+            .map(|program| {
+                let setup = setup.clone();
+                tokio::task::spawn_blocking(move || {
+                    let hash = program_hash(&program);
+                    let _span = tracing::info_span!("setup", program_hash = %hash).entered();
+                    setup(&program).map(|(value, bytes)| (hash, value, bytes))
+                })
+            })
+            .collect();
+
+        let mut results = Vec::with_capacity(tasks.len());
+        for task in tasks {
+            let result = match task.await {
+                Ok(Ok((hash, value, bytes))) => {
+                    self.insert(hash.clone(), value, bytes);
+                    tracing::info!("preloaded program {}", hash);
+                    Ok(hash)
+                }
+                Ok(Err(e)) => Err(e),
+                Err(e) => Err(ProverError::Other(format!("preload task failed: {}", e))),
+            };
+            results.push(result);
+        }
+        results
+    }
+
+    /// Preload the programs listed in the config's `preload_paths`. Missing
+    /// files and setup failures are logged and don't abort the rest.
+    pub async fn initialize(&self, setup: SetupFn<T>) -> Vec<Result<ProgramHash, ProverError>> {
+        let mut programs = Vec::with_capacity(self.config.preload_paths.len());
+        let mut read_errors = Vec::new();
+        for path in &self.config.preload_paths {
+            match std::fs::read(path) {
+                Ok(program) => programs.push(program),
+                Err(e) => {
+                    tracing::warn!("cannot preload {}: {}", path.display(), e);
+                    read_errors.push(Err(ProverError::IOError(e)));
+                }
+            }
+        }
+        let mut results = self.preload(programs, setup).await;
+        for result in &results {
+            if let Err(e) = result {
+                tracing::warn!("program preload failed: {:?}", e);
+            }
+        }
+        results.extend(read_errors);
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru_eviction() {
+        let cache = ProgramCache::new(ProgramCacheConfig {
+            max_entries: 2,
+            max_bytes: 100,
+            ..ProgramCacheConfig::default()
+        });
+        cache.insert("a".to_string(), 1, 10);
+        cache.insert("b".to_string(), 2, 10);
+        cache.get("a");
+        cache.insert("c".to_string(), 3, 10);
+
+        assert!(cache.get("a").is_some());
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("c").is_some());
+    }
+
+    #[test]
+    fn test_eviction_events() {
+        let bus = Arc::new(EventBus::new());
+        let mut events = bus.subscribe_channel();
+        let cache = ProgramCache::new(ProgramCacheConfig {
+            max_entries: 1,
+            ..ProgramCacheConfig::default()
+        })
+        .with_events(bus);
+        cache.insert("a".to_string(), 1, 10);
+        assert!(events.try_recv().is_err());
+        cache.insert("b".to_string(), 2, 10);
+        assert_eq!(
+            events.try_recv().unwrap(),
+            LifecycleEvent::CacheEvicted {
+                cache: "programs".to_string(),
+                key: "a".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_byte_limit_and_pinning() {
+        let cache = ProgramCache::new(ProgramCacheConfig {
+            max_entries: 10,
+            max_bytes: 100,
+            ..ProgramCacheConfig::default()
+        });
+        cache.pin_program("big");
+        cache.insert("big".to_string(), 1, 80);
+        cache.insert("small".to_string(), 2, 10);
+        cache.insert("other".to_string(), 3, 15);
+
+        assert!(cache.get("big").is_some());
+        assert!(cache.get("small").is_none());
+        assert_eq!(cache.total_bytes(), 95);
+    }
+
+    #[tokio::test]
+    async fn test_preload() {
+        let cache = ProgramCache::new(ProgramCacheConfig::default());
+        let setup: SetupFn<usize> = Arc::new(|program| match program {
+            b"bad" => Err(ProverError::InvalidProgram("bad".to_string())),
+            _ => Ok((program.len(), program.len() as u64)),
+        });
+
+        let results = cache.preload(vec![b"elf-a".to_vec(), b"bad".to_vec()], setup).await;
+        let hash = results[0].as_ref().unwrap();
+        assert_eq!(cache.get(hash).as_deref(), Some(&5));
+        assert!(results[1].is_err());
+        assert_eq!(cache.len(), 1);
+    }
+}
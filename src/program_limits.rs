@@ -0,0 +1,257 @@
+use crate::metrics::ProverMetrics;
+use crate::registry::BackendMiddleware;
+use crate::types::{ProgramHash, ProverError, program_hash};
+use frostgate_zkip::{ZkBackend, ZkError};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::time::Duration;
+
+/// Retry hint given when a program's queue is full
+const PROGRAM_RETRY_AFTER: Duration = Duration::from_secs(5);
+
+/// Requests that may wait for a program's slot unless set otherwise
+const DEFAULT_PROGRAM_QUEUE_DEPTH: usize = 16;
+
+/// Bounds on one program's proofs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProgramLimit {
+    /// Proofs of the program running at once, across every backend
+    pub max_concurrent: usize,
+    /// Requests that may wait for a slot; further requests are rejected
+    /// with [`ProverError::Busy`]
+    #[serde(default = "default_queue_depth")]
+    pub max_queue_depth: usize,
+}
+
+impl ProgramLimit {
+    pub fn new(max_concurrent: usize) -> Self {
+        Self {
+            max_concurrent,
+            max_queue_depth: DEFAULT_PROGRAM_QUEUE_DEPTH,
+        }
+    }
+}
+
+fn default_queue_depth() -> usize {
+    DEFAULT_PROGRAM_QUEUE_DEPTH
+}
+
+#[derive(Default)]
+struct Slots {
+    running: usize,
+    queued: usize,
+}
+
+/// Caps concurrent proofs per program hash, e.g. for guests that need tens
+/// of GB of RAM each, whatever the backends' own limits are. Programs
+/// without a limit pass straight through.
+pub struct ProgramLimiter {
+    limits: HashMap<ProgramHash, ProgramLimit>,
+    slots: Mutex<HashMap<ProgramHash, Slots>>,
+    freed: Condvar,
+    metrics: Option<Arc<ProverMetrics>>,
+}
+
+impl ProgramLimiter {
+    pub fn new() -> Self {
+        Self {
+            limits: HashMap::new(),
+            slots: Mutex::new(HashMap::new()),
+            freed: Condvar::new(),
+            metrics: None,
+        }
+    }
+
+    pub fn with_limit(mut self, program_hash: ProgramHash, limit: ProgramLimit) -> Self {
+        self.limits.insert(program_hash, limit);
+        self
+    }
+
+    /// Report each limited program's running and queued proofs, on
+    /// metrics with [`ProverMetrics::with_program_labels`] enabled
+    pub fn with_metrics(mut self, metrics: Arc<ProverMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub fn limit(&self, program_hash: &str) -> Option<ProgramLimit> {
+        self.limits.get(program_hash).copied()
+    }
+
+    /// Take a slot for `program_hash`, waiting while the program is at its
+    /// limit. Returns `None` for programs without a limit.
+    pub fn admit(&self, program_hash: &str) -> Result<Option<ProgramPermit<'_>>, ProverError> {
+        let Some(limit) = self.limit(program_hash) else {
+            return Ok(None);
+        };
+        let max_concurrent = limit.max_concurrent.max(1);
+        let mut all = self.slots.lock().unwrap_or_else(PoisonError::into_inner);
+        let slots = all.entry(program_hash.to_string()).or_default();
+        let waiting = slots.running >= max_concurrent;
+        if waiting {
+            if slots.queued >= limit.max_queue_depth {
+                return Err(ProverError::Busy {
+                    reason: format!(
+                        "program {} has {} proofs running and {} queued",
+                        program_hash, slots.running, slots.queued
+                    ),
+                    retry_after: PROGRAM_RETRY_AFTER,
+                });
+            }
+            slots.queued += 1;
+            self.report(program_hash, slots);
+            while all.get(program_hash).is_some_and(|slots| slots.running >= max_concurrent) {
+                all = self.freed.wait(all).unwrap_or_else(PoisonError::into_inner);
+            }
+        }
+        let slots = all.entry(program_hash.to_string()).or_default();
+        if waiting {
+            slots.queued = slots.queued.saturating_sub(1);
+        }
+        slots.running += 1;
+        self.report(program_hash, slots);
+        Ok(Some(ProgramPermit {
+            limiter: self,
+            program_hash: program_hash.to_string(),
+        }))
+    }
+
+    /// Proofs of `program_hash` running and waiting
+    pub fn slots(&self, program_hash: &str) -> (usize, usize) {
+        let all = self.slots.lock().unwrap_or_else(PoisonError::into_inner);
+        all.get(program_hash)
+            .map_or((0, 0), |slots| (slots.running, slots.queued))
+    }
+
+    fn release(&self, program_hash: &str) {
+        let mut all = self.slots.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(slots) = all.get_mut(program_hash) {
+            slots.running -= 1;
+            self.report(program_hash, slots);
+        }
+        // Waiters of every program share the condvar
+        self.freed.notify_all();
+    }
+
+    fn report(&self, program_hash: &str, slots: &Slots) {
+        if let Some(metrics) = &self.metrics {
+            metrics.set_program_slots(program_hash, slots.running, slots.queued);
+        }
+    }
+}
+
+impl Default for ProgramLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A program's proving slot, released on drop
+pub struct ProgramPermit<'a> {
+    limiter: &'a ProgramLimiter,
+    program_hash: ProgramHash,
+}
+
+impl Drop for ProgramPermit<'_> {
+    fn drop(&mut self) {
+        self.limiter.release(&self.program_hash);
+    }
+}
+
+/// Backend whose prove calls take a slot from a shared [`ProgramLimiter`]
+pub struct ProgramLimitedBackend {
+    inner: Arc<dyn ZkBackend>,
+    limiter: Arc<ProgramLimiter>,
+}
+
+impl ProgramLimitedBackend {
+    pub fn new(inner: Arc<dyn ZkBackend>, limiter: Arc<ProgramLimiter>) -> Self {
+        Self { inner, limiter }
+    }
+
+    /// Prove, keeping [`ProverError::Busy`] and its retry hint intact
+    pub fn prove_limited(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ProverError> {
+        let _permit = self.limiter.admit(&program_hash(program))?;
+        Ok(self.inner.prove(program, input)?)
+    }
+}
+
+impl ZkBackend for ProgramLimitedBackend {
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        self.prove_limited(program, input).map_err(ProverError::into_zk_error)
+    }
+
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        self.inner.verify(program, proof)
+    }
+}
+
+/// Registry middleware applying one [`ProgramLimiter`] to every backend, so
+/// the limits hold across backends on the same host. Add it before
+/// [`AdmissionMiddleware`](crate::admission::AdmissionMiddleware) so requests
+/// waiting for their program don't occupy a backend slot meanwhile.
+pub struct ProgramLimitMiddleware(Arc<ProgramLimiter>);
+
+impl ProgramLimitMiddleware {
+    pub fn new(limiter: Arc<ProgramLimiter>) -> Self {
+        Self(limiter)
+    }
+}
+
+impl BackendMiddleware for ProgramLimitMiddleware {
+    fn wrap(&self, _id: &str, backend: Arc<dyn ZkBackend>) -> Arc<dyn ZkBackend> {
+        Arc::new(ProgramLimitedBackend::new(backend, self.0.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockBackend, MockConfig};
+
+    #[test]
+    fn test_program_limit() {
+        let metrics = Arc::new(ProverMetrics::new().unwrap().with_program_labels(8));
+        let hungry = program_hash(b"hungry");
+        let limiter = Arc::new(
+            ProgramLimiter::new()
+                .with_limit(
+                    hungry.clone(),
+                    ProgramLimit {
+                        max_concurrent: 1,
+                        max_queue_depth: 1,
+                    },
+                )
+                .with_metrics(metrics.clone()),
+        );
+        let backend = Arc::new(ProgramLimitedBackend::new(
+            Arc::new(MockBackend::new(MockConfig {
+                prove_latency: Duration::from_millis(200),
+                ..MockConfig::default()
+            })),
+            limiter.clone(),
+        ));
+
+        let workers: Vec<_> = (0..2u8)
+            .map(|i| {
+                let backend = backend.clone();
+                std::thread::spawn(move || backend.prove_limited(b"hungry", &[i]))
+            })
+            .collect();
+        while limiter.slots(&hungry) != (1, 1) {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        let text = metrics.gather().unwrap();
+        assert!(text.contains(&format!(r#"frostgate_program_proofs_queued{{program_hash="{}"}} 1"#, hungry)));
+
+        assert!(matches!(backend.prove_limited(b"hungry", b"3"), Err(ProverError::Busy { .. })));
+        // Unlimited programs aren't held up
+        assert!(backend.prove_limited(b"small", b"input").is_ok());
+        for worker in workers {
+            assert!(worker.join().unwrap().is_ok());
+        }
+        assert_eq!(limiter.slots(&hungry), (0, 0));
+    }
+}
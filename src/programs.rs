@@ -0,0 +1,192 @@
+use crate::audit::now_ms;
+use crate::events::{EventBus, LifecycleEvent};
+use crate::types::{ProgramHash, ProverError, ct_eq, program_hash};
+use semver::{Version, VersionReq};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// Description of one registered program version
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProgramVersionInfo {
+    pub name: String,
+    pub version: String,
+    pub program_hash: ProgramHash,
+    pub size_bytes: usize,
+    pub registered_at_ms: u64,
+    /// Why the version was deprecated, if it was
+    pub deprecated: Option<String>,
+}
+
+struct StoredProgram {
+    info: ProgramVersionInfo,
+    elf: Arc<Vec<u8>>,
+}
+
+#[derive(Default)]
+struct StoreState {
+    by_name: HashMap<String, BTreeMap<Version, StoredProgram>>,
+    by_hash: HashMap<ProgramHash, (String, Version)>,
+}
+
+/// Program ELFs registered under human-readable names and semantic versions
+#[derive(Default)]
+pub struct ProgramStore {
+    state: RwLock<StoreState>,
+    events: Option<Arc<EventBus>>,
+}
+
+impl ProgramStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Emit [`LifecycleEvent::ProgramRegistered`] for new programs
+    pub fn with_events(mut self, bus: Arc<EventBus>) -> Self {
+        self.events = Some(bus);
+        self
+    }
+
+    /// Register `elf` as `name` at `version`. Registering the same ELF again
+    /// is a no-op; registering a different ELF under an existing version fails.
+    pub fn register(&self, name: &str, version: &str, elf: &[u8]) -> Result<ProgramHash, ProverError> {
+        if name.is_empty() {
+            return Err(ProverError::InvalidProgram("program name is empty".to_string()));
+        }
+        let version = parse_version(version)?;
+        let hash = program_hash(elf);
+
+        let mut state = self.state.write().unwrap_or_else(PoisonError::into_inner);
+        if let Some(existing) = state.by_name.get(name).and_then(|versions| versions.get(&version)) {
+            if ct_eq(existing.info.program_hash.as_bytes(), hash.as_bytes()) {
+                return Ok(hash);
+            }
+            return Err(ProverError::InvalidProgram(format!(
+                "{} {} is already registered with a different ELF",
+                name, version
+            )));
+        }
+        let info = ProgramVersionInfo {
+            name: name.to_string(),
+            version: version.to_string(),
+            program_hash: hash.clone(),
+            size_bytes: elf.len(),
+            registered_at_ms: now_ms(),
+            deprecated: None,
+        };
+        state.by_name.entry(name.to_string()).or_default().insert(
+            version.clone(),
+            StoredProgram {
+                info,
+                elf: Arc::new(elf.to_vec()),
+            },
+        );
+        state.by_hash.insert(hash.clone(), (name.to_string(), version.clone()));
+        drop(state);
+
+        tracing::info!("registered program {} {} ({})", name, version, hash);
+        if let Some(bus) = &self.events {
+            bus.emit(LifecycleEvent::ProgramRegistered {
+                program_hash: hash.clone(),
+            });
+        }
+        Ok(hash)
+    }
+
+    /// Resolve `name` to the highest non-deprecated version matching
+    /// `requirement`, e.g. `"1.2.0"`, `"^1.2"` or `"*"`. An exact version
+    /// resolves even if deprecated.
+    pub fn resolve(&self, name: &str, requirement: &str) -> Result<(ProgramVersionInfo, Arc<Vec<u8>>), ProverError> {
+        let state = self.state.read().unwrap_or_else(PoisonError::into_inner);
+        let versions = state.by_name.get(name).ok_or(ProverError::ProgramNotFound)?;
+
+        if let Ok(exact) = Version::parse(requirement) {
+            return versions
+                .get(&exact)
+                .map(|program| (program.info.clone(), program.elf.clone()))
+                .ok_or(ProverError::ProgramNotFound);
+        }
+        let requirement = VersionReq::parse(requirement)
+            .map_err(|e| ProverError::Other(format!("invalid version requirement '{}': {}", requirement, e)))?;
+        versions
+            .iter()
+            .rev()
+            .find(|(version, program)| requirement.matches(version) && program.info.deprecated.is_none())
+            .map(|(_, program)| (program.info.clone(), program.elf.clone()))
+            .ok_or(ProverError::ProgramNotFound)
+    }
+
+    /// Look up a program by its hash
+    pub fn get_by_hash(&self, hash: &str) -> Option<(ProgramVersionInfo, Arc<Vec<u8>>)> {
+        let state = self.state.read().unwrap_or_else(PoisonError::into_inner);
+        let (name, version) = state.by_hash.get(hash)?;
+        let program = state.by_name.get(name)?.get(version)?;
+        Some((program.info.clone(), program.elf.clone()))
+    }
+
+    /// All registered program names, sorted
+    pub fn names(&self) -> Vec<String> {
+        let state = self.state.read().unwrap_or_else(PoisonError::into_inner);
+        let mut names: Vec<_> = state.by_name.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Every version of `name`, oldest first
+    pub fn versions(&self, name: &str) -> Vec<ProgramVersionInfo> {
+        let state = self.state.read().unwrap_or_else(PoisonError::into_inner);
+        state
+            .by_name
+            .get(name)
+            .map(|versions| versions.values().map(|program| program.info.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Mark a version as deprecated so range lookups skip it
+    pub fn deprecate(&self, name: &str, version: &str, reason: &str) -> Result<(), ProverError> {
+        let version = parse_version(version)?;
+        let mut state = self.state.write().unwrap_or_else(PoisonError::into_inner);
+        let program = state
+            .by_name
+            .get_mut(name)
+            .and_then(|versions| versions.get_mut(&version))
+            .ok_or(ProverError::ProgramNotFound)?;
+        program.info.deprecated = Some(reason.to_string());
+        tracing::info!("deprecated program {} {}: {}", name, version, reason);
+        Ok(())
+    }
+}
+
+fn parse_version(version: &str) -> Result<Version, ProverError> {
+    Version::parse(version).map_err(|e| ProverError::Other(format!("invalid version '{}': {}", version, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_versions() {
+        let store = ProgramStore::new();
+        store.register("light-client", "1.1.0", b"v110").unwrap();
+        let v120 = store.register("light-client", "1.2.0", b"v120").unwrap();
+        store.register("light-client", "2.0.0", b"v200").unwrap();
+
+        assert_eq!(store.resolve("light-client", "^1").unwrap().0.version, "1.2.0");
+        assert_eq!(store.resolve("light-client", "*").unwrap().0.version, "2.0.0");
+        assert_eq!(store.get_by_hash(&v120).unwrap().0.version, "1.2.0");
+
+        store.deprecate("light-client", "1.2.0", "bad constraint").unwrap();
+        assert_eq!(store.resolve("light-client", "^1").unwrap().0.version, "1.1.0");
+        assert!(store.resolve("light-client", "1.2.0").unwrap().0.deprecated.is_some());
+    }
+
+    #[test]
+    fn test_version_is_immutable() {
+        let store = ProgramStore::new();
+        store.register("relay", "0.1.0", b"elf").unwrap();
+        assert!(store.register("relay", "0.1.0", b"elf").is_ok());
+        assert!(store.register("relay", "0.1.0", b"other").is_err());
+        assert!(store.register("relay", "latest", b"elf").is_err());
+    }
+}
@@ -0,0 +1,103 @@
+use crate::pipeline::ProofMode;
+use frostgate_zkip::{ZkBackend, ZkError};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+/// Coarse progress of a single proof
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProveProgress {
+    /// Proving started
+    Started,
+    /// Guest execution finished
+    ExecutionDone { cycles: u64 },
+    /// Shard `index` (zero-based) of `total` proven
+    ShardProven { index: usize, total: usize },
+    /// Recursively compressing the shard proofs into one
+    Compressing,
+    /// Wrapping the compressed proof into `mode`
+    Wrapping { mode: ProofMode },
+    /// The proof is complete
+    Done,
+}
+
+/// Receives progress reports
+pub type ProgressSink = Arc<dyn Fn(ProveProgress) + Send + Sync>;
+
+/// A sink that forwards reports into a channel, for consumers on another task
+pub fn progress_channel() -> (ProgressSink, mpsc::UnboundedReceiver<ProveProgress>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let sink: ProgressSink = Arc::new(move |progress| {
+        let _ = tx.send(progress);
+    });
+    (sink, rx)
+}
+
+/// Backends that can report progress while proving
+pub trait ProgressBackend: ZkBackend {
+    /// Prove, reporting progress into `sink`
+    fn prove_with_progress(&self, program: &[u8], input: &[u8], sink: &ProgressSink) -> Result<Vec<u8>, ZkError>;
+}
+
+/// Adapts a backend without native progress reporting, emitting only
+/// `Started` and `Done`
+pub struct CoarseProgress<B: ?Sized> {
+    inner: Arc<B>,
+}
+
+impl<B: ZkBackend + ?Sized> CoarseProgress<B> {
+    pub fn new(inner: Arc<B>) -> Self {
+        Self { inner }
+    }
+}
+
+impl<B: ZkBackend + ?Sized> ZkBackend for CoarseProgress<B> {
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        self.inner.prove(program, input)
+    }
+
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        self.inner.verify(program, proof)
+    }
+}
+
+impl<B: ZkBackend + ?Sized> ProgressBackend for CoarseProgress<B> {
+    fn prove_with_progress(&self, program: &[u8], input: &[u8], sink: &ProgressSink) -> Result<Vec<u8>, ZkError> {
+        sink(ProveProgress::Started);
+        let proof = self.inner.prove(program, input)?;
+        sink(ProveProgress::Done);
+        Ok(proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockBackend, MockConfig};
+
+    #[tokio::test]
+    async fn test_coarse_progress_into_channel() {
+        let (sink, mut rx) = progress_channel();
+        let backend = CoarseProgress::new(Arc::new(MockBackend::default()));
+        let proof = backend.prove_with_progress(b"elf", b"input", &sink).unwrap();
+        assert!(backend.verify(b"elf", &proof).unwrap());
+        drop(sink);
+
+        let mut reports = Vec::new();
+        while let Some(progress) = rx.recv().await {
+            reports.push(progress);
+        }
+        assert_eq!(reports, [ProveProgress::Started, ProveProgress::Done]);
+    }
+
+    #[test]
+    fn test_failed_proof_not_done() {
+        let (sink, mut rx) = progress_channel();
+        let backend = CoarseProgress::new(Arc::new(MockBackend::new(MockConfig {
+            fail_first_proves: 1,
+            ..MockConfig::default()
+        })));
+        assert!(backend.prove_with_progress(b"elf", b"input", &sink).is_err());
+        assert_eq!(rx.try_recv().unwrap(), ProveProgress::Started);
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -0,0 +1,122 @@
+pub use crate::compat::SP1_VERSION;
+use crate::types::{ProgramHash, ProverError, ct_eq, program_hash};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+use std::collections::{BTreeMap, HashMap};
+
+/// Version of this crate
+pub const PROVER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Prefix for provenance keys in proof metadata custom fields
+pub const PROVENANCE_PREFIX: &str = "provenance.";
+
+/// Ties a proof to an exact, reproducible build of its guest program
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildProvenance {
+    pub program_hash: ProgramHash,
+    pub guest_toolchain: Option<String>,
+    pub sp1_version: String,
+    pub prover_version: String,
+    pub artifact_digests: BTreeMap<String, String>,
+}
+
+impl BuildProvenance {
+    /// Provenance for `program` built by this prover
+    pub fn for_program(program: &[u8]) -> Self {
+        Self {
+            program_hash: program_hash(program),
+            guest_toolchain: None,
+            sp1_version: SP1_VERSION.to_string(),
+            prover_version: PROVER_VERSION.to_string(),
+            artifact_digests: BTreeMap::new(),
+        }
+    }
+
+    /// Record the toolchain the guest was compiled with, e.g. `succinct-1.85.0`
+    pub fn with_toolchain(mut self, toolchain: &str) -> Self {
+        self.guest_toolchain = Some(toolchain.to_string());
+        self
+    }
+
+    /// Record the digest of an artifact used to produce the proof
+    pub fn with_artifact(mut self, name: &str, contents: &[u8]) -> Self {
+        self.artifact_digests
+            .insert(name.to_string(), hex::encode(Sha3_256::digest(contents)));
+        self
+    }
+
+    /// Whether `program` is the build this provenance describes
+    pub fn matches_program(&self, program: &[u8]) -> bool {
+        ct_eq(self.program_hash.as_bytes(), program_hash(program).as_bytes())
+    }
+
+    /// Encode as proof metadata custom fields
+    pub fn to_custom_fields(&self) -> HashMap<String, String> {
+        let mut fields = HashMap::new();
+        fields.insert(key("program_hash"), self.program_hash.clone());
+        fields.insert(key("sp1_version"), self.sp1_version.clone());
+        fields.insert(key("prover_version"), self.prover_version.clone());
+        if let Some(toolchain) = &self.guest_toolchain {
+            fields.insert(key("guest_toolchain"), toolchain.clone());
+        }
+        for (name, digest) in &self.artifact_digests {
+            fields.insert(key(&format!("artifact.{}", name)), digest.clone());
+        }
+        fields
+    }
+
+    /// Decode from proof metadata custom fields
+    pub fn from_custom_fields(fields: &HashMap<String, String>) -> Result<Self, ProverError> {
+        let required = |name: &str| {
+            fields
+                .get(&key(name))
+                .cloned()
+                .ok_or_else(|| ProverError::Other(format!("Missing provenance field '{}'", name)))
+        };
+        let artifact_prefix = key("artifact.");
+        let artifact_digests = fields
+            .iter()
+            .filter_map(|(k, v)| Some((k.strip_prefix(&artifact_prefix)?.to_string(), v.clone())))
+            .collect();
+
+        Ok(Self {
+            program_hash: required("program_hash")?,
+            guest_toolchain: fields.get(&key("guest_toolchain")).cloned(),
+            sp1_version: required("sp1_version")?,
+            prover_version: required("prover_version")?,
+            artifact_digests,
+        })
+    }
+}
+
+fn key(name: &str) -> String {
+    format!("{}{}", PROVENANCE_PREFIX, name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_fields_round_trip() {
+        let provenance = BuildProvenance::for_program(b"elf")
+            .with_toolchain("succinct-1.85.0")
+            .with_artifact("groth16_vk.bin", b"vk")
+            .with_artifact("plonk_vk.bin", b"other vk");
+        let mut fields = provenance.to_custom_fields();
+        assert!(fields.keys().all(|k| k.starts_with(PROVENANCE_PREFIX)));
+        assert_eq!(BuildProvenance::from_custom_fields(&fields).unwrap(), provenance);
+        assert!(provenance.matches_program(b"elf"));
+        assert!(!provenance.matches_program(b"other"));
+
+        // Fields of other kinds sit alongside without being picked up
+        fields.insert("team".to_string(), "bridge".to_string());
+        assert_eq!(BuildProvenance::from_custom_fields(&fields).unwrap(), provenance);
+
+        let bare = BuildProvenance::for_program(b"elf");
+        assert_eq!(BuildProvenance::from_custom_fields(&bare.to_custom_fields()).unwrap(), bare);
+
+        fields.remove(&key("sp1_version"));
+        assert!(BuildProvenance::from_custom_fields(&fields).is_err());
+    }
+}
@@ -0,0 +1,48 @@
+use crate::binding::PublicValuesExtractor;
+use crate::compat::sdk::SP1ProofWithPublicValues;
+use crate::envelope::ProofEnvelope;
+use crate::types::ProverError;
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
+
+/// Public values committed by a bincode-serialized SP1 proof, whatever its
+/// proof type
+pub fn sp1_public_values(proof: &[u8]) -> Result<Vec<u8>, ProverError> {
+    let proof: SP1ProofWithPublicValues =
+        bincode::deserialize(proof).map_err(|e| ProverError::Other(format!("decoding SP1 proof: {}", e)))?;
+    Ok(proof.public_values.to_vec())
+}
+
+/// [`sp1_public_values`] as an extractor for [`crate::binding::BoundVerifier`]
+pub fn sp1_extractor() -> PublicValuesExtractor {
+    Arc::new(|proof: &[u8]| sp1_public_values(proof).map_err(ProverError::into_zk_error))
+}
+
+/// Decode public values the guest committed with `sp1_zkvm::io::commit::<T>()`
+pub fn decode_public_values<T: DeserializeOwned>(public_values: &[u8]) -> Result<T, ProverError> {
+    bincode::deserialize(public_values)
+        .map_err(|e| ProverError::Other(format!("decoding public values: {}", e)))
+}
+
+impl ProofEnvelope {
+    /// Public values decoded as the type the guest committed
+    pub fn decode_public_values<T: DeserializeOwned>(&self) -> Result<T, ProverError> {
+        decode_public_values(&self.public_values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::canned_envelope;
+
+    #[test]
+    fn test_typed_decoding() {
+        let committed = bincode::serialize(&(7u64, [1u8; 4])).unwrap();
+        let envelope = canned_envelope(b"elf", &committed);
+
+        let (height, root): (u64, [u8; 4]) = envelope.decode_public_values().unwrap();
+        assert_eq!(height, 7);
+        assert_eq!(root, [1; 4]);
+        assert!(envelope.decode_public_values::<(u64, [u8; 32])>().is_err());
+    }
+}
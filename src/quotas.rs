@@ -0,0 +1,443 @@
+use crate::estimation::CycleCounter;
+use crate::payments::PricingFn;
+use crate::types::{ProgramHash, ProverError, program_hash};
+use frostgate_zkip::{ZkBackend, ZkError};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Tenant charged for requests that don't name one
+pub const DEFAULT_TENANT: &str = "default";
+
+/// What a quota applies to
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum QuotaScope {
+    Program(ProgramHash),
+    Tenant(String),
+}
+
+impl std::fmt::Display for QuotaScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QuotaScope::Program(hash) => write!(f, "program {}", hash),
+            QuotaScope::Tenant(tenant) => write!(f, "tenant '{}'", tenant),
+        }
+    }
+}
+
+/// Daily limits; `None` means unlimited
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QuotaLimits {
+    pub proofs_per_day: Option<u64>,
+    pub cycles_per_day: Option<u64>,
+    pub spend_per_day: Option<u64>,
+}
+
+/// Resources consumed by one request, or remaining under a quota
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QuotaUsage {
+    pub proofs: u64,
+    pub cycles: u64,
+    pub spend: u64,
+}
+
+/// Remaining allowance for a scope today; `None` fields are unlimited
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QuotaRemaining {
+    pub proofs: Option<u64>,
+    pub cycles: Option<u64>,
+    pub spend: Option<u64>,
+}
+
+#[derive(Default)]
+struct QuotaState {
+    limits: HashMap<QuotaScope, QuotaLimits>,
+    usage: HashMap<QuotaScope, (u64, QuotaUsage)>,
+}
+
+/// Enforces daily quotas per program hash and per tenant
+#[derive(Default)]
+pub struct QuotaManager {
+    state: Mutex<QuotaState>,
+}
+
+impl QuotaManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the limits for a scope
+    pub fn set_limits(&self, scope: QuotaScope, limits: QuotaLimits) {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner).limits.insert(scope, limits);
+    }
+
+    /// Check a request against the tenant's and the program's quotas and
+    /// record it if both allow it. A scope that has used up a resource
+    /// rejects requests even if they ask for none of it.
+    pub fn consume(&self, tenant: &str, program: &str, usage: QuotaUsage) -> Result<(), ProverError> {
+        self.consume_on(today(), tenant, program, usage)
+    }
+
+    /// Record usage only known once a request has run, such as its cycles,
+    /// without checking it against the limits
+    pub fn record(&self, tenant: &str, program: &str, usage: QuotaUsage) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        record_on(&mut state, today(), tenant, program, usage);
+    }
+
+    /// Give back usage consumed for a request that then failed
+    pub fn refund(&self, tenant: &str, program: &str, usage: QuotaUsage) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let day = today();
+        for scope in [
+            QuotaScope::Tenant(tenant.to_string()),
+            QuotaScope::Program(program.to_string()),
+        ] {
+            if let Some((recorded_day, used)) = state.usage.get_mut(&scope)
+                && *recorded_day == day
+            {
+                used.proofs = used.proofs.saturating_sub(usage.proofs);
+                used.cycles = used.cycles.saturating_sub(usage.cycles);
+                used.spend = used.spend.saturating_sub(usage.spend);
+            }
+        }
+    }
+
+    /// Remaining allowance for a scope today
+    pub fn remaining(&self, scope: &QuotaScope) -> QuotaRemaining {
+        self.remaining_on(today(), scope)
+    }
+
+    fn consume_on(&self, day: u64, tenant: &str, program: &str, usage: QuotaUsage) -> Result<(), ProverError> {
+        let scopes = [
+            QuotaScope::Tenant(tenant.to_string()),
+            QuotaScope::Program(program.to_string()),
+        ];
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+        for scope in &scopes {
+            let Some(limits) = state.limits.get(scope).copied() else {
+                continue;
+            };
+            let used = used_on(&state, day, scope);
+            let checks = [
+                ("proofs", limits.proofs_per_day, used.proofs, usage.proofs),
+                ("cycles", limits.cycles_per_day, used.cycles, usage.cycles),
+                ("spend", limits.spend_per_day, used.spend, usage.spend),
+            ];
+            for (resource, limit, used, requested) in checks {
+                if let Some(limit) = limit
+                    && (used.saturating_add(requested) > limit || used >= limit)
+                {
+                    return Err(ProverError::QuotaExceeded {
+                        scope: scope.to_string(),
+                        resource: resource.to_string(),
+                        limit,
+                        requested,
+                    });
+                }
+            }
+        }
+
+        record_on(&mut state, day, tenant, program, usage);
+        Ok(())
+    }
+
+    fn remaining_on(&self, day: u64, scope: &QuotaScope) -> QuotaRemaining {
+        let state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let limits = state.limits.get(scope).copied().unwrap_or_default();
+        let used = used_on(&state, day, scope);
+        QuotaRemaining {
+            proofs: limits.proofs_per_day.map(|l| l.saturating_sub(used.proofs)),
+            cycles: limits.cycles_per_day.map(|l| l.saturating_sub(used.cycles)),
+            spend: limits.spend_per_day.map(|l| l.saturating_sub(used.spend)),
+        }
+    }
+}
+
+fn record_on(state: &mut QuotaState, day: u64, tenant: &str, program: &str, usage: QuotaUsage) {
+    let scopes = [
+        QuotaScope::Tenant(tenant.to_string()),
+        QuotaScope::Program(program.to_string()),
+    ];
+    for scope in scopes {
+        let used = used_on(state, day, &scope);
+        state.usage.insert(
+            scope,
+            (
+                day,
+                QuotaUsage {
+                    proofs: used.proofs.saturating_add(usage.proofs),
+                    cycles: used.cycles.saturating_add(usage.cycles),
+                    spend: used.spend.saturating_add(usage.spend),
+                },
+            ),
+        );
+    }
+}
+
+/// Usage recorded for `scope` on `day`; older usage has expired
+fn used_on(state: &QuotaState, day: u64, scope: &QuotaScope) -> QuotaUsage {
+    match state.usage.get(scope) {
+        Some((recorded_day, usage)) if *recorded_day == day => *usage,
+        _ => QuotaUsage::default(),
+    }
+}
+
+fn today() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / SECONDS_PER_DAY)
+        .unwrap_or(0)
+}
+
+/// Backend charging one tenant's quotas for its proofs. Each proof is
+/// consumed before proving, so exhausted quotas reject it up front, and
+/// refunded if proving fails; its cycles and spend are recorded once it is
+/// proven.
+pub struct QuotaBackend {
+    inner: Arc<dyn ZkBackend>,
+    quotas: Arc<QuotaManager>,
+    tenant: String,
+    count_cycles: Option<CycleCounter>,
+    pricing: Option<PricingFn>,
+}
+
+impl QuotaBackend {
+    pub fn new(inner: Arc<dyn ZkBackend>, quotas: Arc<QuotaManager>, tenant: &str) -> Self {
+        Self {
+            inner,
+            quotas,
+            tenant: tenant.to_string(),
+            count_cycles: None,
+            pricing: None,
+        }
+    }
+
+    /// Execute proven requests to record their cycles
+    pub fn with_cycle_counter(mut self, count_cycles: CycleCounter) -> Self {
+        self.count_cycles = Some(count_cycles);
+        self
+    }
+
+    /// Record what `pricing` charges for each proven request as spend
+    pub fn with_pricing(mut self, pricing: PricingFn) -> Self {
+        self.pricing = Some(pricing);
+        self
+    }
+
+    /// Prove, keeping [`ProverError::QuotaExceeded`] intact
+    pub fn prove_metered(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ProverError> {
+        let hash = program_hash(program);
+        let charged = QuotaUsage {
+            proofs: 1,
+            ..QuotaUsage::default()
+        };
+        self.quotas.consume(&self.tenant, &hash, charged)?;
+        let proof = self.inner.prove(program, input).inspect_err(|_| {
+            self.quotas.refund(&self.tenant, &hash, charged);
+        })?;
+
+        let cycles = match &self.count_cycles {
+            Some(count_cycles) => count_cycles(program, input).unwrap_or_else(|e| {
+                tracing::warn!("can't count the cycles of a proof of {}: {:?}", hash, e);
+                0
+            }),
+            None => 0,
+        };
+        let spend = self.pricing.as_ref().map_or(0, |pricing| pricing(program, input));
+        self.quotas.record(
+            &self.tenant,
+            &hash,
+            QuotaUsage {
+                proofs: 0,
+                cycles,
+                spend,
+            },
+        );
+        Ok(proof)
+    }
+}
+
+impl ZkBackend for QuotaBackend {
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        self.prove_metered(program, input).map_err(ProverError::into_zk_error)
+    }
+
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        self.inner.verify(program, proof)
+    }
+}
+
+/// Quotas the servers charge each request's tenant, wrapping the backend
+/// a tenant proves on in a [`QuotaBackend`]
+#[derive(Clone)]
+pub struct TenantQuotas {
+    quotas: Arc<QuotaManager>,
+    count_cycles: Option<CycleCounter>,
+    pricing: Option<PricingFn>,
+}
+
+impl TenantQuotas {
+    pub fn new(quotas: Arc<QuotaManager>) -> Self {
+        Self {
+            quotas,
+            count_cycles: None,
+            pricing: None,
+        }
+    }
+
+    /// See [`QuotaBackend::with_cycle_counter`]
+    pub fn with_cycle_counter(mut self, count_cycles: CycleCounter) -> Self {
+        self.count_cycles = Some(count_cycles);
+        self
+    }
+
+    /// See [`QuotaBackend::with_pricing`]
+    pub fn with_pricing(mut self, pricing: PricingFn) -> Self {
+        self.pricing = Some(pricing);
+        self
+    }
+
+    pub fn quotas(&self) -> &Arc<QuotaManager> {
+        &self.quotas
+    }
+
+    /// `backend`, charging its proofs to `tenant`
+    pub fn wrap(&self, tenant: &str, backend: Arc<dyn ZkBackend>) -> Arc<dyn ZkBackend> {
+        let mut metered = QuotaBackend::new(backend, self.quotas.clone(), tenant);
+        if let Some(count_cycles) = &self.count_cycles {
+            metered = metered.with_cycle_counter(count_cycles.clone());
+        }
+        if let Some(pricing) = &self.pricing {
+            metered = metered.with_pricing(pricing.clone());
+        }
+        Arc::new(metered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockBackend;
+
+    fn proof(cycles: u64) -> QuotaUsage {
+        QuotaUsage {
+            proofs: 1,
+            cycles,
+            spend: 0,
+        }
+    }
+
+    #[test]
+    fn test_quotas() {
+        let quotas = QuotaManager::new();
+        quotas.set_limits(
+            QuotaScope::Tenant("analytics".to_string()),
+            QuotaLimits {
+                proofs_per_day: Some(2),
+                ..Default::default()
+            },
+        );
+        quotas.set_limits(
+            QuotaScope::Program("heavy".to_string()),
+            QuotaLimits {
+                cycles_per_day: Some(1_000),
+                ..Default::default()
+            },
+        );
+
+        quotas.consume_on(1, "analytics", "light", proof(10)).unwrap();
+        quotas.consume_on(1, "analytics", "heavy", proof(900)).unwrap();
+        assert!(matches!(
+            quotas.consume_on(1, "analytics", "light", proof(10)),
+            Err(ProverError::QuotaExceeded { .. })
+        ));
+        assert!(quotas.consume_on(1, "bridge", "heavy", proof(200)).is_err());
+        quotas.consume_on(1, "bridge", "heavy", proof(100)).unwrap();
+
+        let remaining = quotas.remaining_on(1, &QuotaScope::Program("heavy".to_string()));
+        assert_eq!(remaining.cycles, Some(0));
+        assert_eq!(remaining.proofs, None);
+
+        // Quotas reset the next day
+        quotas.consume_on(2, "analytics", "light", proof(10)).unwrap();
+        assert_eq!(
+            quotas.remaining_on(2, &QuotaScope::Tenant("analytics".to_string())).proofs,
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_backend_records_usage() {
+        let quotas = Arc::new(QuotaManager::new());
+        let tenant = QuotaScope::Tenant("relayer".to_string());
+        quotas.set_limits(
+            tenant.clone(),
+            QuotaLimits {
+                cycles_per_day: Some(5_000),
+                spend_per_day: Some(1_000),
+                ..Default::default()
+            },
+        );
+        let counter: CycleCounter = Arc::new(|_, input| Ok(input.len() as u64 * 1_000));
+        let backend = TenantQuotas::new(quotas.clone())
+            .with_cycle_counter(counter)
+            .with_pricing(Arc::new(|_, _| 400))
+            .wrap("relayer", Arc::new(MockBackend::default()));
+
+        backend.prove(b"elf", b"123").unwrap();
+        assert_eq!(
+            quotas.remaining(&tenant),
+            QuotaRemaining {
+                proofs: None,
+                cycles: Some(2_000),
+                spend: Some(600),
+            }
+        );
+        // Usage is recorded in full even where a proof overshoots
+        backend.prove(b"elf", b"123").unwrap();
+        assert_eq!(quotas.remaining(&tenant).cycles, Some(0));
+        assert_eq!(quotas.remaining(&QuotaScope::Program(program_hash(b"elf"))).proofs, None);
+
+        // Nothing is left to spend, so the next proof is rejected up front
+        let metered = QuotaBackend::new(Arc::new(MockBackend::default()), quotas.clone(), "relayer");
+        assert!(matches!(
+            metered.prove_metered(b"elf", b"1"),
+            Err(ProverError::QuotaExceeded { ref resource, .. }) if resource == "cycles"
+        ));
+        QuotaBackend::new(Arc::new(MockBackend::default()), quotas, "bridge")
+            .prove_metered(b"elf", b"1")
+            .unwrap();
+    }
+
+    #[test]
+    fn test_failed_proof_refunded() {
+        let quotas = Arc::new(QuotaManager::new());
+        let tenant = QuotaScope::Tenant("relayer".to_string());
+        quotas.set_limits(
+            tenant.clone(),
+            QuotaLimits {
+                proofs_per_day: Some(1),
+                ..Default::default()
+            },
+        );
+        let mock = Arc::new(MockBackend::default());
+        let metered = QuotaBackend::new(mock.clone(), quotas.clone(), "relayer");
+
+        mock.inject_failure(ZkError::Config("prover crashed".to_string()));
+        assert!(metered.prove_metered(b"elf", b"1").is_err());
+        assert_eq!(quotas.remaining(&tenant).proofs, Some(1));
+
+        metered.prove_metered(b"elf", b"1").unwrap();
+        assert_eq!(quotas.remaining(&tenant).proofs, Some(0));
+        assert!(matches!(
+            metered.prove_metered(b"elf", b"1"),
+            Err(ProverError::QuotaExceeded { .. })
+        ));
+    }
+}
@@ -0,0 +1,169 @@
+use crate::audit::now_ms;
+use crate::envelope::ProofEnvelope;
+use crate::types::{ProgramHash, ProverError};
+use ed25519_dalek::{Signer as _, Verifier as _};
+use sha3::{Digest, Sha3_256};
+
+/// Metadata key prefix for receipt fields
+pub const RECEIPT_PREFIX: &str = "receipt.";
+
+/// Domain separator for receipt signatures
+const RECEIPT_DOMAIN: &[u8] = b"frostgate-receipt-v1:";
+
+/// Key a prover instance signs receipts with
+pub enum ReceiptSigningKey {
+    Ed25519(ed25519_dalek::SigningKey),
+    Secp256k1(k256::ecdsa::SigningKey),
+}
+
+/// Public key used to check receipts
+#[derive(Debug, Clone)]
+pub enum ReceiptVerifyingKey {
+    Ed25519(ed25519_dalek::VerifyingKey),
+    Secp256k1(k256::ecdsa::VerifyingKey),
+}
+
+impl ReceiptSigningKey {
+    fn algorithm(&self) -> &'static str {
+        match self {
+            ReceiptSigningKey::Ed25519(_) => "ed25519",
+            ReceiptSigningKey::Secp256k1(_) => "secp256k1",
+        }
+    }
+
+    pub fn verifying_key(&self) -> ReceiptVerifyingKey {
+        match self {
+            ReceiptSigningKey::Ed25519(key) => ReceiptVerifyingKey::Ed25519(key.verifying_key()),
+            ReceiptSigningKey::Secp256k1(key) => ReceiptVerifyingKey::Secp256k1(*key.verifying_key()),
+        }
+    }
+}
+
+/// Attribution of a proof to the prover instance that produced it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofReceipt {
+    pub signer: String,
+    pub algorithm: String,
+    pub program_hash: ProgramHash,
+    pub proof_hash: String,
+    pub public_values_hash: String,
+    pub timestamp_ms: u64,
+}
+
+impl ProofReceipt {
+    fn for_envelope(envelope: &ProofEnvelope, signer: &str, algorithm: &str, timestamp_ms: u64) -> Self {
+        Self {
+            signer: signer.to_string(),
+            algorithm: algorithm.to_string(),
+            program_hash: envelope.program_hash.clone(),
+            proof_hash: hex::encode(Sha3_256::digest(&envelope.payload)),
+            public_values_hash: hex::encode(Sha3_256::digest(&envelope.public_values)),
+            timestamp_ms,
+        }
+    }
+
+    fn message(&self) -> Vec<u8> {
+        let fields = format!(
+            "{}:{}:{}:{}:{}:{}",
+            self.signer, self.algorithm, self.program_hash, self.proof_hash, self.public_values_hash, self.timestamp_ms
+        );
+        [RECEIPT_DOMAIN, fields.as_bytes()].concat()
+    }
+}
+
+/// Signs receipts for the proofs this instance produces
+pub struct ReceiptSigner {
+    signer_id: String,
+    key: ReceiptSigningKey,
+}
+
+impl ReceiptSigner {
+    pub fn new(signer_id: &str, key: ReceiptSigningKey) -> Self {
+        Self {
+            signer_id: signer_id.to_string(),
+            key,
+        }
+    }
+
+    /// Sign `(program_hash, proof_hash, public_values_hash, timestamp)` and
+    /// store the receipt in the envelope metadata
+    pub fn sign(&self, envelope: &mut ProofEnvelope) -> ProofReceipt {
+        let receipt = ProofReceipt::for_envelope(envelope, &self.signer_id, self.key.algorithm(), now_ms());
+        let message = receipt.message();
+        let signature = match &self.key {
+            ReceiptSigningKey::Ed25519(key) => key.sign(&message).to_bytes().to_vec(),
+            ReceiptSigningKey::Secp256k1(key) => {
+                let signature: k256::ecdsa::Signature = key.sign(&message);
+                signature.to_bytes().to_vec()
+            }
+        };
+        let mut set = |field: &str, value: String| {
+            envelope.metadata.insert(format!("{}{}", RECEIPT_PREFIX, field), value);
+        };
+        set("signer", receipt.signer.clone());
+        set("algorithm", receipt.algorithm.clone());
+        set("timestamp_ms", receipt.timestamp_ms.to_string());
+        set("signature", hex::encode(signature));
+        envelope.seal();
+        receipt
+    }
+}
+
+/// Check the receipt embedded in `envelope` against `key`, returning it if
+/// the signature covers the envelope's current contents
+pub fn verify_receipt(envelope: &ProofEnvelope, key: &ReceiptVerifyingKey) -> Result<ProofReceipt, ProverError> {
+    let field = |name: &str| {
+        envelope
+            .metadata
+            .get(&format!("{}{}", RECEIPT_PREFIX, name))
+            .ok_or_else(|| ProverError::SignatureInvalid(format!("receipt has no {}", name)))
+    };
+    let timestamp_ms = field("timestamp_ms")?
+        .parse::<u64>()
+        .map_err(|e| ProverError::SignatureInvalid(format!("bad receipt timestamp: {}", e)))?;
+    let receipt = ProofReceipt::for_envelope(envelope, field("signer")?, field("algorithm")?, timestamp_ms);
+    let signature = hex::decode(field("signature")?)
+        .map_err(|e| ProverError::SignatureInvalid(format!("bad receipt signature encoding: {}", e)))?;
+    let message = receipt.message();
+
+    let valid = match key {
+        ReceiptVerifyingKey::Ed25519(key) if receipt.algorithm == "ed25519" => {
+            ed25519_dalek::Signature::from_slice(&signature).is_ok_and(|sig| key.verify(&message, &sig).is_ok())
+        }
+        ReceiptVerifyingKey::Secp256k1(key) if receipt.algorithm == "secp256k1" => {
+            k256::ecdsa::Signature::from_slice(&signature).is_ok_and(|sig| key.verify(&message, &sig).is_ok())
+        }
+        _ => false,
+    };
+    if !valid {
+        return Err(ProverError::SignatureInvalid(format!(
+            "receipt from {} does not verify",
+            receipt.signer
+        )));
+    }
+    Ok(receipt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::canned_envelope;
+
+    #[test]
+    fn test_sign_and_verify() {
+        for key in [
+            ReceiptSigningKey::Ed25519(ed25519_dalek::SigningKey::from_bytes(&[7; 32])),
+            ReceiptSigningKey::Secp256k1(k256::ecdsa::SigningKey::from_slice(&[7; 32]).unwrap()),
+        ] {
+            let verifying_key = key.verifying_key();
+            let signer = ReceiptSigner::new("prover-eu-1", key);
+            let mut envelope = canned_envelope(b"elf", b"input");
+
+            let receipt = signer.sign(&mut envelope);
+            assert_eq!(verify_receipt(&envelope, &verifying_key).unwrap(), receipt);
+
+            envelope.public_values.push(0);
+            assert!(verify_receipt(&envelope, &verifying_key).is_err());
+        }
+    }
+}
@@ -3,22 +3,322 @@
 #![allow(dead_code)]
 #![allow(unused_imports)]
 
+use crate::config::{ConfigError, ConfigLayer};
+use crate::isolation::IsolatedBackend;
+use crate::external::{EXTERNAL_CAPABILITY, ExternalBackend};
+use crate::types::ProverError;
+use crate::wasm::WASM_CAPABILITY;
 use lazy_static::lazy_static;
 use frostgate_zkip::{
     ZkBackend, ZkBackendExt, ZkError, ZkResult,
     types::{HealthStatus, ResourceUsage, ZkConfig},
 };
-use std::sync::{Arc, Mutex};
-use std::collections::HashMap;
+use std::sync::{Arc, PoisonError, RwLock};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use serde::Deserialize;
 
 lazy_static::lazy_static! {
-    static ref REGISTRY: Mutex<BackendRegistry> = Mutex::new(BackendRegistry::new());
+    // Lookups share the read lock; it is never held across an await point, so
+    // callers on async executors only ever wait for a map operation
+    static ref REGISTRY: RwLock<BackendRegistry> = RwLock::new(BackendRegistry::new());
+}
+
+/// Features a backend may offer, used for discovery
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ZkCapability {
+    /// Proves locally on the CPU
+    LocalCpu,
+    /// Proves on a local GPU
+    Gpu,
+    /// Delegates proving to a remote network
+    Network,
+    /// Produces compressed STARK proofs
+    Compressed,
+    /// Produces PlonkBn254 proofs
+    Plonk,
+    /// Produces Groth16Bn254 proofs
+    Groth16,
+    /// Can verify proofs without a proving key
+    VerifyOnly,
+    /// Backend-specific capability, e.g. `Custom("halo2")` for a circuit backend
+    Custom(&'static str),
+}
+
+impl ZkCapability {
+    /// Parse a capability name as written in a [`RegistryConfig`]. Custom
+    /// capabilities are only recognized if listed in `custom`.
+    pub fn parse(name: &str, custom: &[&'static str]) -> Option<Self> {
+        match name {
+            "local-cpu" => Some(ZkCapability::LocalCpu),
+            "gpu" => Some(ZkCapability::Gpu),
+            "network" => Some(ZkCapability::Network),
+            "compressed" => Some(ZkCapability::Compressed),
+            "plonk" => Some(ZkCapability::Plonk),
+            "groth16" => Some(ZkCapability::Groth16),
+            "verify-only" => Some(ZkCapability::VerifyOnly),
+            other => custom.iter().find(|c| **c == other).map(|c| ZkCapability::Custom(c)),
+        }
+    }
+}
+
+/// Capabilities and last reported health of a registered backend
+#[derive(Debug, Clone)]
+pub struct BackendProfile {
+    pub capabilities: HashSet<ZkCapability>,
+    pub healthy: bool,
+    /// Current load in `0.0..=1.0`, lower is better
+    pub load: f32,
+}
+
+impl Default for BackendProfile {
+    fn default() -> Self {
+        Self {
+            capabilities: HashSet::new(),
+            healthy: true,
+            load: 0.0,
+        }
+    }
+}
+
+/// Wraps backends handed out by the registry, e.g. to add logging, metrics,
+/// auth or rate limiting around every caller's use of them
+pub trait BackendMiddleware: Send + Sync {
+    fn wrap(&self, id: &str, backend: Arc<dyn ZkBackend>) -> Arc<dyn ZkBackend>;
+}
+
+impl<F> BackendMiddleware for F
+where
+    F: Fn(&str, Arc<dyn ZkBackend>) -> Arc<dyn ZkBackend> + Send + Sync,
+{
+    fn wrap(&self, id: &str, backend: Arc<dyn ZkBackend>) -> Arc<dyn ZkBackend> {
+        self(id, backend)
+    }
+}
+
+/// Middleware logging every prove and verify call with its duration
+pub struct LoggingMiddleware;
+
+impl BackendMiddleware for LoggingMiddleware {
+    fn wrap(&self, id: &str, backend: Arc<dyn ZkBackend>) -> Arc<dyn ZkBackend> {
+        Arc::new(LoggedBackend {
+            id: id.to_string(),
+            inner: backend,
+        })
+    }
+}
+
+struct LoggedBackend {
+    id: String,
+    inner: Arc<dyn ZkBackend>,
+}
+
+impl ZkBackend for LoggedBackend {
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        let start = std::time::Instant::now();
+        let result = self.inner.prove(program, input);
+        tracing::info!("{}: prove finished in {:?}, ok: {}", self.id, start.elapsed(), result.is_ok());
+        result
+    }
+
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        let start = std::time::Instant::now();
+        let result = self.inner.verify(program, proof);
+        tracing::info!("{}: verify finished in {:?}, result: {:?}", self.id, start.elapsed(), result.as_ref().ok());
+        result
+    }
+}
+
+/// One backend declared in a [`RegistryConfig`]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BackendSpec {
+    pub id: String,
+    /// Factory building the backend: `sp1`, `external`, `mock` (test builds
+    /// only) or a kind added with [`BackendFactories::with_kind`]
+    pub kind: String,
+    /// Proof system the envelopes of `external` backends are tagged with
+    #[serde(default)]
+    pub proof_system: Option<String>,
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    /// Key holder and network settings, for kinds that launch a key holder
+    #[serde(default)]
+    pub prover: ConfigLayer,
+}
+
+/// Declarative set of backends, e.g. read from the `[[backends]]` tables of
+/// a TOML file
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RegistryConfig {
+    pub backends: Vec<BackendSpec>,
+}
+
+impl RegistryConfig {
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        toml::from_str(&text).map_err(|e| ConfigError::Parse {
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        })
+    }
+}
+
+/// Builds the backend described by a spec
+pub type BackendFactory = Arc<dyn Fn(&BackendSpec) -> Result<Arc<dyn ZkBackend>, ZkError> + Send + Sync>;
+
+/// Factories by backend kind, used to instantiate a [`RegistryConfig`]
+#[derive(Clone, Default)]
+pub struct BackendFactories {
+    factories: HashMap<String, BackendFactory>,
+    custom_capabilities: Vec<&'static str>,
+}
+
+impl BackendFactories {
+    /// No factories at all
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The built-in kinds: `sp1` launches a key holder from the spec's
+    /// `prover` settings, proving locally or on the network depending on
+    /// `use_network`; `external` launches another proof system's host the
+    /// same way, tagging proofs with the spec's `proof_system`; `mock` is
+    /// available in test builds
+    pub fn builtin() -> Self {
+        let factories = Self::new()
+            .with_kind(
+                "sp1",
+                Arc::new(|spec: &BackendSpec| -> Result<Arc<dyn ZkBackend>, ZkError> {
+                    let config = spec.prover.clone().resolve().map_err(|e| ZkError::Config(e.to_string()))?;
+                    let backend = IsolatedBackend::try_new(&config).map_err(ProverError::into_zk_error)?;
+                    Ok(Arc::new(backend))
+                }),
+            )
+            .with_kind(
+                "external",
+                Arc::new(|spec: &BackendSpec| -> Result<Arc<dyn ZkBackend>, ZkError> {
+                    let proof_system = spec.proof_system.as_deref().ok_or_else(|| {
+                        ZkError::Config(format!("Backend '{}' of kind external needs a proof_system", spec.id))
+                    })?;
+                    let key_holder = spec
+                        .prover
+                        .clone()
+                        .resolve()
+                        .and_then(|config| config.key_holder_config())
+                        .map_err(|e| ZkError::Config(e.to_string()))?;
+                    let backend = ExternalBackend::spawn(&key_holder, &spec.id, proof_system)
+                        .map_err(ProverError::into_zk_error)?;
+                    Ok(Arc::new(backend))
+                }),
+            )
+            .with_capability(EXTERNAL_CAPABILITY)
+            .with_capability(WASM_CAPABILITY);
+        #[cfg(any(test, feature = "testing"))]
+        let factories = factories.with_kind(
+            "mock",
+            Arc::new(|_: &BackendSpec| -> Result<Arc<dyn ZkBackend>, ZkError> {
+                Ok(Arc::new(crate::testing::MockBackend::default()))
+            }),
+        );
+        factories
+    }
+
+    pub fn with_kind(mut self, kind: &str, factory: BackendFactory) -> Self {
+        self.factories.insert(kind.to_string(), factory);
+        self
+    }
+
+    /// Accept a custom capability in spec capability lists, by its name
+    pub fn with_capability(mut self, capability: ZkCapability) -> Self {
+        if let ZkCapability::Custom(name) = capability {
+            self.custom_capabilities.push(name);
+        }
+        self
+    }
+
+    fn build(&self, spec: &BackendSpec) -> Result<(Arc<dyn ZkBackend>, HashSet<ZkCapability>), ZkError> {
+        let capabilities = spec
+            .capabilities
+            .iter()
+            .map(|name| {
+                ZkCapability::parse(name, &self.custom_capabilities)
+                    .ok_or_else(|| ZkError::Config(format!("Backend '{}' has unknown capability '{}'", spec.id, name)))
+            })
+            .collect::<Result<_, _>>()?;
+        let factory = self
+            .factories
+            .get(&spec.kind)
+            .ok_or_else(|| ZkError::Config(format!("Backend '{}' has unknown kind '{}'", spec.id, spec.kind)))?;
+        Ok((factory(spec)?, capabilities))
+    }
+}
+
+/// What a [`BackendRegistry::reload`] changed, by backend id
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReloadSummary {
+    pub added: Vec<String>,
+    /// Backends whose spec changed and were rebuilt
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+    pub unchanged: Vec<String>,
+}
+
+/// Backends built for a reload, before they are swapped in
+struct StagedReload {
+    /// Config-managed specs the reload was planned against
+    base: HashMap<String, BackendSpec>,
+    built: Vec<(BackendSpec, Arc<dyn ZkBackend>, HashSet<ZkCapability>)>,
+    removed: Vec<String>,
+    unchanged: Vec<String>,
+}
+
+impl StagedReload {
+    /// Build the backends that are new or whose spec changed. Nothing is
+    /// applied, so a failing factory leaves the registry as it was.
+    fn build(
+        base: HashMap<String, BackendSpec>,
+        config: &RegistryConfig,
+        factories: &BackendFactories,
+    ) -> Result<Self, ZkError> {
+        let mut seen = HashSet::new();
+        let mut built = Vec::new();
+        let mut unchanged = Vec::new();
+        for spec in &config.backends {
+            if !seen.insert(spec.id.as_str()) {
+                return Err(ZkError::Config(format!("Backend '{}' declared twice", spec.id)));
+            }
+            if base.get(&spec.id) == Some(spec) {
+                unchanged.push(spec.id.clone());
+                continue;
+            }
+            let (backend, capabilities) = factories.build(spec)?;
+            built.push((spec.clone(), backend, capabilities));
+        }
+        let mut removed: Vec<String> = base.keys().filter(|id| !seen.contains(id.as_str())).cloned().collect();
+        removed.sort();
+        unchanged.sort();
+        Ok(Self {
+            base,
+            built,
+            removed,
+            unchanged,
+        })
+    }
 }
 
 /// Registry for managing ZK backends
 #[derive(Default)]
 pub struct BackendRegistry {
     backends: HashMap<String, Arc<dyn ZkBackend>>,
+    profiles: HashMap<String, BackendProfile>,
+    middleware: Vec<Arc<dyn BackendMiddleware>>,
+    /// Specs of the backends managed by [`BackendRegistry::reload`]
+    specs: HashMap<String, BackendSpec>,
 }
 
 impl BackendRegistry {
@@ -26,7 +326,85 @@ impl BackendRegistry {
     pub fn new() -> Self {
         Self {
             backends: HashMap::new(),
+            profiles: HashMap::new(),
+            middleware: Vec::new(),
+            specs: HashMap::new(),
+        }
+    }
+
+    /// Instantiate and register every backend in `config`
+    pub fn from_config(config: &RegistryConfig, factories: &BackendFactories) -> Result<Self, ZkError> {
+        let mut registry = Self::new();
+        registry.reload(config, factories)?;
+        Ok(registry)
+    }
+
+    /// Bring the config-managed backends in line with `config`: new specs
+    /// are built and registered, changed ones rebuilt and swapped in, and
+    /// those no longer listed unregistered. Backends whose spec is unchanged
+    /// are kept as they are, along with their health reports. Callers
+    /// already holding a replaced or removed backend keep using it until
+    /// they drop it, so in-flight proofs aren't interrupted.
+    ///
+    /// Everything is built before anything is applied; if any backend fails
+    /// to build the registry is left untouched.
+    pub fn reload(&mut self, config: &RegistryConfig, factories: &BackendFactories) -> Result<ReloadSummary, ZkError> {
+        let staged = StagedReload::build(self.specs.clone(), config, factories)?;
+        self.apply(staged)
+    }
+
+    fn apply(&mut self, staged: StagedReload) -> Result<ReloadSummary, ZkError> {
+        if staged.base != self.specs {
+            return Err(ZkError::Config("Registry config changed during reload".to_string()));
         }
+        if let Some((spec, ..)) = staged
+            .built
+            .iter()
+            .find(|(spec, ..)| self.backends.contains_key(&spec.id) && !self.specs.contains_key(&spec.id))
+        {
+            return Err(ZkError::Config(format!("Backend '{}' already registered", spec.id)));
+        }
+
+        let mut summary = ReloadSummary {
+            removed: staged.removed,
+            unchanged: staged.unchanged,
+            ..ReloadSummary::default()
+        };
+        for id in &summary.removed {
+            self.unregister(id);
+        }
+        for (spec, backend, capabilities) in staged.built {
+            let id = spec.id.clone();
+            if self.specs.insert(id.clone(), spec).is_some() {
+                summary.updated.push(id.clone());
+            } else {
+                summary.added.push(id.clone());
+            }
+            self.backends.insert(id.clone(), backend);
+            self.profiles.insert(
+                id,
+                BackendProfile {
+                    capabilities,
+                    ..BackendProfile::default()
+                },
+            );
+        }
+        summary.added.sort();
+        summary.updated.sort();
+        Ok(summary)
+    }
+
+    /// Add middleware applied to every backend returned by lookups. The
+    /// first middleware added is the outermost layer.
+    pub fn add_middleware(&mut self, middleware: Arc<dyn BackendMiddleware>) {
+        self.middleware.push(middleware);
+    }
+
+    fn instrument(&self, id: &str, backend: Arc<dyn ZkBackend>) -> Arc<dyn ZkBackend> {
+        self.middleware
+            .iter()
+            .rev()
+            .fold(backend, |backend, middleware| middleware.wrap(id, backend))
     }
 
     /// Register a new backend
@@ -43,7 +421,8 @@ impl BackendRegistry {
 
     /// Get a backend by ID
     pub fn get(&self, id: &str) -> Option<Arc<dyn ZkBackend>> {
-        self.backends.get(id).cloned()
+        let backend = self.backends.get(id)?.clone();
+        Some(self.instrument(id, backend))
     }
 
     /// List all registered backend IDs
@@ -53,28 +432,160 @@ impl BackendRegistry {
 
     /// Remove a backend from the registry
     pub fn unregister(&mut self, id: &str) -> Option<Arc<dyn ZkBackend>> {
+        self.profiles.remove(id);
+        self.specs.remove(id);
         self.backends.remove(id)
     }
+
+    /// Register a backend along with the capabilities it offers
+    pub fn register_with_capabilities<B>(
+        &mut self,
+        id: String,
+        backend: Arc<B>,
+        capabilities: &[ZkCapability],
+    ) -> Result<(), ZkError>
+    where
+        B: ZkBackend + 'static,
+    {
+        self.register(id.clone(), backend)?;
+        self.profiles.insert(
+            id,
+            BackendProfile {
+                capabilities: capabilities.iter().copied().collect(),
+                ..BackendProfile::default()
+            },
+        );
+        Ok(())
+    }
+
+    /// Record the latest health check and load of a backend
+    pub fn report_health(&mut self, id: &str, healthy: bool, load: f32) -> Result<(), ZkError> {
+        if !self.backends.contains_key(id) {
+            return Err(ZkError::Config(format!("Backend '{}' is not registered", id)));
+        }
+        let profile = self.profiles.entry(id.to_string()).or_default();
+        profile.healthy = healthy;
+        profile.load = load.clamp(0.0, 1.0);
+        Ok(())
+    }
+
+    /// Profile of a backend, if it was registered
+    pub fn profile(&self, id: &str) -> Option<BackendProfile> {
+        self.backends
+            .contains_key(id)
+            .then(|| self.profiles.get(id).cloned().unwrap_or_default())
+    }
+
+    /// All healthy backends offering every capability in `required`
+    pub fn find_by_capability(&self, required: &[ZkCapability]) -> Vec<Arc<dyn ZkBackend>> {
+        self.matching(required)
+            .into_iter()
+            .filter_map(|(id, _)| self.get(id))
+            .collect()
+    }
+
+    /// The healthy backend with the lowest load among those offering every
+    /// capability in `required`
+    pub fn select_best(&self, required: &[ZkCapability]) -> Option<(String, Arc<dyn ZkBackend>)> {
+        self.matching(required)
+            .into_iter()
+            .min_by(|(a_id, a), (b_id, b)| a.load.total_cmp(&b.load).then_with(|| a_id.cmp(b_id)))
+            .and_then(|(id, _)| Some((id.clone(), self.get(id)?)))
+    }
+
+    /// Healthy backends offering every capability in `required`, by ID
+    fn matching(&self, required: &[ZkCapability]) -> Vec<(&String, &BackendProfile)> {
+        let mut matches: Vec<_> = self
+            .profiles
+            .iter()
+            .filter(|(id, profile)| {
+                profile.healthy
+                    && self.backends.contains_key(*id)
+                    && required.iter().all(|cap| profile.capabilities.contains(cap))
+            })
+            .collect();
+        matches.sort_by(|a, b| a.0.cmp(b.0));
+        matches
+    }
 }
 
 /// Register a backend globally
 pub fn register_backend<B: ZkBackend + 'static>(id: String, backend: Arc<B>) -> Result<(), ZkError> {
-    REGISTRY.lock().unwrap().register(id, backend)
+    REGISTRY.write().unwrap_or_else(PoisonError::into_inner).register(id, backend)
 }
 
 /// Get a backend by ID
 pub fn get_backend(id: &str) -> Option<Arc<dyn ZkBackend>> {
-    REGISTRY.lock().unwrap().get(id)
+    REGISTRY.read().unwrap_or_else(PoisonError::into_inner).get(id)
+}
+
+/// Add middleware applied to every backend returned by global lookups
+pub fn add_backend_middleware(middleware: Arc<dyn BackendMiddleware>) {
+    REGISTRY.write().unwrap_or_else(PoisonError::into_inner).add_middleware(middleware)
 }
 
 /// List all registered backend IDs
 pub fn list_backends() -> Vec<String> {
-    REGISTRY.lock().unwrap().list_backends()
+    REGISTRY.read().unwrap_or_else(PoisonError::into_inner).list_backends()
+}
+
+/// Register a backend globally along with its capabilities
+pub fn register_backend_with_capabilities<B: ZkBackend + 'static>(
+    id: String,
+    backend: Arc<B>,
+    capabilities: &[ZkCapability],
+) -> Result<(), ZkError> {
+    REGISTRY
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .register_with_capabilities(id, backend, capabilities)
+}
+
+/// Find healthy registered backends offering every capability in `required`
+pub fn find_backends_by_capability(required: &[ZkCapability]) -> Vec<Arc<dyn ZkBackend>> {
+    REGISTRY.read().unwrap_or_else(PoisonError::into_inner).find_by_capability(required)
+}
+
+/// Pick the best registered backend offering every capability in `required`
+pub fn select_best_backend(required: &[ZkCapability]) -> Option<(String, Arc<dyn ZkBackend>)> {
+    REGISTRY.read().unwrap_or_else(PoisonError::into_inner).select_best(required)
+}
+
+/// Profile of a globally registered backend
+pub fn backend_profile(id: &str) -> Option<BackendProfile> {
+    REGISTRY.read().unwrap_or_else(PoisonError::into_inner).profile(id)
+}
+
+/// Record the latest health check and load of a globally registered backend
+pub fn report_backend_health(id: &str, healthy: bool, load: f32) -> Result<(), ZkError> {
+    REGISTRY
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .report_health(id, healthy, load)
 }
 
 /// Remove a backend from the registry
 pub fn unregister_backend(id: &str) -> Option<Arc<dyn ZkBackend>> {
-    REGISTRY.lock().unwrap().unregister(id)
+    REGISTRY.write().unwrap_or_else(PoisonError::into_inner).unregister(id)
+}
+
+/// Reload the globally registered config-managed backends, see
+/// [`BackendRegistry::reload`]. Backends are built without holding the
+/// registry lock, so lookups continue while key holders start.
+pub fn reload_backends(config: &RegistryConfig, factories: &BackendFactories) -> Result<ReloadSummary, ZkError> {
+    let base = REGISTRY.read().unwrap_or_else(PoisonError::into_inner).specs.clone();
+    let staged = StagedReload::build(base, config, factories)?;
+    REGISTRY.write().unwrap_or_else(PoisonError::into_inner).apply(staged)
+}
+
+/// Register a backend globally, from async code
+pub async fn register<B: ZkBackend + 'static>(id: String, backend: Arc<B>) -> Result<(), ZkError> {
+    register_backend(id, backend)
+}
+
+/// Get a backend by ID, from async code
+pub async fn get(id: &str) -> Option<Arc<dyn ZkBackend>> {
+    get_backend(id)
 }
 
 #[cfg(test)]
@@ -110,4 +621,145 @@ mod tests {
         let removed = registry.unregister("mock").unwrap();
         assert!(registry.get("mock").is_none());
     }
+
+    #[test]
+    fn test_capability_selection() {
+        let mut registry = BackendRegistry::new();
+        registry
+            .register_with_capabilities("cpu".to_string(), Arc::new(MockBackend), &[ZkCapability::LocalCpu, ZkCapability::Groth16])
+            .unwrap();
+        registry
+            .register_with_capabilities("network".to_string(), Arc::new(MockBackend), &[ZkCapability::Network, ZkCapability::Groth16])
+            .unwrap();
+
+        assert_eq!(registry.find_by_capability(&[ZkCapability::Groth16]).len(), 2);
+        assert_eq!(registry.find_by_capability(&[ZkCapability::Gpu]).len(), 0);
+
+        registry
+            .register_with_capabilities("halo2".to_string(), Arc::new(MockBackend), &[ZkCapability::Custom("halo2")])
+            .unwrap();
+        assert_eq!(registry.find_by_capability(&[ZkCapability::Custom("halo2")]).len(), 1);
+
+        registry.report_health("cpu", true, 0.9).unwrap();
+        registry.report_health("network", true, 0.1).unwrap();
+        assert_eq!(registry.select_best(&[ZkCapability::Groth16]).unwrap().0, "network");
+
+        registry.report_health("network", false, 0.1).unwrap();
+        assert_eq!(registry.select_best(&[ZkCapability::Groth16]).unwrap().0, "cpu");
+        assert_eq!(registry.find_by_capability(&[ZkCapability::Groth16]).len(), 1);
+        assert!(registry.find_by_capability(&[ZkCapability::Network]).is_empty());
+    }
+
+    struct Tagged(&'static [u8], Arc<dyn ZkBackend>);
+
+    impl ZkBackend for Tagged {
+        fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+            Ok([self.1.prove(program, input)?, self.0.to_vec()].concat())
+        }
+
+        fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+            self.1.verify(program, proof)
+        }
+    }
+
+    #[test]
+    fn test_middleware_order() {
+        let mut registry = BackendRegistry::new();
+        registry.register("mock".to_string(), Arc::new(MockBackend)).unwrap();
+        registry.add_middleware(Arc::new(|_: &str, b: Arc<dyn ZkBackend>| -> Arc<dyn ZkBackend> { Arc::new(Tagged(b"outer", b)) }));
+        registry.add_middleware(Arc::new(|_: &str, b: Arc<dyn ZkBackend>| -> Arc<dyn ZkBackend> { Arc::new(Tagged(b"inner", b)) }));
+
+        let proof = registry.get("mock").unwrap().prove(b"elf", b"input").unwrap();
+        assert_eq!(proof, [&[1, 2, 3][..], b"inner", b"outer"].concat());
+    }
+
+    fn spec(id: &str, kind: &str, capabilities: &[&str]) -> BackendSpec {
+        BackendSpec {
+            id: id.to_string(),
+            kind: kind.to_string(),
+            proof_system: None,
+            capabilities: capabilities.iter().map(|c| c.to_string()).collect(),
+            prover: ConfigLayer::default(),
+        }
+    }
+
+    #[test]
+    fn test_reload_from_config() {
+        let builds = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let factories = {
+            let builds = builds.clone();
+            BackendFactories::new().with_capability(ZkCapability::Custom("halo2")).with_kind(
+                "counted",
+                Arc::new(move |_: &BackendSpec| -> Result<Arc<dyn ZkBackend>, ZkError> {
+                    builds.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    Ok(Arc::new(MockBackend))
+                }),
+            )
+        };
+        let config: RegistryConfig = toml::from_str(
+            r#"
+            [[backends]]
+            id = "local"
+            kind = "counted"
+            capabilities = ["local-cpu", "groth16"]
+
+            [[backends]]
+            id = "remote"
+            kind = "counted"
+            capabilities = ["network"]
+            "#,
+        )
+        .unwrap();
+        let mut registry = BackendRegistry::from_config(&config, &factories).unwrap();
+        registry.register("manual".to_string(), Arc::new(MockBackend)).unwrap();
+        registry.report_health("local", true, 0.5).unwrap();
+        let local = registry.backends["local"].clone();
+        assert_eq!(builds.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+        let reloaded = RegistryConfig {
+            backends: vec![
+                spec("local", "counted", &["local-cpu", "groth16"]),
+                spec("circuit", "counted", &["halo2"]),
+            ],
+        };
+        let summary = registry.reload(&reloaded, &factories).unwrap();
+        assert_eq!(summary.added, ["circuit"]);
+        assert_eq!(summary.removed, ["remote"]);
+        assert_eq!(summary.unchanged, ["local"]);
+        assert_eq!(builds.load(std::sync::atomic::Ordering::SeqCst), 3);
+        assert!(Arc::ptr_eq(&registry.backends["local"], &local));
+        assert_eq!(registry.profile("local").unwrap().load, 0.5);
+        assert!(registry.get("remote").is_none());
+        assert!(registry.get("manual").is_some());
+        assert_eq!(registry.find_by_capability(&[ZkCapability::Custom("halo2")]).len(), 1);
+
+        let changed = RegistryConfig {
+            backends: vec![spec("local", "counted", &["local-cpu"])],
+        };
+        let summary = registry.reload(&changed, &factories).unwrap();
+        assert_eq!(summary.updated, ["local"]);
+        assert!(!Arc::ptr_eq(&registry.backends["local"], &local));
+
+        let invalid = [
+            RegistryConfig { backends: vec![spec("manual", "counted", &[])] },
+            RegistryConfig { backends: vec![spec("local", "sp2", &[])] },
+            RegistryConfig { backends: vec![spec("local", "counted", &["quantum"])] },
+        ];
+        for config in &invalid {
+            assert!(registry.reload(config, &factories).is_err());
+        }
+        // External backends must say which proof system they tag proofs with
+        assert!(BackendFactories::builtin().build(&spec("risc0", "external", &["external"])).is_err());
+        assert_eq!(registry.specs, changed.backends.iter().map(|s| (s.id.clone(), s.clone())).collect());
+    }
+
+    #[tokio::test]
+    async fn test_async_registry() {
+        register("async-mock".to_string(), Arc::new(MockBackend)).await.unwrap();
+        let lookups = (0..8).map(|_| tokio::spawn(async { get("async-mock").await.is_some() }));
+        for lookup in lookups {
+            assert!(lookup.await.unwrap());
+        }
+        unregister_backend("async-mock");
+    }
 }
\ No newline at end of file
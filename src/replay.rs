@@ -0,0 +1,163 @@
+use crate::envelope::ProofEnvelope;
+use crate::types::{ProverError, ct_eq};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+use std::collections::HashSet;
+use std::sync::{Mutex, PoisonError};
+
+/// Length of the tag digest committed at the start of the public values
+pub const TAG_DIGEST_LEN: usize = 32;
+
+/// Metadata key holding the domain of an envelope's replay tag
+pub const REPLAY_DOMAIN_KEY: &str = "replay_domain";
+
+/// Metadata key holding the hex nonce of an envelope's replay tag
+pub const REPLAY_NONCE_KEY: &str = "replay_nonce";
+
+/// Caller-supplied domain and nonce binding a proof to one message on one chain
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ReplayTag {
+    /// Domain separator, e.g. `"frostgate:eth-mainnet:bridge-v1"`
+    pub domain: String,
+    /// Unique per message, e.g. the message id
+    pub nonce: Vec<u8>,
+}
+
+impl ReplayTag {
+    pub fn new(domain: &str, nonce: &[u8]) -> Self {
+        Self {
+            domain: domain.to_string(),
+            nonce: nonce.to_vec(),
+        }
+    }
+
+    /// Digest the guest commits as the first bytes of its public values
+    pub fn digest(&self) -> [u8; TAG_DIGEST_LEN] {
+        let mut hasher = Sha3_256::new();
+        hasher.update((self.domain.len() as u64).to_le_bytes());
+        hasher.update(self.domain.as_bytes());
+        hasher.update(&self.nonce);
+        hasher.finalize().into()
+    }
+
+    /// Public values the guest is expected to commit: tag digest, then `public_inputs`
+    pub fn bind(&self, public_inputs: &[u8]) -> Vec<u8> {
+        let mut bound = self.digest().to_vec();
+        bound.extend_from_slice(public_inputs);
+        bound
+    }
+
+    /// Check that committed public values carry this tag, returning the
+    /// application public values that follow it
+    pub fn check<'a>(&self, committed: &'a [u8]) -> Result<&'a [u8], ProverError> {
+        if committed.len() < TAG_DIGEST_LEN || !ct_eq(&committed[..TAG_DIGEST_LEN], &self.digest()) {
+            return Err(ProverError::ReplayDetected(format!(
+                "proof is not bound to domain '{}' and nonce {}",
+                self.domain,
+                hex::encode(&self.nonce)
+            )));
+        }
+        Ok(&committed[TAG_DIGEST_LEN..])
+    }
+
+    /// Record the tag in `envelope`'s metadata, covered by its checksum
+    pub fn stamp(&self, envelope: &mut ProofEnvelope) {
+        envelope.insert_metadata(REPLAY_DOMAIN_KEY, self.domain.clone());
+        envelope.insert_metadata(REPLAY_NONCE_KEY, hex::encode(&self.nonce));
+    }
+
+    /// The tag recorded in `envelope`, if it carries one
+    pub fn from_envelope(envelope: &ProofEnvelope) -> Result<Option<Self>, ProverError> {
+        let domain = envelope.metadata.get(REPLAY_DOMAIN_KEY);
+        let nonce = envelope.metadata.get(REPLAY_NONCE_KEY);
+        match (domain, nonce) {
+            (None, None) => Ok(None),
+            (Some(domain), Some(nonce)) => {
+                let nonce = hex::decode(nonce)
+                    .map_err(|e| ProverError::MalformedEnvelope(format!("replay nonce is not hex: {}", e)))?;
+                Ok(Some(Self {
+                    domain: domain.clone(),
+                    nonce,
+                }))
+            }
+            _ => Err(ProverError::MalformedEnvelope(
+                "replay tag needs both a domain and a nonce".to_string(),
+            )),
+        }
+    }
+}
+
+/// Remembers consumed tags so a proof is accepted at most once per process
+#[derive(Default)]
+pub struct ReplayGuard {
+    seen: Mutex<HashSet<ReplayTag>>,
+}
+
+impl ReplayGuard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check the committed public values against `tag` and mark it consumed
+    pub fn accept<'a>(&self, tag: &ReplayTag, committed: &'a [u8]) -> Result<&'a [u8], ProverError> {
+        let public_values = tag.check(committed)?;
+        self.consume(tag)?;
+        Ok(public_values)
+    }
+
+    /// Mark `tag` consumed, failing if it already was
+    pub fn consume(&self, tag: &ReplayTag) -> Result<(), ProverError> {
+        if !self.seen.lock().unwrap_or_else(PoisonError::into_inner).insert(tag.clone()) {
+            return Err(ProverError::ReplayDetected(format!(
+                "nonce {} already used in domain '{}'",
+                hex::encode(&tag.nonce),
+                tag.domain
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tag_binding() {
+        let tag = ReplayTag::new("frostgate:test", b"message-1");
+        let committed = tag.bind(b"payload");
+        assert_eq!(tag.check(&committed).unwrap(), b"payload");
+
+        let other_message = ReplayTag::new("frostgate:test", b"message-2");
+        assert!(other_message.check(&committed).is_err());
+        let other_chain = ReplayTag::new("frostgate:other", b"message-1");
+        assert!(other_chain.check(&committed).is_err());
+    }
+
+    #[test]
+    fn test_guard_rejects_reuse() {
+        let guard = ReplayGuard::new();
+        let tag = ReplayTag::new("frostgate:test", b"message-1");
+        let committed = tag.bind(b"payload");
+
+        guard.accept(&tag, &committed).unwrap();
+        assert!(matches!(guard.accept(&tag, &committed), Err(ProverError::ReplayDetected(_))));
+    }
+
+    #[test]
+    fn test_envelope_metadata() {
+        let tag = ReplayTag::new("frostgate:test", b"message-1");
+        let mut envelope = ProofEnvelope::new("mock", "hash".to_string(), b"proof".to_vec(), tag.bind(b"payload"));
+        assert_eq!(ReplayTag::from_envelope(&envelope).unwrap(), None);
+
+        tag.stamp(&mut envelope);
+        assert!(envelope.checksum_valid());
+        assert_eq!(ReplayTag::from_envelope(&envelope).unwrap(), Some(tag));
+
+        envelope.metadata.remove(REPLAY_NONCE_KEY);
+        assert!(matches!(
+            ReplayTag::from_envelope(&envelope),
+            Err(ProverError::MalformedEnvelope(_))
+        ));
+    }
+}
@@ -0,0 +1,222 @@
+use frostgate_zkip::{ZkBackend, ZkError};
+use serde::Serialize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Instant;
+
+/// Kernel clock ticks per second used by `/proc/<pid>/stat` on Linux
+const CLOCK_TICKS_PER_SEC: f64 = 100.0;
+
+/// Point-in-time resource usage of this process and node. Fields are `None`
+/// where the platform doesn't expose them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ResourceSnapshot {
+    /// CPU used by this process since the previous sample, in percent of one core
+    pub cpu_percent: Option<f64>,
+    pub rss_bytes: Option<u64>,
+    pub available_memory_bytes: Option<u64>,
+    pub total_memory_bytes: Option<u64>,
+    pub cpu_cores: usize,
+    /// Proofs currently running
+    pub active_tasks: usize,
+    /// Proof requests waiting for a slot
+    pub queue_depth: usize,
+    /// Requests submitted to a proving network and not yet fulfilled
+    pub pending_remote_jobs: usize,
+}
+
+/// Tracks running and queued proofs and samples process resources from procfs
+pub struct ResourceTracker {
+    active: AtomicUsize,
+    queued: AtomicUsize,
+    remote: AtomicUsize,
+    last_cpu_sample: Mutex<Option<(Instant, u64)>>,
+}
+
+impl Default for ResourceTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ResourceTracker {
+    pub fn new() -> Self {
+        Self {
+            active: AtomicUsize::new(0),
+            queued: AtomicUsize::new(0),
+            remote: AtomicUsize::new(0),
+            last_cpu_sample: Mutex::new(None),
+        }
+    }
+
+    /// Count a proof as running until the guard is dropped
+    pub fn track_task(&self) -> TaskGuard<'_> {
+        self.active.fetch_add(1, Ordering::SeqCst);
+        TaskGuard { counter: &self.active }
+    }
+
+    /// Count a request as queued until the guard is dropped
+    pub fn track_queued(&self) -> TaskGuard<'_> {
+        self.queued.fetch_add(1, Ordering::SeqCst);
+        TaskGuard { counter: &self.queued }
+    }
+
+    /// Count a network request as pending until the guard is dropped
+    pub fn track_remote(&self) -> TaskGuard<'_> {
+        self.remote.fetch_add(1, Ordering::SeqCst);
+        TaskGuard { counter: &self.remote }
+    }
+
+    pub fn active_tasks(&self) -> usize {
+        self.active.load(Ordering::SeqCst)
+    }
+
+    pub fn queue_depth(&self) -> usize {
+        self.queued.load(Ordering::SeqCst)
+    }
+
+    pub fn pending_remote_jobs(&self) -> usize {
+        self.remote.load(Ordering::SeqCst)
+    }
+
+    /// Sample current usage. CPU is averaged over the time since the
+    /// previous call, so the first sample reports none.
+    pub fn sample(&self) -> ResourceSnapshot {
+        let meminfo = std::fs::read_to_string("/proc/meminfo").ok();
+        let meminfo_field = |name: &str| {
+            meminfo.as_deref().and_then(|info| {
+                info.lines()
+                    .find_map(|line| line.strip_prefix(name))
+                    .and_then(parse_kb)
+            })
+        };
+
+        ResourceSnapshot {
+            cpu_percent: self.cpu_percent(),
+            rss_bytes: read_rss_bytes(),
+            available_memory_bytes: meminfo_field("MemAvailable:"),
+            total_memory_bytes: meminfo_field("MemTotal:"),
+            cpu_cores: num_cpus::get(),
+            active_tasks: self.active_tasks(),
+            queue_depth: self.queue_depth(),
+            pending_remote_jobs: self.pending_remote_jobs(),
+        }
+    }
+
+    fn cpu_percent(&self) -> Option<f64> {
+        let ticks = read_cpu_ticks()?;
+        let now = Instant::now();
+        let mut last = self.last_cpu_sample.lock().unwrap_or_else(PoisonError::into_inner);
+        let previous = last.replace((now, ticks));
+        let (then, then_ticks) = previous?;
+        let wall = now.duration_since(then).as_secs_f64();
+        if wall <= 0.0 {
+            return None;
+        }
+        let cpu = ticks.saturating_sub(then_ticks) as f64 / CLOCK_TICKS_PER_SEC;
+        Some(cpu / wall * 100.0)
+    }
+}
+
+/// Decrements a [`ResourceTracker`] counter on drop
+pub struct TaskGuard<'a> {
+    counter: &'a AtomicUsize,
+}
+
+impl Drop for TaskGuard<'_> {
+    fn drop(&mut self) {
+        self.counter.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+fn parse_kb(field: &str) -> Option<u64> {
+    field
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(|kb| kb * 1024)
+}
+
+/// `VmRSS` from `/proc/self/status`
+pub(crate) fn read_rss_bytes() -> Option<u64> {
+    read_status_rss("/proc/self/status")
+}
+
+/// Resident set size of process `pid`, e.g. a key holder
+pub(crate) fn read_process_rss_bytes(pid: u32) -> Option<u64> {
+    read_status_rss(&format!("/proc/{}/status", pid))
+}
+
+fn read_status_rss(path: &str) -> Option<u64> {
+    let status = std::fs::read_to_string(path).ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))
+        .and_then(parse_kb)
+}
+
+/// User plus system time of this process from `/proc/self/stat`, in clock ticks
+fn read_cpu_ticks() -> Option<u64> {
+    let stat = std::fs::read_to_string("/proc/self/stat").ok()?;
+    // The command name may contain spaces; fields after it are positional
+    let rest = &stat[stat.rfind(')')? + 2..];
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+    // utime and stime are fields 14 and 15, i.e. 11 and 12 after the name
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    Some(utime + stime)
+}
+
+/// Backend wrapper that reports its running proofs to a [`ResourceTracker`]
+pub struct TrackedBackend {
+    inner: Arc<dyn ZkBackend>,
+    tracker: Arc<ResourceTracker>,
+}
+
+impl TrackedBackend {
+    pub fn new(inner: Arc<dyn ZkBackend>, tracker: Arc<ResourceTracker>) -> Self {
+        Self { inner, tracker }
+    }
+}
+
+impl ZkBackend for TrackedBackend {
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        let _task = self.tracker.track_task();
+        self.inner.prove(program, input)
+    }
+
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        self.inner.verify(program, proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_counting() {
+        let tracker = ResourceTracker::new();
+        {
+            let _running = tracker.track_task();
+            let _waiting = tracker.track_queued();
+            let snapshot = tracker.sample();
+            assert_eq!(snapshot.active_tasks, 1);
+            assert_eq!(snapshot.queue_depth, 1);
+        }
+        assert_eq!(tracker.active_tasks(), 0);
+        assert_eq!(tracker.queue_depth(), 0);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_reads_procfs() {
+        let tracker = ResourceTracker::new();
+        let snapshot = tracker.sample();
+        assert!(snapshot.rss_bytes.unwrap() > 0);
+        assert!(snapshot.total_memory_bytes.unwrap() >= snapshot.available_memory_bytes.unwrap());
+        assert!(tracker.sample().cpu_percent.is_some());
+    }
+}
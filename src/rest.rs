@@ -0,0 +1,529 @@
+use crate::jobs::{JobId, JobStatus, ProverJobManager};
+use crate::metrics::ProverMetrics;
+use crate::quotas::DEFAULT_TENANT;
+use crate::registry;
+use crate::server_state::ServerState;
+use crate::signatures::ProgramSignature;
+use crate::types::{ErrorReport, ProveRequest, ProverError};
+use axum::body::Bytes;
+use axum::extract::{DefaultBodyLimit, Path, State};
+use axum::http::{HeaderMap, HeaderValue, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use utoipa::{OpenApi, ToSchema};
+
+/// OpenAPI document of the REST API, served at `/openapi.json`
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Frostgate prover",
+        description = "Prove and verify with the backends registered in a frostgate prover. Binary fields are hex encoded."
+    ),
+    paths(health, metrics, submit_program, submit_job, job_status, cancel_job, fetch_proof, verify_proof)
+)]
+pub struct ApiDoc;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BackendHealth {
+    pub id: String,
+    pub healthy: bool,
+    /// Last reported load in `0.0..=1.0`
+    pub load: f32,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct HealthResponse {
+    pub status: String,
+    pub backends: Vec<BackendHealth>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SubmitProgramResponse {
+    pub program_hash: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SubmitJobRequest {
+    pub backend: String,
+    /// Hash returned by `POST /programs`
+    pub program_hash: String,
+    pub input_hex: String,
+    /// Resubmitting a key with the same program and input returns the
+    /// original job instead of proving again
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SubmitJobResponse {
+    pub job_id: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct JobStatusResponse {
+    pub state: JobState,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CancelJobResponse {
+    pub cancelled: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ProofResponse {
+    pub proof_hex: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct VerifyProofRequest {
+    pub backend: String,
+    pub program_hash: String,
+    pub proof_hex: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct VerifyProofResponse {
+    pub valid: bool,
+}
+
+/// A failed request: the HTTP status plus an [`ErrorReport`] body
+pub struct ApiError {
+    status: StatusCode,
+    error: ProverError,
+}
+
+impl ApiError {
+    fn not_found(message: String) -> Self {
+        Self {
+            status: StatusCode::NOT_FOUND,
+            error: ProverError::Other(message),
+        }
+    }
+
+    fn bad_request(message: String) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            error: ProverError::Other(message),
+        }
+    }
+}
+
+impl From<ProverError> for ApiError {
+    fn from(error: ProverError) -> Self {
+        let status = match &error {
+            ProverError::ProgramNotFound => StatusCode::NOT_FOUND,
+            ProverError::InvalidProgram(_)
+            | ProverError::PublicInputsMismatch
+            | ProverError::MalformedEnvelope(_)
+            | ProverError::UnsupportedProofVersion(_) => StatusCode::BAD_REQUEST,
+            ProverError::PolicyViolation(_)
+            | ProverError::SignatureInvalid(_)
+            | ProverError::ReplayDetected(_)
+            | ProverError::VkeyMismatch { .. } => StatusCode::FORBIDDEN,
+            ProverError::IdempotencyConflict { .. } | ProverError::Cancelled => StatusCode::CONFLICT,
+            ProverError::QueueFull
+            | ProverError::Busy { .. }
+            | ProverError::InsufficientCapacity(_)
+            | ProverError::BudgetExceeded { .. }
+            | ProverError::QuotaExceeded { .. } => StatusCode::TOO_MANY_REQUESTS,
+            ProverError::NetworkUnavailable(_) | ProverError::Degraded(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ProverError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        Self { status, error }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let report = self.error.to_report();
+        let mut response = (self.status, Json(report)).into_response();
+        if let Some(retry_after) = self.error.retry_after() {
+            let seconds = retry_after.as_millis().div_ceil(1000) as u64;
+            response.headers_mut().insert(header::RETRY_AFTER, seconds.into());
+        }
+        response
+    }
+}
+
+/// Header naming the tenant a job is charged to, set by an authenticating
+/// proxy in front of the server. Jobs without it are charged to
+/// [`DEFAULT_TENANT`].
+pub const TENANT_HEADER: &str = "x-frostgate-tenant";
+
+/// Header carrying a signature over an uploaded program, as
+/// `<signer>:<hex signature>`; may be repeated
+pub const PROGRAM_SIGNATURE_HEADER: &str = "x-frostgate-program-signature";
+
+/// Serves the global backend registry over HTTP, the REST counterpart of
+/// the gRPC [`ProverService`](crate::service::ProverService)
+pub struct RestServer {
+    state: Arc<ServerState>,
+    metrics: Option<Arc<ProverMetrics>>,
+}
+
+impl Default for RestServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RestServer {
+    pub fn new() -> Self {
+        Self::with_state(Arc::new(ServerState::new()))
+    }
+
+    /// Serve `state`, e.g. shared with a
+    /// [`ProverService`](crate::service::ProverService)
+    pub fn with_state(state: Arc<ServerState>) -> Self {
+        Self { state, metrics: None }
+    }
+
+    /// Expose `metrics` at `/metrics`
+    pub fn with_metrics(mut self, metrics: Arc<ProverMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub fn router(self) -> Router {
+        // Inputs and proofs travel hex encoded, at twice their size
+        let body_limit = self.state.max_program_bytes().saturating_mul(2);
+        Router::new()
+            .route("/health", get(health))
+            .route("/metrics", get(metrics))
+            .route("/programs", post(submit_program))
+            .route("/jobs", post(submit_job))
+            .route("/jobs/:id", get(job_status).delete(cancel_job))
+            .route("/proofs/:job_id", get(fetch_proof))
+            .route("/proofs/verify", post(verify_proof))
+            .route("/openapi.json", get(|| async { Json(ApiDoc::openapi()) }))
+            .layer(DefaultBodyLimit::max(body_limit))
+            .with_state(Arc::new(self))
+    }
+
+    /// Serve on `listener` until the task is dropped
+    pub async fn serve(self, listener: TcpListener) -> std::io::Result<()> {
+        axum::serve(listener, self.router()).await
+    }
+
+    fn program(&self, hash: &str) -> Result<Arc<Vec<u8>>, ApiError> {
+        self.state
+            .program(hash)
+            .ok_or_else(|| ApiError::not_found(format!("unknown program {}", hash)))
+    }
+
+    fn manager(&self, backend: &str, tenant: &str) -> Result<Arc<ProverJobManager>, ApiError> {
+        self.state
+            .manager(backend, tenant)
+            .ok_or_else(|| ApiError::not_found(format!("unknown backend {}", backend)))
+    }
+
+    fn job(&self, job_id: &str) -> Result<(JobId, Arc<ProverJobManager>), ApiError> {
+        let id: JobId = job_id.parse().map_err(|e: ProverError| ApiError::bad_request(e.to_string()))?;
+        let manager = self
+            .state
+            .job(id)
+            .ok_or_else(|| ApiError::not_found(format!("unknown job {}", job_id)))?;
+        Ok((id, manager))
+    }
+}
+
+fn decode_hex(field: &str, value: &str) -> Result<Vec<u8>, ApiError> {
+    hex::decode(value).map_err(|e| ApiError::bad_request(format!("{} is not valid hex: {}", field, e)))
+}
+
+/// Registered backends and their last reported health
+#[utoipa::path(get, path = "/health", responses((status = 200, body = HealthResponse)))]
+async fn health() -> Json<HealthResponse> {
+    let mut ids = registry::list_backends();
+    ids.sort();
+    let backends = ids
+        .into_iter()
+        .map(|id| {
+            let profile = registry::backend_profile(&id).unwrap_or_default();
+            BackendHealth {
+                id,
+                healthy: profile.healthy,
+                load: profile.load,
+            }
+        })
+        .collect();
+    Json(HealthResponse {
+        status: "ok".to_string(),
+        backends,
+    })
+}
+
+/// Prometheus metrics in the text exposition format
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    responses(
+        (status = 200, body = String, content_type = "text/plain; version=0.0.4"),
+        (status = 404, description = "Metrics aren't enabled", body = ErrorReport)
+    )
+)]
+async fn metrics(State(server): State<Arc<RestServer>>) -> Result<Response, ApiError> {
+    let metrics = server
+        .metrics
+        .as_ref()
+        .ok_or_else(|| ApiError::not_found("metrics aren't enabled".to_string()))?;
+    let text = metrics.gather()?;
+    Ok(([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], text).into_response())
+}
+
+/// Upload a program ELF; jobs refer to it by the returned hash
+#[utoipa::path(
+    post,
+    path = "/programs",
+    request_body(content = String, content_type = "application/octet-stream", description = "Program ELF"),
+    params((
+        "x-frostgate-program-signature" = Option<String>,
+        Header,
+        description = "`<signer>:<hex signature>`, needed when the server enforces program signatures; may be repeated"
+    )),
+    responses(
+        (status = 201, body = SubmitProgramResponse),
+        (status = 400, body = ErrorReport),
+        (status = 403, description = "The program isn't signed by an allowed signer", body = ErrorReport)
+    )
+)]
+async fn submit_program(
+    State(server): State<Arc<RestServer>>,
+    headers: HeaderMap,
+    program: Bytes,
+) -> Result<(StatusCode, Json<SubmitProgramResponse>), ApiError> {
+    let signatures = headers
+        .get_all(PROGRAM_SIGNATURE_HEADER)
+        .iter()
+        .map(parse_signature)
+        .collect::<Result<Vec<_>, _>>()?;
+    let hash = server.state.submit_program(program.to_vec(), signatures)?;
+    Ok((StatusCode::CREATED, Json(SubmitProgramResponse { program_hash: hash })))
+}
+
+fn parse_signature(value: &HeaderValue) -> Result<ProgramSignature, ApiError> {
+    let invalid = || ApiError::bad_request(format!("{} must be <signer>:<hex signature>", PROGRAM_SIGNATURE_HEADER));
+    let (signer, signature) = value.to_str().ok().and_then(|v| v.split_once(':')).ok_or_else(invalid)?;
+    let signature = decode_hex(PROGRAM_SIGNATURE_HEADER, signature)?
+        .try_into()
+        .map_err(|_| invalid())?;
+    Ok(ProgramSignature {
+        signer: signer.to_string(),
+        signature,
+    })
+}
+
+/// Start proving in the background
+#[utoipa::path(
+    post,
+    path = "/jobs",
+    request_body = SubmitJobRequest,
+    params(("x-frostgate-tenant" = Option<String>, Header, description = "Tenant charged for the job")),
+    responses(
+        (status = 202, body = SubmitJobResponse),
+        (status = 403, description = "The input breaks the program's policy", body = ErrorReport),
+        (status = 404, description = "Unknown program or backend", body = ErrorReport),
+        (status = 409, description = "Idempotency key reused for another request", body = ErrorReport)
+    )
+)]
+async fn submit_job(
+    State(server): State<Arc<RestServer>>,
+    headers: HeaderMap,
+    Json(request): Json<SubmitJobRequest>,
+) -> Result<(StatusCode, Json<SubmitJobResponse>), ApiError> {
+    let program = server.program(&request.program_hash)?;
+    let input = decode_hex("input_hex", &request.input_hex)?;
+    let tenant = headers
+        .get(TENANT_HEADER)
+        .and_then(|tenant| tenant.to_str().ok())
+        .unwrap_or(DEFAULT_TENANT);
+    let manager = server.manager(&request.backend, tenant)?;
+    let id = server.state.submit_job(
+        manager,
+        tenant,
+        &ProveRequest::new(program.to_vec(), input),
+        request.idempotency_key.as_deref(),
+    )?;
+    Ok((StatusCode::ACCEPTED, Json(SubmitJobResponse { job_id: id.to_string() })))
+}
+
+#[utoipa::path(
+    get,
+    path = "/jobs/{id}",
+    params(("id" = String, Path, description = "Job id")),
+    responses(
+        (status = 200, body = JobStatusResponse),
+        (status = 404, body = ErrorReport)
+    )
+)]
+async fn job_status(
+    State(server): State<Arc<RestServer>>,
+    Path(job_id): Path<String>,
+) -> Result<Json<JobStatusResponse>, ApiError> {
+    let (id, manager) = server.job(&job_id)?;
+    let status = manager
+        .status(id)
+        .ok_or_else(|| ApiError::not_found(format!("unknown job {}", id)))?;
+    let (state, error) = match status {
+        JobStatus::Running => (JobState::Running, None),
+        JobStatus::Completed => (JobState::Completed, None),
+        JobStatus::Failed(e) => (JobState::Failed, Some(e)),
+        JobStatus::Cancelled => (JobState::Cancelled, None),
+    };
+    Ok(Json(JobStatusResponse {
+        state,
+        error,
+        elapsed_ms: manager.elapsed(id).map(|d| d.as_millis() as u64).unwrap_or(0),
+    }))
+}
+
+/// Cancel a running job; its result is discarded
+#[utoipa::path(
+    delete,
+    path = "/jobs/{id}",
+    params(("id" = String, Path, description = "Job id")),
+    responses(
+        (status = 200, body = CancelJobResponse),
+        (status = 404, body = ErrorReport)
+    )
+)]
+async fn cancel_job(
+    State(server): State<Arc<RestServer>>,
+    Path(job_id): Path<String>,
+) -> Result<Json<CancelJobResponse>, ApiError> {
+    let (id, manager) = server.job(&job_id)?;
+    Ok(Json(CancelJobResponse {
+        cancelled: manager.cancel(id),
+    }))
+}
+
+/// The proof of a completed job
+#[utoipa::path(
+    get,
+    path = "/proofs/{job_id}",
+    params(("job_id" = String, Path, description = "Job id")),
+    responses(
+        (status = 200, body = ProofResponse),
+        (status = 404, body = ErrorReport),
+        (status = 409, description = "The job is still running", body = ErrorReport)
+    )
+)]
+async fn fetch_proof(
+    State(server): State<Arc<RestServer>>,
+    Path(job_id): Path<String>,
+) -> Result<Json<ProofResponse>, ApiError> {
+    let (id, manager) = server.job(&job_id)?;
+    match manager.result(id)? {
+        Some(proof) => Ok(Json(ProofResponse {
+            proof_hex: hex::encode(proof),
+        })),
+        None => Err(ApiError {
+            status: StatusCode::CONFLICT,
+            error: ProverError::Other(format!("job {} is still running", id)),
+        }),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/proofs/verify",
+    request_body = VerifyProofRequest,
+    responses(
+        (status = 200, body = VerifyProofResponse),
+        (status = 404, body = ErrorReport)
+    )
+)]
+async fn verify_proof(
+    State(server): State<Arc<RestServer>>,
+    Json(request): Json<VerifyProofRequest>,
+) -> Result<Json<VerifyProofResponse>, ApiError> {
+    let program = server.program(&request.program_hash)?;
+    let proof = decode_hex("proof_hex", &request.proof_hex)?;
+    let backend = registry::get(&request.backend)
+        .await
+        .ok_or_else(|| ApiError::not_found(format!("unknown backend {}", request.backend)))?;
+    let valid = tokio::task::spawn_blocking(move || backend.verify(&program, &proof))
+        .await
+        .map_err(|e| ProverError::Other(format!("verification task failed: {}", e)))?
+        .map_err(ProverError::from)?;
+    Ok(Json(VerifyProofResponse { valid }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockBackend;
+    use serde_json::{Value, json};
+
+    fn call(method: &str, url: &str, body: Option<Value>) -> (u16, Value) {
+        let request = ureq::request(method, url);
+        let response = match body {
+            Some(body) => request.set("content-type", "application/json").send_string(&body.to_string()),
+            None => request.call(),
+        };
+        let response = match response {
+            Ok(response) => response,
+            Err(ureq::Error::Status(_, response)) => response,
+            Err(e) => panic!("{} {}: {}", method, url, e),
+        };
+        (response.status(), serde_json::from_reader(response.into_reader()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_prove_over_http() {
+        registry::register_backend("rest-mock".to_string(), Arc::new(MockBackend::default())).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(RestServer::new().serve(listener));
+
+        tokio::task::spawn_blocking(move || {
+            let program = ureq::post(&format!("{}/programs", base)).send_bytes(b"elf").unwrap();
+            assert_eq!(program.status(), 201);
+            let program_hash = serde_json::from_reader::<_, Value>(program.into_reader()).unwrap()["program_hash"].clone();
+
+            let job = json!({ "backend": "rest-mock", "program_hash": program_hash, "input_hex": "00ff" });
+            let (status, body) = call("POST", &format!("{}/jobs", base), Some(job));
+            assert_eq!(status, 202);
+            let job_id = body["job_id"].as_str().unwrap().to_string();
+
+            while call("GET", &format!("{}/jobs/{}", base, job_id), None).1["state"] == "running" {
+                std::thread::sleep(std::time::Duration::from_millis(5));
+            }
+            let (status, body) = call("GET", &format!("{}/proofs/{}", base, job_id), None);
+            assert_eq!(status, 200);
+            let verify = json!({ "backend": "rest-mock", "program_hash": program_hash, "proof_hex": body["proof_hex"] });
+            assert_eq!(call("POST", &format!("{}/proofs/verify", base), Some(verify)).1["valid"], true);
+
+            let (status, body) = call("GET", &format!("{}/jobs/not-a-job", base), None);
+            assert_eq!(status, 400);
+            assert_eq!(body["code"], 5000);
+            let unknown = json!({ "backend": "rest-mock", "program_hash": "00", "input_hex": "" });
+            assert_eq!(call("POST", &format!("{}/jobs", base), Some(unknown)).0, 404);
+
+            let (_, spec) = call("GET", &format!("{}/openapi.json", base), None);
+            assert!(spec["paths"]["/jobs/{id}"]["delete"].is_object());
+        })
+        .await
+        .unwrap();
+
+        server.abort();
+        registry::unregister_backend("rest-mock");
+    }
+}
@@ -0,0 +1,217 @@
+use crate::types::{ErrorKind, classify_zk_error};
+use frostgate_zkip::{ZkBackend, ZkError};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Decides whether a backend error is worth retrying
+pub type ErrorClassifier = Arc<dyn Fn(&ZkError) -> ErrorKind + Send + Sync>;
+
+/// Backoff schedule for [`RetryingBackend`]
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total attempts, including the first
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub multiplier: f64,
+    /// Fraction of each delay that is randomized, in `0.0..=1.0`
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `retry` (starting at one)
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let exponent = retry.saturating_sub(1).min(32) as i32;
+        let base = self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent);
+        let base = base.min(self.max_backoff.as_secs_f64());
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        // Uniform in [1 - jitter, 1]
+        let unit = (Uuid::new_v4().as_u128() % 10_000) as f64 / 10_000.0;
+        Duration::from_secs_f64(base * (1.0 - jitter * unit))
+    }
+}
+
+/// Caps retries to a fraction of successful calls so a failing backend
+/// isn't hammered by every caller at once. Shared between backends that
+/// should draw from the same budget.
+pub struct RetryBudget {
+    tokens: Mutex<f64>,
+    max_tokens: f64,
+    deposit_per_success: f64,
+}
+
+impl RetryBudget {
+    /// Allow up to `ratio` retries per successful call, with at most
+    /// `max_tokens` retries banked
+    pub fn new(ratio: f64, max_tokens: f64) -> Self {
+        Self {
+            tokens: Mutex::new(max_tokens),
+            max_tokens,
+            deposit_per_success: ratio,
+        }
+    }
+
+    fn deposit(&self) {
+        let mut tokens = self.tokens.lock().unwrap_or_else(PoisonError::into_inner);
+        *tokens = (*tokens + self.deposit_per_success).min(self.max_tokens);
+    }
+
+    fn try_withdraw(&self) -> bool {
+        let mut tokens = self.tokens.lock().unwrap_or_else(PoisonError::into_inner);
+        if *tokens >= 1.0 {
+            *tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Retries currently available
+    pub fn available(&self) -> f64 {
+        *self.tokens.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Default for RetryBudget {
+    fn default() -> Self {
+        Self::new(0.2, 10.0)
+    }
+}
+
+/// Retries transient backend failures with exponential backoff and jitter
+pub struct RetryingBackend<B> {
+    inner: B,
+    policy: RetryPolicy,
+    budget: Arc<RetryBudget>,
+    classify: ErrorClassifier,
+}
+
+impl<B: ZkBackend> RetryingBackend<B> {
+    pub fn new(inner: B, policy: RetryPolicy) -> Self {
+        Self {
+            inner,
+            policy,
+            budget: Arc::new(RetryBudget::default()),
+            classify: Arc::new(classify_zk_error),
+        }
+    }
+
+    /// Draw retries from a budget shared with other backends
+    pub fn with_budget(mut self, budget: Arc<RetryBudget>) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Replace the default message-based error classification
+    pub fn with_classifier(mut self, classify: ErrorClassifier) -> Self {
+        self.classify = classify;
+        self
+    }
+
+    fn run<T>(&self, op: &str, f: impl Fn() -> Result<T, ZkError>) -> Result<T, ZkError> {
+        let mut attempt = 1;
+        loop {
+            let e = match f() {
+                Ok(value) => {
+                    self.budget.deposit();
+                    return Ok(value);
+                }
+                Err(e) => e,
+            };
+            let kind = (self.classify)(&e);
+            if !kind.is_retryable() || attempt >= self.policy.max_attempts {
+                return Err(e);
+            }
+            if !self.budget.try_withdraw() {
+                tracing::warn!("{} failed and the retry budget is exhausted: {:?}", op, e);
+                return Err(e);
+            }
+            let delay = self.policy.backoff(attempt);
+            tracing::warn!(
+                "{} attempt {}/{} failed ({:?}), retrying in {:?}: {:?}",
+                op,
+                attempt,
+                self.policy.max_attempts,
+                kind,
+                delay,
+                e
+            );
+            std::thread::sleep(delay);
+            attempt += 1;
+        }
+    }
+}
+
+impl<B: ZkBackend> ZkBackend for RetryingBackend<B> {
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        self.run("prove", || self.inner.prove(program, input))
+    }
+
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        self.run("verify", || self.inner.verify(program, proof))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockBackend, MockConfig};
+
+    fn fast_policy() -> RetryPolicy {
+        RetryPolicy {
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(2),
+            ..RetryPolicy::default()
+        }
+    }
+
+    #[test]
+    fn test_retries_transient_failures() {
+        let mock = MockBackend::default();
+        mock.inject_failure(ZkError::Config("network prover timed out".to_string()));
+        mock.inject_failure(ZkError::Config("connection reset".to_string()));
+        let backend = RetryingBackend::new(mock, fast_policy());
+
+        assert!(backend.prove(b"elf", b"input").is_ok());
+        assert_eq!(backend.inner.prove_calls(), 3);
+    }
+
+    #[test]
+    fn test_fatal_errors_are_not_retried() {
+        let backend = RetryingBackend::new(
+            MockBackend::new(MockConfig {
+                fail_first_proves: 1,
+                ..MockConfig::default()
+            }),
+            fast_policy(),
+        );
+
+        assert!(backend.prove(b"elf", b"input").is_err());
+        assert_eq!(backend.inner.prove_calls(), 1);
+    }
+
+    #[test]
+    fn test_budget_limits_retries() {
+        let mock = MockBackend::default();
+        for _ in 0..3 {
+            mock.inject_failure(ZkError::Config("timeout".to_string()));
+        }
+        let backend = RetryingBackend::new(mock, fast_policy()).with_budget(Arc::new(RetryBudget::new(0.1, 1.0)));
+
+        assert!(backend.prove(b"elf", b"input").is_err());
+        assert_eq!(backend.inner.prove_calls(), 2);
+    }
+}
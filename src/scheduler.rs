@@ -0,0 +1,436 @@
+use crate::timeout::ProveOptions;
+use crate::types::ProverError;
+use frostgate_zkip::ZkBackend;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use tokio::sync::Notify;
+
+/// Tenant used for requests that don't name one
+pub const DEFAULT_TENANT: &str = "default";
+
+/// Scheduling class of a request; lower classes wait for higher ones
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Liveness-critical work, e.g. bridge proofs; may use reserved slots
+    Critical,
+    #[default]
+    Normal,
+    /// Background work such as analytics
+    Batch,
+}
+
+/// Limits and share of one tenant
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TenantQuota {
+    /// Proofs the tenant may run at once
+    pub max_concurrent: usize,
+    /// Relative share of contended slots
+    pub weight: f64,
+}
+
+impl Default for TenantQuota {
+    fn default() -> Self {
+        Self {
+            max_concurrent: usize::MAX,
+            weight: 1.0,
+        }
+    }
+}
+
+/// Sizing of a [`FairScheduler`]
+#[derive(Debug, Clone)]
+pub struct SchedulerConfig {
+    pub slots: usize,
+    /// Slots only [`Priority::Critical`] requests may take
+    pub critical_reserve: usize,
+    pub default_quota: TenantQuota,
+    pub quotas: HashMap<String, TenantQuota>,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            slots: num_cpus::get().max(1),
+            critical_reserve: 0,
+            default_quota: TenantQuota::default(),
+            quotas: HashMap::new(),
+        }
+    }
+}
+
+struct Waiter {
+    id: u64,
+    tenant: String,
+    priority: Priority,
+}
+
+#[derive(Default)]
+struct TenantState {
+    running: usize,
+    /// Slots granted so far divided by weight; the lowest goes next
+    virtual_time: f64,
+}
+
+#[derive(Default)]
+struct SchedulerState {
+    running: usize,
+    /// Tenants with proofs running or queued; idle ones are dropped
+    tenants: HashMap<String, TenantState>,
+    waiters: Vec<Waiter>,
+    next_id: u64,
+}
+
+impl SchedulerState {
+    fn is_active(&self, tenant: &str) -> bool {
+        self.tenants.get(tenant).is_some_and(|t| t.running > 0) || self.waiters.iter().any(|w| w.tenant == tenant)
+    }
+
+    fn evict_if_idle(&mut self, tenant: &str) {
+        if !self.is_active(tenant) {
+            self.tenants.remove(tenant);
+        }
+    }
+}
+
+/// Grants proving slots by priority class, then by weighted fair share
+/// between tenants, within per-tenant concurrency quotas. Replaces a plain
+/// semaphore where several tenants share one prover.
+pub struct FairScheduler {
+    config: SchedulerConfig,
+    state: Mutex<SchedulerState>,
+    changed: Notify,
+}
+
+impl FairScheduler {
+    pub fn new(config: SchedulerConfig) -> Arc<Self> {
+        Arc::new(Self {
+            config,
+            state: Mutex::new(SchedulerState::default()),
+            changed: Notify::new(),
+        })
+    }
+
+    fn quota(&self, tenant: &str) -> TenantQuota {
+        self.config.quotas.get(tenant).copied().unwrap_or(self.config.default_quota)
+    }
+
+    /// Wait for a slot. Dropping the returned permit frees it; dropping the
+    /// future while waiting gives up the place in the queue.
+    pub async fn acquire(self: &Arc<Self>, options: &ProveOptions) -> SchedulerPermit {
+        let tenant = options.tenant.clone().unwrap_or_else(|| DEFAULT_TENANT.to_string());
+        let id = {
+            let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+            let id = state.next_id;
+            state.next_id += 1;
+            // A tenant returning from idle starts level with the others
+            // still competing instead of cashing in the time it spent away
+            let floor = state
+                .tenants
+                .iter()
+                .filter(|(name, _)| **name != tenant && state.is_active(name))
+                .map(|(_, t)| t.virtual_time)
+                .fold(f64::INFINITY, f64::min);
+            let entry = state.tenants.entry(tenant.clone()).or_default();
+            if floor.is_finite() && entry.running == 0 {
+                entry.virtual_time = entry.virtual_time.max(floor);
+            }
+            state.waiters.push(Waiter {
+                id,
+                tenant: tenant.clone(),
+                priority: options.priority,
+            });
+            id
+        };
+        let _queued = QueuedGuard { scheduler: self, id };
+
+        loop {
+            let notified = self.changed.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            if self.try_grant(id) {
+                return SchedulerPermit {
+                    scheduler: self.clone(),
+                    tenant,
+                };
+            }
+            notified.await;
+        }
+    }
+
+    fn try_grant(&self, id: u64) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if self.next_eligible(&state) != Some(id) {
+            return false;
+        }
+        let Some(index) = state.waiters.iter().position(|w| w.id == id) else {
+            return false;
+        };
+        let waiter = state.waiters.remove(index);
+        let weight = self.quota(&waiter.tenant).weight.max(f64::EPSILON);
+        state.running += 1;
+        let tenant = state.tenants.entry(waiter.tenant).or_default();
+        tenant.running += 1;
+        tenant.virtual_time += 1.0 / weight;
+        // Waiters woken by the same release may have checked before this
+        // grant and gone back to sleep behind it, so hand on what is left
+        let open = self.config.slots.saturating_sub(state.running);
+        if open > 0 && !state.waiters.is_empty() {
+            drop(state);
+            self.changed.notify_waiters();
+        }
+        true
+    }
+
+    fn next_eligible(&self, state: &SchedulerState) -> Option<u64> {
+        let open = self.config.slots.saturating_sub(state.running);
+        let virtual_time = |tenant: &str| state.tenants.get(tenant).map_or(0.0, |t| t.virtual_time);
+        state
+            .waiters
+            .iter()
+            .filter(|w| {
+                let needed = if w.priority == Priority::Critical {
+                    1
+                } else {
+                    self.config.critical_reserve + 1
+                };
+                let running = state.tenants.get(&w.tenant).map_or(0, |t| t.running);
+                open >= needed && running < self.quota(&w.tenant).max_concurrent
+            })
+            .min_by(|a, b| {
+                a.priority
+                    .cmp(&b.priority)
+                    .then(virtual_time(&a.tenant).total_cmp(&virtual_time(&b.tenant)))
+                    .then(a.id.cmp(&b.id))
+            })
+            .map(|w| w.id)
+    }
+
+    /// Proofs currently holding a slot
+    pub fn running(&self) -> usize {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner).running
+    }
+
+    /// Requests waiting for a slot
+    pub fn queued(&self) -> usize {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner).waiters.len()
+    }
+
+    /// Prove once a slot is granted to `options`
+    pub async fn prove(
+        self: &Arc<Self>,
+        backend: Arc<dyn ZkBackend>,
+        program: Vec<u8>,
+        input: Vec<u8>,
+        options: &ProveOptions,
+    ) -> Result<Vec<u8>, ProverError> {
+        let _permit = self.acquire(options).await;
+        tokio::task::spawn_blocking(move || backend.prove(&program, &input))
+            .await
+            .map_err(|e| ProverError::Other(format!("proving task failed: {}", e)))?
+            .map_err(ProverError::from)
+    }
+}
+
+/// Removes an abandoned waiter from the queue
+struct QueuedGuard<'a> {
+    scheduler: &'a FairScheduler,
+    id: u64,
+}
+
+impl Drop for QueuedGuard<'_> {
+    fn drop(&mut self) {
+        let mut state = self.scheduler.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(index) = state.waiters.iter().position(|w| w.id == self.id) else {
+            return;
+        };
+        let waiter = state.waiters.remove(index);
+        state.evict_if_idle(&waiter.tenant);
+        drop(state);
+        self.scheduler.changed.notify_waiters();
+    }
+}
+
+/// A granted slot, released on drop
+pub struct SchedulerPermit {
+    scheduler: Arc<FairScheduler>,
+    tenant: String,
+}
+
+impl Drop for SchedulerPermit {
+    fn drop(&mut self) {
+        let mut state = self.scheduler.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.running -= 1;
+        if let Some(tenant) = state.tenants.get_mut(&self.tenant) {
+            tenant.running -= 1;
+        }
+        state.evict_if_idle(&self.tenant);
+        drop(state);
+        self.scheduler.changed.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn options(tenant: &str, priority: Priority) -> ProveOptions {
+        ProveOptions::default().for_tenant(tenant, priority)
+    }
+
+    /// Queue one request per entry behind a held slot, release it and return
+    /// the order the queued requests were granted in
+    async fn grant_order(scheduler: &Arc<FairScheduler>, requests: Vec<ProveOptions>) -> Vec<String> {
+        let blocker = scheduler.acquire(&ProveOptions::default()).await;
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut tasks = Vec::new();
+        for (i, request) in requests.into_iter().enumerate() {
+            let (shared, order) = (scheduler.clone(), order.clone());
+            tasks.push(tokio::spawn(async move {
+                let _permit = shared.acquire(&request).await;
+                order.lock().unwrap().push(format!("{}-{}", request.tenant.unwrap(), i));
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }));
+            while scheduler.queued() <= i {
+                tokio::task::yield_now().await;
+            }
+        }
+        drop(blocker);
+        for task in tasks {
+            task.await.unwrap();
+        }
+        Arc::try_unwrap(order).unwrap().into_inner().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_priority_and_fair_share() {
+        let scheduler = FairScheduler::new(SchedulerConfig {
+            slots: 1,
+            ..SchedulerConfig::default()
+        });
+        let order = grant_order(
+            &scheduler,
+            vec![
+                options("analytics", Priority::Batch),
+                options("a", Priority::Normal),
+                options("a", Priority::Normal),
+                options("b", Priority::Normal),
+                options("bridge", Priority::Critical),
+            ],
+        )
+        .await;
+        assert_eq!(order, vec!["bridge-4", "a-1", "b-3", "a-2", "analytics-0"]);
+    }
+
+    #[tokio::test]
+    async fn test_tenant_quota_and_reserve() {
+        let mut quotas = HashMap::new();
+        quotas.insert(
+            "greedy".to_string(),
+            TenantQuota {
+                max_concurrent: 1,
+                weight: 1.0,
+            },
+        );
+        let scheduler = FairScheduler::new(SchedulerConfig {
+            slots: 3,
+            critical_reserve: 1,
+            default_quota: TenantQuota::default(),
+            quotas,
+        });
+
+        let _first = scheduler.acquire(&options("greedy", Priority::Normal)).await;
+        let waiting = {
+            let scheduler = scheduler.clone();
+            tokio::spawn(async move { scheduler.acquire(&options("greedy", Priority::Normal)).await })
+        };
+        let _other = scheduler.acquire(&options("other", Priority::Normal)).await;
+        assert_eq!(scheduler.running(), 2);
+
+        // The last slot is reserved for critical work
+        let critical = tokio::time::timeout(
+            Duration::from_millis(100),
+            scheduler.acquire(&options("bridge", Priority::Critical)),
+        )
+        .await;
+        assert!(critical.is_ok());
+        assert!(!waiting.is_finished());
+        waiting.abort();
+    }
+
+    #[tokio::test]
+    async fn test_idle_tenants_evicted() {
+        let scheduler = FairScheduler::new(SchedulerConfig {
+            slots: 2,
+            ..SchedulerConfig::default()
+        });
+        let virtual_time = |tenant: &str| scheduler.state.lock().unwrap().tenants.get(tenant).map(|t| t.virtual_time);
+
+        for _ in 0..3 {
+            drop(scheduler.acquire(&options("a", Priority::Normal)).await);
+        }
+        drop(scheduler.acquire(&options("idle", Priority::Normal)).await);
+        assert!(scheduler.state.lock().unwrap().tenants.is_empty());
+
+        // Abandoned waits leave nothing behind either
+        let _held = (
+            scheduler.acquire(&options("b", Priority::Normal)).await,
+            scheduler.acquire(&options("b", Priority::Normal)).await,
+        );
+        let waiting = options("c", Priority::Normal);
+        let abandoned = tokio::time::timeout(Duration::from_millis(20), scheduler.acquire(&waiting));
+        assert!(abandoned.await.is_err());
+        assert_eq!(virtual_time("c"), None);
+
+        // A returning tenant starts level with the active one, not the idle ones
+        let returning = {
+            let scheduler = scheduler.clone();
+            tokio::spawn(async move { scheduler.acquire(&options("a", Priority::Normal)).await })
+        };
+        while scheduler.queued() == 0 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(virtual_time("a"), virtual_time("b"));
+        returning.abort();
+    }
+
+    #[tokio::test]
+    async fn test_simultaneous_releases() {
+        let scheduler = FairScheduler::new(SchedulerConfig {
+            slots: 2,
+            ..SchedulerConfig::default()
+        });
+        let held = (
+            scheduler.acquire(&ProveOptions::default()).await,
+            scheduler.acquire(&ProveOptions::default()).await,
+        );
+        // Woken together, the batch request may check first, find the normal
+        // one ahead of it and go back to sleep
+        let (granted, mut grants) = tokio::sync::mpsc::unbounded_channel();
+        let waiters: Vec<_> = [("analytics", Priority::Batch), ("relayer", Priority::Normal)]
+            .into_iter()
+            .map(|(tenant, priority)| {
+                let (scheduler, granted) = (scheduler.clone(), granted.clone());
+                tokio::spawn(async move {
+                    let permit = scheduler.acquire(&options(tenant, priority)).await;
+                    granted.send(tenant).unwrap();
+                    // Hold the slot so only the releases above can free one
+                    std::future::pending::<()>().await;
+                    drop(permit);
+                })
+            })
+            .collect();
+        while scheduler.queued() < 2 {
+            tokio::task::yield_now().await;
+        }
+
+        drop(held);
+        for _ in 0..2 {
+            tokio::time::timeout(Duration::from_secs(1), grants.recv()).await.unwrap().unwrap();
+        }
+        assert_eq!(scheduler.running(), 2);
+        for waiter in waiters {
+            waiter.abort();
+        }
+    }
+}
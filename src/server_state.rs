@@ -0,0 +1,242 @@
+use crate::access::ProgramAccessControl;
+use crate::jobs::{JobId, ProverJobManager};
+use crate::policies::InputPolicies;
+use crate::program_cache::{ProgramCache, ProgramCacheConfig};
+use crate::quotas::TenantQuotas;
+use crate::registry;
+use crate::signatures::{ProgramSignature, ProgramSignatures};
+use crate::types::{ProgramHash, ProveRequest, ProverError, program_hash};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Programs, job managers and jobs shared by the gRPC
+/// [`ProverService`](crate::service::ProverService) and the REST
+/// [`RestServer`](crate::rest::RestServer), so a job submitted through one
+/// can be followed through the other
+pub struct ServerState {
+    max_program_bytes: usize,
+    max_jobs: usize,
+    /// Uploaded programs, least recently used evicted first
+    programs: ProgramCache<Vec<u8>>,
+    /// Job managers by backend and tenant
+    managers: Mutex<HashMap<(String, String), Arc<ProverJobManager>>>,
+    jobs: Mutex<HashMap<JobId, Arc<ProverJobManager>>>,
+    policies: Option<Arc<InputPolicies>>,
+    quotas: Option<TenantQuotas>,
+    access: Option<Arc<ProgramAccessControl>>,
+    signatures: Option<Arc<ProgramSignatures>>,
+}
+
+impl Default for ServerState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ServerState {
+    pub fn new() -> Self {
+        Self {
+            max_program_bytes: 64 * 1024 * 1024,
+            max_jobs: 10_000,
+            programs: ProgramCache::new(ProgramCacheConfig {
+                max_entries: 256,
+                max_bytes: 4 * 1024 * 1024 * 1024,
+                preload_paths: Vec::new(),
+            }),
+            managers: Mutex::new(HashMap::new()),
+            jobs: Mutex::new(HashMap::new()),
+            policies: None,
+            quotas: None,
+            access: None,
+            signatures: None,
+        }
+    }
+
+    /// Reject uploaded programs larger than `max` bytes
+    pub fn with_max_program_bytes(mut self, max: usize) -> Self {
+        self.max_program_bytes = max;
+        self
+    }
+
+    /// How many uploaded programs are kept, and their total size. Proofs
+    /// of an evicted program need it uploaded again.
+    pub fn with_program_cache(mut self, config: ProgramCacheConfig) -> Self {
+        self.programs = ProgramCache::new(config);
+        self
+    }
+
+    /// Jobs tracked at once. Once full, finished jobs past their managers'
+    /// retention are dropped, and submissions are refused while none are.
+    pub fn with_max_jobs(mut self, max: usize) -> Self {
+        self.max_jobs = max;
+        self
+    }
+
+    /// Reject proof requests that break their program's input policy
+    pub fn with_policies(mut self, policies: Arc<InputPolicies>) -> Self {
+        self.policies = Some(policies);
+        self
+    }
+
+    /// Charge each proof to the quotas of the tenant requesting it
+    pub fn with_quotas(mut self, quotas: TenantQuotas) -> Self {
+        self.quotas = Some(quotas);
+        self
+    }
+
+    /// Only accept proof requests for programs the requesting tenant may prove
+    pub fn with_access_control(mut self, access: Arc<ProgramAccessControl>) -> Self {
+        self.access = Some(access);
+        self
+    }
+
+    /// Record the signatures uploaded with programs in `signatures`, and
+    /// refuse uploads it rejects
+    pub fn with_signatures(mut self, signatures: Arc<ProgramSignatures>) -> Self {
+        self.signatures = Some(signatures);
+        self
+    }
+
+    pub fn max_program_bytes(&self) -> usize {
+        self.max_program_bytes
+    }
+
+    /// Check and keep an uploaded program, returning its hash
+    pub fn submit_program(
+        &self,
+        program: Vec<u8>,
+        signatures: Vec<ProgramSignature>,
+    ) -> Result<ProgramHash, ProverError> {
+        if program.is_empty() {
+            return Err(ProverError::InvalidProgram("program is empty".to_string()));
+        }
+        if program.len() > self.max_program_bytes {
+            return Err(ProverError::InvalidProgram(format!(
+                "program is {} bytes, limit is {}",
+                program.len(),
+                self.max_program_bytes
+            )));
+        }
+        if let Some(checks) = &self.signatures {
+            for signature in signatures {
+                checks.add_signature(&program, signature);
+            }
+            checks.enforce(&program)?;
+        }
+        let hash = program_hash(&program);
+        let bytes = program.len() as u64;
+        self.programs.insert(hash.clone(), program, bytes);
+        Ok(hash)
+    }
+
+    /// An uploaded program, unless it was never uploaded or was evicted
+    pub fn program(&self, hash: &str) -> Option<Arc<Vec<u8>>> {
+        self.programs.get(hash)
+    }
+
+    /// The job manager proving on `backend` for `tenant`, unless no such
+    /// backend is registered
+    pub fn manager(&self, backend: &str, tenant: &str) -> Option<Arc<ProverJobManager>> {
+        let key = (backend.to_string(), tenant.to_string());
+        let mut managers = self.managers.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(manager) = managers.get(&key) {
+            return Some(manager.clone());
+        }
+        let mut zk_backend = registry::get_backend(backend)?;
+        if let Some(quotas) = &self.quotas {
+            zk_backend = quotas.wrap(tenant, zk_backend);
+        }
+        let mut manager = ProverJobManager::new(zk_backend);
+        if let Some(policies) = &self.policies {
+            manager = manager.with_policies(policies.clone());
+        }
+        let manager = Arc::new(manager);
+        managers.insert(key, manager.clone());
+        Some(manager)
+    }
+
+    /// Submit `request` for `tenant` to `manager`, under `key` if given,
+    /// and track the job
+    pub fn submit_job(
+        &self,
+        manager: Arc<ProverJobManager>,
+        tenant: &str,
+        request: &ProveRequest,
+        key: Option<&str>,
+    ) -> Result<JobId, ProverError> {
+        if let Some(access) = &self.access {
+            access.check(tenant, &request.program_hash())?;
+        }
+        self.reserve_job()?;
+        let id = manager.submit_request(request, key)?;
+        self.jobs.lock().unwrap_or_else(PoisonError::into_inner).insert(id, manager);
+        Ok(id)
+    }
+
+    /// The manager of a tracked job
+    pub fn job(&self, id: JobId) -> Option<Arc<ProverJobManager>> {
+        self.jobs.lock().unwrap_or_else(PoisonError::into_inner).get(&id).cloned()
+    }
+
+    /// Make sure another job can be tracked, forgetting the jobs the
+    /// managers have pruned if the table is full
+    fn reserve_job(&self) -> Result<(), ProverError> {
+        let mut jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+        if jobs.len() < self.max_jobs {
+            return Ok(());
+        }
+        let managers: Vec<_> = self
+            .managers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .cloned()
+            .collect();
+        for manager in managers {
+            manager.prune();
+        }
+        jobs.retain(|id, manager| manager.status(*id).is_some());
+        if jobs.len() >= self.max_jobs {
+            return Err(ProverError::QueueFull);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jobs::JobStatus;
+    use crate::testing::MockBackend;
+
+    #[tokio::test]
+    async fn test_jobs_shared_by_tenant_managers() {
+        registry::register_backend("state-mock".to_string(), Arc::new(MockBackend::default())).unwrap();
+        let state = ServerState::new().with_max_jobs(2);
+        assert!(matches!(
+            state.submit_program(Vec::new(), Vec::new()),
+            Err(ProverError::InvalidProgram(_))
+        ));
+        let hash = state.submit_program(b"elf".to_vec(), Vec::new()).unwrap();
+        let program = state.program(&hash).unwrap();
+
+        let alice = state.manager("state-mock", "alice").unwrap();
+        let bob = state.manager("state-mock", "bob").unwrap();
+        assert!(!Arc::ptr_eq(&alice, &bob));
+        assert!(Arc::ptr_eq(&alice, &state.manager("state-mock", "alice").unwrap()));
+        assert!(state.manager("missing", "alice").is_none());
+
+        let request = ProveRequest::new(program.to_vec(), b"input".to_vec());
+        let first = state.submit_job(alice, "alice", &request, None).unwrap();
+        let second = state.submit_job(bob.clone(), "bob", &request, None).unwrap();
+        assert!(matches!(
+            state.submit_job(bob, "bob", &request, None),
+            Err(ProverError::QueueFull)
+        ));
+        for id in [first, second] {
+            let manager = state.job(id).unwrap();
+            assert_eq!(manager.wait(id).await, Some(JobStatus::Completed));
+        }
+        registry::unregister_backend("state-mock");
+    }
+}
@@ -0,0 +1,324 @@
+// tonic handlers return `Status` by value
+#![allow(clippy::result_large_err)]
+
+use crate::jobs::{JobId, JobStatus, ProverJobManager};
+use crate::quotas::DEFAULT_TENANT;
+use crate::registry;
+use crate::server_state::ServerState;
+use crate::types::{ProveRequest, ProverError};
+use std::sync::Arc;
+use tonic::{Request, Response, Status};
+
+/// Generated gRPC types for `proto/prover.proto`
+pub mod proto {
+    tonic::include_proto!("frostgate.prover.v1");
+}
+
+use proto::prover_server::Prover;
+use proto::*;
+
+pub use proto::prover_server::ProverServer;
+
+/// Response metadata carrying [`ProverError::code`] on failed calls
+pub const ERROR_CODE_METADATA_KEY: &str = "x-frostgate-error-code";
+
+/// Request metadata naming the tenant a proof is charged to, set by an
+/// authenticating proxy in front of the server. Requests without it are
+/// charged to [`DEFAULT_TENANT`].
+pub const TENANT_METADATA_KEY: &str = "x-frostgate-tenant";
+
+/// Status metadata key carrying how many seconds to wait before retrying
+pub const RETRY_AFTER_METADATA_KEY: &str = "retry-after";
+
+/// Serves the global backend registry over gRPC
+pub struct ProverService {
+    state: Arc<ServerState>,
+}
+
+impl Default for ProverService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProverService {
+    pub fn new() -> Self {
+        Self::with_state(Arc::new(ServerState::new()))
+    }
+
+    /// Serve `state`, e.g. shared with a [`RestServer`](crate::rest::RestServer)
+    pub fn with_state(state: Arc<ServerState>) -> Self {
+        Self { state }
+    }
+
+    /// Wrap in the generated tonic server
+    pub fn into_server(self) -> ProverServer<Self> {
+        ProverServer::new(self)
+    }
+
+    fn program(&self, hash: &str) -> Result<Arc<Vec<u8>>, Status> {
+        self.state
+            .program(hash)
+            .ok_or_else(|| Status::not_found(format!("unknown program {}", hash)))
+    }
+
+    fn manager(&self, backend: &str, tenant: &str) -> Result<Arc<ProverJobManager>, Status> {
+        self.state
+            .manager(backend, tenant)
+            .ok_or_else(|| Status::not_found(format!("unknown backend {}", backend)))
+    }
+
+    fn job(&self, job_id: &str) -> Result<(JobId, Arc<ProverJobManager>), Status> {
+        let id: JobId = job_id.parse().map_err(into_status)?;
+        let manager = self
+            .state
+            .job(id)
+            .ok_or_else(|| Status::not_found(format!("unknown job {}", job_id)))?;
+        Ok((id, manager))
+    }
+}
+
+fn tenant<T>(request: &Request<T>) -> String {
+    request
+        .metadata()
+        .get(TENANT_METADATA_KEY)
+        .and_then(|tenant| tenant.to_str().ok())
+        .unwrap_or(DEFAULT_TENANT)
+        .to_string()
+}
+
+fn into_status(e: ProverError) -> Status {
+    let code = e.code();
+    let retry_after = e.retry_after();
+    let mut status = match e {
+        ProverError::ProgramNotFound => Status::not_found("program not found"),
+        ProverError::InvalidProgram(msg) | ProverError::MalformedEnvelope(msg) => Status::invalid_argument(msg),
+        ProverError::PolicyViolation(msg) | ProverError::SignatureInvalid(msg) => Status::permission_denied(msg),
+        ProverError::QueueFull
+        | ProverError::Busy { .. }
+        | ProverError::BudgetExceeded { .. }
+        | ProverError::QuotaExceeded { .. } => Status::resource_exhausted(e.to_string()),
+        ProverError::NetworkUnavailable(msg) | ProverError::Degraded(msg) => Status::unavailable(msg),
+        ProverError::Timeout(msg) => Status::deadline_exceeded(msg),
+        ProverError::Cancelled => Status::cancelled("cancelled"),
+        ProverError::IdempotencyConflict { .. } => Status::already_exists(e.to_string()),
+        other => Status::internal(other.to_string()),
+    };
+    status
+        .metadata_mut()
+        .insert(ERROR_CODE_METADATA_KEY, code.into());
+    if let Some(retry_after) = retry_after {
+        let seconds = retry_after.as_millis().div_ceil(1000) as u64;
+        status
+            .metadata_mut()
+            .insert(RETRY_AFTER_METADATA_KEY, seconds.into());
+    }
+    status
+}
+
+#[tonic::async_trait]
+impl Prover for ProverService {
+    async fn list_backends(
+        &self,
+        _request: Request<ListBackendsRequest>,
+    ) -> Result<Response<ListBackendsResponse>, Status> {
+        let mut backends = registry::list_backends();
+        backends.sort();
+        Ok(Response::new(ListBackendsResponse { backends }))
+    }
+
+    async fn submit_program(
+        &self,
+        request: Request<SubmitProgramRequest>,
+    ) -> Result<Response<SubmitProgramResponse>, Status> {
+        let SubmitProgramRequest { program, signatures } = request.into_inner();
+        let signatures = signatures
+            .into_iter()
+            .map(|signature| {
+                let bytes: [u8; 64] = signature.signature.try_into().map_err(|_| {
+                    Status::invalid_argument(format!("signature by {} is not 64 bytes", signature.signer))
+                })?;
+                Ok(crate::signatures::ProgramSignature {
+                    signer: signature.signer,
+                    signature: bytes,
+                })
+            })
+            .collect::<Result<Vec<_>, Status>>()?;
+        let hash = self.state.submit_program(program, signatures).map_err(into_status)?;
+        Ok(Response::new(SubmitProgramResponse { program_hash: hash }))
+    }
+
+    async fn request_proof(
+        &self,
+        request: Request<RequestProofRequest>,
+    ) -> Result<Response<RequestProofResponse>, Status> {
+        let tenant = tenant(&request);
+        let request = request.into_inner();
+        let program = self.program(&request.program_hash)?;
+        let manager = self.manager(&request.backend, &tenant)?;
+        let key = Some(request.idempotency_key.as_str()).filter(|key| !key.is_empty());
+        let id = self
+            .state
+            .submit_job(manager, &tenant, &ProveRequest::new(program.to_vec(), request.input), key)
+            .map_err(into_status)?;
+        Ok(Response::new(RequestProofResponse { job_id: id.to_string() }))
+    }
+
+    async fn get_job_status(
+        &self,
+        request: Request<GetJobStatusRequest>,
+    ) -> Result<Response<GetJobStatusResponse>, Status> {
+        let (id, manager) = self.job(&request.into_inner().job_id)?;
+        let status = manager
+            .status(id)
+            .ok_or_else(|| Status::not_found(format!("unknown job {}", id)))?;
+        let (state, error) = match status {
+            JobStatus::Running => (JobState::Running, String::new()),
+            JobStatus::Completed => (JobState::Completed, String::new()),
+            JobStatus::Failed(e) => (JobState::Failed, e),
+            JobStatus::Cancelled => (JobState::Cancelled, String::new()),
+        };
+        let elapsed_ms = manager.elapsed(id).map(|d| d.as_millis() as u64).unwrap_or(0);
+        Ok(Response::new(GetJobStatusResponse {
+            state: state.into(),
+            error,
+            elapsed_ms,
+        }))
+    }
+
+    async fn fetch_proof(
+        &self,
+        request: Request<FetchProofRequest>,
+    ) -> Result<Response<FetchProofResponse>, Status> {
+        let (id, manager) = self.job(&request.into_inner().job_id)?;
+        match manager.result(id).map_err(into_status)? {
+            Some(proof) => Ok(Response::new(FetchProofResponse { proof })),
+            None => Err(Status::failed_precondition(format!("job {} is still running", id))),
+        }
+    }
+
+    async fn cancel_job(
+        &self,
+        request: Request<CancelJobRequest>,
+    ) -> Result<Response<CancelJobResponse>, Status> {
+        let (id, manager) = self.job(&request.into_inner().job_id)?;
+        Ok(Response::new(CancelJobResponse {
+            cancelled: manager.cancel(id),
+        }))
+    }
+
+    async fn verify(&self, request: Request<VerifyRequest>) -> Result<Response<VerifyResponse>, Status> {
+        let request = request.into_inner();
+        let program = self.program(&request.program_hash)?;
+        let backend = registry::get(&request.backend)
+            .await
+            .ok_or_else(|| Status::not_found(format!("unknown backend {}", request.backend)))?;
+        let valid = tokio::task::spawn_blocking(move || backend.verify(&program, &request.proof))
+            .await
+            .map_err(|e| Status::internal(format!("verification task failed: {}", e)))?
+            .map_err(|e| into_status(e.into()))?;
+        Ok(Response::new(VerifyResponse { valid }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::access::{AccessRules, ProgramAccessControl};
+    use crate::testing::MockBackend;
+    use proto::prover_client::ProverClient;
+    use std::collections::HashSet;
+    use tonic::transport::Channel;
+    use tonic::transport::server::TcpIncoming;
+
+    async fn serve(service: ProverService) -> ProverClient<Channel> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let incoming = TcpIncoming::from_listener(listener, true, None).unwrap();
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(service.into_server())
+                .serve_with_incoming(incoming),
+        );
+        ProverClient::connect(format!("http://{}", addr)).await.unwrap()
+    }
+
+    fn prove(program_hash: &str, tenant: &str) -> Request<RequestProofRequest> {
+        let mut request = Request::new(RequestProofRequest {
+            backend: "grpc-mock".to_string(),
+            program_hash: program_hash.to_string(),
+            input: b"input".to_vec(),
+            idempotency_key: String::new(),
+        });
+        request.metadata_mut().insert(TENANT_METADATA_KEY, tenant.parse().unwrap());
+        request
+    }
+
+    #[tokio::test]
+    async fn test_prove_over_grpc() {
+        registry::register_backend("grpc-mock".to_string(), Arc::new(MockBackend::default())).unwrap();
+        let access = Arc::new(ProgramAccessControl::new());
+        access.set_tenant(
+            "locked-out",
+            AccessRules {
+                allow: Some(HashSet::new()),
+                deny: HashSet::new(),
+            },
+        );
+        let state = ServerState::new().with_access_control(access).with_max_jobs(1);
+        let mut client = serve(ProverService::with_state(Arc::new(state))).await;
+
+        let program_hash = client
+            .submit_program(SubmitProgramRequest {
+                program: b"elf".to_vec(),
+                signatures: Vec::new(),
+            })
+            .await
+            .unwrap()
+            .into_inner()
+            .program_hash;
+        let job_id = client
+            .request_proof(prove(&program_hash, "relayer"))
+            .await
+            .unwrap()
+            .into_inner()
+            .job_id;
+        let status = loop {
+            let status = client
+                .get_job_status(GetJobStatusRequest { job_id: job_id.clone() })
+                .await
+                .unwrap()
+                .into_inner();
+            if status.state() != JobState::Running {
+                break status;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        };
+        assert_eq!(status.state(), JobState::Completed, "{}", status.error);
+
+        let proof = client
+            .fetch_proof(FetchProofRequest { job_id })
+            .await
+            .unwrap()
+            .into_inner()
+            .proof;
+        let verified = client
+            .verify(VerifyRequest {
+                backend: "grpc-mock".to_string(),
+                program_hash: program_hash.clone(),
+                proof,
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(verified.valid);
+
+        let denied = client.request_proof(prove(&program_hash, "locked-out")).await.unwrap_err();
+        assert_eq!(denied.code(), tonic::Code::PermissionDenied);
+        // The finished job is still retained, so there's no room for another
+        let full = client.request_proof(prove(&program_hash, "relayer")).await.unwrap_err();
+        assert_eq!(full.code(), tonic::Code::ResourceExhausted);
+        let unknown = client.request_proof(prove("unknown", "relayer")).await.unwrap_err();
+        assert_eq!(unknown.code(), tonic::Code::NotFound);
+    }
+}
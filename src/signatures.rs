@@ -0,0 +1,183 @@
+use crate::registry::BackendMiddleware;
+use crate::types::{ProgramHash, ProverError, program_hash};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use frostgate_zkip::{ZkBackend, ZkError};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// Domain separator for program signatures
+const SIGNATURE_DOMAIN: &[u8] = b"frostgate-program-v1:";
+
+/// How unsigned or mis-signed programs are handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SignatureMode {
+    /// Signatures are not checked
+    #[default]
+    Disabled,
+    /// Failures are logged but programs still run
+    Warn,
+    /// Failures refuse setup and proving
+    Enforce,
+}
+
+/// Signature by an allowlisted signer over a program's hash
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgramSignature {
+    pub signer: String,
+    pub signature: [u8; 64],
+}
+
+/// Sign `program` as `signer`
+pub fn sign_program(key: &SigningKey, signer: &str, program: &[u8]) -> ProgramSignature {
+    ProgramSignature {
+        signer: signer.to_string(),
+        signature: key.sign(&signing_message(&program_hash(program))).to_bytes(),
+    }
+}
+
+fn signing_message(hash: &str) -> Vec<u8> {
+    [SIGNATURE_DOMAIN, hash.as_bytes()].concat()
+}
+
+/// Checks registered programs against an allowlist of signers
+pub struct ProgramSignatures {
+    mode: SignatureMode,
+    signers: HashMap<String, VerifyingKey>,
+    signatures: RwLock<HashMap<ProgramHash, Vec<ProgramSignature>>>,
+}
+
+impl ProgramSignatures {
+    pub fn new(mode: SignatureMode) -> Self {
+        Self {
+            mode,
+            signers: HashMap::new(),
+            signatures: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Allow signatures from `name`
+    pub fn allow_signer(mut self, name: &str, key: VerifyingKey) -> Self {
+        self.signers.insert(name.to_string(), key);
+        self
+    }
+
+    pub fn mode(&self) -> SignatureMode {
+        self.mode
+    }
+
+    /// Attach a signature to a program
+    pub fn add_signature(&self, program: &[u8], signature: ProgramSignature) {
+        self.signatures
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(program_hash(program))
+            .or_default()
+            .push(signature);
+    }
+
+    /// Check that at least one allowlisted signer signed `program`
+    pub fn verify_program(&self, program: &[u8]) -> Result<(), ProverError> {
+        let hash = program_hash(program);
+        let message = signing_message(&hash);
+        let signatures = self.signatures.read().unwrap_or_else(PoisonError::into_inner);
+        let valid = signatures.get(&hash).into_iter().flatten().any(|sig| {
+            self.signers.get(&sig.signer).is_some_and(|key| {
+                key.verify(&message, &Signature::from_bytes(&sig.signature))
+                    .is_ok()
+            })
+        });
+        if valid {
+            Ok(())
+        } else {
+            Err(ProverError::SignatureInvalid(format!(
+                "program {} has no valid signature from an allowlisted signer",
+                hash
+            )))
+        }
+    }
+
+    /// Apply the configured mode to `program`
+    pub fn enforce(&self, program: &[u8]) -> Result<(), ProverError> {
+        match self.mode {
+            SignatureMode::Disabled => Ok(()),
+            SignatureMode::Warn => {
+                if let Err(e) = self.verify_program(program) {
+                    tracing::warn!("running program with bad signature: {:?}", e);
+                }
+                Ok(())
+            }
+            SignatureMode::Enforce => self.verify_program(program),
+        }
+    }
+}
+
+/// Backend wrapper refusing to prove programs that fail signature checks
+pub struct SignedProgramBackend {
+    inner: Arc<dyn ZkBackend>,
+    signatures: Arc<ProgramSignatures>,
+}
+
+impl SignedProgramBackend {
+    pub fn new(inner: Arc<dyn ZkBackend>, signatures: Arc<ProgramSignatures>) -> Self {
+        Self { inner, signatures }
+    }
+}
+
+impl ZkBackend for SignedProgramBackend {
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        self.signatures
+            .enforce(program)
+            .map_err(ProverError::into_zk_error)?;
+        self.inner.prove(program, input)
+    }
+
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        self.inner.verify(program, proof)
+    }
+}
+
+/// Registry middleware checking every backend's programs against one
+/// [`ProgramSignatures`]
+pub struct SignatureMiddleware(Arc<ProgramSignatures>);
+
+impl SignatureMiddleware {
+    pub fn new(signatures: Arc<ProgramSignatures>) -> Self {
+        Self(signatures)
+    }
+}
+
+impl BackendMiddleware for SignatureMiddleware {
+    fn wrap(&self, _id: &str, backend: Arc<dyn ZkBackend>) -> Arc<dyn ZkBackend> {
+        Arc::new(SignedProgramBackend::new(backend, self.0.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enforcing_mode() {
+        let release_key = SigningKey::from_bytes(&[7u8; 32]);
+        let rogue_key = SigningKey::from_bytes(&[9u8; 32]);
+        let signatures =
+            ProgramSignatures::new(SignatureMode::Enforce).allow_signer("release", release_key.verifying_key());
+
+        let program = b"guest elf";
+        assert!(signatures.enforce(program).is_err());
+
+        // Right name, wrong key
+        signatures.add_signature(program, sign_program(&rogue_key, "release", program));
+        assert!(signatures.enforce(program).is_err());
+
+        signatures.add_signature(program, sign_program(&release_key, "release", program));
+        signatures.enforce(program).unwrap();
+
+        // Signatures don't carry over to other programs
+        let other = b"other elf";
+        signatures.add_signature(other, sign_program(&release_key, "release", program));
+        assert!(signatures.enforce(other).is_err());
+    }
+}
@@ -0,0 +1,397 @@
+use crate::audit::now_ms;
+use crate::job_store::JobStore;
+use crate::jobs::{JobId, JobStatus, ProverJobManager};
+use crate::offline::{OfflineQueue, ParkedResult};
+use crate::pinning::VkeyPins;
+use crate::program_cache::{CachedProgram, ProgramCache, SetupFn};
+use crate::types::{ProgramHash, ProveRequest, ProverError, ct_eq, program_hash};
+use crate::vkeys::{ExportedVkey, VkeyStore};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+/// Current snapshot format version. Version 1 snapshots had no verifying
+/// keys, cached programs or running jobs and load with those empty.
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+const MANIFEST_FILE: &str = "snapshot.json";
+const PROGRAMS_DIR: &str = "programs";
+
+/// A job that had not completed when the snapshot was taken
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingJob {
+    pub program_hash: ProgramHash,
+    #[serde(with = "crate::envelope::hex_bytes")]
+    pub input: Vec<u8>,
+    /// Values the proof must commit to, empty if unbound
+    #[serde(default, with = "crate::envelope::hex_bytes", skip_serializing_if = "Vec::is_empty")]
+    pub public_inputs: Vec<u8>,
+}
+
+/// Prover state persisted by [`snapshot`]; program ELFs are stored next to it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProverSnapshot {
+    pub version: u32,
+    pub created_at_ms: u64,
+    pub programs: Vec<ProgramHash>,
+    pub vkey_pins: BTreeMap<ProgramHash, String>,
+    /// Jobs parked in the offline queue
+    pub pending_jobs: Vec<PendingJob>,
+    /// Jobs a [`ProverJobManager`] was still proving, or its job store had
+    /// recorded as running
+    #[serde(default)]
+    pub running_jobs: Vec<PendingJob>,
+    /// Verifying keys, so restored nodes verify without running setup
+    #[serde(default)]
+    pub vkeys: Vec<ExportedVkey>,
+    /// Programs whose proving keys were cached, to set up again on restore
+    #[serde(default)]
+    pub cached_programs: Vec<CachedProgram>,
+}
+
+/// State to capture in a snapshot
+#[derive(Default)]
+pub struct SnapshotSources<'a> {
+    /// ELFs of the registered programs
+    pub programs: Vec<&'a [u8]>,
+    pub vkey_pins: Option<&'a VkeyPins>,
+    pub offline_queue: Option<&'a OfflineQueue>,
+    pub vkeys: Option<&'a VkeyStore>,
+    /// Contents of the proving key cache, from [`ProgramCache::cached`].
+    /// Only programs whose ELF is also captured can be set up again.
+    pub cached_programs: Vec<CachedProgram>,
+    pub jobs: Option<&'a ProverJobManager>,
+    pub job_store: Option<&'a dyn JobStore>,
+}
+
+/// Write the prover state to `dir`, so a node can be migrated or upgraded
+/// without re-registering every program
+pub fn snapshot(dir: &Path, sources: &SnapshotSources<'_>) -> Result<ProverSnapshot, ProverError> {
+    let programs_dir = dir.join(PROGRAMS_DIR);
+    fs::create_dir_all(&programs_dir)?;
+
+    let mut programs = Vec::new();
+    let mut write_program = |elf: &[u8]| -> Result<ProgramHash, ProverError> {
+        let hash = program_hash(elf);
+        if !programs.contains(&hash) {
+            fs::write(programs_dir.join(format!("{}.elf", hash)), elf)?;
+            programs.push(hash.clone());
+        }
+        Ok(hash)
+    };
+
+    for elf in &sources.programs {
+        write_program(elf)?;
+    }
+    let mut pending_jobs = Vec::new();
+    if let Some(queue) = sources.offline_queue {
+        for (elf, input) in queue.parked_jobs() {
+            pending_jobs.push(PendingJob {
+                program_hash: write_program(&elf)?,
+                input,
+                public_inputs: Vec::new(),
+            });
+        }
+    }
+
+    let mut running_jobs = Vec::new();
+    let mut seen = HashSet::new();
+    let mut running = Vec::new();
+    if let Some(jobs) = sources.jobs {
+        running.extend(jobs.pending_requests());
+    }
+    if let Some(job_store) = sources.job_store {
+        running.extend(
+            job_store
+                .load()?
+                .into_iter()
+                .filter(|record| record.status == JobStatus::Running)
+                .map(|record| {
+                    let request = ProveRequest::new(record.program, record.input);
+                    (record.id, request.with_public_inputs(record.public_inputs))
+                }),
+        );
+    }
+    for (id, request) in running {
+        // A manager's jobs are usually in its job store as well
+        if seen.insert(id) {
+            running_jobs.push(PendingJob {
+                program_hash: write_program(&request.program)?,
+                input: request.stdin_inputs,
+                public_inputs: request.public_inputs,
+            });
+        }
+    }
+
+    let mut vkeys = sources.vkeys.map(VkeyStore::all).unwrap_or_default();
+    vkeys.sort_by(|a, b| a.program_hash.cmp(&b.program_hash));
+
+    let snapshot = ProverSnapshot {
+        version: SNAPSHOT_VERSION,
+        created_at_ms: now_ms(),
+        programs,
+        vkey_pins: sources
+            .vkey_pins
+            .map(|pins| pins.all().into_iter().collect())
+            .unwrap_or_default(),
+        pending_jobs,
+        running_jobs,
+        vkeys,
+        cached_programs: sources.cached_programs.clone(),
+    };
+
+    // Write the manifest last so a partial snapshot is never loaded
+    let tmp = dir.join(format!("{}.tmp", MANIFEST_FILE));
+    fs::write(&tmp, serde_json::to_vec_pretty(&snapshot)?)?;
+    fs::rename(&tmp, dir.join(MANIFEST_FILE))?;
+    Ok(snapshot)
+}
+
+/// State loaded by [`restore`]
+#[derive(Debug, Clone)]
+pub struct RestoredState {
+    pub snapshot: ProverSnapshot,
+    /// Program ELFs keyed by hash, integrity-checked
+    pub programs: HashMap<ProgramHash, Vec<u8>>,
+}
+
+impl RestoredState {
+    /// Re-apply pinned vkeys
+    pub fn apply_pins(&self, pins: &VkeyPins) {
+        for (program, vkey) in &self.snapshot.vkey_pins {
+            pins.pin(program.clone(), vkey.clone());
+        }
+    }
+
+    /// Re-park pending jobs; callers receive fresh result handles
+    pub fn requeue(&self, queue: &OfflineQueue) -> Result<Vec<ParkedResult>, ProverError> {
+        self.snapshot
+            .pending_jobs
+            .iter()
+            .map(|job| {
+                let elf = self
+                    .programs
+                    .get(&job.program_hash)
+                    .ok_or(ProverError::ProgramNotFound)?;
+                queue.park(elf, &job.input)
+            })
+            .collect()
+    }
+
+    /// Import the snapshot's verifying keys, checking each against its
+    /// vkey hash. Returns how many were imported.
+    pub fn import_vkeys(&self, vkeys: &VkeyStore) -> Result<usize, ProverError> {
+        for key in &self.snapshot.vkeys {
+            vkeys.import(key.clone())?;
+        }
+        Ok(self.snapshot.vkeys.len())
+    }
+
+    /// Submit the jobs that were running when the snapshot was taken, under
+    /// fresh IDs. Must be called from within a tokio runtime.
+    pub fn resubmit(&self, jobs: &ProverJobManager) -> Result<Vec<JobId>, ProverError> {
+        self.snapshot
+            .running_jobs
+            .iter()
+            .map(|job| {
+                let elf = self
+                    .programs
+                    .get(&job.program_hash)
+                    .ok_or(ProverError::ProgramNotFound)?;
+                let request = ProveRequest::new(elf.clone(), job.input.clone());
+                jobs.submit_request(&request.with_public_inputs(job.public_inputs.clone()), None)
+            })
+            .collect()
+    }
+
+    /// Set up the programs that were in the proving key cache again and
+    /// re-pin the pinned ones. Programs whose ELF wasn't captured are
+    /// skipped.
+    pub async fn rewarm<T: Send + Sync + 'static>(
+        &self,
+        cache: &ProgramCache<T>,
+        setup: SetupFn<T>,
+    ) -> Vec<Result<ProgramHash, ProverError>> {
+        let mut programs = Vec::new();
+        for cached in &self.snapshot.cached_programs {
+            if cached.pinned {
+                cache.pin_program(&cached.program_hash);
+            }
+            match self.programs.get(&cached.program_hash) {
+                Some(elf) => programs.push(elf.clone()),
+                None => tracing::warn!("no ELF for cached program {} in the snapshot", cached.program_hash),
+            }
+        }
+        cache.preload(programs, setup).await
+    }
+}
+
+/// Load a snapshot written by [`snapshot`], checking every stored ELF
+/// against its hash
+pub fn restore(dir: &Path) -> Result<RestoredState, ProverError> {
+    let snapshot: ProverSnapshot = serde_json::from_slice(&fs::read(dir.join(MANIFEST_FILE))?)?;
+    if snapshot.version > SNAPSHOT_VERSION {
+        return Err(ProverError::UnsupportedProofVersion(format!(
+            "snapshot version {}",
+            snapshot.version
+        )));
+    }
+
+    let mut programs = HashMap::new();
+    for hash in &snapshot.programs {
+        let elf = fs::read(dir.join(PROGRAMS_DIR).join(format!("{}.elf", hash)))?;
+        if !ct_eq(program_hash(&elf).as_bytes(), hash.as_bytes()) {
+            return Err(ProverError::InvalidProgram(format!(
+                "snapshot program {} does not match its hash",
+                hash
+            )));
+        }
+        programs.insert(hash.clone(), elf);
+    }
+    Ok(RestoredState { snapshot, programs })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::job_store::JobRecord;
+    use crate::offline::OfflineQueueConfig;
+    use crate::pinning::VerificationMode;
+    use crate::program_cache::ProgramCacheConfig;
+    use crate::testing::{MockBackend, MockConfig, mock_committing_proof, mock_vkey_hash, mock_vkeys};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{Duration, SystemTime};
+
+    /// Job store holding fixed records
+    struct FixedJobs(Vec<JobRecord>);
+
+    impl JobStore for FixedJobs {
+        fn insert(&self, _job: &JobRecord) -> Result<(), ProverError> {
+            Ok(())
+        }
+
+        fn finish(&self, _id: JobId, _status: &JobStatus, _proof: Option<&[u8]>) -> Result<(), ProverError> {
+            Ok(())
+        }
+
+        fn load(&self) -> Result<Vec<JobRecord>, ProverError> {
+            Ok(self.0.clone())
+        }
+
+        fn remove(&self, _ids: &[JobId]) -> Result<(), ProverError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let dir = std::env::temp_dir().join(format!("frostgate-snapshot-{}", uuid::Uuid::new_v4()));
+        let pins = VkeyPins::new(VerificationMode::Strict);
+        pins.pin(program_hash(b"elf-a"), "vk-a".to_string());
+        let queue = OfflineQueue::new(Arc::new(MockBackend::default()), OfflineQueueConfig::default());
+        queue.park(b"elf-b", b"input").unwrap();
+
+        let sources = SnapshotSources {
+            programs: vec![b"elf-a"],
+            vkey_pins: Some(&pins),
+            offline_queue: Some(&queue),
+            ..Default::default()
+        };
+        let written = snapshot(&dir, &sources).unwrap();
+        assert_eq!(written.programs.len(), 2);
+
+        let restored = restore(&dir).unwrap();
+        assert_eq!(restored.snapshot, written);
+
+        let new_pins = VkeyPins::new(VerificationMode::Strict);
+        restored.apply_pins(&new_pins);
+        new_pins.check(&program_hash(b"elf-a"), "vk-a").unwrap();
+
+        let new_queue = OfflineQueue::new(Arc::new(MockBackend::default()), OfflineQueueConfig::default());
+        assert_eq!(restored.requeue(&new_queue).unwrap().len(), 1);
+        assert_eq!(new_queue.parked_jobs(), vec![(b"elf-b".to_vec(), b"input".to_vec())]);
+
+        fs::write(dir.join(PROGRAMS_DIR).join(format!("{}.elf", program_hash(b"elf-a"))), b"tampered").unwrap();
+        assert!(matches!(restore(&dir), Err(ProverError::InvalidProgram(_))));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_restores_keys_cache_and_jobs() {
+        let dir = std::env::temp_dir().join(format!("frostgate-snapshot-{}", uuid::Uuid::new_v4()));
+        let vkeys = mock_vkeys();
+        vkeys.insert(program_hash(b"elf-a"), mock_vkey_hash(b"vk-a"), b"vk-a".to_vec());
+        let cache = ProgramCache::<u8>::new(ProgramCacheConfig::default());
+        cache.insert(program_hash(b"elf-a"), 1, 1);
+        cache.pin_program(&program_hash(b"elf-a"));
+
+        // One job still proving, one recorded as running, one finished
+        let slow = ProverJobManager::new(Arc::new(MockBackend::new(MockConfig {
+            prove_latency: Duration::from_millis(500),
+            ..MockConfig::default()
+        })));
+        slow.submit_prove(b"elf-b", b"input-b");
+        let record = |program: &[u8], status| JobRecord {
+            id: "00000000-0000-0000-0000-000000000001".parse().unwrap(),
+            program: program.to_vec(),
+            input: b"input-c".to_vec(),
+            public_inputs: b"bound".to_vec(),
+            idempotency_key: None,
+            status,
+            proof: None,
+            submitted_at: SystemTime::now(),
+            finished_at: None,
+        };
+        let mut done = record(b"elf-d", JobStatus::Completed);
+        done.id = "00000000-0000-0000-0000-000000000002".parse().unwrap();
+        let job_store = FixedJobs(vec![record(b"elf-c", JobStatus::Running), done]);
+
+        let sources = SnapshotSources {
+            programs: vec![b"elf-a"],
+            vkeys: Some(&vkeys),
+            cached_programs: cache.cached(),
+            jobs: Some(&slow),
+            job_store: Some(&job_store),
+            ..Default::default()
+        };
+        let written = snapshot(&dir, &sources).unwrap();
+        assert_eq!(written.running_jobs.len(), 2);
+        let restored = restore(&dir).unwrap();
+        assert_eq!(restored.snapshot, written);
+
+        // Proofs verify against the restored key without any setup
+        let new_vkeys = mock_vkeys();
+        assert_eq!(restored.import_vkeys(&new_vkeys).unwrap(), 1);
+        let proof = mock_committing_proof(b"vk-a", b"values");
+        assert_eq!(new_vkeys.verify(&program_hash(b"elf-a"), &proof).unwrap(), Some(true));
+
+        let setups = Arc::new(AtomicUsize::new(0));
+        let counter = setups.clone();
+        let new_cache = ProgramCache::<u8>::new(ProgramCacheConfig::default());
+        let results = restored
+            .rewarm(
+                &new_cache,
+                Arc::new(move |_| {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    Ok((1, 1))
+                }),
+            )
+            .await;
+        assert_eq!(results.len(), 1);
+        assert_eq!(setups.load(Ordering::SeqCst), 1);
+        assert_eq!(new_cache.cached(), cache.cached());
+
+        let guest = Arc::new(MockBackend::default().with_guest(Arc::new(|_, _| Ok(b"bound".to_vec()))));
+        let jobs = ProverJobManager::new(guest).with_extractor(crate::testing::mock_public_values());
+        let ids = restored.resubmit(&jobs).unwrap();
+        assert_eq!(ids.len(), 2);
+        for id in ids {
+            assert_eq!(jobs.wait(id).await, Some(JobStatus::Completed));
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,228 @@
+use crate::compat::SP1_VERSION;
+use crate::envelope::{DecodeLimits, ProofEnvelope, decode_envelope};
+use crate::estimation::CycleCounter;
+use crate::gpu::ProverMode;
+use crate::memory::RssProbe;
+use crate::types::ProverError;
+use frostgate_zkip::{ZkBackend, ZkError};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Metadata key for the guest's total cycle count
+pub const CYCLES_KEY: &str = "cycles";
+/// Metadata key for the number of shards the execution was split into
+pub const SHARDS_KEY: &str = "shards";
+/// Metadata key for the peak RSS of the process the proof ran in, in bytes
+pub const PEAK_RSS_KEY: &str = "peak_rss_bytes";
+/// Metadata key for the SP1 release the proof was produced with
+pub const SP1_VERSION_KEY: &str = "sp1_version";
+/// Metadata key for where the proof was produced: `cpu`, `cuda:<n>` or `network`
+pub const PROVER_MODE_KEY: &str = "prover_mode";
+
+/// SP1's default shard size, as a power of two
+const DEFAULT_SHARD_SIZE: u32 = 22;
+
+/// Resource figures of one proving run, stored in envelope metadata for
+/// capacity planning
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProvingStats {
+    pub cycles: Option<u64>,
+    pub shards: Option<u64>,
+    pub peak_rss_bytes: Option<u64>,
+    pub sp1_version: Option<String>,
+    pub prover_mode: Option<String>,
+}
+
+impl ProvingStats {
+    /// Write the known figures into `envelope`'s metadata and reseal it
+    pub fn record(&self, envelope: &mut ProofEnvelope) {
+        let numbers = [
+            (CYCLES_KEY, self.cycles),
+            (SHARDS_KEY, self.shards),
+            (PEAK_RSS_KEY, self.peak_rss_bytes),
+        ];
+        for (key, value) in numbers {
+            if let Some(value) = value {
+                envelope.metadata.insert(key.to_string(), value.to_string());
+            }
+        }
+        for (key, value) in [(SP1_VERSION_KEY, &self.sp1_version), (PROVER_MODE_KEY, &self.prover_mode)] {
+            if let Some(value) = value {
+                envelope.metadata.insert(key.to_string(), value.clone());
+            }
+        }
+        envelope.seal();
+    }
+
+    /// Read figures recorded by [`ProvingStats::record`]; missing or
+    /// malformed entries are left unset
+    pub fn from_envelope(envelope: &ProofEnvelope) -> Self {
+        let number = |key: &str| envelope.metadata.get(key).and_then(|value| value.parse().ok());
+        Self {
+            cycles: number(CYCLES_KEY),
+            shards: number(SHARDS_KEY),
+            peak_rss_bytes: number(PEAK_RSS_KEY),
+            sp1_version: envelope.metadata.get(SP1_VERSION_KEY).cloned(),
+            prover_mode: envelope.metadata.get(PROVER_MODE_KEY).cloned(),
+        }
+    }
+}
+
+/// Polls the RSS of the process proofs run in on a background thread,
+/// keeping the peak
+pub struct RssSampler {
+    stop: Arc<AtomicBool>,
+    peak: Arc<AtomicU64>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl RssSampler {
+    pub fn start(rss_bytes: RssProbe, interval: Duration) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let peak = Arc::new(AtomicU64::new(rss_bytes().unwrap_or(0)));
+        let handle = {
+            let (stop, peak) = (stop.clone(), peak.clone());
+            std::thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    if let Some(rss) = rss_bytes() {
+                        peak.fetch_max(rss, Ordering::Relaxed);
+                    }
+                    std::thread::park_timeout(interval);
+                }
+            })
+        };
+        Self {
+            stop,
+            peak,
+            handle: Some(handle),
+        }
+    }
+
+    /// Stop sampling and return the peak, `None` where RSS can't be read
+    pub fn finish(mut self) -> Option<u64> {
+        self.stop_thread();
+        match self.peak.load(Ordering::Relaxed) {
+            0 => None,
+            peak => Some(peak),
+        }
+    }
+
+    fn stop_thread(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            handle.thread().unpark();
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for RssSampler {
+    fn drop(&mut self) {
+        self.stop_thread();
+    }
+}
+
+/// Records [`ProvingStats`] in the envelopes produced by an
+/// [`EnvelopedBackend`](crate::envelope::EnvelopedBackend)
+pub struct StatsBackend {
+    inner: Arc<dyn ZkBackend>,
+    prover_mode: ProverMode,
+    rss_bytes: RssProbe,
+    count_cycles: Option<CycleCounter>,
+    shard_size: u32,
+    sample_interval: Duration,
+    limits: DecodeLimits,
+}
+
+impl StatsBackend {
+    /// Record stats of proofs run by `inner`, sampling the memory of the
+    /// process they run in with `rss_bytes`: [`own_rss`](crate::memory::own_rss)
+    /// for in-process backends, the key holder's from
+    /// [`IsolatedBackend::worker`](crate::isolation::IsolatedBackend::worker)
+    pub fn new(inner: Arc<dyn ZkBackend>, prover_mode: ProverMode, rss_bytes: RssProbe) -> Self {
+        Self {
+            inner,
+            prover_mode,
+            rss_bytes,
+            count_cycles: None,
+            shard_size: DEFAULT_SHARD_SIZE,
+            sample_interval: Duration::from_millis(100),
+            limits: DecodeLimits::default(),
+        }
+    }
+
+    /// Execute each request to record its cycle and shard counts
+    pub fn with_cycle_counter(mut self, count_cycles: CycleCounter) -> Self {
+        self.count_cycles = Some(count_cycles);
+        self
+    }
+
+    /// Shard size the backend proves with, as a power of two
+    pub fn with_shard_size(mut self, shard_size: u32) -> Self {
+        self.shard_size = shard_size;
+        self
+    }
+
+    fn prove_with_stats(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ProverError> {
+        let cycles = match &self.count_cycles {
+            Some(count_cycles) => Some(count_cycles(program, input)?),
+            None => None,
+        };
+        let sampler = RssSampler::start(self.rss_bytes.clone(), self.sample_interval);
+        let proof = self.inner.prove(program, input);
+        let peak_rss_bytes = sampler.finish();
+        let mut envelope = decode_envelope(&proof?, &self.limits)?;
+
+        let shard_cycles = 1u64.checked_shl(self.shard_size).unwrap_or(u64::MAX);
+        let stats = ProvingStats {
+            cycles,
+            shards: cycles.map(|cycles| cycles.div_ceil(shard_cycles).max(1)),
+            peak_rss_bytes,
+            sp1_version: Some(SP1_VERSION.to_string()),
+            prover_mode: Some(self.prover_mode.name()),
+        };
+        stats.record(&mut envelope);
+        envelope.to_bytes()
+    }
+}
+
+impl ZkBackend for StatsBackend {
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        self.prove_with_stats(program, input).map_err(ProverError::into_zk_error)
+    }
+
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        self.inner.verify(program, proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::envelope::EnvelopedBackend;
+    use crate::testing::MockBackend;
+
+    #[test]
+    fn test_stats_recorded() {
+        let enveloped = Arc::new(EnvelopedBackend::new(Arc::new(MockBackend::default()), "mock", "sp1"));
+        let counter: CycleCounter = Arc::new(|_, input| Ok(input.len() as u64 * 1000));
+        let rss_bytes: RssProbe = Arc::new(|| Some(42 << 20));
+        let backend = StatsBackend::new(enveloped, ProverMode::Cuda { device: 1 }, rss_bytes)
+            .with_cycle_counter(counter)
+            .with_shard_size(10);
+
+        let proof = backend.prove(b"elf", b"input").unwrap();
+        let envelope = decode_envelope(&proof, &DecodeLimits::default()).unwrap();
+        let stats = ProvingStats::from_envelope(&envelope);
+        assert_eq!(stats.cycles, Some(5000));
+        assert_eq!(stats.shards, Some(5));
+        assert_eq!(stats.peak_rss_bytes, Some(42 << 20));
+        assert_eq!(stats.sp1_version.as_deref(), Some(SP1_VERSION));
+        assert_eq!(stats.prover_mode.as_deref(), Some("cuda:1"));
+        assert!(envelope.checksum_valid());
+        assert!(backend.verify(b"elf", &proof).unwrap());
+    }
+}
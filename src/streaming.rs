@@ -0,0 +1,179 @@
+use crate::types::ProverError;
+use frostgate_zkip::{ZkBackend, ZkError};
+use std::io::Read;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::sync::mpsc;
+
+/// Size of the chunks streamed input is split into
+pub const DEFAULT_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+/// Chunks read ahead of the prover; bounds memory to about this many chunks
+const READ_AHEAD: usize = 2;
+
+/// Guest input delivered piece by piece, in order
+pub type InputChunks<'a> = dyn Iterator<Item = Result<Vec<u8>, ZkError>> + Send + 'a;
+
+/// Backends that consume guest input incrementally, so inputs far larger
+/// than memory can be proven. How chunks reach the guest is up to the
+/// backend; SP1 backends write each chunk as a separate stdin value (see
+/// `input::stdin_from_chunks`).
+pub trait StreamingBackend: ZkBackend {
+    fn prove_chunks(&self, program: &[u8], chunks: &mut InputChunks<'_>) -> Result<Vec<u8>, ZkError>;
+}
+
+/// Splits a reader into chunks of at most `chunk_size` bytes
+pub struct ReaderChunks<R> {
+    reader: R,
+    chunk_size: usize,
+    done: bool,
+}
+
+impl<R: Read> ReaderChunks<R> {
+    pub fn new(reader: R, chunk_size: usize) -> Self {
+        Self {
+            reader,
+            chunk_size: chunk_size.max(1),
+            done: false,
+        }
+    }
+}
+
+impl<R: Read> Iterator for ReaderChunks<R> {
+    type Item = Result<Vec<u8>, ZkError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let mut chunk = Vec::with_capacity(self.chunk_size);
+        match (&mut self.reader).take(self.chunk_size as u64).read_to_end(&mut chunk) {
+            Ok(0) => {
+                self.done = true;
+                None
+            }
+            Ok(_) => Some(Ok(chunk)),
+            Err(e) => {
+                self.done = true;
+                Some(Err(ZkError::Config(format!("reading input: {}", e))))
+            }
+        }
+    }
+}
+
+/// Adapts a backend without native streaming, unlike
+/// [`IsolatedBackend`](crate::isolation::IsolatedBackend), by collecting the
+/// chunks into one buffer, refusing inputs over `max_input_bytes` before
+/// they exhaust memory
+pub struct BufferedStreaming<B: ?Sized> {
+    inner: Arc<B>,
+    max_input_bytes: u64,
+}
+
+impl<B: ZkBackend + ?Sized> BufferedStreaming<B> {
+    pub fn new(inner: Arc<B>, max_input_bytes: u64) -> Self {
+        Self { inner, max_input_bytes }
+    }
+}
+
+impl<B: ZkBackend + ?Sized> ZkBackend for BufferedStreaming<B> {
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        self.inner.prove(program, input)
+    }
+
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        self.inner.verify(program, proof)
+    }
+}
+
+impl<B: ZkBackend + ?Sized> StreamingBackend for BufferedStreaming<B> {
+    fn prove_chunks(&self, program: &[u8], chunks: &mut InputChunks<'_>) -> Result<Vec<u8>, ZkError> {
+        let mut input = Vec::new();
+        for chunk in chunks {
+            let chunk = chunk?;
+            if (input.len() + chunk.len()) as u64 > self.max_input_bytes {
+                return Err(ZkError::Config(format!(
+                    "input exceeds {} bytes and {} can't stream it",
+                    self.max_input_bytes,
+                    std::any::type_name::<B>()
+                )));
+            }
+            input.extend_from_slice(&chunk);
+        }
+        self.inner.prove(program, &input)
+    }
+}
+
+/// Prove with input read from `input` until EOF. The input is read in
+/// `chunk_size` pieces on the async runtime while the backend consumes
+/// earlier pieces on a blocking thread, so only a few chunks are in memory
+/// at once.
+pub async fn prove_stream<R>(
+    backend: Arc<dyn StreamingBackend>,
+    program: Vec<u8>,
+    mut input: R,
+    chunk_size: usize,
+) -> Result<Vec<u8>, ProverError>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    let chunk_size = chunk_size.max(1);
+    let (tx, mut rx) = mpsc::channel::<Result<Vec<u8>, ZkError>>(READ_AHEAD);
+    let reader = tokio::spawn(async move {
+        loop {
+            let mut chunk = Vec::with_capacity(chunk_size);
+            let read = (&mut input).take(chunk_size as u64).read_to_end(&mut chunk).await;
+            let item = match read {
+                Ok(0) => return,
+                Ok(_) => Ok(chunk),
+                Err(e) => Err(ZkError::Config(format!("reading input: {}", e))),
+            };
+            let failed = item.is_err();
+            // The prover hung up, e.g. because it failed
+            if tx.send(item).await.is_err() || failed {
+                return;
+            }
+        }
+    });
+
+    let proof = tokio::task::spawn_blocking(move || {
+        let mut chunks = std::iter::from_fn(|| rx.blocking_recv());
+        backend.prove_chunks(&program, &mut chunks)
+    })
+    .await
+    .map_err(|e| ProverError::Other(format!("streaming prove task failed: {}", e)))?;
+    reader.abort();
+    Ok(proof?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockBackend;
+    use std::io::Cursor;
+
+    #[tokio::test]
+    async fn test_streamed_input_matches_buffered() {
+        let input: Vec<u8> = (0..10_000u32).map(|i| i as u8).collect();
+        let mock = Arc::new(MockBackend::default());
+        let backend = Arc::new(BufferedStreaming::new(mock.clone(), 1 << 20));
+
+        let proof = prove_stream(backend, b"elf".to_vec(), Cursor::new(input.clone()), 1024)
+            .await
+            .unwrap();
+        assert_eq!(proof, mock.prove(b"elf", &input).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_buffered_limit() {
+        let backend = Arc::new(BufferedStreaming::new(Arc::new(MockBackend::default()), 100));
+        let result = prove_stream(backend, b"elf".to_vec(), Cursor::new(vec![0u8; 1000]), 64).await;
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_reader_chunks() {
+        let chunks: Vec<Vec<u8>> = ReaderChunks::new(&b"abcdefg"[..], 3).map(Result::unwrap).collect();
+        assert_eq!(chunks, vec![b"abc".to_vec(), b"def".to_vec(), b"g".to_vec()]);
+    }
+}
@@ -0,0 +1,150 @@
+use crate::registry::BackendMiddleware;
+use crate::types::program_hash;
+use frostgate_zkip::{ZkBackend, ZkError};
+use std::sync::Arc;
+
+/// Backend wrapper recording a span around every prove and verify call,
+/// tagged with the backend id and program hash
+pub struct TracedBackend {
+    id: String,
+    inner: Arc<dyn ZkBackend>,
+}
+
+impl TracedBackend {
+    pub fn new(id: &str, inner: Arc<dyn ZkBackend>) -> Self {
+        Self {
+            id: id.to_string(),
+            inner,
+        }
+    }
+}
+
+impl ZkBackend for TracedBackend {
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        let span = tracing::info_span!(
+            "prove",
+            backend = %self.id,
+            program_hash = %program_hash(program),
+            input_bytes = input.len(),
+            proof_bytes = tracing::field::Empty,
+        );
+        let _guard = span.enter();
+        let result = self.inner.prove(program, input);
+        match &result {
+            Ok(proof) => {
+                span.record("proof_bytes", proof.len());
+            }
+            Err(e) => tracing::warn!("prove failed: {:?}", e),
+        }
+        result
+    }
+
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        let span = tracing::info_span!(
+            "verify",
+            backend = %self.id,
+            program_hash = %program_hash(program),
+            proof_bytes = proof.len(),
+            valid = tracing::field::Empty,
+        );
+        let _guard = span.enter();
+        let result = self.inner.verify(program, proof);
+        if let Ok(valid) = &result {
+            span.record("valid", valid);
+        }
+        result
+    }
+}
+
+/// Registry middleware wrapping every looked-up backend in a [`TracedBackend`]
+pub struct TracingMiddleware;
+
+impl BackendMiddleware for TracingMiddleware {
+    fn wrap(&self, id: &str, backend: Arc<dyn ZkBackend>) -> Arc<dyn ZkBackend> {
+        Arc::new(TracedBackend::new(id, backend))
+    }
+}
+
+/// Flushes and shuts down the OTLP exporter when dropped
+#[cfg(feature = "otel")]
+pub struct OtlpGuard {
+    provider: opentelemetry_sdk::trace::TracerProvider,
+}
+
+#[cfg(feature = "otel")]
+impl Drop for OtlpGuard {
+    fn drop(&mut self) {
+        if let Err(e) = self.provider.shutdown() {
+            tracing::error!("failed to shut down OTLP exporter: {}", e);
+        }
+    }
+}
+
+/// Install a global subscriber that logs to stderr and exports spans to the
+/// OTLP collector at `endpoint` (e.g. `http://localhost:4317`). Log levels
+/// follow `RUST_LOG`, defaulting to `info`. Must be called from within a
+/// tokio runtime.
+#[cfg(feature = "otel")]
+pub fn init_otlp(service_name: &str, endpoint: &str) -> Result<OtlpGuard, crate::types::ProverError> {
+    use crate::types::ProverError;
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_otlp::WithExportConfig;
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::util::SubscriberInitExt;
+
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()
+        .map_err(|e| ProverError::Other(format!("cannot build OTLP exporter: {}", e)))?;
+    let provider = opentelemetry_sdk::trace::TracerProvider::builder()
+        .with_batch_exporter(exporter, opentelemetry_sdk::runtime::Tokio)
+        .with_resource(opentelemetry_sdk::Resource::new([opentelemetry::KeyValue::new(
+            "service.name",
+            service_name.to_string(),
+        )]))
+        .build();
+    let tracer = provider.tracer("frostgate-prover");
+
+    let filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info"));
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer())
+        .with(tracing_opentelemetry::layer().with_tracer(tracer))
+        .try_init()
+        .map_err(|e| ProverError::Other(format!("cannot install tracing subscriber: {}", e)))?;
+    opentelemetry::global::set_tracer_provider(provider.clone());
+    Ok(OtlpGuard { provider })
+}
+
+/// [`init_otlp`] with the configured `otlp_endpoint`. Without one no
+/// exporter or subscriber is installed and `None` is returned, leaving the
+/// caller to set up logging.
+#[cfg(feature = "otel")]
+pub fn init_otlp_from_config(
+    service_name: &str,
+    config: &crate::config::ProverConfig,
+) -> Result<Option<OtlpGuard>, crate::types::ProverError> {
+    config
+        .otlp_endpoint
+        .as_deref()
+        .map(|endpoint| init_otlp(service_name, endpoint))
+        .transpose()
+}
+
+#[cfg(all(test, feature = "otel"))]
+mod tests {
+    use super::*;
+    use crate::config::{ConfigLayer, ProverConfig};
+
+    #[tokio::test]
+    async fn test_no_exporter_without_endpoint() {
+        assert!(init_otlp_from_config("test", &ProverConfig::default()).unwrap().is_none());
+        assert!(!tracing::dispatcher::has_been_set());
+
+        let vars = [("FROSTGATE_OTLP_ENDPOINT".to_string(), "http://localhost:4317".to_string())];
+        let config = ConfigLayer::from_vars(vars).unwrap().resolve().unwrap();
+        assert_eq!(config.otlp_endpoint.as_deref(), Some("http://localhost:4317"));
+    }
+}
@@ -0,0 +1,405 @@
+use crate::binding::PublicValuesExtractor;
+use crate::envelope::ProofEnvelope;
+use crate::inspect::PROOF_SYSTEM_KEY;
+use crate::isolation::KeyHolderConfig;
+use crate::offline::OfflineQueue;
+use crate::store::{ProofId, ProofStore, proof_id};
+use crate::types::{ProverError, program_hash};
+use crate::vkeys::VkeyStore;
+use async_trait::async_trait;
+use frostgate_zkip::types::ZkConfig;
+use frostgate_zkip::zkplug::ZkPlug;
+use frostgate_zkip::{ZkBackend, ZkError};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+/// Prefix of every proof produced by [`MockBackend`]
+pub const MOCK_PROOF_PREFIX: &[u8] = b"frostgate-mock-proof:";
+
+/// Behaviour of a [`MockBackend`]
+#[derive(Debug, Clone, Default)]
+pub struct MockConfig {
+    /// Delay applied to every prove call
+    pub prove_latency: Duration,
+    /// Delay applied to every verify call
+    pub verify_latency: Duration,
+    /// Fail the first `n` prove calls
+    pub fail_first_proves: usize,
+    /// Fail every `n`th prove call, counting from one
+    pub fail_every_nth_prove: Option<usize>,
+    /// Make every verify call fail
+    pub fail_verify: bool,
+}
+
+/// Public values a mock guest commits when run on a program and input
+pub type MockGuest = Arc<dyn Fn(&[u8], &[u8]) -> Result<Vec<u8>, ZkError> + Send + Sync>;
+
+/// Deterministic backend for tests: proofs are a hash of program and input
+#[derive(Default)]
+pub struct MockBackend {
+    config: MockConfig,
+    guest: Option<MockGuest>,
+    prove_calls: AtomicUsize,
+    verify_calls: AtomicUsize,
+    injected: Mutex<VecDeque<ZkError>>,
+}
+
+impl MockBackend {
+    pub fn new(config: MockConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// Commit the public values `guest` computes in every proof, see
+    /// [`mock_committing_proof`]
+    pub fn with_guest(mut self, guest: MockGuest) -> Self {
+        self.guest = Some(guest);
+        self
+    }
+
+    pub fn prove_calls(&self) -> usize {
+        self.prove_calls.load(Ordering::SeqCst)
+    }
+
+    pub fn verify_calls(&self) -> usize {
+        self.verify_calls.load(Ordering::SeqCst)
+    }
+
+    /// Make the next prove call fail with `error`. Injected errors queue up
+    /// and are returned before any configured failures.
+    pub fn inject_failure(&self, error: ZkError) {
+        self.injected
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push_back(error);
+    }
+}
+
+/// The proof [`MockBackend`] produces for `program` and `input`
+pub fn mock_proof(program: &[u8], input: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha3_256::new();
+    hasher.update(program);
+    hasher.update(input);
+    [MOCK_PROOF_PREFIX, hasher.finalize().as_slice()].concat()
+}
+
+/// The proof [`MockBackend`] produces for `program` when its guest commits
+/// `public_values`: the values follow a hash binding them to the program
+pub fn mock_committing_proof(program: &[u8], public_values: &[u8]) -> Vec<u8> {
+    [mock_proof(program, public_values).as_slice(), public_values].concat()
+}
+
+/// Extracts the public values of a [`mock_committing_proof`]
+pub fn mock_public_values() -> PublicValuesExtractor {
+    Arc::new(|proof| {
+        if !proof.starts_with(MOCK_PROOF_PREFIX) || proof.len() < MOCK_PROOF_PREFIX.len() + 32 {
+            return Err(ZkError::Config("not a mock proof".to_string()));
+        }
+        Ok(proof[MOCK_PROOF_PREFIX.len() + 32..].to_vec())
+    })
+}
+
+impl ZkBackend for MockBackend {
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        let call = self.prove_calls.fetch_add(1, Ordering::SeqCst);
+        if !self.config.prove_latency.is_zero() {
+            std::thread::sleep(self.config.prove_latency);
+        }
+        if let Some(error) = self.injected.lock().unwrap_or_else(PoisonError::into_inner).pop_front() {
+            return Err(error);
+        }
+        let nth_failure = self.config.fail_every_nth_prove.is_some_and(|n| n > 0 && (call + 1).is_multiple_of(n));
+        if call < self.config.fail_first_proves || nth_failure {
+            return Err(ZkError::Config(format!("mock prove failure {}", call + 1)));
+        }
+        match &self.guest {
+            Some(guest) => Ok(mock_committing_proof(program, &guest(program, input)?)),
+            None => Ok(mock_proof(program, input)),
+        }
+    }
+
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        self.verify_calls.fetch_add(1, Ordering::SeqCst);
+        if !self.config.verify_latency.is_zero() {
+            std::thread::sleep(self.config.verify_latency);
+        }
+        if self.config.fail_verify {
+            return Err(ZkError::Config("mock verify failure".to_string()));
+        }
+        if self.guest.is_some() {
+            let split = MOCK_PROOF_PREFIX.len() + 32;
+            return Ok(proof.len() >= split && proof[..split] == mock_proof(program, &proof[split..])[..]);
+        }
+        Ok(proof.starts_with(MOCK_PROOF_PREFIX) && proof.len() == MOCK_PROOF_PREFIX.len() + 32)
+    }
+}
+
+/// Vkey hash of a mock verifying key
+pub fn mock_vkey_hash(vk: &[u8]) -> String {
+    format!("0x{}", hex::encode(Sha3_256::digest(vk)))
+}
+
+/// Key store for mock keys: hashed with [`mock_vkey_hash`], and verifying
+/// [`mock_committing_proof`]s with the key's bytes as the program
+pub fn mock_vkeys() -> VkeyStore {
+    VkeyStore::new().with_vk_ops(
+        Arc::new(|vk| Ok(mock_vkey_hash(vk))),
+        Arc::new(|vk, proof| {
+            let split = MOCK_PROOF_PREFIX.len() + 32;
+            Ok(Some(proof.len() >= split && proof[..split] == mock_proof(vk, &proof[split..])[..]))
+        }),
+    )
+}
+
+/// Proof made by a [`MockPlug`]: the [`MockBackend`] proof of its input as
+/// the program and its public inputs as the backend input
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MockPlugProof {
+    pub input: Vec<u8>,
+    pub public_inputs: Vec<u8>,
+    pub proof: Vec<u8>,
+}
+
+/// Mock [`ZkPlug`] behaving like a [`MockBackend`] with the same config,
+/// except that its latency is awaited rather than blocking the thread
+pub struct MockPlug {
+    config: MockConfig,
+    backend: MockBackend,
+}
+
+impl MockPlug {
+    pub fn new(config: MockConfig) -> Self {
+        let backend = MockBackend::new(MockConfig {
+            prove_latency: Duration::ZERO,
+            verify_latency: Duration::ZERO,
+            ..config.clone()
+        });
+        Self { config, backend }
+    }
+
+    pub fn prove_calls(&self) -> usize {
+        self.backend.prove_calls()
+    }
+
+    pub fn verify_calls(&self) -> usize {
+        self.backend.verify_calls()
+    }
+
+    /// See [`MockBackend::inject_failure`]
+    pub fn inject_failure(&self, error: ZkError) {
+        self.backend.inject_failure(error);
+    }
+}
+
+impl Default for MockPlug {
+    fn default() -> Self {
+        Self::new(MockConfig::default())
+    }
+}
+
+#[async_trait]
+impl ZkPlug for MockPlug {
+    type Proof = MockPlugProof;
+    type Error = ZkError;
+
+    async fn prove(
+        &self,
+        input: &[u8],
+        public_inputs: Option<&[u8]>,
+        _config: Option<&ZkConfig>,
+    ) -> Result<MockPlugProof, ZkError> {
+        tokio::time::sleep(self.config.prove_latency).await;
+        let public_inputs = public_inputs.unwrap_or_default().to_vec();
+        let proof = self.backend.prove(input, &public_inputs)?;
+        Ok(MockPlugProof {
+            input: input.to_vec(),
+            public_inputs,
+            proof,
+        })
+    }
+
+    /// Given public inputs must be the ones proved
+    async fn verify(
+        &self,
+        proof: &MockPlugProof,
+        public_inputs: Option<&[u8]>,
+        _config: Option<&ZkConfig>,
+    ) -> Result<bool, ZkError> {
+        tokio::time::sleep(self.config.verify_latency).await;
+        let valid = self.backend.verify(&proof.input, &proof.proof)?;
+        Ok(valid
+            && proof.proof == mock_proof(&proof.input, &proof.public_inputs)
+            && public_inputs.is_none_or(|public_inputs| public_inputs == proof.public_inputs))
+    }
+}
+
+/// The envelope an [`EnvelopedBackend`](crate::envelope::EnvelopedBackend)
+/// for backend `mock` and proof system `sp1`, extracting with
+/// [`mock_public_values`], makes of a [`MockBackend`] guest committing
+/// `public_values` for `program`
+pub fn canned_envelope(program: &[u8], public_values: &[u8]) -> ProofEnvelope {
+    let proof = mock_committing_proof(program, public_values);
+    let mut envelope = ProofEnvelope::new("mock", program_hash(program), proof, public_values.to_vec());
+    envelope.insert_metadata(PROOF_SYSTEM_KEY, "sp1");
+    envelope
+}
+
+/// A key holder that answers verify requests with `true` and never answers
+/// a prove request, for exercising aborts. Needs a Unix shell.
+pub fn stalling_key_holder() -> KeyHolderConfig {
+    let script = r#"while read -r line; do case "$line" in *'"prove"'*) exec sleep 600;; *) echo '{"verified":true}';; esac; done"#;
+    KeyHolderConfig {
+        program: PathBuf::from("/bin/sh"),
+        args: vec!["-c".to_string(), script.to_string()],
+        env: vec![("PATH".to_string(), "/bin:/usr/bin".to_string())],
+    }
+}
+
+/// In-memory [`ProofStore`], addressing proofs by content like the real
+/// stores
+#[derive(Default)]
+pub struct FakeProofStore {
+    proofs: Mutex<HashMap<ProofId, ProofEnvelope>>,
+}
+
+impl FakeProofStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.proofs.lock().unwrap_or_else(PoisonError::into_inner).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl ProofStore for FakeProofStore {
+    fn put(&self, envelope: &ProofEnvelope) -> Result<ProofId, ProverError> {
+        let id = proof_id(envelope)?;
+        self.proofs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(id.clone(), envelope.clone());
+        Ok(id)
+    }
+
+    fn get(&self, id: &str) -> Result<Option<ProofEnvelope>, ProverError> {
+        Ok(self.proofs.lock().unwrap_or_else(PoisonError::into_inner).get(id).cloned())
+    }
+
+    fn ids_for_program(&self, program_hash: &str) -> Result<Vec<ProofId>, ProverError> {
+        let proofs = self.proofs.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut ids: Vec<ProofId> = proofs
+            .iter()
+            .filter(|(_, envelope)| envelope.program_hash == program_hash)
+            .map(|(id, _)| id.clone())
+            .collect();
+        ids.sort();
+        Ok(ids)
+    }
+}
+
+/// Run retry rounds on `queue` until it is empty or `max_rounds` is reached,
+/// without waiting on the retry timer. Returns the number of rounds run.
+pub fn drain_offline_queue(queue: &OfflineQueue, max_rounds: usize) -> usize {
+    let mut rounds = 0;
+    while !queue.is_empty() && rounds < max_rounds {
+        queue.retry_parked();
+        rounds += 1;
+    }
+    rounds
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failure_injection() {
+        let backend = MockBackend::new(MockConfig {
+            fail_every_nth_prove: Some(3),
+            ..MockConfig::default()
+        });
+        backend.inject_failure(ZkError::Config("injected".to_string()));
+
+        let outcomes: Vec<bool> = (0..6).map(|_| backend.prove(b"elf", b"input").is_ok()).collect();
+        assert_eq!(outcomes, vec![false, true, false, true, true, false]);
+    }
+
+    #[test]
+    fn test_canned_envelope_matches_enveloped_backend() {
+        use crate::envelope::{DecodeLimits, EnvelopedBackend, decode_envelope};
+        let guest = MockBackend::default().with_guest(Arc::new(|_, input| Ok(input.to_vec())));
+        let enveloped = EnvelopedBackend::new(Arc::new(guest), "mock", "sp1").with_extractor(mock_public_values());
+        let proof = enveloped.prove(b"elf", b"values").unwrap();
+
+        let canned = canned_envelope(b"elf", b"values");
+        assert_eq!(decode_envelope(&proof, &DecodeLimits::default()).unwrap(), canned);
+        assert!(enveloped.verify(b"elf", &canned.to_bytes().unwrap()).unwrap());
+    }
+
+    #[test]
+    fn test_fake_proof_store() {
+        let store = FakeProofStore::new();
+        let envelope = canned_envelope(b"elf", b"values");
+        let id = store.put(&envelope).unwrap();
+        assert_eq!(store.put(&envelope).unwrap(), id);
+        assert_eq!(store.get(&id).unwrap(), Some(envelope.clone()));
+        assert_eq!(store.get("00").unwrap(), None);
+        assert_eq!(store.ids_for_program(&envelope.program_hash).unwrap(), vec![id]);
+        assert_eq!(store.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_mock_plug() {
+        let plug = MockPlug::new(MockConfig {
+            fail_first_proves: 1,
+            ..MockConfig::default()
+        });
+
+        assert!(plug.prove(b"elf", Some(b"input"), None).await.is_err());
+        let proof = plug.prove(b"elf", Some(b"input"), None).await.unwrap();
+        assert_eq!(proof.proof, mock_proof(b"elf", b"input"));
+        assert!(plug.verify(&proof, None, None).await.unwrap());
+        assert!(plug.verify(&proof, Some(b"input"), None).await.unwrap());
+        assert!(!plug.verify(&proof, Some(b"other"), None).await.unwrap());
+
+        let mut forged = proof.clone();
+        forged.public_inputs = b"other".to_vec();
+        assert!(!plug.verify(&forged, None, None).await.unwrap());
+        assert_eq!((plug.prove_calls(), plug.verify_calls()), (2, 4));
+
+        plug.inject_failure(ZkError::Config("injected".to_string()));
+        assert!(plug.prove(b"elf", None, None).await.is_err());
+        let failing = MockPlug::new(MockConfig {
+            fail_verify: true,
+            ..MockConfig::default()
+        });
+        assert!(failing.verify(&proof, None, None).await.is_err());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_mock_plug_latency_is_awaited() {
+        let plug = MockPlug::new(MockConfig {
+            prove_latency: Duration::from_millis(100),
+            ..MockConfig::default()
+        });
+
+        let start = std::time::Instant::now();
+        let (a, b) = tokio::join!(plug.prove(b"elf", Some(b"a"), None), plug.prove(b"elf", Some(b"b"), None));
+        assert!(a.is_ok() && b.is_ok());
+        // Both slept at once on the one runtime thread
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(100) && elapsed < Duration::from_millis(200));
+    }
+}
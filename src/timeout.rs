@@ -0,0 +1,301 @@
+use crate::cancel::{Abort, AbortableBackend};
+use crate::scheduler::Priority;
+use crate::types::ProverError;
+use frostgate_zkip::{ZkBackend, ZkError};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Per-request limits on how long an operation may run, and how it is
+/// scheduled
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProveOptions {
+    /// Maximum run time, measured from the start of the call
+    pub timeout: Option<Duration>,
+    /// Absolute point after which the result is no longer wanted
+    pub deadline: Option<Instant>,
+    /// Tenant the request is accounted to by the [`FairScheduler`]
+    ///
+    /// [`FairScheduler`]: crate::scheduler::FairScheduler
+    pub tenant: Option<String>,
+    pub priority: Priority,
+}
+
+impl ProveOptions {
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_deadline(deadline: Instant) -> Self {
+        Self {
+            deadline: Some(deadline),
+            ..Self::default()
+        }
+    }
+
+    pub fn for_tenant(mut self, tenant: &str, priority: Priority) -> Self {
+        self.tenant = Some(tenant.to_string());
+        self.priority = priority;
+        self
+    }
+
+    /// Time left from `now`, the tighter of the timeout and the deadline
+    pub fn remaining(&self, now: Instant) -> Option<Duration> {
+        let from_deadline = self.deadline.map(|deadline| deadline.saturating_duration_since(now));
+        match (self.timeout, from_deadline) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+/// Backend wrapper that fails calls exceeding their time limit with
+/// [`ProverError::Timeout`].
+///
+/// The call runs on a helper thread. Built with
+/// [`TimeoutBackend::abortable`], a call is timed from when it starts
+/// running rather than from when it was queued (deadlines aside), and on
+/// expiry it alone is stopped and the timeout returned once it has unwound.
+/// Otherwise the timeout is returned at once and the work, which can't be
+/// stopped, finishes on its own with its result discarded.
+pub struct TimeoutBackend {
+    inner: Arc<dyn ZkBackend>,
+    abortable: Option<Arc<dyn AbortableBackend>>,
+    prove_options: ProveOptions,
+    verify_options: ProveOptions,
+}
+
+impl TimeoutBackend {
+    /// Apply `options` to every prove call; verify calls are unlimited
+    pub fn new(inner: Arc<dyn ZkBackend>, options: ProveOptions) -> Self {
+        Self {
+            inner,
+            abortable: None,
+            prove_options: options,
+            verify_options: ProveOptions::default(),
+        }
+    }
+
+    /// Like [`TimeoutBackend::new`], stopping timed out calls, e.g. by
+    /// killing the key holder of an
+    /// [`IsolatedBackend`](crate::isolation::IsolatedBackend) while it
+    /// answers them
+    pub fn abortable(inner: Arc<dyn AbortableBackend>, options: ProveOptions) -> Self {
+        Self {
+            abortable: Some(inner.clone()),
+            ..Self::new(inner, options)
+        }
+    }
+
+    pub fn with_verify_options(mut self, options: ProveOptions) -> Self {
+        self.verify_options = options;
+        self
+    }
+
+    /// Prove with limits for this request only
+    pub fn prove_with_options(
+        &self,
+        program: &[u8],
+        input: &[u8],
+        options: &ProveOptions,
+    ) -> Result<Vec<u8>, ProverError> {
+        let program = program.to_vec();
+        let input = input.to_vec();
+        match &self.abortable {
+            Some(inner) => {
+                let inner = inner.clone();
+                run_with_limit("prove", options, true, move |started| {
+                    inner.prove_abortable(&program, &input, started)
+                })
+            }
+            None => {
+                let inner = self.inner.clone();
+                run_with_limit("prove", options, false, move |_| inner.prove(&program, &input))
+            }
+        }
+    }
+
+    /// Verify with limits for this request only
+    pub fn verify_with_options(&self, program: &[u8], proof: &[u8], options: &ProveOptions) -> Result<bool, ProverError> {
+        let program = program.to_vec();
+        let proof = proof.to_vec();
+        match &self.abortable {
+            Some(inner) => {
+                let inner = inner.clone();
+                run_with_limit("verify", options, true, move |started| {
+                    inner.verify_abortable(&program, &proof, started)
+                })
+            }
+            None => {
+                let inner = self.inner.clone();
+                run_with_limit("verify", options, false, move |_| inner.verify(&program, &proof))
+            }
+        }
+    }
+}
+
+enum Progress<T> {
+    Started(Instant, Abort),
+    Finished(Result<T, ZkError>),
+}
+
+/// Run `f` on a helper thread within `options`. An `abortable` call is
+/// timed from when it reports having started; others from now.
+fn run_with_limit<T, F>(op: &str, options: &ProveOptions, abortable: bool, f: F) -> Result<T, ProverError>
+where
+    T: Send + 'static,
+    F: FnOnce(&(dyn Fn(Abort) + Sync)) -> Result<T, ZkError> + Send + 'static,
+{
+    let now = Instant::now();
+    let Some(mut limit) = options.remaining(now) else {
+        return Ok(f(&|_| {})?);
+    };
+    if limit.is_zero() {
+        return Err(ProverError::Timeout(format!("{} deadline already passed", op)));
+    }
+
+    // Set once the caller has given up; a call starting after that stops itself
+    let abandoned = Arc::new(Mutex::new(false));
+    let (tx, rx) = mpsc::channel();
+    let starts = tx.clone();
+    let given_up = abandoned.clone();
+    std::thread::Builder::new()
+        .name(format!("frostgate-{}", op))
+        .spawn(move || {
+            let started = move |abort: Abort| {
+                let given_up = given_up.lock().unwrap_or_else(PoisonError::into_inner);
+                if *given_up {
+                    abort();
+                }
+                let _ = starts.send(Progress::Started(Instant::now(), abort));
+            };
+            let _ = tx.send(Progress::Finished(f(&started)));
+        })?;
+
+    // Until an abortable call starts running, only the deadline applies
+    let mut expires = if abortable { options.deadline } else { Some(now + limit) };
+    let mut abort = None;
+    loop {
+        let progress = match expires {
+            Some(expires) => rx.recv_timeout(expires.saturating_duration_since(Instant::now())),
+            None => rx.recv().map_err(|_| mpsc::RecvTimeoutError::Disconnected),
+        };
+        match progress {
+            Ok(Progress::Started(at, call_abort)) => {
+                limit = options.remaining(at).unwrap_or(limit);
+                expires = Some(at + limit);
+                abort = Some(call_abort);
+            }
+            Ok(Progress::Finished(result)) => return Ok(result?),
+            Err(mpsc::RecvTimeoutError::Timeout) => break,
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                return Err(ProverError::Other(format!("{} thread panicked", op)));
+            }
+        }
+    }
+
+    let abort = abort.or_else(|| {
+        let mut given_up = abandoned.lock().unwrap_or_else(PoisonError::into_inner);
+        *given_up = true;
+        rx.try_iter().find_map(|progress| match progress {
+            Progress::Started(_, abort) => Some(abort),
+            Progress::Finished(_) => None,
+        })
+    });
+    match abort {
+        Some(abort) => {
+            tracing::warn!("{} exceeded its {:?} limit, aborting it", op, limit);
+            abort();
+            // Its result, if any, is discarded
+            let _ = rx.iter().find(|progress| matches!(progress, Progress::Finished(_)));
+        }
+        None => tracing::warn!("{} exceeded its {:?} limit, leaving it to finish on its own", op, limit),
+    }
+    Err(ProverError::Timeout(format!("{} exceeded {:?}", op, limit)))
+}
+
+impl ZkBackend for TimeoutBackend {
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        self.prove_with_options(program, input, &self.prove_options)
+            .map_err(ProverError::into_zk_error)
+    }
+
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        self.verify_with_options(program, proof, &self.verify_options)
+            .map_err(ProverError::into_zk_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::isolation::IsolatedBackend;
+    use crate::testing::{MockBackend, MockConfig, stalling_key_holder};
+
+    #[test]
+    fn test_prove_times_out() {
+        let key_holder = Arc::new(IsolatedBackend::spawn(&stalling_key_holder()).unwrap());
+        let limit = ProveOptions::with_timeout(Duration::from_millis(50));
+        let backend = TimeoutBackend::abortable(key_holder, limit.clone());
+
+        let err = backend.prove_with_options(b"elf", b"input", &limit).unwrap_err();
+        assert!(matches!(err, ProverError::Timeout(_)));
+        // The stalled key holder was killed and replaced
+        assert!(backend.verify(b"elf", b"proof").unwrap());
+        assert!(backend.prove(b"elf", b"input").is_err());
+    }
+
+    #[test]
+    fn test_queued_call_timed_from_start() {
+        let key_holder = Arc::new(IsolatedBackend::spawn(&stalling_key_holder()).unwrap());
+        let limit = ProveOptions::with_timeout(Duration::from_millis(100));
+        let backend = Arc::new(TimeoutBackend::abortable(key_holder, limit.clone()));
+
+        let start = Instant::now();
+        let calls: Vec<_> = (0..2)
+            .map(|_| {
+                let backend = backend.clone();
+                let limit = limit.clone();
+                std::thread::spawn(move || backend.prove_with_options(b"elf", b"input", &limit))
+            })
+            .collect();
+        for call in calls {
+            // Each ran out its own time: the first one's abort didn't fail the second
+            assert!(matches!(call.join().unwrap(), Err(ProverError::Timeout(_))));
+        }
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        assert!(backend.verify(b"elf", b"proof").unwrap());
+    }
+
+    #[test]
+    fn test_unabortable_work_returns_promptly() {
+        let slow = MockBackend::new(MockConfig {
+            prove_latency: Duration::from_millis(300),
+            ..MockConfig::default()
+        });
+        let limit = ProveOptions::with_timeout(Duration::from_millis(20));
+        let backend = TimeoutBackend::new(Arc::new(slow), limit.clone());
+
+        let start = Instant::now();
+        let err = backend.prove_with_options(b"elf", b"input", &limit).unwrap_err();
+        assert!(matches!(err, ProverError::Timeout(_)));
+        assert!(start.elapsed() < Duration::from_millis(300));
+    }
+
+    #[test]
+    fn test_within_limit() {
+        let backend = TimeoutBackend::new(
+            Arc::new(MockBackend::default()),
+            ProveOptions::with_deadline(Instant::now() + Duration::from_secs(5)),
+        );
+        assert!(backend.prove(b"elf", b"input").is_ok());
+        assert!(
+            backend
+                .prove_with_options(b"elf", b"input", &ProveOptions::with_deadline(Instant::now()))
+                .is_err()
+        );
+    }
+}
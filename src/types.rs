@@ -1,23 +1,417 @@
+use crate::config::ConfigError;
 use frostgate_zkip::zkplug::*;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+use std::time::Duration;
 
 pub type ProgramHash = String;
 
+/// Marks backend errors made by [`ProverError::into_zk_error`], which are
+/// `frostgate:<code>:<kind>: <message>`
+const ZK_ERROR_TAG: &str = "frostgate:";
+
+/// Compute the hash identifying a program
+pub fn program_hash(program: &[u8]) -> ProgramHash {
+  hex::encode(Sha3_256::digest(program))
+}
+
+/// Compare digests in time independent of where they differ
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+  if a.len() != b.len() {
+    return false;
+  }
+  let diff = a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y));
+  std::hint::black_box(diff) == 0
+}
+
 #[derive(Debug)]
 pub enum ProverError {
   ZKError(ZkError),
   ProgramNotFound,
+  InvalidProgram(String),
+  PolicyViolation(String),
+  SignatureInvalid(String),
+  PublicInputsMismatch,
+  MalformedEnvelope(String),
+  UnsupportedProofVersion(String),
+  ReplayDetected(String),
+  VkeyMismatch { program_hash: ProgramHash, expected: Option<String>, found: String },
+  /// An idempotency key was resubmitted with a different program or input
+  IdempotencyConflict { key: String },
+  QueueFull,
+  /// Rejected by admission control; the caller may retry after `retry_after`
+  Busy { reason: String, retry_after: Duration },
+  InsufficientCapacity(String),
+  Degraded(String),
+  NetworkUnavailable(String),
+  Timeout(String),
+  Cancelled,
+  BudgetExceeded { requested: u64, remaining: u64 },
+  QuotaExceeded { scope: String, resource: String, limit: u64, requested: u64 },
+  Config(ConfigError),
   IOError(std::io::Error),
+  SerializationError(serde_json::Error),
   Other(String),
 }
 
+/// Whether retrying a failed operation may succeed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "rest", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+  /// Timeouts and connectivity failures
+  Transient,
+  /// Out of memory, capacity or queue slots; may clear up after a while
+  ResourceExhausted,
+  /// Bad input, invalid programs, policy rejections; retrying won't help
+  Fatal,
+}
+
+impl ErrorKind {
+  pub fn is_retryable(&self) -> bool {
+    !matches!(self, ErrorKind::Fatal)
+  }
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      ErrorKind::Transient => "transient",
+      ErrorKind::ResourceExhausted => "resource_exhausted",
+      ErrorKind::Fatal => "fatal",
+    }
+  }
+
+  /// Parse a kind name as produced by [`ErrorKind::name`]
+  pub fn parse(name: &str) -> Option<Self> {
+    match name {
+      "transient" => Some(ErrorKind::Transient),
+      "resource_exhausted" => Some(ErrorKind::ResourceExhausted),
+      "fatal" => Some(ErrorKind::Fatal),
+      _ => None,
+    }
+  }
+}
+
+/// Code and kind of the [`ProverError`] a backend error was converted from
+/// by [`ProverError::into_zk_error`], if it was
+pub fn carried_error(e: &ZkError) -> Option<(u32, ErrorKind)> {
+  let text = format!("{:?}", e);
+  let (_, tagged) = text.split_once(ZK_ERROR_TAG)?;
+  let mut fields = tagged.splitn(3, ':');
+  let code = fields.next()?.parse().ok()?;
+  let kind = ErrorKind::parse(fields.next()?)?;
+  Some((code, kind))
+}
+
+/// Whether `text` contains `phrase` as whole words, ignoring case and
+/// punctuation, so "oom" matches "OOM killed" but not "room"
+pub fn mentions(text: &str, phrase: &str) -> bool {
+  let words = |s: &str| -> Vec<String> {
+    s.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()).map(str::to_lowercase).collect()
+  };
+  let (text, phrase) = (words(text), words(phrase));
+  !phrase.is_empty() && text.windows(phrase.len()).any(|window| window == phrase)
+}
+
+/// Classify a backend error by the error it carries, or else from its message
+pub fn classify_zk_error(e: &ZkError) -> ErrorKind {
+  if let Some((_, kind)) = carried_error(e) {
+    return kind;
+  }
+  let msg = format!("{:?}", e).to_lowercase();
+  let any = |needles: &[&str]| needles.iter().any(|needle| msg.contains(needle));
+  if any(&["timeout", "timed out", "network", "connect", "unreachable", "dns", "unavailable", "503", "429"]) {
+    ErrorKind::Transient
+  } else if any(&["out of memory", "oom", "resource exhausted", "too many", "capacity", "busy"]) {
+    ErrorKind::ResourceExhausted
+  } else {
+    ErrorKind::Fatal
+  }
+}
+
+impl ProverError {
+  /// Stable numeric code identifying the error variant. Codes are grouped by
+  /// thousands: 1xxx bad input, 2xxx rejected by policy, 3xxx out of
+  /// capacity, 4xxx temporarily unavailable, 5xxx internal. Codes are never
+  /// reused once assigned.
+  pub fn code(&self) -> u32 {
+    match self {
+      ProverError::ProgramNotFound => 1001,
+      ProverError::InvalidProgram(_) => 1002,
+      ProverError::PublicInputsMismatch => 1003,
+      ProverError::MalformedEnvelope(_) => 1004,
+      ProverError::UnsupportedProofVersion(_) => 1005,
+      ProverError::IdempotencyConflict { .. } => 1006,
+      ProverError::Config(_) => 1007,
+      ProverError::PolicyViolation(_) => 2001,
+      ProverError::SignatureInvalid(_) => 2002,
+      ProverError::ReplayDetected(_) => 2003,
+      ProverError::VkeyMismatch { .. } => 2004,
+      ProverError::QueueFull => 3001,
+      ProverError::InsufficientCapacity(_) => 3002,
+      ProverError::BudgetExceeded { .. } => 3003,
+      ProverError::QuotaExceeded { .. } => 3004,
+      ProverError::Busy { .. } => 3005,
+      ProverError::NetworkUnavailable(_) => 4001,
+      ProverError::Degraded(_) => 4002,
+      ProverError::Timeout(_) => 4003,
+      ProverError::Other(_) => 5000,
+      ProverError::ZKError(e) => carried_error(e).map_or(5001, |(code, _)| code),
+      ProverError::IOError(_) => 5002,
+      ProverError::SerializationError(_) => 5003,
+      ProverError::Cancelled => 5004,
+    }
+  }
+
+  pub fn is_retryable(&self) -> bool {
+    self.kind().is_retryable()
+  }
+
+  /// Serializable summary for RPC responses and logs, keeping the messages
+  /// of the whole source chain
+  pub fn to_report(&self) -> ErrorReport {
+    let mut causes = Vec::new();
+    let mut source = std::error::Error::source(self);
+    while let Some(cause) = source {
+      causes.push(cause.to_string());
+      source = cause.source();
+    }
+    ErrorReport {
+      code: self.code(),
+      kind: self.kind(),
+      retryable: self.is_retryable(),
+      message: self.to_string(),
+      causes,
+      retry_after_ms: self.retry_after().map(|d| d.as_millis() as u64),
+    }
+  }
+
+  /// How long the caller should wait before retrying, when the error says
+  pub fn retry_after(&self) -> Option<Duration> {
+    match self {
+      ProverError::Busy { retry_after, .. } => Some(*retry_after),
+      _ => None,
+    }
+  }
+
+  /// Whether retrying may succeed
+  pub fn kind(&self) -> ErrorKind {
+    match self {
+      ProverError::ZKError(e) => classify_zk_error(e),
+      ProverError::NetworkUnavailable(_) | ProverError::Degraded(_) | ProverError::Timeout(_) => {
+        ErrorKind::Transient
+      }
+      ProverError::QueueFull | ProverError::Busy { .. } | ProverError::InsufficientCapacity(_) => {
+        ErrorKind::ResourceExhausted
+      }
+      ProverError::IOError(e) => match e.kind() {
+        std::io::ErrorKind::TimedOut | std::io::ErrorKind::Interrupted | std::io::ErrorKind::WouldBlock => {
+          ErrorKind::Transient
+        }
+        _ => ErrorKind::Fatal,
+      },
+      _ => ErrorKind::Fatal,
+    }
+  }
+
+  /// Convert into a backend error, for wrappers implementing `ZkBackend`.
+  /// The code and kind travel in the message, so a [`ProverError`] made
+  /// from the result reports them again.
+  pub fn into_zk_error(self) -> ZkError {
+    match self {
+      ProverError::ZKError(e) => e,
+      other => ZkError::Config(format!("{}{}:{}: {}", ZK_ERROR_TAG, other.code(), other.kind().name(), other)),
+    }
+  }
+}
+
+impl std::fmt::Display for ProverError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      ProverError::ZKError(e) => write!(f, "backend error: {}", e),
+      ProverError::ProgramNotFound => write!(f, "program not found"),
+      ProverError::InvalidProgram(msg) => write!(f, "invalid program: {}", msg),
+      ProverError::PolicyViolation(msg) => write!(f, "policy violation: {}", msg),
+      ProverError::SignatureInvalid(msg) => write!(f, "invalid signature: {}", msg),
+      ProverError::PublicInputsMismatch => write!(f, "proof does not commit to the expected public inputs"),
+      ProverError::MalformedEnvelope(msg) => write!(f, "malformed proof envelope: {}", msg),
+      ProverError::UnsupportedProofVersion(msg) => write!(f, "unsupported proof version: {}", msg),
+      ProverError::IdempotencyConflict { key } => {
+        write!(f, "idempotency key '{}' was already used for a different request", key)
+      }
+      ProverError::ReplayDetected(msg) => write!(f, "replay detected: {}", msg),
+      ProverError::VkeyMismatch { program_hash, expected, found } => match expected {
+        Some(expected) => write!(f, "program {} has vkey {}, expected {}", program_hash, found, expected),
+        None => write!(f, "program {} has unpinned vkey {}", program_hash, found),
+      },
+      ProverError::QueueFull => write!(f, "prover queue is full"),
+      ProverError::Busy { reason, retry_after } => {
+        write!(f, "prover is busy: {}; retry after {} ms", reason, retry_after.as_millis())
+      }
+      ProverError::InsufficientCapacity(msg) => write!(f, "insufficient capacity: {}", msg),
+      ProverError::Degraded(msg) => write!(f, "prover is in verify-only mode: {}", msg),
+      ProverError::NetworkUnavailable(msg) => write!(f, "network unavailable: {}", msg),
+      ProverError::Timeout(msg) => write!(f, "timed out: {}", msg),
+      ProverError::Cancelled => write!(f, "cancelled"),
+      ProverError::BudgetExceeded { requested, remaining } => {
+        write!(f, "budget exceeded: requested {}, {} remaining", requested, remaining)
+      }
+      ProverError::QuotaExceeded { scope, resource, limit, requested } => {
+        write!(f, "{} quota for {} exceeded: limit {}, requested {}", resource, scope, limit, requested)
+      }
+      ProverError::Config(e) => write!(f, "config error: {}", e),
+      ProverError::IOError(e) => write!(f, "I/O error: {}", e),
+      ProverError::SerializationError(e) => write!(f, "serialization error: {}", e),
+      ProverError::Other(msg) => write!(f, "{}", msg),
+    }
+  }
+}
+
+impl std::error::Error for ProverError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      ProverError::ZKError(e) => Some(e),
+      ProverError::Config(e) => Some(e),
+      ProverError::IOError(e) => Some(e),
+      ProverError::SerializationError(e) => Some(e),
+      _ => None,
+    }
+  }
+}
+
+/// Wire form of a [`ProverError`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "rest", derive(utoipa::ToSchema))]
+pub struct ErrorReport {
+  pub code: u32,
+  pub kind: ErrorKind,
+  pub retryable: bool,
+  pub message: String,
+  /// Messages of the underlying errors, outermost first
+  pub causes: Vec<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub retry_after_ms: Option<u64>,
+}
+
 impl From<ZkError> for ProverError {
   fn from(e: ZkError) -> Self {
     ProverError::ZKError(e)
   }
 }
 
+impl From<ConfigError> for ProverError {
+  fn from(e: ConfigError) -> Self {
+    ProverError::Config(e)
+  }
+}
+
 impl From<std::io::Error> for ProverError {
   fn from(e: std::io::Error) -> Self {
     ProverError::IOError(e)
   }
-}
\ No newline at end of file
+}
+
+impl From<serde_json::Error> for ProverError {
+  fn from(e: serde_json::Error) -> Self {
+    ProverError::SerializationError(e)
+  }
+}
+
+/// A single proof to produce
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProveRequest {
+  pub program: Vec<u8>,
+  /// Private data written to the guest's stdin; never revealed by the proof
+  pub stdin_inputs: Vec<u8>,
+  /// Values the guest must commit as public outputs; empty if the caller
+  /// doesn't bind the proof to a statement
+  pub public_inputs: Vec<u8>,
+}
+
+impl ProveRequest {
+  pub fn new(program: impl Into<Vec<u8>>, stdin_inputs: impl Into<Vec<u8>>) -> Self {
+    Self { program: program.into(), stdin_inputs: stdin_inputs.into(), public_inputs: Vec::new() }
+  }
+
+  /// Require the proof to commit to exactly `public_inputs`
+  pub fn with_public_inputs(mut self, public_inputs: impl Into<Vec<u8>>) -> Self {
+    self.public_inputs = public_inputs.into();
+    self
+  }
+
+  pub fn program_hash(&self) -> ProgramHash {
+    program_hash(&self.program)
+  }
+}
+
+/// A single proof to check against the program that produced it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyRequest {
+  pub program: Vec<u8>,
+  pub proof: Vec<u8>,
+}
+
+impl VerifyRequest {
+  pub fn new(program: impl Into<Vec<u8>>, proof: impl Into<Vec<u8>>) -> Self {
+    Self { program: program.into(), proof: proof.into() }
+  }
+
+  pub fn program_hash(&self) -> ProgramHash {
+    program_hash(&self.program)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_error_report() {
+    let io = std::io::Error::new(std::io::ErrorKind::TimedOut, "read timed out");
+    let report = ProverError::from(io).to_report();
+    assert_eq!(report.code, 5002);
+    assert!(report.retryable);
+    assert_eq!(report.causes, vec!["read timed out".to_string()]);
+
+    let json = serde_json::to_string(&ProverError::QueueFull.to_report()).unwrap();
+    let decoded: ErrorReport = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded.kind, ErrorKind::ResourceExhausted);
+    assert_eq!(decoded.message, "prover queue is full");
+
+    let config = ConfigError::Missing { field: "key_holder".to_string(), required_by: "to prove".to_string() };
+    let report = ProverError::from(config).to_report();
+    assert_eq!(report.code, 1007);
+    assert_eq!(report.causes, vec!["key_holder must be set to prove".to_string()]);
+  }
+
+  #[test]
+  fn test_code_survives_backend_boundary() {
+    let busy = ProverError::Busy { reason: "full".to_string(), retry_after: Duration::from_secs(1) };
+    let crossed = ProverError::from(busy.into_zk_error());
+    assert_eq!(crossed.code(), 3005);
+    assert_eq!(crossed.kind(), ErrorKind::ResourceExhausted);
+    assert!(crossed.to_string().contains("prover is busy: full"));
+
+    let quota = ProverError::QuotaExceeded {
+      scope: "tenant relayer".to_string(),
+      resource: "proofs".to_string(),
+      limit: 1,
+      requested: 1,
+    };
+    let crossed = ProverError::from(quota.into_zk_error());
+    assert_eq!(crossed.code(), 3004);
+    assert!(!crossed.is_retryable());
+
+    let plain = ProverError::from(ZkError::Config("connection refused".to_string()));
+    assert_eq!(plain.code(), 5001);
+    assert_eq!(plain.kind(), ErrorKind::Transient);
+  }
+
+  #[test]
+  fn test_mentions() {
+    assert!(mentions("Killed: OOM (signal 9)", "oom"));
+    assert!(mentions("CUDA error: out of   memory", "out of memory"));
+    assert!(!mentions("no room left", "oom"));
+    assert!(!mentions("out of memoryless", "out of memory"));
+    assert!(!mentions("anything", ""));
+  }
+}
@@ -0,0 +1,131 @@
+use crate::types::{ProgramHash, ProverError, program_hash};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+const KNOWN_PROGRAMS_FILE: &str = "known_programs.json";
+
+/// Tracks which programs a proving network already holds, so ELFs are
+/// uploaded once per network rather than once per process
+pub struct UploadTracker {
+    path: Option<PathBuf>,
+    known: Mutex<HashSet<ProgramHash>>,
+}
+
+impl UploadTracker {
+    /// Create a tracker that forgets everything on restart
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            known: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Open a tracker persisted in `cache_dir`, next to the key cache
+    pub fn open(cache_dir: &Path) -> Result<Self, ProverError> {
+        fs::create_dir_all(cache_dir)?;
+        let path = cache_dir.join(KNOWN_PROGRAMS_FILE);
+        let known = if path.exists() {
+            serde_json::from_slice(&fs::read(&path)?)?
+        } else {
+            HashSet::new()
+        };
+        Ok(Self {
+            path: Some(path),
+            known: Mutex::new(known),
+        })
+    }
+
+    /// Whether the network is known to hold this program
+    pub fn is_known(&self, hash: &str) -> bool {
+        self.known.lock().unwrap_or_else(PoisonError::into_inner).contains(hash)
+    }
+
+    /// Record that the network now holds this program
+    pub fn mark_uploaded(&self, hash: ProgramHash) -> Result<(), ProverError> {
+        let mut known = self.known.lock().unwrap_or_else(PoisonError::into_inner);
+        if known.insert(hash) {
+            self.persist(&known)?;
+        }
+        Ok(())
+    }
+
+    /// Forget a program, e.g. after the network reports it missing
+    pub fn forget(&self, hash: &str) -> Result<(), ProverError> {
+        let mut known = self.known.lock().unwrap_or_else(PoisonError::into_inner);
+        if known.remove(hash) {
+            self.persist(&known)?;
+        }
+        Ok(())
+    }
+
+    /// Run `upload` unless the network already holds `program`
+    pub fn upload_if_needed<F>(&self, program: &[u8], upload: F) -> Result<ProgramHash, ProverError>
+    where
+        F: FnOnce(&[u8]) -> Result<(), ProverError>,
+    {
+        let hash = program_hash(program);
+        if self.is_known(&hash) {
+            tracing::debug!("skipping upload of known program {}", hash);
+            return Ok(hash);
+        }
+        upload(program)?;
+        self.mark_uploaded(hash.clone())?;
+        Ok(hash)
+    }
+
+    fn persist(&self, known: &HashSet<ProgramHash>) -> Result<(), ProverError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec(known)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_persists_across_restarts() {
+        let dir = std::env::temp_dir().join(format!("frostgate-uploads-{}", uuid::Uuid::new_v4()));
+        let tracker = UploadTracker::open(&dir).unwrap();
+        let uploads = Cell::new(0);
+        let upload = |_: &[u8]| {
+            uploads.set(uploads.get() + 1);
+            Ok(())
+        };
+        let hash = tracker.upload_if_needed(b"elf", upload).unwrap();
+        assert_eq!(hash, program_hash(b"elf"));
+        tracker.upload_if_needed(b"elf", upload).unwrap();
+        assert_eq!(uploads.get(), 1);
+        tracker.mark_uploaded(program_hash(b"other")).unwrap();
+
+        let reopened = UploadTracker::open(&dir).unwrap();
+        assert!(reopened.is_known(&hash));
+        assert!(reopened.is_known(&program_hash(b"other")));
+        reopened.upload_if_needed(b"elf", upload).unwrap();
+        assert_eq!(uploads.get(), 1);
+
+        // Forgotten programs are uploaded again, also after a restart
+        reopened.forget(&hash).unwrap();
+        assert!(!UploadTracker::open(&dir).unwrap().is_known(&hash));
+        reopened.upload_if_needed(b"elf", upload).unwrap();
+        assert_eq!(uploads.get(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_failed_upload_not_recorded() {
+        let tracker = UploadTracker::in_memory();
+        let failed = tracker.upload_if_needed(b"elf", |_| Err(ProverError::NetworkUnavailable("down".to_string())));
+        assert!(matches!(failed, Err(ProverError::NetworkUnavailable(_))));
+        assert!(!tracker.is_known(&program_hash(b"elf")));
+    }
+}
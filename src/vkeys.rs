@@ -0,0 +1,250 @@
+use crate::compat::{SP1_VERSION, is_compatible};
+use crate::envelope::hex_bytes;
+use crate::pinning::VkeyResolver;
+use crate::types::{ProgramHash, ProverError, ct_eq, program_hash};
+use frostgate_zkip::ZkError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// A program's verifying key in a form that can be shipped to other machines
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedVkey {
+    pub program_hash: ProgramHash,
+    pub vkey_hash: String,
+    /// Serialized verifying key
+    #[serde(with = "hex_bytes")]
+    pub vk: Vec<u8>,
+    /// SP1 release the key was produced by
+    pub sp1_version: String,
+}
+
+/// Computes the vkey hash of a serialized verifying key
+pub type VkHasher = Arc<dyn Fn(&[u8]) -> Result<String, ZkError> + Send + Sync>;
+
+/// Checks a proof against a serialized verifying key, answering `None` for
+/// proofs it leaves to the prover, such as SP1 Plonk and Groth16 wrappers
+pub type VkVerifier = Arc<dyn Fn(&[u8], &[u8]) -> Result<Option<bool>, ZkError> + Send + Sync>;
+
+/// Verifying keys known to this process, either from local setup or imported,
+/// so proofs can be verified without running setup on the full ELF
+pub struct VkeyStore {
+    keys: RwLock<HashMap<ProgramHash, ExportedVkey>>,
+    hash_vk: Option<VkHasher>,
+    verify_vk: Option<VkVerifier>,
+}
+
+impl Default for VkeyStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VkeyStore {
+    /// Store for SP1 keys. Verifier-only builds have no SDK to check keys
+    /// with, so they need [`VkeyStore::with_vk_ops`] to import any.
+    pub fn new() -> Self {
+        #[cfg(any(feature = "sp1-v4", feature = "sp1-v5"))]
+        let (hash_vk, verify_vk): (Option<VkHasher>, Option<VkVerifier>) =
+            (Some(Arc::new(sp1_vkey_hash)), Some(Arc::new(sp1_verify_with_vk)));
+        #[cfg(not(any(feature = "sp1-v4", feature = "sp1-v5")))]
+        let (hash_vk, verify_vk) = (None, None);
+        Self {
+            keys: RwLock::new(HashMap::new()),
+            hash_vk,
+            verify_vk,
+        }
+    }
+
+    /// Check and use keys with `hash_vk` and `verify_vk` instead of SP1's
+    pub fn with_vk_ops(mut self, hash_vk: VkHasher, verify_vk: VkVerifier) -> Self {
+        self.hash_vk = Some(hash_vk);
+        self.verify_vk = Some(verify_vk);
+        self
+    }
+
+    /// Record the key produced by setting up a program
+    pub fn insert(&self, program_hash: ProgramHash, vkey_hash: String, vk: Vec<u8>) {
+        let key = ExportedVkey {
+            program_hash: program_hash.clone(),
+            vkey_hash,
+            vk,
+            sp1_version: SP1_VERSION.to_string(),
+        };
+        self.keys
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(program_hash, key);
+    }
+
+    pub fn get(&self, program_hash: &str) -> Option<ExportedVkey> {
+        self.keys
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(program_hash)
+            .cloned()
+    }
+
+    /// Every stored key
+    pub fn all(&self) -> Vec<ExportedVkey> {
+        self.keys
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// Serialize the key of `program_hash` for import elsewhere
+    pub fn export_vk(&self, program_hash: &str) -> Result<Vec<u8>, ProverError> {
+        let key = self.get(program_hash).ok_or(ProverError::ProgramNotFound)?;
+        Ok(serde_json::to_vec(&key)?)
+    }
+
+    /// Import a key produced by [`VkeyStore::export_vk`], returning the hash
+    /// of the program it belongs to. The key must hash to its vkey hash.
+    pub fn import_vk(&self, bytes: &[u8]) -> Result<ProgramHash, ProverError> {
+        self.import(serde_json::from_slice(bytes)?)
+    }
+
+    /// Like [`VkeyStore::import_vk`], for a key already deserialized
+    pub fn import(&self, key: ExportedVkey) -> Result<ProgramHash, ProverError> {
+        if !is_compatible(&key.sp1_version) {
+            return Err(ProverError::UnsupportedProofVersion(format!(
+                "verifying key from SP1 {}, this build uses {}",
+                key.sp1_version, SP1_VERSION
+            )));
+        }
+        let hash_vk = self
+            .hash_vk
+            .as_ref()
+            .ok_or_else(|| ProverError::Other("no way to check imported verifying keys".to_string()))?;
+        let computed = hash_vk(&key.vk)?;
+        if !ct_eq(computed.as_bytes(), key.vkey_hash.as_bytes()) {
+            return Err(ProverError::VkeyMismatch {
+                program_hash: key.program_hash,
+                expected: Some(computed),
+                found: key.vkey_hash,
+            });
+        }
+        if let Some(existing) = self.get(&key.program_hash)
+            && !ct_eq(existing.vkey_hash.as_bytes(), key.vkey_hash.as_bytes())
+        {
+            return Err(ProverError::VkeyMismatch {
+                program_hash: key.program_hash,
+                expected: Some(existing.vkey_hash),
+                found: key.vkey_hash,
+            });
+        }
+        let hash = key.program_hash.clone();
+        self.keys
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(hash.clone(), key);
+        tracing::info!("imported verifying key for program {}", hash);
+        Ok(hash)
+    }
+
+    /// Verify `proof` of the program with hash `program_hash` against its
+    /// stored key. `None` when that isn't possible here: the key is unknown
+    /// or the proof is of a kind left to the prover.
+    pub fn verify(&self, program_hash: &str, proof: &[u8]) -> Result<Option<bool>, ProverError> {
+        let (Some(key), Some(verify_vk)) = (self.get(program_hash), &self.verify_vk) else {
+            return Ok(None);
+        };
+        Ok(verify_vk(&key.vk, proof)?)
+    }
+
+    /// Resolver answering vkey hashes from this store, for verifiers such as
+    /// [`PinnedBackend`](crate::pinning::PinnedBackend)
+    pub fn resolver(self: &Arc<Self>) -> VkeyResolver {
+        let store = self.clone();
+        Arc::new(move |program| {
+            let hash = program_hash(program);
+            store
+                .get(&hash)
+                .map(|key| key.vkey_hash)
+                .ok_or_else(|| ZkError::Config(format!("no verifying key for program {}", hash)))
+        })
+    }
+}
+
+/// `vk.bytes32()` of a bincode-serialized SP1 verifying key
+#[cfg(any(feature = "sp1-v4", feature = "sp1-v5"))]
+pub fn sp1_vkey_hash(vk: &[u8]) -> Result<String, ZkError> {
+    use crate::compat::sdk::{HashableKey, SP1VerifyingKey};
+    let vk: SP1VerifyingKey =
+        bincode::deserialize(vk).map_err(|e| ZkError::Config(format!("decoding verifying key: {}", e)))?;
+    Ok(vk.bytes32())
+}
+
+/// Verify a bincode-serialized SP1 core or compressed proof against a
+/// serialized verifying key. Undecodable proofs are rejected.
+#[cfg(any(feature = "sp1-v4", feature = "sp1-v5"))]
+pub fn sp1_verify_with_vk(vk: &[u8], proof: &[u8]) -> Result<Option<bool>, ZkError> {
+    use crate::compat::sdk::{CpuProver, Prover, SP1Proof, SP1ProofWithPublicValues, SP1VerifyingKey};
+    let vk: SP1VerifyingKey =
+        bincode::deserialize(vk).map_err(|e| ZkError::Config(format!("decoding verifying key: {}", e)))?;
+    let Ok(proof) = bincode::deserialize::<SP1ProofWithPublicValues>(proof) else {
+        return Ok(Some(false));
+    };
+    match proof.proof {
+        SP1Proof::Core(_) | SP1Proof::Compressed(_) => Ok(Some(CpuProver::new().verify(&proof, &vk).is_ok())),
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{mock_committing_proof, mock_vkey_hash, mock_vkeys};
+
+    #[test]
+    fn test_export_import() {
+        let prover = mock_vkeys();
+        let hash = program_hash(b"elf");
+        prover.insert(hash.clone(), mock_vkey_hash(&[1, 2, 3]), vec![1, 2, 3]);
+        let exported = prover.export_vk(&hash).unwrap();
+
+        let verifier = Arc::new(mock_vkeys());
+        assert_eq!(verifier.import_vk(&exported).unwrap(), hash);
+        assert_eq!(verifier.get(&hash).unwrap().vk, vec![1, 2, 3]);
+        assert_eq!((verifier.resolver())(b"elf").unwrap(), mock_vkey_hash(&[1, 2, 3]));
+        assert!((verifier.resolver())(b"other").is_err());
+
+        prover.insert(hash.clone(), mock_vkey_hash(&[4]), vec![4]);
+        assert!(matches!(
+            verifier.import_vk(&prover.export_vk(&hash).unwrap()),
+            Err(ProverError::VkeyMismatch { .. })
+        ));
+        assert!(matches!(prover.export_vk("unknown"), Err(ProverError::ProgramNotFound)));
+    }
+
+    #[test]
+    fn test_import_checks_vk_hash() {
+        let prover = mock_vkeys();
+        let hash = program_hash(b"elf");
+        // A key claiming another key's hash
+        prover.insert(hash.clone(), mock_vkey_hash(b"real"), b"forged".to_vec());
+
+        let verifier = mock_vkeys();
+        assert!(matches!(
+            verifier.import_vk(&prover.export_vk(&hash).unwrap()),
+            Err(ProverError::VkeyMismatch { .. })
+        ));
+        assert!(verifier.get(&hash).is_none());
+    }
+
+    #[test]
+    fn test_verify_with_imported_vk() {
+        let prover = mock_vkeys();
+        let hash = program_hash(b"elf");
+        prover.insert(hash.clone(), mock_vkey_hash(b"vk"), b"vk".to_vec());
+        let verifier = mock_vkeys();
+        assert_eq!(verifier.verify(&hash, b"proof").unwrap(), None);
+
+        verifier.import_vk(&prover.export_vk(&hash).unwrap()).unwrap();
+        assert_eq!(verifier.verify(&hash, &mock_committing_proof(b"vk", b"values")).unwrap(), Some(true));
+        assert_eq!(verifier.verify(&hash, b"garbage").unwrap(), Some(false));
+    }
+}
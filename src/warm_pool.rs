@@ -0,0 +1,284 @@
+use crate::types::ProverError;
+use frostgate_zkip::{ZkBackend, ZkError};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Retry hint given when every worker is checked out
+const POOL_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// Creates a fully initialized prover context: keys loaded, artifacts mapped,
+/// subprocesses spawned
+pub type WorkerFactory = Arc<dyn Fn() -> Result<Arc<dyn ZkBackend>, ProverError> + Send + Sync>;
+
+/// Sizing of a [`WarmPool`]
+#[derive(Debug, Clone)]
+pub struct WarmPoolConfig {
+    /// Idle workers kept ready at all times
+    pub min_idle: usize,
+    /// Upper bound on idle plus checked-out workers
+    pub max_workers: usize,
+    /// Idle workers beyond `min_idle` are dropped after this long
+    pub idle_timeout: Duration,
+}
+
+impl Default for WarmPoolConfig {
+    fn default() -> Self {
+        Self {
+            min_idle: 1,
+            max_workers: num_cpus::get().max(1),
+            idle_timeout: Duration::from_secs(10 * 60),
+        }
+    }
+}
+
+/// Pool of pre-initialized prover workers, so a job starts on a warm context
+/// instead of paying initialization after idle periods
+pub struct WarmPool {
+    config: WarmPoolConfig,
+    factory: WorkerFactory,
+    idle: Mutex<VecDeque<(Arc<dyn ZkBackend>, Instant)>>,
+    checked_out: AtomicUsize,
+}
+
+impl WarmPool {
+    /// Create the pool and initialize `min_idle` workers up front
+    pub fn new(config: WarmPoolConfig, factory: WorkerFactory) -> Result<Arc<Self>, ProverError> {
+        let pool = Arc::new(Self {
+            config,
+            factory,
+            idle: Mutex::new(VecDeque::new()),
+            checked_out: AtomicUsize::new(0),
+        });
+        pool.refill()?;
+        Ok(pool)
+    }
+
+    /// Number of workers ready to take a job
+    pub fn idle(&self) -> usize {
+        self.idle.lock().unwrap_or_else(PoisonError::into_inner).len()
+    }
+
+    /// Number of workers currently running jobs
+    pub fn checked_out(&self) -> usize {
+        self.checked_out.load(Ordering::SeqCst)
+    }
+
+    /// Take a warm worker, initializing a new one if none is idle. Fails
+    /// with [`ProverError::Busy`] when `max_workers` are already checked out.
+    pub fn checkout(self: &Arc<Self>) -> Result<PooledWorker, ProverError> {
+        let warm = {
+            let mut idle = self.idle.lock().unwrap_or_else(PoisonError::into_inner);
+            let warm = idle.pop_back().map(|(worker, _)| worker);
+            // Counted under the idle lock so concurrent checkouts can't
+            // both take the last slot
+            if warm.is_none() && self.checked_out() >= self.config.max_workers {
+                return Err(ProverError::Busy {
+                    reason: format!("all {} warm pool workers are busy", self.config.max_workers),
+                    retry_after: POOL_RETRY_AFTER,
+                });
+            }
+            self.checked_out.fetch_add(1, Ordering::SeqCst);
+            warm
+        };
+        let worker = match warm {
+            Some(worker) => worker,
+            None => {
+                tracing::debug!("warm pool empty, initializing a worker on demand");
+                (self.factory)().inspect_err(|_| {
+                    self.checked_out.fetch_sub(1, Ordering::SeqCst);
+                })?
+            }
+        };
+        Ok(PooledWorker {
+            worker,
+            pool: self.clone(),
+            discarded: false,
+        })
+    }
+
+    /// Top idle workers up to `min_idle` and drop workers idle for too long
+    pub fn refill(&self) -> Result<(), ProverError> {
+        {
+            let mut idle = self.idle.lock().unwrap_or_else(PoisonError::into_inner);
+            while idle.len() > self.config.min_idle
+                && idle
+                    .front()
+                    .is_some_and(|(_, since)| since.elapsed() > self.config.idle_timeout)
+            {
+                idle.pop_front();
+            }
+        }
+
+        loop {
+            let idle = self.idle();
+            if idle >= self.config.min_idle || idle + self.checked_out() >= self.config.max_workers {
+                return Ok(());
+            }
+            let worker = (self.factory)()?;
+            self.idle
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push_back((worker, Instant::now()));
+        }
+    }
+
+    /// Spawn a task that refills the pool every `interval`
+    pub fn spawn_refill_loop(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let pool = self.clone();
+                match tokio::task::spawn_blocking(move || pool.refill()).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => tracing::warn!("failed to refill warm pool: {:?}", e),
+                    Err(e) => tracing::error!("warm pool refill task failed: {}", e),
+                }
+            }
+        })
+    }
+
+    fn check_in(&self, worker: Arc<dyn ZkBackend>) {
+        self.checked_out.fetch_sub(1, Ordering::SeqCst);
+        let mut idle = self.idle.lock().unwrap_or_else(PoisonError::into_inner);
+        if idle.len() + self.checked_out() < self.config.max_workers {
+            idle.push_back((worker, Instant::now()));
+        }
+    }
+}
+
+/// A worker checked out of a [`WarmPool`], returned when dropped
+pub struct PooledWorker {
+    worker: Arc<dyn ZkBackend>,
+    pool: Arc<WarmPool>,
+    discarded: bool,
+}
+
+impl PooledWorker {
+    pub fn backend(&self) -> &dyn ZkBackend {
+        self.worker.as_ref()
+    }
+
+    /// Drop the worker instead of returning it, e.g. after it failed
+    pub fn discard(mut self) {
+        self.discarded = true;
+    }
+}
+
+impl Drop for PooledWorker {
+    fn drop(&mut self) {
+        if self.discarded {
+            self.pool.checked_out.fetch_sub(1, Ordering::SeqCst);
+        } else {
+            self.pool.check_in(self.worker.clone());
+        }
+    }
+}
+
+/// Backend that runs each call on a warm worker. Workers whose call fails
+/// are discarded rather than trusted with the next job.
+pub struct WarmPoolBackend {
+    pool: Arc<WarmPool>,
+}
+
+impl WarmPoolBackend {
+    pub fn new(pool: Arc<WarmPool>) -> Self {
+        Self { pool }
+    }
+}
+
+impl ZkBackend for WarmPoolBackend {
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        let worker = self.pool.checkout().map_err(ProverError::into_zk_error)?;
+        let result = worker.backend().prove(program, input);
+        if result.is_err() {
+            worker.discard();
+        }
+        result
+    }
+
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        let worker = self.pool.checkout().map_err(ProverError::into_zk_error)?;
+        let result = worker.backend().verify(program, proof);
+        if result.is_err() {
+            worker.discard();
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockBackend, MockConfig};
+
+    #[test]
+    fn test_pool_keeps_workers_warm() {
+        let created = Arc::new(AtomicUsize::new(0));
+        let counter = created.clone();
+        let factory: WorkerFactory = Arc::new(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok(Arc::new(MockBackend::default()) as Arc<dyn ZkBackend>)
+        });
+        let config = WarmPoolConfig {
+            min_idle: 2,
+            max_workers: 3,
+            idle_timeout: Duration::from_secs(60),
+        };
+        let pool = WarmPool::new(config, factory).unwrap();
+        assert_eq!(pool.idle(), 2);
+
+        let a = pool.checkout().unwrap();
+        let b = pool.checkout().unwrap();
+        assert_eq!(created.load(Ordering::SeqCst), 2);
+        assert!(a.backend().prove(b"elf", b"input").is_ok());
+
+        // One more fits under max_workers
+        pool.refill().unwrap();
+        assert_eq!(pool.idle(), 1);
+        assert_eq!(created.load(Ordering::SeqCst), 3);
+
+        // Every worker is taken
+        let c = pool.checkout().unwrap();
+        assert!(matches!(pool.checkout(), Err(ProverError::Busy { .. })));
+        assert_eq!(created.load(Ordering::SeqCst), 3);
+
+        drop(a);
+        drop(c);
+        b.discard();
+        assert_eq!(pool.idle(), 2);
+        assert_eq!(pool.checked_out(), 0);
+    }
+
+    #[test]
+    fn test_failed_workers_discarded() {
+        let created = Arc::new(AtomicUsize::new(0));
+        let counter = created.clone();
+        let factory: WorkerFactory = Arc::new(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            let worker = MockBackend::new(MockConfig {
+                fail_verify: true,
+                ..MockConfig::default()
+            });
+            Ok(Arc::new(worker) as Arc<dyn ZkBackend>)
+        });
+        let config = WarmPoolConfig {
+            min_idle: 1,
+            max_workers: 1,
+            idle_timeout: Duration::from_secs(60),
+        };
+        let pool = WarmPool::new(config, factory).unwrap();
+        let backend = WarmPoolBackend::new(pool.clone());
+
+        assert!(backend.verify(b"elf", b"proof").is_err());
+        assert_eq!(pool.idle(), 0);
+        assert_eq!(pool.checked_out(), 0);
+
+        // A fresh worker takes the next call and is kept
+        assert!(backend.prove(b"elf", b"input").is_ok());
+        assert_eq!(created.load(Ordering::SeqCst), 2);
+        assert_eq!(pool.idle(), 1);
+    }
+}
@@ -0,0 +1,215 @@
+use crate::binding::PublicValuesExtractor;
+use crate::envelope::EnvelopedBackend;
+use crate::registry::{self, ZkCapability};
+use crate::types::{ProverError, ct_eq};
+use frostgate_zkip::{ZkBackend, ZkError};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+use std::sync::Arc;
+
+/// Proof system name recorded in the envelopes of WASM proofs
+pub const WASM_PROOF_SYSTEM: &str = "sp1-wasm";
+
+/// Capability advertised by backends proving WASM modules
+pub const WASM_CAPABILITY: ZkCapability = ZkCapability::Custom("wasm");
+
+const WASM_MAGIC: [u8; 4] = *b"\0asm";
+const WASM_VERSION: u32 = 1;
+
+/// Check that `module` is a binary WASM module of a version the interpreter
+/// runs, before spending time on proving
+pub fn validate_wasm(module: &[u8]) -> Result<(), ProverError> {
+    if module.len() < 8 || module[..4] != WASM_MAGIC {
+        return Err(ProverError::InvalidProgram("not a WASM module".to_string()));
+    }
+    let version = u32::from_le_bytes([module[4], module[5], module[6], module[7]]);
+    if version != WASM_VERSION {
+        return Err(ProverError::InvalidProgram(format!(
+            "expected WASM version {}, found {}",
+            WASM_VERSION, version
+        )));
+    }
+    Ok(())
+}
+
+/// What the interpreter guest reads as its input
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WasmInvocation {
+    pub module: Vec<u8>,
+    pub input: Vec<u8>,
+}
+
+impl WasmInvocation {
+    pub fn encode(&self) -> Result<Vec<u8>, ProverError> {
+        bincode::serialize(self).map_err(|e| ProverError::Other(format!("encoding WASM invocation: {}", e)))
+    }
+}
+
+/// Digest the interpreter guest commits ahead of the module's own public
+/// values: SHA3-256 of the module, the raw bytes of its program hash
+pub fn module_digest(module: &[u8]) -> Vec<u8> {
+    Sha3_256::digest(module).to_vec()
+}
+
+/// Runs modules on a WASM interpreter compiled for the zkVM
+struct Interpreted {
+    host: Arc<dyn ZkBackend>,
+    interpreter: Vec<u8>,
+    extract: PublicValuesExtractor,
+}
+
+impl Interpreted {
+    fn prove_module(&self, module: &[u8], input: &[u8]) -> Result<Vec<u8>, ProverError> {
+        validate_wasm(module)?;
+        let invocation = WasmInvocation {
+            module: module.to_vec(),
+            input: input.to_vec(),
+        };
+        let proof = self.host.prove(&self.interpreter, &invocation.encode()?)?;
+        if !self.commits_module(module, &proof)? {
+            return Err(ProverError::Other(
+                "interpreter guest did not commit the module hash".to_string(),
+            ));
+        }
+        Ok(proof)
+    }
+
+    /// Whether `proof`'s public values start with the digest of `module`
+    fn commits_module(&self, module: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        let committed = (self.extract)(proof)?;
+        let digest = module_digest(module);
+        Ok(committed.len() >= digest.len() && ct_eq(&committed[..digest.len()], &digest))
+    }
+}
+
+impl ZkBackend for Interpreted {
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        self.prove_module(program, input).map_err(ProverError::into_zk_error)
+    }
+
+    /// The interpreter proof alone would pass for any module, so it must
+    /// also commit to this one
+    fn verify(&self, module: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        if !self.commits_module(module, proof)? {
+            tracing::warn!("WASM proof does not commit to the module it is verified against");
+            return Ok(false);
+        }
+        self.host.verify(&self.interpreter, proof)
+    }
+}
+
+/// Backend proving WASM modules instead of RISC-V ELFs, by proving a WASM
+/// interpreter guest that runs the module on the given input. Callers pass
+/// the module where they would pass an ELF.
+///
+/// Proofs are wrapped in envelopes tagged `sp1-wasm` and bound to the
+/// module's hash rather than the interpreter's. The interpreter guest must
+/// commit the [`module_digest`] first in its public values, which `extract`
+/// reads from the host's proofs; verification checks it against the module,
+/// so an interpreter proof can't be passed off as one of another module.
+pub struct WasmBackend {
+    inner: EnvelopedBackend,
+}
+
+impl WasmBackend {
+    /// Prove through `host` with `interpreter` as the guest ELF
+    pub fn new(
+        host: Arc<dyn ZkBackend>,
+        interpreter: Vec<u8>,
+        backend_id: &str,
+        extract: PublicValuesExtractor,
+    ) -> Self {
+        let interpreted = Interpreted {
+            host,
+            interpreter,
+            extract: extract.clone(),
+        };
+        Self {
+            inner: EnvelopedBackend::new(Arc::new(interpreted), backend_id, WASM_PROOF_SYSTEM).with_extractor(extract),
+        }
+    }
+
+    /// Launch an SP1 key holder and prove the interpreter through it
+    #[cfg(any(feature = "sp1-v4", feature = "sp1-v5"))]
+    pub fn spawn(
+        config: &crate::isolation::KeyHolderConfig,
+        interpreter: Vec<u8>,
+        backend_id: &str,
+    ) -> Result<Self, ProverError> {
+        let host = Arc::new(crate::isolation::IsolatedBackend::spawn(config)?);
+        Ok(Self::new(host, interpreter, backend_id, crate::public_values::sp1_extractor()))
+    }
+
+    /// Register in the global registry as a local CPU backend with the
+    /// [`WASM_CAPABILITY`]
+    pub fn register(self, id: &str) -> Result<(), ZkError> {
+        registry::register_backend_with_capabilities(
+            id.to_string(),
+            Arc::new(self),
+            &[ZkCapability::LocalCpu, WASM_CAPABILITY],
+        )
+    }
+}
+
+impl ZkBackend for WasmBackend {
+    fn prove(&self, program: &[u8], input: &[u8]) -> Result<Vec<u8>, ZkError> {
+        self.inner.prove(program, input)
+    }
+
+    fn verify(&self, program: &[u8], proof: &[u8]) -> Result<bool, ZkError> {
+        self.inner.verify(program, proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::envelope::{DecodeLimits, decode_envelope};
+    use crate::testing::{MockBackend, mock_public_values};
+
+    const MODULE: &[u8] = b"\0asm\x01\0\0\0\x01\x04\x01\x60\0\0";
+
+    /// Mock interpreter committing the digest of `module`, whatever it runs
+    fn interpreter(module: Option<&'static [u8]>) -> Arc<MockBackend> {
+        Arc::new(MockBackend::default().with_guest(Arc::new(move |_, input| {
+            let invocation: WasmInvocation = bincode::deserialize(input).map_err(|e| ZkError::Config(e.to_string()))?;
+            Ok(module_digest(module.unwrap_or(&invocation.module)))
+        })))
+    }
+
+    #[test]
+    fn test_wasm_modules_are_proven() {
+        let host = interpreter(None);
+        let wasm = WasmBackend::new(host.clone(), b"interpreter-elf".to_vec(), "wasm-local", mock_public_values());
+
+        let proof = wasm.prove(MODULE, b"input").unwrap();
+        assert!(wasm.verify(MODULE, &proof).unwrap());
+        assert!(!wasm.verify(b"\0asm\x01\0\0\0", &proof).unwrap());
+        assert_eq!(host.prove_calls(), 1);
+
+        assert!(wasm.prove(b"\x7fELF", b"input").is_err());
+        assert!(wasm.prove(b"\0asm\x02\0\0\0", b"input").is_err());
+        assert_eq!(host.prove_calls(), 1);
+    }
+
+    #[test]
+    fn test_proofs_bound_to_their_module() {
+        const OTHER: &[u8] = b"\0asm\x01\0\0\0";
+        let wasm = WasmBackend::new(interpreter(None), b"interpreter-elf".to_vec(), "wasm-local", mock_public_values());
+
+        // An envelope relabelled with another module's hash
+        let mut envelope = decode_envelope(&wasm.prove(MODULE, b"input").unwrap(), &DecodeLimits::default()).unwrap();
+        envelope.program_hash = crate::types::program_hash(OTHER);
+        envelope.seal();
+        assert!(!wasm.verify(OTHER, &envelope.to_bytes().unwrap()).unwrap());
+
+        // An interpreter that doesn't commit the module it ran
+        let lying = WasmBackend::new(
+            interpreter(Some(OTHER)),
+            b"interpreter-elf".to_vec(),
+            "wasm-local",
+            mock_public_values(),
+        );
+        assert!(lying.prove(MODULE, b"input").is_err());
+    }
+}
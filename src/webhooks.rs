@@ -0,0 +1,147 @@
+use crate::events::{EventSubscriber, LifecycleEvent};
+use serde_json::json;
+use std::sync::Arc;
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::time::Duration;
+
+/// Events waiting to be posted; further events are dropped while the
+/// endpoint is slow rather than stalling the prover
+const QUEUE_CAPACITY: usize = 256;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Decides which events are posted
+pub type EventFilter = Arc<dyn Fn(&LifecycleEvent) -> bool + Send + Sync>;
+
+/// Body posted for each event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookFormat {
+    /// The event itself, as serialized by [`LifecycleEvent`]
+    Json,
+    /// A Slack incoming-webhook message with the event's summary
+    Slack,
+}
+
+impl WebhookFormat {
+    fn body(&self, event: &LifecycleEvent) -> serde_json::Value {
+        match self {
+            WebhookFormat::Json => json!(event),
+            WebhookFormat::Slack => json!({ "text": event.summary() }),
+        }
+    }
+}
+
+/// Posts lifecycle events to an HTTP endpoint, e.g. to notify a Slack
+/// channel when production proofs fail. Posting happens on a background
+/// thread so subscribers never block the event bus; failed posts are
+/// logged and not retried.
+pub struct WebhookSubscriber {
+    queue: SyncSender<LifecycleEvent>,
+    filter: EventFilter,
+}
+
+impl WebhookSubscriber {
+    /// Post failures (see [`LifecycleEvent::is_failure`]) to `url`
+    pub fn new(url: &str, format: WebhookFormat) -> Self {
+        let (queue, events) = mpsc::sync_channel::<LifecycleEvent>(QUEUE_CAPACITY);
+        let url = url.to_string();
+        std::thread::spawn(move || {
+            let agent = ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build();
+            for event in events {
+                let body = format.body(&event).to_string();
+                if let Err(e) = agent
+                    .post(&url)
+                    .set("content-type", "application/json")
+                    .send_string(&body)
+                {
+                    tracing::warn!("webhook post of '{}' failed: {}", event.summary(), e);
+                }
+            }
+        });
+        Self {
+            queue,
+            filter: Arc::new(LifecycleEvent::is_failure),
+        }
+    }
+
+    /// Post the events `filter` accepts instead of failures only
+    pub fn with_filter(mut self, filter: EventFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+}
+
+impl EventSubscriber for WebhookSubscriber {
+    fn on_event(&self, event: &LifecycleEvent) {
+        if !(self.filter)(event) {
+            return;
+        }
+        match self.queue.try_send(event.clone()) {
+            Ok(()) => {}
+            Err(TrySendError::Full(event)) => {
+                tracing::warn!("webhook queue full, dropping '{}'", event.summary())
+            }
+            Err(TrySendError::Disconnected(_)) => tracing::warn!("webhook sender stopped"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventBus;
+    use crate::pipeline::ProofMode;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    /// Accept one request and return its body
+    fn receive_post(listener: &TcpListener) -> String {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line == "\r\n" {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':')
+                && name.eq_ignore_ascii_case("content-length")
+            {
+                content_length = value.trim().parse().unwrap();
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).unwrap();
+        reader
+            .get_mut()
+            .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+            .unwrap();
+        String::from_utf8(body).unwrap()
+    }
+
+    #[test]
+    fn test_failures_are_posted() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let bus = EventBus::new();
+        bus.subscribe(Arc::new(WebhookSubscriber::new(&url, WebhookFormat::Slack)));
+
+        bus.emit(LifecycleEvent::ProofStarted {
+            backend: "sp1-network".to_string(),
+            program_hash: "ab".repeat(32),
+        });
+        bus.emit(LifecycleEvent::ProofFailed {
+            backend: "sp1-network".to_string(),
+            program_hash: "ab".repeat(32),
+            mode: ProofMode::Groth16,
+            error: "out of gas".to_string(),
+        });
+
+        let body: serde_json::Value = serde_json::from_str(&receive_post(&listener)).unwrap();
+        assert_eq!(
+            body["text"],
+            "sp1-network: proving program abababababab failed: out of gas"
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use crate::types::ProverError;
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// Explicitly configured directories for circuit artifacts and per-job scratch space
+#[derive(Debug, Clone)]
+pub struct WorkDirs {
+    artifacts: PathBuf,
+    temp_root: PathBuf,
+}
+
+impl WorkDirs {
+    /// Create (if needed) and permission-check both directories
+    pub fn new(artifacts: &Path, temp_root: &Path) -> Result<Self, ProverError> {
+        Ok(Self {
+            artifacts: ensure_secure_dir(artifacts)?,
+            temp_root: ensure_secure_dir(temp_root)?,
+        })
+    }
+
+    /// Directory holding the Bn254 circuit artifacts
+    pub fn artifacts(&self) -> &Path {
+        &self.artifacts
+    }
+
+    /// Root under which per-job temp dirs are created
+    pub fn temp_root(&self) -> &Path {
+        &self.temp_root
+    }
+
+    /// Create a private scratch directory for one job
+    pub fn job_dir(&self) -> Result<JobTempDir, ProverError> {
+        let path = self.temp_root.join(format!("job-{}", Uuid::new_v4()));
+        create_private_dir(&path)?;
+        Ok(JobTempDir { path: Some(path) })
+    }
+}
+
+/// Per-job scratch directory, removed when dropped, including when proving
+/// fails or unwinds
+#[derive(Debug)]
+pub struct JobTempDir {
+    path: Option<PathBuf>,
+}
+
+impl JobTempDir {
+    pub fn path(&self) -> &Path {
+        self.path.as_deref().unwrap_or(Path::new(""))
+    }
+
+    /// Keep the directory on disk, e.g. to debug a failed job
+    pub fn keep(mut self) -> PathBuf {
+        self.path.take().unwrap_or_default()
+    }
+}
+
+impl Drop for JobTempDir {
+    fn drop(&mut self) {
+        if let Some(path) = self.path.take()
+            && let Err(e) = fs::remove_dir_all(&path)
+        {
+            tracing::warn!("failed to remove job dir {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Create `path` if missing and refuse directories other users can write to
+pub fn ensure_secure_dir(path: &Path) -> Result<PathBuf, ProverError> {
+    if !path.exists() {
+        create_private_dir(path)?;
+    }
+    let metadata = fs::metadata(path)?;
+    if !metadata.is_dir() {
+        return Err(ProverError::Other(format!("{} is not a directory", path.display())));
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = metadata.permissions().mode();
+        if mode & 0o022 != 0 {
+            return Err(ProverError::Other(format!(
+                "{} is writable by other users (mode {:o})",
+                path.display(),
+                mode & 0o777
+            )));
+        }
+    }
+
+    Ok(fs::canonicalize(path)?)
+}
+
+fn create_private_dir(path: &Path) -> Result<(), ProverError> {
+    fs::create_dir_all(path)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o700))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_dir_cleanup() {
+        let root = std::env::temp_dir().join(format!("frostgate-workdirs-{}", Uuid::new_v4()));
+        let dirs = WorkDirs::new(&root.join("artifacts"), &root.join("tmp")).unwrap();
+
+        let job = dirs.job_dir().unwrap();
+        let path = job.path().to_path_buf();
+        fs::write(path.join("stdin.bin"), b"input").unwrap();
+        drop(job);
+        assert!(!path.exists());
+
+        let kept = dirs.job_dir().unwrap().keep();
+        assert!(kept.exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_rejects_world_writable_dir() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = std::env::temp_dir().join(format!("frostgate-workdirs-{}", Uuid::new_v4()));
+        fs::create_dir_all(&root).unwrap();
+        fs::set_permissions(&root, fs::Permissions::from_mode(0o777)).unwrap();
+        assert!(ensure_secure_dir(&root).is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}